@@ -107,7 +107,8 @@ async function main() {
   );
 
   const matcherContext = Keypair.generate();
-  const CTX_SIZE = 320;
+  // Version-2 contexts extend the shared 320-byte layout
+  const CTX_SIZE = 448;
 
   console.log(`\nCreating uptime market for: ${ncnName}`);
   console.log(`  NCN: ${ncnAddress.toBase58()}`);
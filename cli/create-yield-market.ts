@@ -98,7 +98,8 @@ async function main() {
 
   // Create matcher context account
   const matcherContext = Keypair.generate();
-  const CTX_SIZE = 320;
+  // Version-2 contexts extend the shared 320-byte layout
+  const CTX_SIZE = 448;
 
   console.log(`\nCreating yield market for: ${ncnName}`);
   console.log(`  NCN: ${ncnAddress.toBase58()}`);
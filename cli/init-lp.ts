@@ -82,7 +82,8 @@ async function main() {
     fillIdx !== -1 ? parseInt(args[fillIdx + 1]) : 100_000;
 
   const matcherContext = Keypair.generate();
-  const CTX_SIZE = 320;
+  // Version-2 contexts extend the shared 320-byte layout
+  const CTX_SIZE = 448;
 
   const NCN_ORACLE_PROGRAM_ID = new PublicKey(
     "NCNRsk1111111111111111111111111111111111111"
@@ -111,7 +111,8 @@ function buildRecordNcnPerformanceIx(
   uptimeE6: BN,
   totalRestakedSol: BN,
   restakerCount: number,
-  slashingEvent: boolean
+  slashingEvent: boolean,
+  slashingFractionE6: BN
 ): TransactionInstruction {
   // Anchor instruction discriminator for record_ncn_performance
   const discriminator = anchor.utils.bytes.utf8.encode(
@@ -119,7 +120,7 @@ function buildRecordNcnPerformanceIx(
   );
 
   // Build data buffer (simplified — real impl uses Anchor IDL encoding)
-  const data = Buffer.alloc(8 + 8 + 8 + 4 + 1);
+  const data = Buffer.alloc(8 + 8 + 8 + 4 + 1 + 8);
   // Anchor discriminator (first 8 bytes) would be computed from sighash
   data.writeBigUInt64LE(BigInt("0x" + Buffer.from(
     anchor.utils.sha256.hash("global:record_ncn_performance")
@@ -128,6 +129,7 @@ function buildRecordNcnPerformanceIx(
   totalRestakedSol.toBuffer("le", 8).copy(data, 16);
   data.writeUInt32LE(restakerCount, 24);
   data.writeUInt8(slashingEvent ? 1 : 0, 28);
+  slashingFractionE6.toBuffer("le", 8).copy(data, 29);
 
   return new TransactionInstruction({
     programId: NCN_ORACLE_PROGRAM_ID,
@@ -26,7 +26,8 @@ const SIGNAL_LOW = 1;
 const SIGNAL_HIGH = 2;
 const SIGNAL_CRITICAL = 3;
 
-// Signal-based spread additions (in bps)
+// Signal-based spread additions (in bps). Informational only: the matcher derives the
+// charged spread from the severity stored on the NCN oracle and ignores the sync field.
 const SIGNAL_SPREAD_MAP: Record<number, number> = {
   [SIGNAL_NONE]: 0,
   [SIGNAL_LOW]: 25,
@@ -43,18 +44,21 @@ function buildUptimeSyncIx(
   ncnOracle: PublicKey,
   newUptimeE6: BN,
   signalSeverity: BN,
-  signalAdjustedSpread: BN
+  signalAdjustedSpread: BN,
+  oracleSequence: BN
 ): TransactionInstruction {
   // Data layout:
   //   [0]    tag (0x03)
   //   [1..9] new_uptime_e6 (u64 LE, 0-1_000_000)
-  //   [9..17] signal_severity (u64 LE, 0-3)
-  //   [17..25] signal_adjusted_spread (u64 LE)
-  const data = Buffer.alloc(25);
+  //   [9..17] signal_severity (u64 LE, ignored on-chain -- read from the oracle)
+  //   [17..25] signal_adjusted_spread (u64 LE, ignored on-chain)
+  //   [25..33] oracle_sequence (u64 LE, the feed's last_updated; strictly increasing per market)
+  const data = Buffer.alloc(33);
   data.writeUInt8(0x03, 0);
   newUptimeE6.toBuffer("le", 8).copy(data, 1);
   signalSeverity.toBuffer("le", 8).copy(data, 9);
   signalAdjustedSpread.toBuffer("le", 8).copy(data, 17);
+  oracleSequence.toBuffer("le", 8).copy(data, 25);
 
   return new TransactionInstruction({
     programId: UPTIME_MATCHER_PROGRAM_ID,
@@ -70,6 +74,57 @@ function buildUptimeSyncIx(
   });
 }
 
+// ============================================================================
+// Instruction Builder — BatchUptimeSync (tag 0x07)
+// ============================================================================
+
+const MAX_BATCH_SYNC_MARKETS = 8;
+const SYNC_PAYLOAD_SIZE = 32;
+
+interface UptimeSyncTarget {
+  matcherContext: PublicKey;
+  ncnOracle: PublicKey;
+  newUptimeE6: BN;
+  signalSeverity: BN;
+  signalAdjustedSpread: BN;
+  oracleSequence: BN;
+}
+
+function buildBatchUptimeSyncIx(
+  targets: UptimeSyncTarget[]
+): TransactionInstruction {
+  if (targets.length === 0 || targets.length > MAX_BATCH_SYNC_MARKETS) {
+    throw new Error(
+      `Batch sync supports 1-${MAX_BATCH_SYNC_MARKETS} markets, got ${targets.length}`
+    );
+  }
+
+  // Data layout:
+  //   [0]    tag (0x07)
+  //   [1]    market count (u8)
+  //   [2..]  32 bytes per market: uptime_e6, signal_severity, signal_adjusted_spread,
+  //          oracle_sequence (u64 LE each)
+  const data = Buffer.alloc(2 + targets.length * SYNC_PAYLOAD_SIZE);
+  data.writeUInt8(0x07, 0);
+  data.writeUInt8(targets.length, 1);
+  targets.forEach((t, i) => {
+    const offset = 2 + i * SYNC_PAYLOAD_SIZE;
+    t.newUptimeE6.toBuffer("le", 8).copy(data, offset);
+    t.signalSeverity.toBuffer("le", 8).copy(data, offset + 8);
+    t.signalAdjustedSpread.toBuffer("le", 8).copy(data, offset + 16);
+    t.oracleSequence.toBuffer("le", 8).copy(data, offset + 24);
+  });
+
+  return new TransactionInstruction({
+    programId: UPTIME_MATCHER_PROGRAM_ID,
+    keys: targets.flatMap((t) => [
+      { pubkey: t.matcherContext, isSigner: false, isWritable: true },
+      { pubkey: t.ncnOracle, isSigner: false, isWritable: false },
+    ]),
+    data,
+  });
+}
+
 // ============================================================================
 // Sync Loop
 // ============================================================================
@@ -108,7 +163,8 @@ async function runUptimeSync(
       //   matcherContext, ncnOracle,
       //   new BN(data.uptimeE6),
       //   new BN(data.signalSeverity),
-      //   new BN(signalSpread)
+      //   new BN(signalSpread),
+      //   new BN(feed.lastUpdated) // sequence = feed's last_updated, never ahead of it
       // );
       // const tx = new Transaction().add(ix);
       // await connection.sendTransaction(tx, [authority]);
@@ -190,6 +246,8 @@ async function main() {
 
 export {
   buildUptimeSyncIx,
+  buildBatchUptimeSyncIx,
+  UptimeSyncTarget,
   runUptimeSync,
   UptimeData,
   SIGNAL_NONE,
@@ -23,7 +23,8 @@ const YIELD_MATCHER_PROGRAM_ID = new PublicKey(
   "YLDMtch111111111111111111111111111111111111"
 );
 
-// Yield regime thresholds (variance in bps)
+// Yield regime thresholds (variance in bps) -- the oracle defaults; a feed
+// authority can retune them on-chain via set_regime_thresholds
 const REGIME_THRESHOLDS = {
   VERY_LOW: 50,
   LOW: 150,
@@ -128,22 +129,28 @@ function buildOracleSyncIx(
   yieldMarkPriceE6: BN,
   regime: number,
   yield7dAvgBps: BN,
-  yield30dAvgBps: BN
+  yield30dAvgBps: BN,
+  oracleSequence: BN,
+  yieldVarianceBps: BN
 ): TransactionInstruction {
   // Data layout:
   //   [0]    tag (0x03)
-  //   [1..9] current_yield_bps (u64 LE)
-  //   [9..17] yield_mark_price_e6 (u64 LE)
+  //   [1..9] current_yield_bps (i64 LE, negative when net yield is underwater)
+  //   [9..17] yield_mark_price_e6 (i64 LE)
   //   [17]   regime (u8)
   //   [18..26] yield_7d_avg_bps (u64 LE)
   //   [26..34] yield_30d_avg_bps (u64 LE)
-  const data = Buffer.alloc(34);
+  //   [34..42] oracle_sequence (u64 LE, the yield feed's last_updated; strictly increasing per market)
+  //   [42..50] yield_variance_bps (u64 LE, priced by continuous spread scaling)
+  const data = Buffer.alloc(50);
   data.writeUInt8(0x03, 0);
-  currentYieldBps.toBuffer("le", 8).copy(data, 1);
-  yieldMarkPriceE6.toBuffer("le", 8).copy(data, 9);
+  currentYieldBps.toTwos(64).toArrayLike(Buffer, "le", 8).copy(data, 1);
+  yieldMarkPriceE6.toTwos(64).toArrayLike(Buffer, "le", 8).copy(data, 9);
   data.writeUInt8(regime, 17);
   yield7dAvgBps.toBuffer("le", 8).copy(data, 18);
   yield30dAvgBps.toBuffer("le", 8).copy(data, 26);
+  oracleSequence.toBuffer("le", 8).copy(data, 34);
+  yieldVarianceBps.toBuffer("le", 8).copy(data, 42);
 
   return new TransactionInstruction({
     programId: YIELD_MATCHER_PROGRAM_ID,
@@ -208,7 +215,9 @@ async function runYieldSync(
       // const ix = buildOracleSyncIx(
       //   matcherContext, ncnYieldFeed, ncnPerformanceFeed,
       //   new BN(snapshot.currentApyBps), new BN(yieldMarkPriceE6),
-      //   regime, new BN(snapshot.yield7dAvgBps), new BN(snapshot.yield30dAvgBps)
+      //   regime, new BN(snapshot.yield7dAvgBps), new BN(snapshot.yield30dAvgBps),
+      //   new BN(yieldFeed.lastUpdated), // sequence = feed's last_updated, never ahead of it
+      //   new BN(snapshot.yieldVarianceBps)
       // );
       // const tx = new Transaction().add(ix);
       // await connection.sendTransaction(tx, [authority]);
@@ -0,0 +1,117 @@
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError, pubkey::Pubkey};
+
+use matcher_common::{verify_magic, CTX_SIZE, LP_PDA_OFFSET};
+
+// Header fields both matchers keep after matcher-common's magic
+pub const VERSION_OFFSET: usize = 72; // u32
+pub const MODE_OFFSET: usize = 76; // u8, matcher-specific modes
+
+/// Context layout versions. Version 1 is the shared CTX_SIZE layout; version 2 appends a
+/// matcher-specific extension and needs a CTX_V2_SIZE account.
+pub const CTX_VERSION_1: u32 = 1;
+pub const CTX_VERSION_2: u32 = 2;
+pub const CTX_V2_SIZE: usize = CTX_SIZE + 128;
+
+/// Fixed header fields of a validated matcher context
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeaderView {
+    pub version: u32,
+    pub mode: u8,
+    pub lp_pda: Pubkey,
+}
+
+/// Reject a context not owned by this program. Magic bytes alone prove nothing: any
+/// program can write them into an account it owns.
+pub fn verify_context_owner(ctx_account: &AccountInfo, program_id: &Pubkey, name: &str) -> ProgramResult {
+    if ctx_account.owner != program_id {
+        msg!("{}: Context owned by {}, expected {}", name, ctx_account.owner, program_id);
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Validate a context in one place, in a fixed order: owned by this program, full
+/// size, then the matcher's `magic`, then the size its version needs. A context
+/// belonging to another matcher fails cleanly as UninitializedAccount before any field
+/// is read. `name` prefixes the log lines.
+pub fn validate_header(ctx_account: &AccountInfo, program_id: &Pubkey, magic: u64, name: &str) -> Result<HeaderView, ProgramError> {
+    verify_context_owner(ctx_account, program_id, name)?;
+    let ctx_data = ctx_account.try_borrow_data()?;
+    if ctx_data.len() < CTX_SIZE {
+        msg!("{}: Context is {} bytes, expected {}", name, ctx_data.len(), CTX_SIZE);
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    if !verify_magic(&ctx_data, magic) {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let version = read_ctx_u32(&ctx_data, VERSION_OFFSET)?;
+    if version >= CTX_VERSION_2 && ctx_data.len() < CTX_V2_SIZE {
+        msg!("{}: Version {} context is {} bytes, expected {}", name, version, ctx_data.len(), CTX_V2_SIZE);
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    Ok(HeaderView {
+        version,
+        mode: ctx_data[MODE_OFFSET],
+        lp_pda: Pubkey::new_from_array(
+            ctx_data[LP_PDA_OFFSET..LP_PDA_OFFSET + 32]
+                .try_into()
+                .map_err(|_| ProgramError::InvalidAccountData)?,
+        ),
+    })
+}
+
+/// Whether the context carries the version-2 extension; a version-1 context reads every
+/// extension field as zero
+pub fn has_extension(ctx_data: &[u8]) -> bool {
+    ctx_data.len() >= CTX_V2_SIZE
+        && matches!(read_ctx_u32(ctx_data, VERSION_OFFSET), Ok(version) if version >= CTX_VERSION_2)
+}
+
+/// Read a little-endian u32 context field at `offset`
+pub fn read_ctx_u32(ctx_data: &[u8], offset: usize) -> Result<u32, ProgramError> {
+    Ok(u32::from_le_bytes(
+        ctx_data[offset..offset + 4]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
+
+/// Bounds-checked u128 read (liquidity, max fill, instruction amounts); a short buffer is
+/// an error rather than a panic
+pub fn read_u128(ctx_data: &[u8], offset: usize) -> Result<u128, ProgramError> {
+    let end = offset.checked_add(16).ok_or(ProgramError::InvalidAccountData)?;
+    ctx_data
+        .get(offset..end)
+        .and_then(|b| b.try_into().ok())
+        .map(u128::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+/// Counterpart of read_u128 for writing context fields
+pub fn write_u128(ctx_data: &mut [u8], offset: usize, value: u128) -> ProgramResult {
+    let end = offset.checked_add(16).ok_or(ProgramError::InvalidAccountData)?;
+    ctx_data
+        .get_mut(offset..end)
+        .ok_or(ProgramError::InvalidAccountData)?
+        .copy_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+/// Canonical match log line both matchers emit, so one parser handles either:
+///   MATCH v=1 price=<i64> spread_bps=<u64> mark_e6=<i64>[ <key>=<value>...]
+/// Price and mark are signed e6 because yield marks can go negative underwater.
+/// Matcher-specific fields follow as space-separated key=value pairs; keys never
+/// contain spaces or '='.
+pub fn format_match_log(price: i64, spread_bps: u64, mark_e6: i64, extra: &str) -> String {
+    let mut line = format!("MATCH v=1 price={} spread_bps={} mark_e6={}", price, spread_bps, mark_e6);
+    if !extra.is_empty() {
+        line.push(' ');
+        line.push_str(extra);
+    }
+    line
+}
+
+/// Emit the canonical match log line (see format_match_log)
+pub fn log_match(price: i64, spread_bps: u64, mark_e6: i64, extra: &str) {
+    msg!("{}", format_match_log(price, spread_bps, mark_e6, extra));
+}
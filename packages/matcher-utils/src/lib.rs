@@ -0,0 +1,46 @@
+//! Helpers shared by the Vigil programs: fixed-point scales and integer math (no
+//! dependencies, usable from the Anchor oracle), plus with the default `program` feature
+//! the versioned context header both matchers put on top of matcher-common's layout.
+
+#[cfg(feature = "program")]
+mod context;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+
+#[cfg(feature = "program")]
+pub use context::*;
+
+/// Fixed-point scales: spreads, fees and weights are bps; probabilities, marks and
+/// ratios are e6
+pub const BPS_SCALE: u64 = 10_000;
+pub const E6_SCALE: u64 = 1_000_000;
+
+/// Integer square root: largest r such that r * r <= x
+pub fn isqrt(x: u128) -> u128 {
+    if x < 2 {
+        return x;
+    }
+    // Newton's method from an initial guess >= sqrt(x); iterates decrease monotonically
+    let mut r = 1u128 << (128 - x.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (r + x / r) / 2;
+        if next >= r {
+            return r;
+        }
+        r = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::isqrt;
+
+    #[test]
+    fn test_isqrt_is_the_floor_root() {
+        for x in 0u128..10_000 {
+            let r = isqrt(x);
+            assert!(r * r <= x && (r + 1) * (r + 1) > x, "isqrt({}) = {}", x, r);
+        }
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+}
@@ -0,0 +1,63 @@
+//! Test support: an LP-owned matcher context that handler tests build up field by field.
+//! Each matcher adds its own defaults and setters through a local extension trait.
+
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+use matcher_common::{write_header, CTX_SIZE};
+
+use crate::{CTX_V2_SIZE, CTX_VERSION_1, VERSION_OFFSET};
+
+/// Instruction handler signature shared by both matchers
+pub type Handler = fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult;
+
+/// CTX_V2_SIZE context bytes plus the LP key it is bound to
+pub struct MockContext {
+    pub lp_key: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl MockContext {
+    /// Zeroed context carrying only matcher-common's header (magic, mode, LP binding)
+    pub fn with_header(magic: u64, mode: u8, lp_key: Pubkey) -> Self {
+        let mut data = vec![0u8; CTX_V2_SIZE];
+        write_header(&mut data, magic, mode, &lp_key);
+        Self { lp_key, data }
+    }
+
+    /// Version-1 context: the shared 320-byte layout without the extension
+    pub fn version_1(mut self) -> Self {
+        self.data.truncate(CTX_SIZE);
+        self.put(VERSION_OFFSET, &CTX_VERSION_1.to_le_bytes())
+    }
+
+    /// Raw little-endian write for fields without a dedicated setter
+    pub fn put(mut self, offset: usize, bytes: &[u8]) -> Self {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Run an LP-signed (LP, context) handler against the built context; returns the
+    /// result and the context bytes afterwards
+    pub fn run(mut self, handler: Handler, data: &[u8]) -> (ProgramResult, Vec<u8>) {
+        let result = run_lp_signed(handler, &self.lp_key, &mut self.data, data);
+        (result, self.data)
+    }
+}
+
+/// Run an LP-signed two-account handler (LP, context) against the given context, with
+/// both owned by a fresh program id
+pub fn run_lp_signed(handler: Handler, lp_key: &Pubkey, ctx_data: &mut [u8], data: &[u8]) -> ProgramResult {
+    let program_id = Pubkey::new_unique();
+    let ctx_key = Pubkey::new_unique();
+    let (mut l0, mut l1) = (0u64, 0u64);
+    let mut lp_data = [0u8; 0];
+    let accounts = [
+        AccountInfo::new(lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0),
+        AccountInfo::new(&ctx_key, false, true, &mut l1, ctx_data, &program_id, false, 0),
+    ];
+    handler(&program_id, &accounts, data)
+}
@@ -37,4 +37,46 @@ pub enum NcnOracleError {
 
     #[msg("Invalid yield regime (must be 0-4)")]
     InvalidYieldRegime,
+
+    #[msg("Invalid signal decay interval (must be >= 0)")]
+    InvalidDecayInterval,
+
+    #[msg("Invalid slashing fraction (must be 0-1_000_000)")]
+    InvalidSlashingFraction,
+
+    #[msg("Invalid horizon (must be >= 0 seconds)")]
+    InvalidHorizon,
+
+    #[msg("No keeper rewards claimable (nothing accrued or vault unfunded)")]
+    NoKeeperRewards,
+
+    #[msg("No performance sample at or before the requested timestamp")]
+    NoSampleAtTimestamp,
+
+    #[msg("Invalid yield curve (max 8 points, strictly increasing tenors)")]
+    InvalidYieldCurve,
+
+    #[msg("Yield and performance feeds belong to different NCNs")]
+    NcnAddressMismatch,
+
+    #[msg("Invalid sample interval (must be > 0 seconds)")]
+    InvalidSampleInterval,
+
+    #[msg("Invalid regime thresholds (must be strictly increasing)")]
+    InvalidRegimeThresholds,
+
+    #[msg("Registry page full (max 16 entries); add a new page")]
+    RegistryPageFull,
+
+    #[msg("NCN feed already registered on this page")]
+    NcnAlreadyRegistered,
+
+    #[msg("NCN feed not registered on this page")]
+    NcnNotRegistered,
+
+    #[msg("Sample timestamp precedes the last recorded sample")]
+    StaleTimestamp,
+
+    #[msg("Invalid history cap (must be 1-720 and not below the samples already kept)")]
+    InvalidHistoryCap,
 }
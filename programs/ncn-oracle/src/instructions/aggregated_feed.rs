@@ -16,6 +16,7 @@ pub fn initialize_aggregated_feed(
     feed.is_active = true;
     feed.last_updated = clock.unix_timestamp;
     feed.bump = ctx.bumps.aggregated_feed;
+    feed.oldest_member_update = clock.unix_timestamp;
 
     Ok(())
 }
@@ -48,6 +49,43 @@ pub fn update_aggregated_feed(
     Ok(())
 }
 
+/// Recompute protocol TVL from the tracked NCN feeds, passed as remaining accounts
+/// in the same order as `ncn_feeds`, and record the stalest member's update time
+pub fn recompute_aggregated_tvl<'info>(
+    ctx: Context<'_, '_, 'info, 'info, UpdateAggregatedFeed<'info>>,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.aggregated_feed;
+    require!(
+        ctx.remaining_accounts.len() == feed.ncn_feeds.len(),
+        NcnOracleError::NcnFeedNotFound
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let mut tvls = Vec::with_capacity(feed.ncn_feeds.len());
+    let mut oldest_update = now;
+    for (account, expected) in ctx.remaining_accounts.iter().zip(feed.ncn_feeds.iter()) {
+        require_keys_eq!(account.key(), *expected, NcnOracleError::NcnFeedNotFound);
+        let ncn_feed = Account::<NcnPerformanceFeed>::try_from(account)?;
+        tvls.push(ncn_feed.total_restaked_sol);
+        oldest_update = oldest_update.min(ncn_feed.last_updated);
+    }
+
+    feed.total_restaked_sol = AggregatedRestakingFeed::checked_total_restaked_sol(tvls)
+        .ok_or(NcnOracleError::MathOverflow)?;
+    feed.oldest_member_update = oldest_update;
+    feed.last_updated = now;
+
+    Ok(())
+}
+
+/// Self-heal `ncn_count` from `ncn_feeds` and return the corrected count
+pub fn reconcile_count(ctx: Context<ReconcileNcnCount>) -> Result<u32> {
+    let feed = &mut ctx.accounts.aggregated_feed;
+    require!(feed.ncn_feeds.len() <= 32, NcnOracleError::MaxNcnFeedsReached);
+
+    Ok(feed.reconcile_count())
+}
+
 #[derive(Accounts)]
 pub struct InitializeAggregatedRestakingFeed<'info> {
     #[account(mut)]
@@ -78,6 +116,17 @@ pub struct AddNcnFeed<'info> {
     pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
 }
 
+#[derive(Accounts)]
+pub struct ReconcileNcnCount<'info> {
+    #[account(
+        constraint = authority.key() == aggregated_feed.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub aggregated_feed: Account<'info, AggregatedRestakingFeed>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateAggregatedFeed<'info> {
     #[account(
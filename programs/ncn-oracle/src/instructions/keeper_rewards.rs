@@ -0,0 +1,148 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use crate::state::*;
+use crate::errors::NcnOracleError;
+
+pub fn set_keeper_reward(
+    ctx: Context<SetKeeperReward>,
+    reward_per_update_lamports: u64,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    feed.keeper_reward_per_update_lamports = reward_per_update_lamports;
+
+    Ok(())
+}
+
+pub fn initialize_keeper_reward_vault(
+    ctx: Context<InitializeKeeperRewardVault>,
+) -> Result<()> {
+    let vault = &mut ctx.accounts.keeper_reward_vault;
+
+    vault.ncn_performance_feed = ctx.accounts.ncn_performance_feed.key();
+    vault.authority = ctx.accounts.authority.key();
+    vault.bump = ctx.bumps.keeper_reward_vault;
+
+    Ok(())
+}
+
+pub fn fund_keeper_reward_vault(
+    ctx: Context<FundKeeperRewardVault>,
+    amount: u64,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.funder.to_account_info(),
+                to: ctx.accounts.keeper_reward_vault.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+pub fn claim_keeper_rewards(
+    ctx: Context<ClaimKeeperRewards>,
+) -> Result<()> {
+    let vault_info = ctx.accounts.keeper_reward_vault.to_account_info();
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(vault_info.data_len());
+
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    let payout = feed.claimable_keeper_rewards(vault_info.lamports(), rent_exempt_minimum);
+    require!(payout > 0, NcnOracleError::NoKeeperRewards);
+
+    // Vault is program-owned, so lamports can be moved without a CPI
+    **vault_info.try_borrow_mut_lamports()? -= payout;
+    **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += payout;
+
+    feed.keeper_rewards_lamports -= payout;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetKeeperReward<'info> {
+    /// The vault admin: the keeper being paid cannot set its own rate
+    #[account(
+        constraint = authority.key() == keeper_reward_vault.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+
+    #[account(
+        seeds = [b"keeper_vault", ncn_performance_feed.key().as_ref()],
+        bump = keeper_reward_vault.bump
+    )]
+    pub keeper_reward_vault: Account<'info, KeeperRewardVault>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeKeeperRewardVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Vault admin stored on the vault: the registry authority, and not the feed's keeper
+    #[account(
+        constraint = KeeperRewardVault::is_valid_admin(
+            &authority.key(),
+            &registry.authority,
+            &ncn_performance_feed.authority,
+        ) @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"ncn_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, NcnRegistry>,
+
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + KeeperRewardVault::INIT_SPACE,
+        seeds = [b"keeper_vault", ncn_performance_feed.key().as_ref()],
+        bump
+    )]
+    pub keeper_reward_vault: Account<'info, KeeperRewardVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundKeeperRewardVault<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"keeper_vault", keeper_reward_vault.ncn_performance_feed.as_ref()],
+        bump = keeper_reward_vault.bump
+    )]
+    pub keeper_reward_vault: Account<'info, KeeperRewardVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimKeeperRewards<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == ncn_performance_feed.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+
+    #[account(
+        mut,
+        seeds = [b"keeper_vault", ncn_performance_feed.key().as_ref()],
+        bump = keeper_reward_vault.bump
+    )]
+    pub keeper_reward_vault: Account<'info, KeeperRewardVault>,
+}
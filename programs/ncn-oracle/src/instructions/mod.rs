@@ -2,3 +2,6 @@ pub mod performance_feed;
 pub mod yield_feed;
 pub mod aggregated_feed;
 pub mod signal;
+pub mod queries;
+pub mod keeper_rewards;
+pub mod registry;
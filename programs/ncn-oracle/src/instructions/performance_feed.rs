@@ -27,6 +27,13 @@ pub fn initialize_ncn_performance_feed(
     feed.is_active = true;
     feed.last_updated = clock.unix_timestamp;
     feed.bump = ctx.bumps.ncn_performance_feed;
+    feed.signal_set_time = clock.unix_timestamp;
+    feed.signal_decay_interval_secs = 0;
+    feed.last_slashing_fraction_e6 = 0;
+    feed.keeper_reward_per_update_lamports = 0;
+    feed.keeper_rewards_lamports = 0;
+    feed.history_cap = MAX_HISTORY_SAMPLES as u16;
+    feed.recent_slashing_times = [0; RECENT_SLASHING_TIMES];
 
     Ok(())
 }
@@ -37,8 +44,10 @@ pub fn record_ncn_performance(
     total_restaked_sol: u64,
     restaker_count: u32,
     slashing_event: bool,
+    slashing_fraction_e6: u64,
 ) -> Result<()> {
     require!(uptime_e6 <= 1_000_000, NcnOracleError::InvalidUptimeProbability);
+    require!(slashing_fraction_e6 <= 1_000_000, NcnOracleError::InvalidSlashingFraction);
 
     let feed = &mut ctx.accounts.ncn_performance_feed;
     let clock = Clock::get()?;
@@ -50,22 +59,93 @@ pub fn record_ncn_performance(
     if slashing_event {
         feed.total_slashing_events += 1;
         feed.last_slashing_time = clock.unix_timestamp;
+        feed.record_slashing_time(clock.unix_timestamp);
+        feed.last_slashing_fraction_e6 = slashing_fraction_e6;
+
+        // Escalate (never lower) the signal according to slash size
+        feed.signal_severity = std::cmp::max(
+            feed.signal_severity,
+            NcnPerformanceFeed::severity_for_slash(slashing_fraction_e6),
+        );
+        // A new incident restarts the signal decay clock
+        feed.signal_set_time = clock.unix_timestamp;
+    } else {
+        feed.apply_signal_decay(clock.unix_timestamp);
     }
 
-    // Add to performance history (circular buffer, max 168)
-    feed.performance_history.push(NcnPerformanceSample {
-        uptime_e6,
-        total_restaked_sol,
-        restaker_count,
-        timestamp: clock.unix_timestamp,
-    });
-
-    if feed.performance_history.len() > 168 {
-        feed.performance_history.remove(0);
+    if let Some(gap_secs) = feed.resume_gap(clock.unix_timestamp) {
+        emit!(FeedResumedAfterGap {
+            ncn_performance_feed: feed.key(),
+            ncn_address: feed.ncn_address,
+            previous_sample_timestamp: clock.unix_timestamp - gap_secs,
+            gap_secs,
+            timestamp: clock.unix_timestamp,
+        });
     }
 
+    // Add to performance history (rolling window of history_cap samples)
+    let cap = effective_history_cap(feed.history_cap);
+    append_capped_history(
+        &mut feed.performance_history,
+        &[NcnPerformanceSample {
+            uptime_e6,
+            total_restaked_sol,
+            restaker_count,
+            timestamp: clock.unix_timestamp,
+        }],
+        cap,
+    );
+
+    feed.last_updated = clock.unix_timestamp;
+
+    feed.accrue_keeper_reward().ok_or(NcnOracleError::MathOverflow)?;
+
+    Ok(())
+}
+
+/// Keeper liveness proof: refreshes `last_updated` without recording a sample
+pub fn heartbeat_ncn_performance(
+    ctx: Context<RecordNcnPerformance>,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    feed.last_updated = Clock::get()?.unix_timestamp;
+
+    Ok(())
+}
+
+/// Change how many performance samples the feed keeps. The account is reallocated to fit
+/// (see ResizePerformanceHistory); a single call can grow it by at most 10 KiB, so
+/// large increases take several calls. The cap never drops below the samples held.
+pub fn resize_performance_history(
+    ctx: Context<ResizePerformanceHistory>,
+    history_cap: u16,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    require!(
+        is_valid_history_cap(history_cap, feed.performance_history.len()),
+        NcnOracleError::InvalidHistoryCap
+    );
+    feed.history_cap = history_cap;
+
+    Ok(())
+}
+
+/// Permanently stop the feed accepting updates and announce it to dependent matchers
+pub fn deactivate_ncn_performance_feed(
+    ctx: Context<RecordNcnPerformance>,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    let clock = Clock::get()?;
+
+    feed.is_active = false;
     feed.last_updated = clock.unix_timestamp;
 
+    emit!(FeedDeactivated {
+        ncn_performance_feed: feed.key(),
+        ncn_address: feed.ncn_address,
+        timestamp: clock.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -104,3 +184,24 @@ pub struct RecordNcnPerformance<'info> {
     )]
     pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
 }
+
+#[derive(Accounts)]
+#[instruction(history_cap: u16)]
+pub struct ResizePerformanceHistory<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == ncn_performance_feed.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = ncn_performance_feed.is_active @ NcnOracleError::FeedInactive,
+        realloc = history_account_space(NcnPerformanceFeed::INIT_SPACE, NcnPerformanceSample::INIT_SPACE, history_cap),
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+
+    pub system_program: Program<'info, System>,
+}
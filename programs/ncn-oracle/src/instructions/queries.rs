@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::NcnOracleError;
+
+pub fn get_implied_default_probability(
+    ctx: Context<ReadNcnPerformanceFeed>,
+    horizon_secs: i64,
+) -> Result<u64> {
+    require!(horizon_secs >= 0, NcnOracleError::InvalidHorizon);
+
+    let clock = Clock::get()?;
+    Ok(ctx
+        .accounts
+        .ncn_performance_feed
+        .implied_default_probability(horizon_secs, clock.unix_timestamp))
+}
+
+pub fn get_sample_at_or_before(
+    ctx: Context<ReadNcnPerformanceFeed>,
+    timestamp: i64,
+) -> Result<NcnPerformanceSample> {
+    ctx.accounts
+        .ncn_performance_feed
+        .sample_at_or_before(timestamp)
+        .copied()
+        .ok_or_else(|| error!(NcnOracleError::NoSampleAtTimestamp))
+}
+
+pub fn get_sample_interval_stats(
+    ctx: Context<ReadNcnPerformanceFeed>,
+    max_interval_secs: i64,
+) -> Result<SampleIntervalStats> {
+    require!(max_interval_secs > 0, NcnOracleError::InvalidSampleInterval);
+
+    Ok(ctx
+        .accounts
+        .ncn_performance_feed
+        .sample_interval_stats(max_interval_secs))
+}
+
+/// Seconds since the newest history sample (i64::MAX with no history)
+pub fn get_latest_sample_age(
+    ctx: Context<ReadNcnPerformanceFeed>,
+) -> Result<i64> {
+    let clock = Clock::get()?;
+    Ok(ctx.accounts.ncn_performance_feed.latest_sample_age(clock.unix_timestamp))
+}
+
+/// Seconds since the last slashing event and whether it counts as recent (24h)
+pub fn get_slashing_recency(
+    ctx: Context<ReadNcnPerformanceFeed>,
+) -> Result<SlashingRecency> {
+    let clock = Clock::get()?;
+    Ok(ctx.accounts.ncn_performance_feed.slashing_recency(clock.unix_timestamp))
+}
+
+/// 95% confidence band around the uptime estimate, wider with fewer history samples
+pub fn get_uptime_confidence_band(
+    ctx: Context<ReadNcnPerformanceFeed>,
+) -> Result<UptimeConfidenceBand> {
+    let feed = &ctx.accounts.ncn_performance_feed;
+    let (lower_e6, upper_e6) = feed.uptime_confidence_band();
+    Ok(UptimeConfidenceBand {
+        lower_e6,
+        upper_e6,
+        sample_count: feed.performance_history.len() as u32,
+    })
+}
+
+pub fn export_performance_history(
+    ctx: Context<ReadNcnPerformanceFeed>,
+    start: u16,
+    max_samples: u8,
+) -> Result<Vec<u8>> {
+    Ok(ctx.accounts.ncn_performance_feed.export_history(start, max_samples))
+}
+
+pub fn get_annualized_volatility(
+    ctx: Context<ReadNcnYieldFeed>,
+) -> Result<u64> {
+    Ok(ctx.accounts.ncn_yield_feed.annualized_volatility_bps())
+}
+
+pub fn get_apy_for_tenor(
+    ctx: Context<ReadNcnYieldFeed>,
+    tenor_days: u16,
+) -> Result<u64> {
+    Ok(ctx.accounts.ncn_yield_feed.apy_for_tenor(tenor_days))
+}
+
+pub fn export_yield_history(
+    ctx: Context<ReadNcnYieldFeed>,
+    start: u16,
+    max_samples: u8,
+) -> Result<Vec<u8>> {
+    Ok(ctx.accounts.ncn_yield_feed.export_history(start, max_samples))
+}
+
+pub fn get_yield_decomposition(
+    ctx: Context<ReadNcnYieldFeed>,
+) -> Result<YieldDecomposition> {
+    ctx.accounts
+        .ncn_yield_feed
+        .yield_decomposition()
+        .ok_or_else(|| error!(NcnOracleError::MathOverflow))
+}
+
+pub fn get_slashing_adjusted_apy(
+    ctx: Context<ReadNcnYieldAndPerformance>,
+) -> Result<u64> {
+    let clock = Clock::get()?;
+    Ok(ctx
+        .accounts
+        .ncn_yield_feed
+        .slashing_adjusted_apy_bps(&ctx.accounts.ncn_performance_feed, clock.unix_timestamp))
+}
+
+pub fn get_max_member_staleness(
+    ctx: Context<ReadAggregatedFeed>,
+) -> Result<i64> {
+    let clock = Clock::get()?;
+    Ok(ctx.accounts.aggregated_feed.max_member_staleness_secs(clock.unix_timestamp))
+}
+
+// Read-only account contexts
+#[derive(Accounts)]
+pub struct ReadNcnPerformanceFeed<'info> {
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+}
+
+#[derive(Accounts)]
+pub struct ReadNcnYieldFeed<'info> {
+    pub ncn_yield_feed: Account<'info, NcnYieldFeed>,
+}
+
+#[derive(Accounts)]
+pub struct ReadNcnYieldAndPerformance<'info> {
+    #[account(
+        constraint = ncn_yield_feed.ncn_address == ncn_performance_feed.ncn_address @ NcnOracleError::NcnAddressMismatch
+    )]
+    pub ncn_yield_feed: Account<'info, NcnYieldFeed>,
+
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+}
+
+#[derive(Accounts)]
+pub struct ReadAggregatedFeed<'info> {
+    pub aggregated_feed: Account<'info, AggregatedRestakingFeed>,
+}
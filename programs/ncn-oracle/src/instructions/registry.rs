@@ -0,0 +1,190 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::errors::NcnOracleError;
+
+pub fn initialize_ncn_registry(
+    ctx: Context<InitializeNcnRegistry>,
+) -> Result<()> {
+    let registry = &mut ctx.accounts.registry;
+
+    registry.authority = ctx.accounts.authority.key();
+    registry.page_count = 0;
+    registry.ncn_count = 0;
+    registry.bump = ctx.bumps.registry;
+
+    Ok(())
+}
+
+/// Create the next registry page at index `page_count`
+pub fn add_ncn_registry_page(
+    ctx: Context<AddNcnRegistryPage>,
+) -> Result<()> {
+    let registry = &mut ctx.accounts.registry;
+    let page = &mut ctx.accounts.registry_page;
+
+    page.page_index = registry.page_count;
+    page.entries = Vec::new();
+    page.bump = ctx.bumps.registry_page;
+
+    registry.page_count = registry.page_count.checked_add(1).ok_or(NcnOracleError::MathOverflow)?;
+
+    Ok(())
+}
+
+/// Record a performance feed on a registry page. The feed's NcnRegistration marker is
+/// created alongside, so registering it again on any page fails.
+pub fn register_ncn(
+    ctx: Context<RegisterNcn>,
+) -> Result<()> {
+    let page = &mut ctx.accounts.registry_page;
+    let feed = &ctx.accounts.ncn_performance_feed;
+
+    require!(page.position(&feed.key()).is_none(), NcnOracleError::NcnAlreadyRegistered);
+    require!(!page.is_full(), NcnOracleError::RegistryPageFull);
+
+    page.entries.push(NcnRegistryEntry {
+        ncn_performance_feed: feed.key(),
+        ncn_name: feed.ncn_name.clone(),
+    });
+    let registration = &mut ctx.accounts.registration;
+    registration.ncn_performance_feed = feed.key();
+    registration.page_index = page.page_index;
+    registration.bump = ctx.bumps.registration;
+
+    let registry = &mut ctx.accounts.registry;
+    registry.ncn_count = registry.ncn_count.saturating_add(1);
+
+    Ok(())
+}
+
+/// Remove a feed from a registry page and close its NcnRegistration marker. Takes the
+/// pubkey rather than the account so feeds that have since been closed can still be dropped.
+pub fn deregister_ncn(
+    ctx: Context<DeregisterNcn>,
+    ncn_performance_feed: Pubkey,
+) -> Result<()> {
+    let page = &mut ctx.accounts.registry_page;
+    let index = page
+        .position(&ncn_performance_feed)
+        .ok_or(NcnOracleError::NcnNotRegistered)?;
+
+    page.entries.swap_remove(index);
+    let registry = &mut ctx.accounts.registry;
+    registry.ncn_count = registry.ncn_count.saturating_sub(1);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeNcnRegistry<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + NcnRegistry::INIT_SPACE,
+        seeds = [b"ncn_registry"],
+        bump
+    )]
+    pub registry: Account<'info, NcnRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddNcnRegistryPage<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == registry.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ncn_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, NcnRegistry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + NcnRegistryPage::INIT_SPACE,
+        seeds = [b"ncn_registry_page", registry.page_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub registry_page: Account<'info, NcnRegistryPage>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterNcn<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == registry.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ncn_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, NcnRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"ncn_registry_page", registry_page.page_index.to_le_bytes().as_ref()],
+        bump = registry_page.bump
+    )]
+    pub registry_page: Account<'info, NcnRegistryPage>,
+
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+
+    /// Fails to init if the feed is already registered on any page
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + NcnRegistration::INIT_SPACE,
+        seeds = [b"ncn_registry_entry", ncn_performance_feed.key().as_ref()],
+        bump
+    )]
+    pub registration: Account<'info, NcnRegistration>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(ncn_performance_feed: Pubkey)]
+pub struct DeregisterNcn<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == registry.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ncn_registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, NcnRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"ncn_registry_page", registry_page.page_index.to_le_bytes().as_ref()],
+        bump = registry_page.bump
+    )]
+    pub registry_page: Account<'info, NcnRegistryPage>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"ncn_registry_entry", ncn_performance_feed.as_ref()],
+        bump = registration.bump,
+        constraint = registration.page_index == registry_page.page_index @ NcnOracleError::NcnNotRegistered
+    )]
+    pub registration: Account<'info, NcnRegistration>,
+}
@@ -12,11 +12,35 @@ pub fn update_signal_severity(
     let clock = Clock::get()?;
 
     feed.signal_severity = severity;
+    feed.signal_set_time = clock.unix_timestamp;
     feed.last_updated = clock.unix_timestamp;
 
     Ok(())
 }
 
+pub fn set_signal_decay_interval(
+    ctx: Context<UpdateSignalSeverity>,
+    decay_interval_secs: i64,
+) -> Result<()> {
+    require!(decay_interval_secs >= 0, NcnOracleError::InvalidDecayInterval);
+
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    feed.signal_decay_interval_secs = decay_interval_secs;
+
+    Ok(())
+}
+
+pub fn decay_signal(
+    ctx: Context<DecaySignal>,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_performance_feed;
+    let clock = Clock::get()?;
+
+    feed.apply_signal_decay(clock.unix_timestamp);
+
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct UpdateSignalSeverity<'info> {
     #[account(
@@ -30,3 +54,12 @@ pub struct UpdateSignalSeverity<'info> {
     )]
     pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
 }
+
+#[derive(Accounts)]
+pub struct DecaySignal<'info> {
+    #[account(
+        mut,
+        constraint = ncn_performance_feed.is_active @ NcnOracleError::FeedInactive
+    )]
+    pub ncn_performance_feed: Account<'info, NcnPerformanceFeed>,
+}
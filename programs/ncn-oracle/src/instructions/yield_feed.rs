@@ -23,13 +23,20 @@ pub fn initialize_ncn_yield_feed(
     feed.is_active = true;
     feed.last_updated = clock.unix_timestamp;
     feed.bump = ctx.bumps.ncn_yield_feed;
+    feed.yield_curve = Vec::new();
+    feed.consecutive_zero_apy_reads = 0;
+    feed.signal_severity = 0;
+    feed.net_apy_bps = i64::try_from(initial_apy_bps).map_err(|_| NcnOracleError::InvalidApy)?;
+    feed.regime_thresholds = RegimeThresholds::default();
+    feed.apy_running_variance = RunningVariance::default();
+    feed.history_cap = MAX_HISTORY_SAMPLES as u16;
 
     Ok(())
 }
 
 pub fn record_ncn_yield(
     ctx: Context<RecordNcnYield>,
-    current_apy_bps: u64,
+    net_apy_bps: i64,
     base_staking_apy_bps: u64,
     mev_apy_bps: u64,
     restaking_premium_bps: u64,
@@ -37,49 +44,124 @@ pub fn record_ncn_yield(
     let feed = &mut ctx.accounts.ncn_yield_feed;
     let clock = Clock::get()?;
 
-    feed.current_apy_bps = current_apy_bps;
+    let current_apy_bps = feed.apply_net_apy(net_apy_bps);
     feed.base_staking_apy_bps = base_staking_apy_bps;
     feed.mev_apy_bps = mev_apy_bps;
     feed.restaking_premium_bps = restaking_premium_bps;
 
     // Add to yield history
     let current_variance = feed.yield_variance_bps;
-    feed.yield_history.push(YieldSample {
+    feed.push_yield_sample(YieldSample {
         apy_bps: current_apy_bps,
         variance_bps: current_variance,
         timestamp: clock.unix_timestamp,
-    });
+    })?;
 
-    if feed.yield_history.len() > 168 {
-        feed.yield_history.remove(0);
+    // Recalculate averages
+    let (avg_7d, avg_30d) = feed.window_averages(clock.unix_timestamp);
+    if let Some(avg) = avg_7d {
+        feed.apy_7d_avg = avg;
+    }
+    if let Some(avg) = avg_30d {
+        feed.apy_30d_avg = avg;
     }
 
-    // Recalculate averages
-    let samples_7d: Vec<u64> = feed.yield_history
-        .iter()
-        .filter(|s| clock.unix_timestamp - s.timestamp <= 7 * 86400)
-        .map(|s| s.apy_bps)
-        .collect();
-
-    if !samples_7d.is_empty() {
-        feed.apy_7d_avg = samples_7d.iter().sum::<u64>() / samples_7d.len() as u64;
+    // Variance from the running accumulators (no pass over the history)
+    feed.yield_variance_bps = feed.apy_running_variance.stddev_bps();
+    feed.yield_regime = feed.current_regime();
+
+    // Surface a stuck-at-zero keeper instead of letting matchers treat it as "not synced"
+    // A negative net yield is a real reading, so only an exact zero counts as stuck
+    if feed.track_zero_apy_read(net_apy_bps.unsigned_abs()) {
+        msg!(
+            "NCN yield feed {} recorded {} consecutive zero APY reads -- suspected oracle failure",
+            feed.key(),
+            feed.consecutive_zero_apy_reads
+        );
+        emit!(SuspectedOracleFailure {
+            ncn_yield_feed: feed.key(),
+            ncn_address: feed.ncn_address,
+            consecutive_zero_reads: feed.consecutive_zero_apy_reads,
+            timestamp: clock.unix_timestamp,
+        });
     }
 
-    let samples_30d: Vec<u64> = feed.yield_history
-        .iter()
-        .filter(|s| clock.unix_timestamp - s.timestamp <= 30 * 86400)
-        .map(|s| s.apy_bps)
-        .collect();
+    feed.last_updated = clock.unix_timestamp;
+
+    Ok(())
+}
 
-    if !samples_30d.is_empty() {
-        feed.apy_30d_avg = samples_30d.iter().sum::<u64>() / samples_30d.len() as u64;
-    }
+/// Replace the yield term structure; an empty curve reverts to the flat current APY
+pub fn record_ncn_yield_curve(
+    ctx: Context<RecordNcnYield>,
+    points: Vec<YieldCurvePoint>,
+) -> Result<()> {
+    require!(NcnYieldFeed::is_valid_yield_curve(&points), NcnOracleError::InvalidYieldCurve);
 
-    // Recalculate variance and regime
-    feed.yield_variance_bps = feed.calculate_variance();
-    feed.yield_regime = NcnYieldFeed::classify_regime(feed.yield_variance_bps);
+    let feed = &mut ctx.accounts.ncn_yield_feed;
+    feed.yield_curve = points;
+    feed.last_updated = Clock::get()?.unix_timestamp;
 
-    feed.last_updated = clock.unix_timestamp;
+    Ok(())
+}
+
+/// Retune the variance bands behind `yield_regime` and reclassify the current variance
+pub fn set_regime_thresholds(
+    ctx: Context<RecordNcnYield>,
+    thresholds: RegimeThresholds,
+) -> Result<()> {
+    require!(thresholds.is_valid(), NcnOracleError::InvalidRegimeThresholds);
+
+    let feed = &mut ctx.accounts.ncn_yield_feed;
+    feed.regime_thresholds = thresholds;
+    feed.yield_regime = feed.current_regime();
+
+    Ok(())
+}
+
+/// Change how many yield samples the feed keeps; same limits as resize_performance_history
+pub fn resize_yield_history(
+    ctx: Context<ResizeYieldHistory>,
+    history_cap: u16,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_yield_feed;
+    require!(
+        is_valid_history_cap(history_cap, feed.yield_history.len()),
+        NcnOracleError::InvalidHistoryCap
+    );
+    feed.history_cap = history_cap;
+
+    Ok(())
+}
+
+/// Permissionless crank: recompute the averages, variance and regime from the stored
+/// history, restoring them after any bulk history change. The performance feed has no
+/// stored aggregates (`average_uptime` is computed on read), so only yield feeds need this.
+pub fn recompute_derived(
+    ctx: Context<RecomputeDerived>,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_yield_feed;
+    let clock = Clock::get()?;
+
+    feed.recompute_derived(clock.unix_timestamp);
+    msg!(
+        "NCN yield feed {} recomputed: 7d={} 30d={} variance={} regime={}",
+        feed.key(),
+        feed.apy_7d_avg,
+        feed.apy_30d_avg,
+        feed.yield_variance_bps,
+        feed.yield_regime
+    );
+
+    Ok(())
+}
+
+/// Keeper liveness proof: refreshes `last_updated` without recording a sample
+pub fn heartbeat_ncn_yield(
+    ctx: Context<RecordNcnYield>,
+) -> Result<()> {
+    let feed = &mut ctx.accounts.ncn_yield_feed;
+    feed.last_updated = Clock::get()?.unix_timestamp;
 
     Ok(())
 }
@@ -117,3 +199,33 @@ pub struct RecordNcnYield<'info> {
     )]
     pub ncn_yield_feed: Account<'info, NcnYieldFeed>,
 }
+
+#[derive(Accounts)]
+pub struct RecomputeDerived<'info> {
+    #[account(
+        mut,
+        constraint = ncn_yield_feed.is_active @ NcnOracleError::FeedInactive
+    )]
+    pub ncn_yield_feed: Account<'info, NcnYieldFeed>,
+}
+
+#[derive(Accounts)]
+#[instruction(history_cap: u16)]
+pub struct ResizeYieldHistory<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == ncn_yield_feed.authority @ NcnOracleError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = ncn_yield_feed.is_active @ NcnOracleError::FeedInactive,
+        realloc = history_account_space(NcnYieldFeed::INIT_SPACE, YieldSample::INIT_SPACE, history_cap),
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub ncn_yield_feed: Account<'info, NcnYieldFeed>,
+
+    pub system_program: Program<'info, System>,
+}
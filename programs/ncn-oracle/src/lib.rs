@@ -10,6 +10,10 @@ use instructions::performance_feed::*;
 use instructions::yield_feed::*;
 use instructions::aggregated_feed::*;
 use instructions::signal::*;
+use instructions::keeper_rewards::*;
+use instructions::registry::*;
+use instructions::queries::*;
+use state::{NcnPerformanceSample, RegimeThresholds, SampleIntervalStats, SlashingRecency, UptimeConfidenceBand, YieldCurvePoint, YieldDecomposition};
 
 #[program]
 pub mod ncn_oracle {
@@ -35,6 +39,7 @@ pub mod ncn_oracle {
         total_restaked_sol: u64,
         restaker_count: u32,
         slashing_event: bool,
+        slashing_fraction_e6: u64,
     ) -> Result<()> {
         instructions::performance_feed::record_ncn_performance(
             ctx,
@@ -42,9 +47,33 @@ pub mod ncn_oracle {
             total_restaked_sol,
             restaker_count,
             slashing_event,
+            slashing_fraction_e6,
         )
     }
 
+    /// Keeper heartbeat: marks the feed fresh during quiet periods without new data
+    pub fn heartbeat_ncn_performance(
+        ctx: Context<RecordNcnPerformance>,
+    ) -> Result<()> {
+        instructions::performance_feed::heartbeat_ncn_performance(ctx)
+    }
+
+    /// Authority grows (or shrinks, down to the samples held) the performance history cap,
+    /// reallocating the feed account to fit
+    pub fn resize_performance_history(
+        ctx: Context<ResizePerformanceHistory>,
+        history_cap: u16,
+    ) -> Result<()> {
+        instructions::performance_feed::resize_performance_history(ctx, history_cap)
+    }
+
+    /// Authority deactivates a performance feed (emits FeedDeactivated for matcher keepers)
+    pub fn deactivate_ncn_performance_feed(
+        ctx: Context<RecordNcnPerformance>,
+    ) -> Result<()> {
+        instructions::performance_feed::deactivate_ncn_performance_feed(ctx)
+    }
+
     // =========================================================================
     // NCN Yield Feed Instructions
     // =========================================================================
@@ -57,23 +86,62 @@ pub mod ncn_oracle {
         instructions::yield_feed::initialize_ncn_yield_feed(ctx, initial_apy_bps)
     }
 
-    /// Keeper records NCN yield data with decomposition
+    /// Keeper records NCN yield data with decomposition. The net APY may be negative
+    /// when slashing losses exceed rewards.
     pub fn record_ncn_yield(
         ctx: Context<RecordNcnYield>,
-        current_apy_bps: u64,
+        net_apy_bps: i64,
         base_staking_apy_bps: u64,
         mev_apy_bps: u64,
         restaking_premium_bps: u64,
     ) -> Result<()> {
         instructions::yield_feed::record_ncn_yield(
             ctx,
-            current_apy_bps,
+            net_apy_bps,
             base_staking_apy_bps,
             mev_apy_bps,
             restaking_premium_bps,
         )
     }
 
+    /// Keeper heartbeat: marks the feed fresh during quiet periods without new data
+    pub fn heartbeat_ncn_yield(
+        ctx: Context<RecordNcnYield>,
+    ) -> Result<()> {
+        instructions::yield_feed::heartbeat_ncn_yield(ctx)
+    }
+
+    /// Keeper sets the APY term structure (tenor_days -> apy_bps points)
+    pub fn record_ncn_yield_curve(
+        ctx: Context<RecordNcnYield>,
+        points: Vec<YieldCurvePoint>,
+    ) -> Result<()> {
+        instructions::yield_feed::record_ncn_yield_curve(ctx, points)
+    }
+
+    /// Feed authority retunes the variance thresholds used to classify the yield regime
+    pub fn set_regime_thresholds(
+        ctx: Context<RecordNcnYield>,
+        thresholds: RegimeThresholds,
+    ) -> Result<()> {
+        instructions::yield_feed::set_regime_thresholds(ctx, thresholds)
+    }
+
+    /// Authority grows (or shrinks, down to the samples held) the yield history cap
+    pub fn resize_yield_history(
+        ctx: Context<ResizeYieldHistory>,
+        history_cap: u16,
+    ) -> Result<()> {
+        instructions::yield_feed::resize_yield_history(ctx, history_cap)
+    }
+
+    /// Permissionless crank: recompute averages, variance and regime from the stored history
+    pub fn recompute_derived(
+        ctx: Context<RecomputeDerived>,
+    ) -> Result<()> {
+        instructions::yield_feed::recompute_derived(ctx)
+    }
+
     // =========================================================================
     // Aggregated Feed Instructions
     // =========================================================================
@@ -101,6 +169,20 @@ pub mod ncn_oracle {
         instructions::aggregated_feed::update_aggregated_feed(ctx, total_restaked_sol, weighted_avg_apy_bps)
     }
 
+    /// Recompute protocol TVL by summing tracked NCN feeds (passed as remaining accounts)
+    pub fn recompute_aggregated_tvl<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateAggregatedFeed<'info>>,
+    ) -> Result<()> {
+        instructions::aggregated_feed::recompute_aggregated_tvl(ctx)
+    }
+
+    /// Reset ncn_count to the number of tracked feeds; returns the corrected count
+    pub fn reconcile_count(
+        ctx: Context<ReconcileNcnCount>,
+    ) -> Result<u32> {
+        instructions::aggregated_feed::reconcile_count(ctx)
+    }
+
     // =========================================================================
     // Signal Instructions
     // =========================================================================
@@ -112,4 +194,197 @@ pub mod ncn_oracle {
     ) -> Result<()> {
         instructions::signal::update_signal_severity(ctx, severity)
     }
+
+    /// Configure how long a signal holds before auto-downgrading one level (0 = never)
+    pub fn set_signal_decay_interval(
+        ctx: Context<UpdateSignalSeverity>,
+        decay_interval_secs: i64,
+    ) -> Result<()> {
+        instructions::signal::set_signal_decay_interval(ctx, decay_interval_secs)
+    }
+
+    /// Permissionless crank: step signal severity down for elapsed decay intervals
+    pub fn decay_signal(
+        ctx: Context<DecaySignal>,
+    ) -> Result<()> {
+        instructions::signal::decay_signal(ctx)
+    }
+
+    // =========================================================================
+    // Keeper Reward Instructions
+    // =========================================================================
+
+    /// Set lamports accrued to the keeper per successful performance update (0 = disabled);
+    /// signed by the vault admin, not the keeper
+    pub fn set_keeper_reward(
+        ctx: Context<SetKeeperReward>,
+        reward_per_update_lamports: u64,
+    ) -> Result<()> {
+        instructions::keeper_rewards::set_keeper_reward(ctx, reward_per_update_lamports)
+    }
+
+    /// Create the per-NCN vault that funds keeper rewards; the registry authority signs
+    /// as its reward-setting admin
+    pub fn initialize_keeper_reward_vault(
+        ctx: Context<InitializeKeeperRewardVault>,
+    ) -> Result<()> {
+        instructions::keeper_rewards::initialize_keeper_reward_vault(ctx)
+    }
+
+    /// Deposit lamports into a keeper reward vault (any funder)
+    pub fn fund_keeper_reward_vault(
+        ctx: Context<FundKeeperRewardVault>,
+        amount: u64,
+    ) -> Result<()> {
+        instructions::keeper_rewards::fund_keeper_reward_vault(ctx, amount)
+    }
+
+    /// Keeper claims accrued rewards from the vault
+    pub fn claim_keeper_rewards(
+        ctx: Context<ClaimKeeperRewards>,
+    ) -> Result<()> {
+        instructions::keeper_rewards::claim_keeper_rewards(ctx)
+    }
+
+    // =========================================================================
+    // NCN Registry Instructions
+    // =========================================================================
+
+    /// Initialize the singleton NCN registry
+    pub fn initialize_ncn_registry(
+        ctx: Context<InitializeNcnRegistry>,
+    ) -> Result<()> {
+        instructions::registry::initialize_ncn_registry(ctx)
+    }
+
+    /// Append a new registry page (16 entries each)
+    pub fn add_ncn_registry_page(
+        ctx: Context<AddNcnRegistryPage>,
+    ) -> Result<()> {
+        instructions::registry::add_ncn_registry_page(ctx)
+    }
+
+    /// Register a performance feed (pubkey and name) on a registry page; a feed can be
+    /// registered on only one page
+    pub fn register_ncn(
+        ctx: Context<RegisterNcn>,
+    ) -> Result<()> {
+        instructions::registry::register_ncn(ctx)
+    }
+
+    /// Remove a performance feed from a registry page, closing its registration marker
+    pub fn deregister_ncn(
+        ctx: Context<DeregisterNcn>,
+        ncn_performance_feed: Pubkey,
+    ) -> Result<()> {
+        instructions::registry::deregister_ncn(ctx, ncn_performance_feed)
+    }
+
+    // =========================================================================
+    // Read-only Queries
+    // =========================================================================
+
+    /// Probability of default within `horizon_secs`, derived from uptime trend,
+    /// slashing frequency within the history window, and signal severity (0-1_000_000)
+    pub fn get_implied_default_probability(
+        ctx: Context<ReadNcnPerformanceFeed>,
+        horizon_secs: i64,
+    ) -> Result<u64> {
+        instructions::queries::get_implied_default_probability(ctx, horizon_secs)
+    }
+
+    /// Performance sample closest to, but not after, `timestamp`
+    pub fn get_sample_at_or_before(
+        ctx: Context<ReadNcnPerformanceFeed>,
+        timestamp: i64,
+    ) -> Result<NcnPerformanceSample> {
+        instructions::queries::get_sample_at_or_before(ctx, timestamp)
+    }
+
+    /// Min/max/average spacing of performance samples, flagging gaps over `max_interval_secs`
+    pub fn get_sample_interval_stats(
+        ctx: Context<ReadNcnPerformanceFeed>,
+        max_interval_secs: i64,
+    ) -> Result<SampleIntervalStats> {
+        instructions::queries::get_sample_interval_stats(ctx, max_interval_secs)
+    }
+
+    /// Seconds since the newest performance sample, so averages over a stale history
+    /// can be discounted
+    pub fn get_latest_sample_age(
+        ctx: Context<ReadNcnPerformanceFeed>,
+    ) -> Result<i64> {
+        instructions::queries::get_latest_sample_age(ctx)
+    }
+
+    /// Seconds since the last slashing event (i64::MAX if never slashed) and whether it
+    /// falls within the 24h `was_recently_slashed` window
+    pub fn get_slashing_recency(
+        ctx: Context<ReadNcnPerformanceFeed>,
+    ) -> Result<SlashingRecency> {
+        instructions::queries::get_slashing_recency(ctx)
+    }
+
+    /// 95% Wilson confidence band (lower_e6, upper_e6) around the uptime probability;
+    /// spans 0-1,000,000 with no history and narrows as samples accumulate
+    pub fn get_uptime_confidence_band(
+        ctx: Context<ReadNcnPerformanceFeed>,
+    ) -> Result<UptimeConfidenceBand> {
+        instructions::queries::get_uptime_confidence_band(ctx)
+    }
+
+    /// Packed page of performance history from sample `start` (0 max_samples = as many
+    /// as fit in return data); see state::HISTORY_EXPORT_LAYOUT_VERSION for the layout
+    pub fn export_performance_history(
+        ctx: Context<ReadNcnPerformanceFeed>,
+        start: u16,
+        max_samples: u8,
+    ) -> Result<Vec<u8>> {
+        instructions::queries::export_performance_history(ctx, start, max_samples)
+    }
+
+    /// Window yield stddev annualized assuming hourly samples (bps)
+    pub fn get_annualized_volatility(
+        ctx: Context<ReadNcnYieldFeed>,
+    ) -> Result<u64> {
+        instructions::queries::get_annualized_volatility(ctx)
+    }
+
+    /// APY for a lockup tenor, interpolated on the feed's yield curve (bps)
+    pub fn get_apy_for_tenor(
+        ctx: Context<ReadNcnYieldFeed>,
+        tenor_days: u16,
+    ) -> Result<u64> {
+        instructions::queries::get_apy_for_tenor(ctx, tenor_days)
+    }
+
+    /// Packed page of yield history from sample `start`, same paging as the performance export
+    pub fn export_yield_history(
+        ctx: Context<ReadNcnYieldFeed>,
+        start: u16,
+        max_samples: u8,
+    ) -> Result<Vec<u8>> {
+        instructions::queries::export_yield_history(ctx, start, max_samples)
+    }
+
+    /// Base / MEV / premium APY with MEV and premium shares of the total (bps)
+    pub fn get_yield_decomposition(
+        ctx: Context<ReadNcnYieldFeed>,
+    ) -> Result<YieldDecomposition> {
+        instructions::queries::get_yield_decomposition(ctx)
+    }
+
+    /// Current APY less expected annual slashing loss from the NCN's performance feed (bps)
+    pub fn get_slashing_adjusted_apy(
+        ctx: Context<ReadNcnYieldAndPerformance>,
+    ) -> Result<u64> {
+        instructions::queries::get_slashing_adjusted_apy(ctx)
+    }
+
+    /// Seconds since the aggregated feed's stalest member was updated, as of its last recompute
+    pub fn get_max_member_staleness(
+        ctx: Context<ReadAggregatedFeed>,
+    ) -> Result<i64> {
+        instructions::queries::get_max_member_staleness(ctx)
+    }
 }
@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use matcher_utils::isqrt;
+
+use crate::errors::NcnOracleError;
 
 // =============================================================================
 // NCN Oracle State — Tracks NCN performance and yield data for restaking risk
@@ -34,7 +37,7 @@ pub struct NcnPerformanceFeed {
     /// Number of restakers in this NCN
     pub restaker_count: u32,
 
-    /// Performance history (max 168 = 7 days hourly)
+    /// Performance history (168 = 7 days hourly at init; up to `history_cap` after resize)
     #[max_len(168)]
     pub performance_history: Vec<NcnPerformanceSample>,
 
@@ -52,6 +55,29 @@ pub struct NcnPerformanceFeed {
 
     /// PDA bump seed
     pub bump: u8,
+
+    /// Timestamp the current signal severity was set (or last decayed)
+    pub signal_set_time: i64,
+
+    /// Seconds without a new incident before severity steps down one level (0 = no decay)
+    pub signal_decay_interval_secs: i64,
+
+    /// Fraction of stake slashed in the most recent slashing event (0-1,000,000)
+    pub last_slashing_fraction_e6: u64,
+
+    /// Lamports accrued to the keeper per successful performance update (0 = disabled)
+    pub keeper_reward_per_update_lamports: u64,
+
+    /// Keeper rewards accrued but not yet claimed (in lamports)
+    pub keeper_rewards_lamports: u64,
+
+    /// Samples kept in `performance_history` (0 = MAX_HISTORY_SAMPLES); raised by
+    /// resize_performance_history, which grows the account to fit
+    pub history_cap: u16,
+
+    /// Timestamps of the most recent slashing events, oldest first (0 = empty slot), so
+    /// slashing rates count only the slashes inside the history window
+    pub recent_slashing_times: [i64; RECENT_SLASHING_TIMES],
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
@@ -66,6 +92,39 @@ pub struct NcnPerformanceSample {
     pub timestamp: i64,
 }
 
+/// Spacing between consecutive performance samples, so consumers can tell whether a
+/// history average covers a contiguous window or a sparse one
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SampleIntervalStats {
+    pub sample_count: u32,
+    pub min_interval_secs: i64,
+    pub max_interval_secs: i64,
+    pub avg_interval_secs: i64,
+    /// Some interval exceeds the caller's max_interval_secs
+    pub has_gaps: bool,
+}
+
+/// How long ago the NCN was last slashed, with the feed's own 24h notion of "recent"
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlashingRecency {
+    /// Seconds since `last_slashing_time` (0 if in the future); i64::MAX if never slashed
+    pub seconds_since_last_slashing: i64,
+    /// Same answer as `NcnPerformanceFeed::was_recently_slashed`
+    pub was_recently_slashed: bool,
+}
+
+/// 95% confidence band around the uptime point estimate (see
+/// `NcnPerformanceFeed::uptime_confidence_band`)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UptimeConfidenceBand {
+    /// Conservative (lower) uptime bound (0-1,000,000)
+    pub lower_e6: u64,
+    /// Optimistic (upper) uptime bound (0-1,000,000)
+    pub upper_e6: u64,
+    /// History samples the band is based on
+    pub sample_count: u32,
+}
+
 /// Per-NCN yield feed — tracks APY, variance, yield decomposition
 #[account]
 #[derive(InitSpace)]
@@ -76,7 +135,7 @@ pub struct NcnYieldFeed {
     /// Fragmetric/Jito NCN identifier
     pub ncn_address: Pubkey,
 
-    /// Current APY in basis points (e.g., 800 = 8%)
+    /// Current APY in basis points (e.g., 800 = 8%), floored at zero; see `net_apy_bps`
     pub current_apy_bps: u64,
 
     /// 7-day average APY in bps
@@ -91,7 +150,7 @@ pub struct NcnYieldFeed {
     /// Yield regime: 0=VeryLow, 1=Low, 2=Normal, 3=High, 4=Extreme
     pub yield_regime: u8,
 
-    /// Yield history (max 168 = 7 days hourly)
+    /// Yield history (168 = 7 days hourly at init; up to `history_cap` after resize)
     #[max_len(168)]
     pub yield_history: Vec<YieldSample>,
 
@@ -112,6 +171,31 @@ pub struct NcnYieldFeed {
 
     /// PDA bump seed
     pub bump: u8,
+
+    /// Term structure: APY per lockup tenor, sorted by strictly increasing tenor (max 8)
+    #[max_len(8)]
+    pub yield_curve: Vec<YieldCurvePoint>,
+
+    /// Consecutive recorded samples with `current_apy_bps == 0`
+    pub consecutive_zero_apy_reads: u16,
+
+    /// Signal severity on this feed (0=NONE..3=CRITICAL); HIGH while APY reads look stuck at zero
+    pub signal_severity: u8,
+
+    /// Net APY in bps, negative when slashing losses exceed rewards. `current_apy_bps`,
+    /// the history and the averages carry this value floored at zero.
+    pub net_apy_bps: i64,
+
+    /// Variance bands used to classify `yield_regime`
+    pub regime_thresholds: RegimeThresholds,
+
+    /// Welford accumulators over the APYs in `yield_history`, kept in step by
+    /// `push_yield_sample` so the variance is updated in O(1) per sample
+    pub apy_running_variance: RunningVariance,
+
+    /// Samples kept in `yield_history` (0 = MAX_HISTORY_SAMPLES); raised by
+    /// resize_yield_history, which grows the account to fit
+    pub history_cap: u16,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
@@ -124,6 +208,130 @@ pub struct YieldSample {
     pub timestamp: i64,
 }
 
+/// Yield components with MEV and premium shares in bps of `current_apy_bps`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct YieldDecomposition {
+    pub base_bps: u64,
+    pub mev_bps: u64,
+    pub premium_bps: u64,
+    pub mev_share_pct: u64,
+    pub premium_share_pct: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct YieldCurvePoint {
+    /// Lockup tenor in days
+    pub tenor_days: u16,
+    /// APY in bps for this tenor
+    pub apy_bps: u64,
+}
+
+/// Inclusive upper variance bounds (bps) for the VeryLow, Low, Normal and High
+/// regimes; variance above the last bound is Extreme. Must be strictly increasing.
+/// A variance equal to a bound belongs to the lower (calmer) regime: with the defaults
+/// 50 is VeryLow and 51 is Low.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct RegimeThresholds {
+    pub upper_bps: [u64; 4],
+}
+
+impl Default for RegimeThresholds {
+    fn default() -> Self {
+        Self { upper_bps: [50, 150, 400, 800] }
+    }
+}
+
+impl RegimeThresholds {
+    pub fn is_valid(&self) -> bool {
+        self.upper_bps.windows(2).all(|w| w[0] < w[1])
+    }
+
+    /// Regime (0=VeryLow..4=Extreme) for a variance in bps
+    pub fn classify(&self, variance_bps: u64) -> u8 {
+        self.upper_bps.iter().take_while(|&&upper| variance_bps > upper).count() as u8
+    }
+}
+
+/// Welford's online mean/M2 over a sliding window of bps values, in fixed point so every
+/// validator computes the same result. Samples leaving the window are removed with the
+/// inverse update.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, Default, PartialEq, Eq)]
+pub struct RunningVariance {
+    /// Samples currently accumulated
+    pub count: u32,
+    /// Mean in bps * 1e6
+    pub mean_e6: i128,
+    /// Sum of squared deviations from the mean in bps^2 * 1e12
+    pub m2_e12: i128,
+}
+
+impl RunningVariance {
+    const SCALE: i128 = 1_000_000;
+
+    pub fn add(&mut self, value_bps: u64) {
+        let x = (value_bps as i128).saturating_mul(Self::SCALE);
+        self.count += 1;
+        let delta = x - self.mean_e6;
+        self.mean_e6 += delta / self.count as i128;
+        self.m2_e12 = self.m2_e12.saturating_add(delta.saturating_mul(x - self.mean_e6));
+    }
+
+    /// Inverse of `add` for a value previously added (the oldest sample leaving the window)
+    pub fn remove(&mut self, value_bps: u64) {
+        if self.count <= 1 {
+            *self = Self::default();
+            return;
+        }
+        let x = (value_bps as i128).saturating_mul(Self::SCALE);
+        self.count -= 1;
+        let delta = x - self.mean_e6;
+        self.mean_e6 -= delta / self.count as i128;
+        // Fixed-point rounding can leave M2 a hair below zero once the window is flat
+        self.m2_e12 = self.m2_e12.saturating_sub(delta.saturating_mul(x - self.mean_e6)).max(0);
+    }
+
+    /// Sample standard deviation in bps (0 until MIN_SAMPLES_FOR_REGIME samples exist)
+    pub fn stddev_bps(&self) -> u64 {
+        if (self.count as usize) < MIN_SAMPLES_FOR_REGIME {
+            return 0;
+        }
+        let variance_e12 = self.m2_e12 / (self.count as i128 - 1);
+        let stddev_e6 = isqrt(variance_e12.max(0) as u128) / Self::SCALE as u128;
+        u64::try_from(stddev_e6).unwrap_or(u64::MAX)
+    }
+}
+
+/// Emitted while a yield feed keeps recording a zero APY — usually a broken keeper
+/// rather than a real 0% yield
+#[event]
+pub struct SuspectedOracleFailure {
+    pub ncn_yield_feed: Pubkey,
+    pub ncn_address: Pubkey,
+    pub consecutive_zero_reads: u16,
+    pub timestamp: i64,
+}
+
+/// Emitted when a performance sample lands long after the previous one; history
+/// averages spanning `gap_secs` blend data from either side of the outage
+#[event]
+pub struct FeedResumedAfterGap {
+    pub ncn_performance_feed: Pubkey,
+    pub ncn_address: Pubkey,
+    pub previous_sample_timestamp: i64,
+    pub gap_secs: i64,
+    pub timestamp: i64,
+}
+
+/// Emitted when a performance feed is deactivated. Matchers referencing the feed keep
+/// quoting until they go stale unless a keeper reacts to this event by calling the
+/// matcher's check-oracle-active instruction, which halts quoting immediately.
+#[event]
+pub struct FeedDeactivated {
+    pub ncn_performance_feed: Pubkey,
+    pub ncn_address: Pubkey,
+    pub timestamp: i64,
+}
+
 /// Protocol-level aggregated restaking feed
 #[account]
 #[derive(InitSpace)]
@@ -152,9 +360,235 @@ pub struct AggregatedRestakingFeed {
 
     /// PDA bump seed
     pub bump: u8,
+
+    /// Oldest `last_updated` across the member feeds at the last recompute; the
+    /// aggregate is only as fresh as this
+    pub oldest_member_update: i64,
+}
+
+/// Per-NCN vault funding keeper rewards — PDA seeded by the performance feed
+#[account]
+#[derive(InitSpace)]
+pub struct KeeperRewardVault {
+    /// Performance feed whose keeper this vault pays
+    pub ncn_performance_feed: Pubkey,
+
+    /// Admin that sets the per-update reward: the registry authority, never the paid
+    /// keeper (the feed authority)
+    pub authority: Pubkey,
+
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Root of the on-chain NCN index — singleton PDA owning the registry pages
+#[account]
+#[derive(InitSpace)]
+pub struct NcnRegistry {
+    /// Authority that can add pages and (de)register NCNs
+    pub authority: Pubkey,
+
+    /// Number of pages created; pages are indexed 0..page_count
+    pub page_count: u16,
+
+    /// NCNs registered across all pages
+    pub ncn_count: u32,
+
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// One entry in a registry page
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
+pub struct NcnRegistryEntry {
+    /// NcnPerformanceFeed account for this NCN
+    pub ncn_performance_feed: Pubkey,
+
+    /// NCN name copied from the feed at registration
+    #[max_len(32)]
+    pub ncn_name: String,
+}
+
+/// Marker that a performance feed is registered — PDA seeded by the feed, so a feed can
+/// hold only one registry entry across all pages
+#[account]
+#[derive(InitSpace)]
+pub struct NcnRegistration {
+    /// NcnPerformanceFeed this marker registers
+    pub ncn_performance_feed: Pubkey,
+
+    /// Page holding the feed's entry
+    pub page_index: u16,
+
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Fixed-size page of registry entries — PDA seeded by its page index
+#[account]
+#[derive(InitSpace)]
+pub struct NcnRegistryPage {
+    /// Position of this page in the registry
+    pub page_index: u16,
+
+    /// Registered NCNs (max MAX_REGISTRY_PAGE_ENTRIES)
+    #[max_len(16)]
+    pub entries: Vec<NcnRegistryEntry>,
+
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+/// Slash fractions (e6) at or above which a slashing event escalates the signal
+pub const SLASH_FRACTION_HIGH_E6: u64 = 10_000;       // 1% of stake
+pub const SLASH_FRACTION_CRITICAL_E6: u64 = 100_000;  // 10% of stake
+
+/// Hourly history samples in a (365-day) year, used to annualize window volatility
+pub const HOURLY_SAMPLES_PER_YEAR: u128 = 8_760;
+
+/// Implied default model: downtime and slash frequency are scaled down to a daily hazard
+pub const DOWNTIME_HAZARD_DIVISOR: u128 = 100;
+pub const SLASH_HAZARD_DIVISOR: u128 = 10;
+
+/// Calendar constants for rate annualization
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const SECONDS_PER_YEAR: i64 = 365 * SECONDS_PER_DAY;
+
+/// Samples kept in performance and yield history (7 days hourly); matches the #[max_len]
+/// the account is created with
+pub const MAX_HISTORY_SAMPLES: usize = 168;
+
+/// Largest history_cap a feed can be resized to (30 days hourly)
+pub const MAX_RESIZED_HISTORY_SAMPLES: usize = 720;
+
+/// Slashing timestamps a performance feed remembers. Windowed slashing rates count at
+/// most this many events; 16 slashes in even a 30-day window already price as certain
+pub const RECENT_SLASHING_TIMES: usize = 16;
+
+/// Entries per NcnRegistryPage; matches the #[max_len]
+pub const MAX_REGISTRY_PAGE_ENTRIES: usize = 16;
+
+/// Maximum tenor points on an NcnYieldFeed yield curve
+pub const MAX_YIELD_CURVE_POINTS: usize = 8;
+
+/// Yield samples needed before variance (and so the regime) is trusted; below this the
+/// regime stays Normal so a couple of early outliers cannot push a new feed to Extreme
+pub const MIN_SAMPLES_FOR_REGIME: usize = 24;
+
+/// Spacing between performance samples (six missed hourly updates) beyond which a new
+/// sample emits FeedResumedAfterGap
+pub const RESUME_GAP_THRESHOLD_SECS: i64 = 6 * 3_600;
+
+/// Consecutive zero-APY samples after which a yield feed is flagged as a suspected failure
+pub const ZERO_APY_FAILURE_THRESHOLD: u16 = 3;
+
+/// Packed history export (all integers LE). Each page is a 6-byte header
+///   [0] layout version, [1..3] total samples in history (u16),
+///   [3..5] index of the first sample in this page (u16), [5] samples in this page (u8)
+/// followed by fixed-size samples:
+///   performance (28 bytes): uptime_e6 u64, total_restaked_sol u64, restaker_count u32, timestamp i64
+///   yield       (24 bytes): apy_bps u64, variance_bps u64, timestamp i64
+/// Pages are sized to fit return data; callers page through with `start`.
+pub const HISTORY_EXPORT_LAYOUT_VERSION: u8 = 1;
+pub const HISTORY_EXPORT_HEADER_SIZE: usize = 6;
+pub const PERFORMANCE_SAMPLE_PACKED_SIZE: usize = 28;
+pub const YIELD_SAMPLE_PACKED_SIZE: usize = 24;
+/// Largest export page: the return data limit less the 4-byte Vec<u8> length prefix
+pub const MAX_HISTORY_EXPORT_BYTES: usize = anchor_lang::solana_program::program::MAX_RETURN_DATA - 4;
+
+/// Pack `samples[start..]` into one export page of at most `max_samples` samples (0 = as
+/// many as fit). A start past the end yields an empty page.
+fn export_history_page<T>(
+    samples: &[T],
+    start: u16,
+    max_samples: u8,
+    packed_size: usize,
+    pack: fn(&T, &mut Vec<u8>),
+) -> Vec<u8> {
+    let capacity = (MAX_HISTORY_EXPORT_BYTES - HISTORY_EXPORT_HEADER_SIZE) / packed_size;
+    let limit = match max_samples {
+        0 => capacity,
+        n => std::cmp::min(n as usize, capacity),
+    };
+    let page = samples.get(start as usize..).unwrap_or(&[]);
+    let page = &page[..std::cmp::min(page.len(), limit)];
+
+    let mut out = Vec::with_capacity(HISTORY_EXPORT_HEADER_SIZE + page.len() * packed_size);
+    out.push(HISTORY_EXPORT_LAYOUT_VERSION);
+    out.extend_from_slice(&(samples.len() as u16).to_le_bytes());
+    out.extend_from_slice(&start.to_le_bytes());
+    out.push(page.len() as u8);
+    for sample in page {
+        pack(sample, &mut out);
+    }
+    out
+}
+
+/// Append `samples` to a history capped at `cap` samples, keeping the newest in order.
+/// The evicted prefix is dropped in one shift, so backfilling m samples costs O(n + m)
+/// rather than a remove(0) per eviction.
+pub fn append_capped_history<T: Clone>(history: &mut Vec<T>, samples: &[T], cap: usize) {
+    if samples.len() >= cap {
+        history.clear();
+        history.extend_from_slice(&samples[samples.len() - cap..]);
+        return;
+    }
+    let evict = (history.len() + samples.len()).saturating_sub(cap);
+    history.drain(..evict);
+    history.extend_from_slice(samples);
+}
+
+/// Effective history cap for a stored `history_cap` (0 = MAX_HISTORY_SAMPLES)
+pub fn effective_history_cap(history_cap: u16) -> usize {
+    match history_cap {
+        0 => MAX_HISTORY_SAMPLES,
+        cap => cap as usize,
+    }
+}
+
+/// Whether `new_cap` is a valid history cap for a history currently holding `len`
+/// samples: at most MAX_RESIZED_HISTORY_SAMPLES and never below the samples already kept
+pub fn is_valid_history_cap(new_cap: u16, len: usize) -> bool {
+    let new_cap = new_cap as usize;
+    new_cap > 0 && new_cap >= len && new_cap <= MAX_RESIZED_HISTORY_SAMPLES
+}
+
+/// Account space for a feed whose InitSpace reserves MAX_HISTORY_SAMPLES samples of
+/// `sample_size`, holding `history_cap` instead. Caps below the initial reservation keep it.
+pub fn history_account_space(init_space: usize, sample_size: usize, history_cap: u16) -> usize {
+    8 + init_space + effective_history_cap(history_cap).saturating_sub(MAX_HISTORY_SAMPLES) * sample_size
+}
+
+impl NcnPerformanceSample {
+    fn pack_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.uptime_e6.to_le_bytes());
+        out.extend_from_slice(&self.total_restaked_sol.to_le_bytes());
+        out.extend_from_slice(&self.restaker_count.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
+}
+
+impl YieldSample {
+    fn pack_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.apy_bps.to_le_bytes());
+        out.extend_from_slice(&self.variance_bps.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+    }
 }
 
 impl NcnPerformanceFeed {
+    /// Minimum signal severity implied by a slash of the given size:
+    /// any slash is LOW, >= 1% HIGH, >= 10% CRITICAL
+    pub fn severity_for_slash(fraction_e6: u64) -> u8 {
+        if fraction_e6 >= SLASH_FRACTION_CRITICAL_E6 {
+            3
+        } else if fraction_e6 >= SLASH_FRACTION_HIGH_E6 {
+            2
+        } else {
+            1
+        }
+    }
+
     /// Check if the NCN has been slashed recently (within last 24h)
     pub fn was_recently_slashed(&self, current_time: i64) -> bool {
         if self.total_slashing_events == 0 {
@@ -171,23 +605,383 @@ impl NcnPerformanceFeed {
         let sum: u128 = self.performance_history.iter().map(|s| s.uptime_e6 as u128).sum();
         (sum / self.performance_history.len() as u128) as u64
     }
+
+    /// Min/max/average spacing between history samples; all zero with fewer than two samples
+    pub fn sample_interval_stats(&self, max_interval_secs: i64) -> SampleIntervalStats {
+        let history = &self.performance_history;
+        let mut stats = SampleIntervalStats {
+            sample_count: history.len() as u32,
+            ..Default::default()
+        };
+        if history.len() < 2 {
+            return stats;
+        }
+        let intervals = history.windows(2).map(|w| w[1].timestamp.saturating_sub(w[0].timestamp));
+        stats.min_interval_secs = intervals.clone().min().unwrap_or(0);
+        stats.max_interval_secs = intervals.max().unwrap_or(0);
+        let span = history[history.len() - 1].timestamp.saturating_sub(history[0].timestamp);
+        stats.avg_interval_secs = span / (history.len() as i64 - 1);
+        stats.has_gaps = stats.max_interval_secs > max_interval_secs;
+        stats
+    }
+
+    /// Time since the last slashing event as of `current_time`
+    pub fn slashing_recency(&self, current_time: i64) -> SlashingRecency {
+        let seconds_since_last_slashing = if self.total_slashing_events == 0 {
+            i64::MAX
+        } else {
+            current_time.saturating_sub(self.last_slashing_time).max(0)
+        };
+        SlashingRecency {
+            seconds_since_last_slashing,
+            was_recently_slashed: self.was_recently_slashed(current_time),
+        }
+    }
+
+    /// 95% Wilson score interval (lower, upper) around `uptime_probability_e6`, treating
+    /// each history sample as one observation, in integer math:
+    ///   center = (n*p + z^2/2) / (n + z^2)
+    ///   half   = z * sqrt(n*p*(1-p) + z^2/4) / (n + z^2)
+    /// with z = 1.96. The band narrows as samples accumulate; with no history it spans
+    /// the whole 0..=1e6 range. Rounds outward (lower down, upper up).
+    pub fn uptime_confidence_band(&self) -> (u64, u64) {
+        const MAX: u128 = 1_000_000;
+        const Z_E2: u128 = 196;
+        const Z2_E4: u128 = 38_416;
+
+        let n = self.performance_history.len() as u128;
+        let p = (self.uptime_probability_e6 as u128).min(MAX);
+        // (n + z^2) scaled by 1e4; n <= 720 keeps every product far below u128::MAX
+        let denom = n * 10_000 + Z2_E4;
+        let center = (n * p * 10_000 + Z2_E4 * MAX / 2) / denom;
+        // n*p*(1-p) + z^2/4 in e12, so its root is in e6
+        let root = isqrt(n * p * (MAX - p) + Z2_E4 * MAX * MAX / 10_000 / 4);
+        let half = (Z_E2 * 100 * root).div_ceil(denom) + 1;
+        (center.saturating_sub(half) as u64, (center + half).min(MAX) as u64)
+    }
+
+    /// Seconds since the newest history sample (0 if it is in the future); i64::MAX with
+    /// no history, so an empty feed never reads as fresh
+    pub fn latest_sample_age(&self, current_time: i64) -> i64 {
+        match self.performance_history.last() {
+            Some(sample) => current_time.saturating_sub(sample.timestamp).max(0),
+            None => i64::MAX,
+        }
+    }
+
+    /// Gap a sample recorded at `current_time` would leave after the newest one, if it
+    /// exceeds RESUME_GAP_THRESHOLD_SECS; averages spanning it mix pre- and post-outage data
+    pub fn resume_gap(&self, current_time: i64) -> Option<i64> {
+        if self.performance_history.is_empty() {
+            return None;
+        }
+        let gap = self.latest_sample_age(current_time);
+        (gap > RESUME_GAP_THRESHOLD_SECS).then_some(gap)
+    }
+
+    /// True if any two consecutive samples are more than `max_interval_secs` apart
+    pub fn has_gaps(&self, max_interval_secs: i64) -> bool {
+        self.sample_interval_stats(max_interval_secs).has_gaps
+    }
+
+    /// Latest history sample taken at or before `timestamp` (history is chronological)
+    pub fn sample_at_or_before(&self, timestamp: i64) -> Option<&NcnPerformanceSample> {
+        let idx = self
+            .performance_history
+            .partition_point(|s| s.timestamp <= timestamp);
+        idx.checked_sub(1).map(|i| &self.performance_history[i])
+    }
+
+    /// Probability (0-1,000,000) that the NCN defaults within `horizon_secs`.
+    ///
+    /// First-order additive daily-hazard model:
+    ///   downtime_e6  = 1e6 - min(current uptime, history average)   (a falling trend counts immediately)
+    ///   slash_rate   = slashing events per day inside the observation window: from the oldest
+    ///                  history sample to now (7 days hourly by default, up to 30 after a
+    ///                  resize), or the last day when the history is shorter or empty
+    ///   hazard_day   = downtime_e6 / 100 + slash_rate_e6 / 10
+    ///   hazard_day  *= signal multiplier (NONE 1x, LOW 1.5x, HIGH 2x, CRITICAL 4x)
+    ///   PD(H)        = min(hazard_day * H / 1 day, 1e6)
+    /// Linear in H (no compounding), so it overstates PD for long horizons — conservative by design.
+    pub fn implied_default_probability(&self, horizon_secs: i64, current_time: i64) -> u64 {
+        const MAX: u128 = 1_000_000;
+        const DAY: u128 = 86_400;
+
+        let uptime = std::cmp::min(self.uptime_probability_e6, self.average_uptime()) as u128;
+        let downtime_e6 = MAX.saturating_sub(uptime);
+
+        let span_secs = self.observation_span_secs(current_time);
+        let slash_rate_e6 = (self.slashing_events_in_window(current_time) as u128) * MAX * DAY / span_secs;
+
+        let hazard_day = downtime_e6 / DOWNTIME_HAZARD_DIVISOR + slash_rate_e6 / SLASH_HAZARD_DIVISOR;
+        let signal_mult_pct: u128 = match self.signal_severity {
+            0 => 100,
+            1 => 150,
+            2 => 200,
+            _ => 400,
+        };
+        let hazard_day = hazard_day * signal_mult_pct / 100;
+
+        let horizon = std::cmp::max(horizon_secs, 0) as u128;
+        std::cmp::min(hazard_day.saturating_mul(horizon) / DAY, MAX) as u64
+    }
+
+    /// Seconds covered by the performance history (oldest sample to now), at least one day:
+    /// the window slashing rates are measured over
+    fn observation_span_secs(&self, current_time: i64) -> u128 {
+        let oldest = self
+            .performance_history
+            .first()
+            .map(|s| s.timestamp)
+            .unwrap_or(current_time);
+        std::cmp::max(current_time.saturating_sub(oldest), SECONDS_PER_DAY) as u128
+    }
+
+    /// Remember a slashing event at `timestamp`, dropping the oldest one remembered
+    pub fn record_slashing_time(&mut self, timestamp: i64) {
+        self.recent_slashing_times.rotate_left(1);
+        self.recent_slashing_times[RECENT_SLASHING_TIMES - 1] = timestamp;
+    }
+
+    /// Slashing events inside the observation window ending at `current_time` (see
+    /// observation_span_secs), at most RECENT_SLASHING_TIMES
+    pub fn slashing_events_in_window(&self, current_time: i64) -> u32 {
+        let span = self.observation_span_secs(current_time) as i64;
+        let window_start = current_time.saturating_sub(span);
+        self.recent_slashing_times
+            .iter()
+            .filter(|&&t| t != 0 && t >= window_start)
+            .count() as u32
+    }
+
+    /// Expected annual slashing loss in bps of stake: the slashing frequency inside the
+    /// history window, annualized over that window, times the most recent slash size
+    pub fn expected_slashing_loss_bps(&self, current_time: i64) -> u64 {
+        let events_e6_per_year = (self.slashing_events_in_window(current_time) as u128) * 1_000_000
+            * SECONDS_PER_YEAR as u128
+            / self.observation_span_secs(current_time);
+        // events/year * fraction_e6 / 1e6 -> fraction of stake; * 10_000 / 1e6 -> bps
+        let loss_bps = events_e6_per_year * self.last_slashing_fraction_e6 as u128 / 1_000_000 / 100;
+        u64::try_from(loss_bps).unwrap_or(u64::MAX)
+    }
+
+    /// Add the per-update keeper reward to the accrued balance (None on overflow)
+    pub fn accrue_keeper_reward(&mut self) -> Option<u64> {
+        self.keeper_rewards_lamports = self
+            .keeper_rewards_lamports
+            .checked_add(self.keeper_reward_per_update_lamports)?;
+        Some(self.keeper_rewards_lamports)
+    }
+
+    /// Rewards payable now: accrued, limited by vault lamports above rent exemption
+    pub fn claimable_keeper_rewards(&self, vault_lamports: u64, rent_exempt_minimum: u64) -> u64 {
+        std::cmp::min(
+            self.keeper_rewards_lamports,
+            vault_lamports.saturating_sub(rent_exempt_minimum),
+        )
+    }
+
+    /// Step signal severity down one level per full decay interval elapsed
+    /// since the signal was last set. No-op when decay is disabled.
+    pub fn apply_signal_decay(&mut self, current_time: i64) {
+        let interval = self.signal_decay_interval_secs;
+        if interval <= 0 || self.signal_severity == 0 {
+            return;
+        }
+        let elapsed = current_time.saturating_sub(self.signal_set_time);
+        if elapsed < interval {
+            return;
+        }
+        let steps = std::cmp::min(elapsed / interval, self.signal_severity as i64);
+        self.signal_severity -= steps as u8;
+        self.signal_set_time = self.signal_set_time.saturating_add(steps * interval);
+    }
+
+    /// One packed page of `performance_history` (see HISTORY_EXPORT_LAYOUT_VERSION)
+    pub fn export_history(&self, start: u16, max_samples: u8) -> Vec<u8> {
+        export_history_page(
+            &self.performance_history,
+            start,
+            max_samples,
+            PERFORMANCE_SAMPLE_PACKED_SIZE,
+            NcnPerformanceSample::pack_into,
+        )
+    }
 }
 
 impl NcnYieldFeed {
-    /// Classify yield regime based on variance
-    pub fn classify_regime(variance_bps: u64) -> u8 {
-        match variance_bps {
-            0..=50 => 0,      // VeryLow
-            51..=150 => 1,    // Low
-            151..=400 => 2,   // Normal
-            401..=800 => 3,   // High
-            _ => 4,           // Extreme
+    /// Record a net APY that may be negative. Sets `current_apy_bps` to the net value
+    /// floored at zero and returns it.
+    pub fn apply_net_apy(&mut self, net_apy_bps: i64) -> u64 {
+        self.net_apy_bps = net_apy_bps;
+        self.current_apy_bps = net_apy_bps.max(0) as u64;
+        self.current_apy_bps
+    }
+
+    /// Track a recorded APY sample for stuck-zero detection. Returns true while the
+    /// consecutive zero reads are at or past `ZERO_APY_FAILURE_THRESHOLD` (severity is
+    /// raised to at least HIGH); a non-zero read resets the counter and clears the signal.
+    pub fn track_zero_apy_read(&mut self, apy_bps: u64) -> bool {
+        if apy_bps != 0 {
+            self.consecutive_zero_apy_reads = 0;
+            self.signal_severity = 0;
+            return false;
+        }
+        self.consecutive_zero_apy_reads = self.consecutive_zero_apy_reads.saturating_add(1);
+        if self.consecutive_zero_apy_reads < ZERO_APY_FAILURE_THRESHOLD {
+            return false;
+        }
+        self.signal_severity = std::cmp::max(self.signal_severity, 2);
+        true
+    }
+
+    /// Annualized yield volatility in bps.
+    ///
+    /// `yield_variance_bps` is the sample stddev over the history window. Assuming
+    /// hourly samples (8_760 per year) and independent increments, it is scaled by
+    ///   sqrt(SAMPLES_PER_YEAR / samples_in_window)
+    /// i.e. the window is treated as one draw of `samples_in_window` hours. Returns 0
+    /// with fewer than 2 samples (no stddev yet).
+    pub fn annualized_volatility_bps(&self) -> u64 {
+        let n = self.yield_history.len() as u128;
+        if n < 2 {
+            return 0;
+        }
+        // sqrt(ratio) in e6 = isqrt(ratio * 1e12)
+        let scale_e6 = isqrt(HOURLY_SAMPLES_PER_YEAR * 1_000_000_000_000 / n);
+        let vol = (self.yield_variance_bps as u128).saturating_mul(scale_e6) / 1_000_000;
+        u64::try_from(vol).unwrap_or(u64::MAX)
+    }
+
+    /// Headline APY less the NCN's expected annual slashing loss (floored at 0)
+    pub fn slashing_adjusted_apy_bps(&self, perf_feed: &NcnPerformanceFeed, current_time: i64) -> u64 {
+        self.current_apy_bps
+            .saturating_sub(perf_feed.expected_slashing_loss_bps(current_time))
+    }
+
+    /// Yield decomposition with component shares in bps of the current APY.
+    /// Shares are zero when the current APY is zero; None if a share overflows u64.
+    pub fn yield_decomposition(&self) -> Option<YieldDecomposition> {
+        let share = |component: u64| -> Option<u64> {
+            if self.current_apy_bps == 0 {
+                return Some(0);
+            }
+            let bps = (component as u128).checked_mul(10_000)? / self.current_apy_bps as u128;
+            u64::try_from(bps).ok()
+        };
+        Some(YieldDecomposition {
+            base_bps: self.base_staking_apy_bps,
+            mev_bps: self.mev_apy_bps,
+            premium_bps: self.restaking_premium_bps,
+            mev_share_pct: share(self.mev_apy_bps)?,
+            premium_share_pct: share(self.restaking_premium_bps)?,
+        })
+    }
+
+    /// Whether a yield curve fits the feed and has strictly increasing tenors
+    pub fn is_valid_yield_curve(points: &[YieldCurvePoint]) -> bool {
+        points.len() <= MAX_YIELD_CURVE_POINTS
+            && points.windows(2).all(|w| w[0].tenor_days < w[1].tenor_days)
+    }
+
+    /// APY in bps for a lockup tenor, linearly interpolated between curve points.
+    /// Tenors outside the curve take the nearest endpoint; with no curve set this
+    /// is the flat `current_apy_bps`.
+    pub fn apy_for_tenor(&self, days: u16) -> u64 {
+        let curve = &self.yield_curve;
+        let (first, last) = match (curve.first(), curve.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return self.current_apy_bps,
+        };
+        if days <= first.tenor_days {
+            return first.apy_bps;
+        }
+        if days >= last.tenor_days {
+            return last.apy_bps;
         }
+
+        let upper = curve.partition_point(|p| p.tenor_days <= days);
+        let (lo, hi) = (&curve[upper - 1], &curve[upper]);
+        let span = (hi.tenor_days - lo.tenor_days) as i128;
+        let offset = (days - lo.tenor_days) as i128;
+        let apy = lo.apy_bps as i128 + (hi.apy_bps as i128 - lo.apy_bps as i128) * offset / span;
+        apy as u64
     }
 
-    /// Calculate yield variance from history
+    /// Classify yield regime based on variance, using this feed's thresholds
+    pub fn classify_regime(&self, variance_bps: u64) -> u8 {
+        self.regime_thresholds.classify(variance_bps)
+    }
+
+    /// Regime for the current history: Normal until MIN_SAMPLES_FOR_REGIME samples exist,
+    /// then classified from `yield_variance_bps`
+    pub fn current_regime(&self) -> u8 {
+        if self.yield_history.len() < MIN_SAMPLES_FOR_REGIME {
+            return 2; // Normal
+        }
+        self.classify_regime(self.yield_variance_bps)
+    }
+
+    /// Append a sample to the capped history, evicting the oldest from the running
+    /// variance when the window is full. The 7d/30d window filters assume the history is
+    /// in time order, so a sample older than the last one is rejected with StaleTimestamp
+    /// (equal timestamps are allowed).
+    pub fn push_yield_sample(&mut self, sample: YieldSample) -> Result<()> {
+        if let Some(last) = self.yield_history.last() {
+            require!(sample.timestamp >= last.timestamp, NcnOracleError::StaleTimestamp);
+        }
+        let cap = effective_history_cap(self.history_cap);
+        if self.yield_history.len() >= cap {
+            self.apy_running_variance.remove(self.yield_history[0].apy_bps);
+        }
+        append_capped_history(&mut self.yield_history, &[sample], cap);
+        self.apy_running_variance.add(sample.apy_bps);
+        Ok(())
+    }
+
+    /// Mean APY of the samples within the last 7 and 30 days of `current_time`, in one pass
+    /// over the history (None for an empty window)
+    pub fn window_averages(&self, current_time: i64) -> (Option<u64>, Option<u64>) {
+        let (mut sum_7d, mut n_7d, mut sum_30d, mut n_30d) = (0u128, 0u128, 0u128, 0u128);
+        for s in &self.yield_history {
+            let age = current_time - s.timestamp;
+            if age <= 30 * 86400 {
+                sum_30d += s.apy_bps as u128;
+                n_30d += 1;
+                if age <= 7 * 86400 {
+                    sum_7d += s.apy_bps as u128;
+                    n_7d += 1;
+                }
+            }
+        }
+        let avg = |sum: u128, n: u128| (n > 0).then(|| (sum / n) as u64);
+        (avg(sum_7d, n_7d), avg(sum_30d, n_30d))
+    }
+
+    /// Rebuild every field derived from `yield_history` -- the 7d/30d averages, the running
+    /// variance accumulators, `yield_variance_bps` and `yield_regime` -- as record_ncn_yield
+    /// would leave them. An empty window keeps its previous average, as on record.
+    pub fn recompute_derived(&mut self, current_time: i64) {
+        let (avg_7d, avg_30d) = self.window_averages(current_time);
+        if let Some(avg) = avg_7d {
+            self.apy_7d_avg = avg;
+        }
+        if let Some(avg) = avg_30d {
+            self.apy_30d_avg = avg;
+        }
+
+        let mut running = RunningVariance::default();
+        self.yield_history.iter().for_each(|s| running.add(s.apy_bps));
+        self.apy_running_variance = running;
+        self.yield_variance_bps = running.stddev_bps();
+        self.yield_regime = self.current_regime();
+    }
+
+    /// Yield variance recomputed over the whole history (0 until MIN_SAMPLES_FOR_REGIME
+    /// samples exist). O(n); record_ncn_yield uses `apy_running_variance` instead, which
+    /// agrees to within 1 bps (this one rounds the mean down first).
     pub fn calculate_variance(&self) -> u64 {
-        if self.yield_history.len() < 2 {
+        if self.yield_history.len() < MIN_SAMPLES_FOR_REGIME {
             return 0;
         }
 
@@ -211,6 +1005,11 @@ impl NcnYieldFeed {
         // Return square root approximation in bps
         (variance as f64).sqrt() as u64
     }
+
+    /// One packed page of `yield_history` (see HISTORY_EXPORT_LAYOUT_VERSION)
+    pub fn export_history(&self, start: u16, max_samples: u8) -> Vec<u8> {
+        export_history_page(&self.yield_history, start, max_samples, YIELD_SAMPLE_PACKED_SIZE, YieldSample::pack_into)
+    }
 }
 
 impl AggregatedRestakingFeed {
@@ -218,4 +1017,975 @@ impl AggregatedRestakingFeed {
     pub fn active_count(&self) -> u32 {
         self.ncn_count
     }
+
+    /// Reset `ncn_count` to the length of `ncn_feeds`, returning the corrected count
+    pub fn reconcile_count(&mut self) -> u32 {
+        self.ncn_count = self.ncn_feeds.len() as u32;
+        self.ncn_count
+    }
+
+    /// Sum per-NCN TVLs in u128 and reject totals that don't fit the u64 field,
+    /// so a protocol-wide overflow is an error rather than a silent wraparound
+    pub fn checked_total_restaked_sol(tvls: impl IntoIterator<Item = u64>) -> Option<u64> {
+        let total: u128 = tvls.into_iter().map(u128::from).sum();
+        u64::try_from(total).ok()
+    }
+
+    /// Seconds since the stalest member feed was updated, as of the last recompute
+    /// (0 if that update is in the future)
+    pub fn max_member_staleness_secs(&self, current_time: i64) -> i64 {
+        current_time.saturating_sub(self.oldest_member_update).max(0)
+    }
+}
+
+impl KeeperRewardVault {
+    /// A vault admin must be the registry authority and not the keeper it pays, so a
+    /// keeper cannot sign in with a second key and set its own rate
+    pub fn is_valid_admin(admin: &Pubkey, registry_authority: &Pubkey, keeper: &Pubkey) -> bool {
+        admin == registry_authority && admin != keeper
+    }
+}
+
+impl NcnRegistryPage {
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= MAX_REGISTRY_PAGE_ENTRIES
+    }
+
+    /// Index of the entry for `ncn_performance_feed` on this page, if registered here
+    pub fn position(&self, ncn_performance_feed: &Pubkey) -> Option<usize> {
+        self.entries.iter().position(|e| e.ncn_performance_feed == *ncn_performance_feed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perf_feed() -> NcnPerformanceFeed {
+        NcnPerformanceFeed {
+            authority: Pubkey::default(),
+            ncn_address: Pubkey::default(),
+            ncn_name: String::new(),
+            uptime_probability_e6: 995_000,
+            total_slashing_events: 0,
+            last_slashing_time: 0,
+            total_restaked_sol: 0,
+            restaker_count: 0,
+            performance_history: Vec::new(),
+            signal_severity: 0,
+            sovereign_infra_score: 0,
+            is_active: true,
+            last_updated: 0,
+            bump: 0,
+            signal_set_time: 0,
+            signal_decay_interval_secs: 0,
+            last_slashing_fraction_e6: 0,
+            keeper_reward_per_update_lamports: 0,
+            keeper_rewards_lamports: 0,
+            history_cap: 0,
+            recent_slashing_times: [0; RECENT_SLASHING_TIMES],
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Signal decay
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_signal_decay_disabled() {
+        let mut feed = perf_feed();
+        feed.signal_severity = 3;
+        feed.apply_signal_decay(1_000_000);
+        assert_eq!(feed.signal_severity, 3);
+    }
+
+    #[test]
+    fn test_signal_decay_steps_per_interval() {
+        let mut feed = perf_feed();
+        feed.signal_severity = 3;
+        feed.signal_set_time = 1_000;
+        feed.signal_decay_interval_secs = 3_600;
+
+        // Not a full interval yet
+        feed.apply_signal_decay(4_599);
+        assert_eq!(feed.signal_severity, 3);
+
+        // One interval -> CRITICAL to HIGH, clock restarts from the step
+        feed.apply_signal_decay(4_600);
+        assert_eq!(feed.signal_severity, 2);
+        assert_eq!(feed.signal_set_time, 4_600);
+
+        // Two more intervals -> NONE
+        feed.apply_signal_decay(4_600 + 2 * 3_600);
+        assert_eq!(feed.signal_severity, 0);
+    }
+
+    #[test]
+    fn test_signal_decay_never_below_none() {
+        let mut feed = perf_feed();
+        feed.signal_severity = 1;
+        feed.signal_decay_interval_secs = 60;
+        feed.apply_signal_decay(60 * 100);
+        assert_eq!(feed.signal_severity, 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // Slash-size severity escalation
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_severity_for_slash() {
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(0), 1);
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(1_000), 1);          // 0.1%
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(9_999), 1);
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(10_000), 2);         // 1%
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(99_999), 2);
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(100_000), 3);        // 10%
+        assert_eq!(NcnPerformanceFeed::severity_for_slash(300_000), 3);        // 30%
+    }
+
+    // -----------------------------------------------------------------------
+    // Implied default probability
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_implied_default_healthy_ncn() {
+        let feed = perf_feed(); // 99.5% uptime, no slashing, no signal
+        // downtime 5_000 -> hazard 50/day
+        assert_eq!(feed.implied_default_probability(86_400, 0), 50);
+        assert_eq!(feed.implied_default_probability(30 * 86_400, 0), 1_500);
+        assert_eq!(feed.implied_default_probability(0, 0), 0);
+    }
+
+    #[test]
+    fn test_implied_default_uses_worse_of_current_and_average() {
+        let mut feed = perf_feed();
+        feed.performance_history.push(NcnPerformanceSample {
+            uptime_e6: 900_000,
+            total_restaked_sol: 0,
+            restaker_count: 0,
+            timestamp: 0,
+        });
+        // average 90% < current 99.5% -> downtime 100_000 -> hazard 1_000/day
+        assert_eq!(feed.implied_default_probability(86_400, 86_400), 1_000);
+    }
+
+    #[test]
+    fn test_implied_default_slashing_and_signal() {
+        let now = 1_700_000_000;
+        let mut feed = perf_feed();
+        feed.uptime_probability_e6 = 1_000_000;
+        feed.record_slashing_time(now - 3_600);
+        feed.total_slashing_events = 1;
+        // No history: 1 slash in the 1-day window -> rate 1e6/day -> hazard 100_000/day
+        assert_eq!(feed.implied_default_probability(86_400, now), 100_000);
+        feed.signal_severity = 3;
+        assert_eq!(feed.implied_default_probability(86_400, now), 400_000);
+        // Capped at certainty
+        assert_eq!(feed.implied_default_probability(10 * 86_400, now), 1_000_000);
+    }
+
+    #[test]
+    fn test_implied_default_counts_slashes_in_history_window() {
+        let now = 1_700_000_000;
+        // Full 7-day hourly history at 99.5% uptime: downtime hazard 50/day
+        let mut feed = perf_feed_with_hourly_history(now);
+        assert_eq!(feed.implied_default_probability(86_400, now), 50);
+
+        // A slash older than the oldest sample is outside the window
+        feed.record_slashing_time(now - 10 * SECONDS_PER_DAY);
+        feed.total_slashing_events = 1;
+        assert_eq!(feed.implied_default_probability(86_400, now), 50);
+
+        // One slash in 7 days -> rate 142_857/day -> hazard 14_285/day on top
+        feed.record_slashing_time(now - 2 * SECONDS_PER_DAY);
+        feed.total_slashing_events = 2;
+        assert_eq!(feed.implied_default_probability(86_400, now), 14_335);
+    }
+
+    // -----------------------------------------------------------------------
+    // Keeper rewards
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_keeper_reward_accrual() {
+        let mut feed = perf_feed();
+        assert_eq!(feed.accrue_keeper_reward(), Some(0));
+
+        feed.keeper_reward_per_update_lamports = 5_000;
+        feed.accrue_keeper_reward();
+        assert_eq!(feed.accrue_keeper_reward(), Some(10_000));
+
+        feed.keeper_rewards_lamports = u64::MAX;
+        assert_eq!(feed.accrue_keeper_reward(), None);
+    }
+
+    #[test]
+    fn test_claimable_keeper_rewards_limited_by_vault() {
+        let mut feed = perf_feed();
+        feed.keeper_rewards_lamports = 50_000;
+
+        // Vault fully funded
+        assert_eq!(feed.claimable_keeper_rewards(1_000_000, 890_880), 50_000);
+        // Vault only partially funded above rent
+        assert_eq!(feed.claimable_keeper_rewards(900_880, 890_880), 10_000);
+        // Vault at (or below) rent exemption pays nothing
+        assert_eq!(feed.claimable_keeper_rewards(890_880, 890_880), 0);
+        assert_eq!(feed.claimable_keeper_rewards(0, 890_880), 0);
+    }
+
+    #[test]
+    fn test_keeper_cannot_administer_vault_with_second_key() {
+        let (registry_authority, keeper, second_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(KeeperRewardVault::is_valid_admin(&registry_authority, &registry_authority, &keeper));
+        // A fresh keypair held by the keeper is not the registry authority
+        assert!(!KeeperRewardVault::is_valid_admin(&second_key, &registry_authority, &keeper));
+        assert!(!KeeperRewardVault::is_valid_admin(&keeper, &registry_authority, &keeper));
+        // Nor may the registry authority administer a vault paying itself
+        assert!(!KeeperRewardVault::is_valid_admin(&keeper, &keeper, &keeper));
+    }
+
+    // -----------------------------------------------------------------------
+    // Point-in-time samples
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_sample_interval_stats() {
+        let sample = |timestamp| NcnPerformanceSample { uptime_e6: 990_000, total_restaked_sol: 0, restaker_count: 0, timestamp };
+        let mut feed = perf_feed();
+        assert_eq!(feed.sample_interval_stats(3_600), SampleIntervalStats::default());
+        feed.performance_history.push(sample(0));
+        assert_eq!(feed.sample_interval_stats(3_600).sample_count, 1);
+        assert!(!feed.has_gaps(3_600));
+
+        // Hourly, hourly, then a 10h hole
+        for ts in [3_600, 7_200, 43_200] {
+            feed.performance_history.push(sample(ts));
+        }
+        let stats = feed.sample_interval_stats(3_600);
+        assert_eq!(
+            (stats.sample_count, stats.min_interval_secs, stats.max_interval_secs, stats.avg_interval_secs),
+            (4, 3_600, 36_000, 14_400)
+        );
+        assert!(stats.has_gaps);
+        assert!(feed.has_gaps(35_999));
+        assert!(!feed.has_gaps(36_000));
+    }
+
+    #[test]
+    fn test_latest_sample_age_and_resume_gap() {
+        let sample = |timestamp| NcnPerformanceSample { uptime_e6: 990_000, total_restaked_sol: 0, restaker_count: 0, timestamp };
+        let mut feed = perf_feed();
+        assert_eq!(feed.latest_sample_age(1_000), i64::MAX);
+        assert_eq!(feed.resume_gap(1_000), None);
+
+        feed.performance_history.push(sample(0));
+        feed.performance_history.push(sample(3_600));
+        assert_eq!(feed.latest_sample_age(7_200), 3_600);
+        assert_eq!(feed.latest_sample_age(0), 0);
+        assert_eq!(feed.resume_gap(3_600 + RESUME_GAP_THRESHOLD_SECS), None);
+        assert_eq!(feed.resume_gap(3_600 + 30 * 86_400), Some(30 * 86_400));
+    }
+
+    #[test]
+    fn test_slashing_recency() {
+        let mut feed = perf_feed();
+        let never = feed.slashing_recency(1_000);
+        assert_eq!(never, SlashingRecency { seconds_since_last_slashing: i64::MAX, was_recently_slashed: false });
+
+        feed.total_slashing_events = 1;
+        feed.last_slashing_time = 10_000;
+        for (now, secs, recent) in [(10_000, 0, true), (96_399, 86_399, true), (96_400, 86_400, false), (5_000, 0, true)] {
+            let recency = feed.slashing_recency(now);
+            assert_eq!(recency, SlashingRecency { seconds_since_last_slashing: secs, was_recently_slashed: recent }, "now {}", now);
+            assert_eq!(recency.was_recently_slashed, feed.was_recently_slashed(now));
+        }
+    }
+
+    #[test]
+    fn test_uptime_confidence_band_narrows_with_samples() {
+        let sample = NcnPerformanceSample { uptime_e6: 990_000, total_restaked_sol: 0, restaker_count: 0, timestamp: 0 };
+        let mut feed = perf_feed();
+        feed.uptime_probability_e6 = 990_000;
+        assert_eq!(feed.uptime_confidence_band(), (0, 1_000_000));
+
+        let mut last_width = 1_000_000;
+        for n in [3usize, 24, 168, 720] {
+            feed.performance_history.resize(n, sample);
+            let (lower, upper) = feed.uptime_confidence_band();
+            assert!(lower < 990_000 && 990_000 < upper && upper <= 1_000_000, "n {}", n);
+            assert!(upper - lower < last_width, "n {}", n);
+            last_width = upper - lower;
+        }
+        // n = 168, p = 0.99 -> Wilson 95% ~ (0.9606, 0.9975)
+        feed.performance_history.truncate(168);
+        let (lower, upper) = feed.uptime_confidence_band();
+        assert!((960_000..961_000).contains(&lower) && (997_000..998_000).contains(&upper));
+
+        // Boundary estimates stay inside 0..=1e6
+        for p in [0, 1_000_000] {
+            feed.uptime_probability_e6 = p;
+            let (lower, upper) = feed.uptime_confidence_band();
+            assert!(lower <= p && p <= upper && upper <= 1_000_000, "p {}", p);
+        }
+    }
+
+    #[test]
+    fn test_export_performance_history_pages() {
+        let mut feed = perf_feed();
+        for i in 0..50u64 {
+            feed.performance_history.push(NcnPerformanceSample {
+                uptime_e6: 990_000 + i,
+                total_restaked_sol: 1_000 * i,
+                restaker_count: i as u32,
+                timestamp: 3_600 * i as i64,
+            });
+        }
+
+        let page = feed.export_history(0, 0);
+        assert!(page.len() <= MAX_HISTORY_EXPORT_BYTES);
+        assert_eq!((page[0], u16::from_le_bytes([page[1], page[2]]), u16::from_le_bytes([page[3], page[4]])), (1, 50, 0));
+        let first_count = page[5] as usize;
+        assert_eq!(page.len(), HISTORY_EXPORT_HEADER_SIZE + first_count * PERFORMANCE_SAMPLE_PACKED_SIZE);
+
+        // Second page picks up where the first stopped and covers the rest
+        let rest = feed.export_history(first_count as u16, 0);
+        assert_eq!(first_count + rest[5] as usize, 50);
+
+        // Sample 2 of a page starting at 10
+        let page = feed.export_history(10, 3);
+        assert_eq!(page[5], 3);
+        let s = &page[HISTORY_EXPORT_HEADER_SIZE + 2 * PERFORMANCE_SAMPLE_PACKED_SIZE..][..PERFORMANCE_SAMPLE_PACKED_SIZE];
+        assert_eq!(u64::from_le_bytes(s[0..8].try_into().unwrap()), 990_012);
+        assert_eq!(u64::from_le_bytes(s[8..16].try_into().unwrap()), 12_000);
+        assert_eq!(u32::from_le_bytes(s[16..20].try_into().unwrap()), 12);
+        assert_eq!(i64::from_le_bytes(s[20..28].try_into().unwrap()), 43_200);
+
+        // Past the end: header only
+        assert_eq!(feed.export_history(50, 0).len(), HISTORY_EXPORT_HEADER_SIZE);
+        assert_eq!(feed.export_history(u16::MAX, 0)[5], 0);
+    }
+
+    #[test]
+    fn test_sample_at_or_before() {
+        let mut feed = perf_feed();
+        assert!(feed.sample_at_or_before(1_000).is_none());
+
+        for (i, ts) in [1_000i64, 4_600, 8_200].iter().enumerate() {
+            feed.performance_history.push(NcnPerformanceSample {
+                uptime_e6: 990_000 + i as u64,
+                total_restaked_sol: 0,
+                restaker_count: 0,
+                timestamp: *ts,
+            });
+        }
+
+        // Before the first sample
+        assert!(feed.sample_at_or_before(999).is_none());
+        // Exact match
+        assert_eq!(feed.sample_at_or_before(4_600).unwrap().uptime_e6, 990_001);
+        // Between samples -> closest prior
+        assert_eq!(feed.sample_at_or_before(8_199).unwrap().uptime_e6, 990_001);
+        // After the last sample
+        assert_eq!(feed.sample_at_or_before(i64::MAX).unwrap().uptime_e6, 990_002);
+    }
+
+    // -----------------------------------------------------------------------
+    // Annualized yield volatility
+    // -----------------------------------------------------------------------
+    fn yield_feed(samples: usize, stddev_bps: u64) -> NcnYieldFeed {
+        NcnYieldFeed {
+            authority: Pubkey::default(),
+            ncn_address: Pubkey::default(),
+            current_apy_bps: 800,
+            apy_7d_avg: 800,
+            apy_30d_avg: 800,
+            yield_variance_bps: stddev_bps,
+            yield_regime: 0,
+            yield_history: vec![
+                YieldSample { apy_bps: 800, variance_bps: stddev_bps, timestamp: 0 };
+                samples
+            ],
+            base_staking_apy_bps: 0,
+            mev_apy_bps: 0,
+            restaking_premium_bps: 0,
+            is_active: true,
+            last_updated: 0,
+            bump: 0,
+            yield_curve: Vec::new(),
+            consecutive_zero_apy_reads: 0,
+            signal_severity: 0,
+            net_apy_bps: 800,
+            regime_thresholds: RegimeThresholds::default(),
+            apy_running_variance: RunningVariance::default(),
+            history_cap: 0,
+        }
+    }
+
+    #[test]
+    fn test_export_yield_history_packs_samples() {
+        let mut feed = yield_feed(0, 0);
+        feed.yield_history.push(YieldSample { apy_bps: 812, variance_bps: 40, timestamp: -5 });
+        let page = feed.export_history(0, 0);
+        assert_eq!(page.len(), HISTORY_EXPORT_HEADER_SIZE + YIELD_SAMPLE_PACKED_SIZE);
+        assert_eq!(page[5], 1);
+        let s = &page[HISTORY_EXPORT_HEADER_SIZE..];
+        assert_eq!(u64::from_le_bytes(s[0..8].try_into().unwrap()), 812);
+        assert_eq!(u64::from_le_bytes(s[8..16].try_into().unwrap()), 40);
+        assert_eq!(i64::from_le_bytes(s[16..24].try_into().unwrap()), -5);
+
+        // A full 168-sample history needs several pages, each within the return data limit
+        let feed = yield_feed(168, 10);
+        let mut start = 0u16;
+        while (start as usize) < feed.yield_history.len() {
+            let page = feed.export_history(start, 0);
+            assert!(page.len() <= MAX_HISTORY_EXPORT_BYTES);
+            assert!(page[5] > 0);
+            start += page[5] as u16;
+        }
+        assert_eq!(start, 168);
+    }
+
+    #[test]
+    fn test_regime_is_normal_until_enough_samples() {
+        let mut feed = yield_feed(0, 0);
+        // Three wildly dispersed early samples
+        for (i, apy) in [100u64, 5_000, 20_000].into_iter().enumerate() {
+            feed.yield_history.push(YieldSample { apy_bps: apy, variance_bps: 0, timestamp: i as i64 });
+        }
+        feed.yield_variance_bps = feed.calculate_variance();
+        assert_eq!(feed.yield_variance_bps, 0);
+        assert_eq!(feed.current_regime(), 2);
+
+        // Once the window is full enough, dispersion drives the regime again
+        while feed.yield_history.len() < MIN_SAMPLES_FOR_REGIME {
+            let apy = if feed.yield_history.len().is_multiple_of(2) { 100 } else { 20_000 };
+            feed.yield_history.push(YieldSample { apy_bps: apy, variance_bps: 0, timestamp: 0 });
+        }
+        feed.yield_variance_bps = feed.calculate_variance();
+        assert!(feed.yield_variance_bps > 0);
+        assert_eq!(feed.current_regime(), feed.classify_regime(feed.yield_variance_bps));
+        assert_eq!(feed.current_regime(), 4);
+    }
+
+    #[test]
+    fn test_regime_thresholds() {
+        let defaults = RegimeThresholds::default();
+        assert!(defaults.is_valid());
+        // Bounds are inclusive: each band ends at its threshold
+        let cases = [(0, 0), (50, 0), (51, 1), (150, 1), (151, 2), (400, 2), (401, 3), (800, 3), (801, 4), (u64::MAX, 4)];
+        for (variance, regime) in cases {
+            assert_eq!(defaults.classify(variance), regime);
+        }
+
+        // Retuned bands reclassify the same variance
+        let mut feed = yield_feed(MIN_SAMPLES_FOR_REGIME, 300);
+        assert_eq!(feed.current_regime(), 2);
+        feed.regime_thresholds = RegimeThresholds { upper_bps: [100, 200, 250, 1_000] };
+        assert_eq!(feed.current_regime(), 3);
+
+        for bad in [[50, 50, 400, 800], [50, 150, 100, 800], [0, 0, 0, 0]] {
+            assert!(!RegimeThresholds { upper_bps: bad }.is_valid());
+        }
+    }
+
+    #[test]
+    fn test_regime_boundaries_belong_to_lower_regime() {
+        // Every bound, and the value just past it, on the feed's own classifier
+        let feed = yield_feed(0, 0);
+        let cases = [(50, 0), (51, 1), (150, 1), (151, 2), (400, 2), (401, 3), (800, 3), (801, 4)];
+        for (variance, regime) in cases {
+            assert_eq!(feed.classify_regime(variance), regime, "variance {}", variance);
+        }
+
+        // Retuned bounds keep the same tie-break, including adjacent bounds one apart
+        for upper_bps in [[100, 200, 250, 1_000], [0, 1, 2, 3], [10, 11, 12, 13]] {
+            let thresholds = RegimeThresholds { upper_bps };
+            assert!(thresholds.is_valid());
+            for (i, &bound) in upper_bps.iter().enumerate() {
+                assert_eq!(thresholds.classify(bound), i as u8, "bound {} of {:?}", bound, upper_bps);
+                assert_eq!(thresholds.classify(bound + 1), i as u8 + 1, "past {} of {:?}", bound, upper_bps);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stuck_zero_apy_reads_raise_signal() {
+        let mut feed = yield_feed(0, 0);
+        for _ in 1..ZERO_APY_FAILURE_THRESHOLD {
+            assert!(!feed.track_zero_apy_read(0));
+            assert_eq!(feed.signal_severity, 0);
+        }
+        // Threshold reached: flagged, and stays flagged while zeros continue
+        assert!(feed.track_zero_apy_read(0));
+        assert!(feed.track_zero_apy_read(0));
+        assert_eq!((feed.consecutive_zero_apy_reads, feed.signal_severity), (ZERO_APY_FAILURE_THRESHOLD + 1, 2));
+
+        // A real reading clears the state
+        assert!(!feed.track_zero_apy_read(800));
+        assert_eq!((feed.consecutive_zero_apy_reads, feed.signal_severity), (0, 0));
+    }
+
+    #[test]
+    fn test_negative_net_apy_is_floored_not_stuck() {
+        let mut feed = yield_feed(0, 0);
+        assert_eq!(feed.apply_net_apy(-250), 0);
+        assert_eq!((feed.net_apy_bps, feed.current_apy_bps), (-250, 0));
+        // An underwater NCN is a real reading, not a keeper stuck at zero
+        for _ in 0..=ZERO_APY_FAILURE_THRESHOLD {
+            assert!(!feed.track_zero_apy_read(feed.net_apy_bps.unsigned_abs()));
+        }
+        assert_eq!(feed.apply_net_apy(900), 900);
+        assert_eq!((feed.net_apy_bps, feed.current_apy_bps), (900, 900));
+    }
+
+    #[test]
+    fn test_stuck_zero_keeps_higher_severity() {
+        let mut feed = yield_feed(0, 0);
+        feed.signal_severity = 3;
+        feed.consecutive_zero_apy_reads = u16::MAX;
+        assert!(feed.track_zero_apy_read(0));
+        assert_eq!((feed.consecutive_zero_apy_reads, feed.signal_severity), (u16::MAX, 3));
+    }
+
+    #[test]
+    fn test_yield_decomposition_shares() {
+        let mut feed = yield_feed(0, 0);
+        feed.base_staking_apy_bps = 600;
+        feed.mev_apy_bps = 120;
+        feed.restaking_premium_bps = 80;
+        assert_eq!(
+            feed.yield_decomposition(),
+            Some(YieldDecomposition {
+                base_bps: 600,
+                mev_bps: 120,
+                premium_bps: 80,
+                mev_share_pct: 1_500,
+                premium_share_pct: 1_000,
+            })
+        );
+
+        // Zero APY: components pass through, shares are zero
+        feed.current_apy_bps = 0;
+        let zero = feed.yield_decomposition().unwrap();
+        assert_eq!((zero.mev_bps, zero.mev_share_pct, zero.premium_share_pct), (120, 0, 0));
+
+        // A share that doesn't fit u64 is rejected rather than truncated
+        feed.current_apy_bps = 1;
+        feed.mev_apy_bps = u64::MAX;
+        assert_eq!(feed.yield_decomposition(), None);
+    }
+
+    fn curve_point(tenor_days: u16, apy_bps: u64) -> YieldCurvePoint {
+        YieldCurvePoint { tenor_days, apy_bps }
+    }
+
+    #[test]
+    fn test_apy_for_tenor_interpolates() {
+        let mut feed = yield_feed(0, 0);
+        // No curve: flat current APY for every tenor
+        assert_eq!(feed.apy_for_tenor(90), 800);
+
+        feed.yield_curve = vec![curve_point(30, 700), curve_point(90, 1_000), curve_point(365, 900)];
+        assert_eq!(feed.apy_for_tenor(30), 700);
+        assert_eq!(feed.apy_for_tenor(60), 850);
+        assert_eq!(feed.apy_for_tenor(90), 1_000);
+        // Inverted segment interpolates downwards
+        assert_eq!(feed.apy_for_tenor(365 - 55), 920);
+        // Outside the curve: nearest endpoint
+        assert_eq!(feed.apy_for_tenor(0), 700);
+        assert_eq!(feed.apy_for_tenor(720), 900);
+    }
+
+    #[test]
+    fn test_yield_curve_validation() {
+        assert!(NcnYieldFeed::is_valid_yield_curve(&[]));
+        assert!(NcnYieldFeed::is_valid_yield_curve(&[curve_point(30, 700), curve_point(90, 1_000)]));
+        assert!(!NcnYieldFeed::is_valid_yield_curve(&[curve_point(90, 700), curve_point(30, 1_000)]));
+        assert!(!NcnYieldFeed::is_valid_yield_curve(&[curve_point(30, 700), curve_point(30, 1_000)]));
+        let too_long: Vec<_> = (1..=MAX_YIELD_CURVE_POINTS as u16 + 1).map(|d| curve_point(d, 800)).collect();
+        assert!(!NcnYieldFeed::is_valid_yield_curve(&too_long));
+    }
+
+    /// Performance feed holding a full default window of hourly samples ending at `now`
+    fn perf_feed_with_hourly_history(now: i64) -> NcnPerformanceFeed {
+        let mut perf = perf_feed();
+        let first = now - MAX_HISTORY_SAMPLES as i64 * 3_600;
+        perf.performance_history = (0..MAX_HISTORY_SAMPLES as i64)
+            .map(|h| NcnPerformanceSample { uptime_e6: 995_000, total_restaked_sol: 0, restaker_count: 0, timestamp: first + h * 3_600 })
+            .collect();
+        perf
+    }
+
+    #[test]
+    fn test_slashing_adjusted_apy() {
+        let now = 1_700_000_000;
+        let yield_feed = yield_feed(0, 0);
+        let mut perf = perf_feed_with_hourly_history(now);
+        // No slashing history: headline APY unchanged
+        assert_eq!(yield_feed.slashing_adjusted_apy_bps(&perf, now), 800);
+
+        // One 0.1% slash inside the 7-day window -> ~52 slashes/year -> 521 bps expected loss
+        perf.record_slashing_time(now - 3 * SECONDS_PER_DAY);
+        perf.total_slashing_events = 1;
+        perf.last_slashing_fraction_e6 = 1_000;
+        assert_eq!(perf.slashing_events_in_window(now), 1);
+        assert_eq!(perf.expected_slashing_loss_bps(now), 521);
+        assert_eq!(yield_feed.slashing_adjusted_apy_bps(&perf, now), 279);
+
+        // Slashes from before the oldest sample are outside the window, however many the
+        // lifetime count holds
+        let mut old = perf_feed_with_hourly_history(now);
+        for days_ago in [30, 20, 10] {
+            old.record_slashing_time(now - days_ago * SECONDS_PER_DAY);
+        }
+        old.total_slashing_events = 3;
+        old.last_slashing_fraction_e6 = 1_000;
+        assert_eq!(old.slashing_events_in_window(now), 0);
+        assert_eq!(yield_feed.slashing_adjusted_apy_bps(&old, now), 800);
+
+        // Two 1% slashes in the window annualize past the APY: floored at 0
+        perf.record_slashing_time(now - SECONDS_PER_DAY);
+        perf.last_slashing_fraction_e6 = 10_000;
+        assert_eq!(yield_feed.slashing_adjusted_apy_bps(&perf, now), 0);
+    }
+
+    #[test]
+    fn test_recent_slashing_times_keep_the_newest() {
+        let mut perf = perf_feed();
+        for t in 1..=RECENT_SLASHING_TIMES as i64 + 4 {
+            perf.record_slashing_time(t * 1_000);
+        }
+        assert_eq!(perf.recent_slashing_times[0], 5_000);
+        assert_eq!(perf.recent_slashing_times[RECENT_SLASHING_TIMES - 1], (RECENT_SLASHING_TIMES as i64 + 4) * 1_000);
+        // Empty history: the window is the last day
+        assert_eq!(perf.slashing_events_in_window(20_000), RECENT_SLASHING_TIMES as u32);
+        assert_eq!(perf.slashing_events_in_window(20_000 + SECONDS_PER_DAY), 1);
+    }
+
+    #[test]
+    fn test_annualized_volatility_needs_two_samples() {
+        assert_eq!(yield_feed(0, 100).annualized_volatility_bps(), 0);
+        assert_eq!(yield_feed(1, 100).annualized_volatility_bps(), 0);
+    }
+
+    #[test]
+    fn test_annualized_volatility_scaling() {
+        // Full-year window: no scaling
+        assert_eq!(yield_feed(8_760, 100).annualized_volatility_bps(), 100);
+        // 168-hour window: sqrt(8760 / 168) = 7.2210...
+        assert_eq!(yield_feed(168, 100).annualized_volatility_bps(), 722);
+        // 24-hour window: sqrt(365) = 19.1049...
+        assert_eq!(yield_feed(24, 50).annualized_volatility_bps(), 955);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        // Squares at the e6 and e12 scales the variance and band math take roots at, and
+        // their lower neighbours
+        for r in [1_000_000u128, 1_000_000_000_000, u64::MAX as u128] {
+            assert_eq!(isqrt(r * r), r);
+            assert_eq!(isqrt(r * r - 1), r - 1);
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Aggregated TVL accumulation
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_total_restaked_sol_sums() {
+        let tvls = [500_000_000_000_000u64, 300_000_000_000_000, 1_000_000_000_000_000];
+        assert_eq!(
+            AggregatedRestakingFeed::checked_total_restaked_sol(tvls),
+            Some(1_800_000_000_000_000)
+        );
+        assert_eq!(AggregatedRestakingFeed::checked_total_restaked_sol([]), Some(0));
+    }
+
+    #[test]
+    fn test_reconcile_count_fixes_desync() {
+        let mut feed = AggregatedRestakingFeed {
+            authority: Pubkey::default(),
+            total_restaked_sol: 0,
+            weighted_avg_apy_bps: 0,
+            ncn_count: 7,
+            ncn_feeds: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            is_active: true,
+            last_updated: 0,
+            bump: 255,
+            oldest_member_update: 0,
+        };
+        assert_ne!(feed.active_count() as usize, feed.ncn_feeds.len());
+
+        assert_eq!(feed.reconcile_count(), 2);
+        assert_eq!(feed.active_count(), 2);
+        // Already consistent: no-op
+        assert_eq!(feed.reconcile_count(), 2);
+    }
+
+    #[test]
+    fn test_max_member_staleness() {
+        let feed = AggregatedRestakingFeed {
+            authority: Pubkey::default(),
+            total_restaked_sol: 0,
+            weighted_avg_apy_bps: 0,
+            ncn_count: 0,
+            ncn_feeds: Vec::new(),
+            is_active: true,
+            last_updated: 10 * SECONDS_PER_DAY,
+            bump: 255,
+            oldest_member_update: 3 * SECONDS_PER_DAY,
+        };
+        // Freshly recomputed, yet one member is a week old
+        assert_eq!(feed.max_member_staleness_secs(feed.last_updated), 7 * SECONDS_PER_DAY);
+        assert_eq!(feed.max_member_staleness_secs(3 * SECONDS_PER_DAY), 0);
+        assert_eq!(feed.max_member_staleness_secs(0), 0);
+        assert_eq!(feed.max_member_staleness_secs(i64::MAX), i64::MAX - 3 * SECONDS_PER_DAY);
+    }
+
+    #[test]
+    fn test_total_restaked_sol_overflow_is_rejected() {
+        // Several max-TVL feeds would wrap a u64 accumulator to near zero
+        assert_eq!(AggregatedRestakingFeed::checked_total_restaked_sol([u64::MAX; 4]), None);
+        assert_eq!(AggregatedRestakingFeed::checked_total_restaked_sol([u64::MAX, 1]), None);
+        // Exactly u64::MAX still fits
+        assert_eq!(
+            AggregatedRestakingFeed::checked_total_restaked_sol([u64::MAX - 1, 1]),
+            Some(u64::MAX)
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // Capped history window
+    // -----------------------------------------------------------------------
+    fn perf_samples(range: std::ops::Range<u64>) -> Vec<NcnPerformanceSample> {
+        range
+            .map(|i| NcnPerformanceSample { uptime_e6: i, total_restaked_sol: 0, restaker_count: 0, timestamp: i as i64 })
+            .collect()
+    }
+
+    fn sample_ids(history: &[NcnPerformanceSample]) -> Vec<u64> {
+        history.iter().map(|s| s.uptime_e6).collect()
+    }
+
+    #[test]
+    fn test_append_capped_history_single_sample() {
+        let mut history = perf_samples(0..167);
+        append_capped_history(&mut history, &perf_samples(167..168), MAX_HISTORY_SAMPLES);
+        assert_eq!(sample_ids(&history), (0..168).collect::<Vec<u64>>());
+
+        // At the cap, each new sample evicts the oldest
+        append_capped_history(&mut history, &perf_samples(168..169), MAX_HISTORY_SAMPLES);
+        assert_eq!(sample_ids(&history), (1..169).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn test_resized_history_cap() {
+        // A raised cap keeps more samples before evicting
+        let mut history = perf_samples(0..168);
+        append_capped_history(&mut history, &perf_samples(168..400), 720);
+        assert_eq!(sample_ids(&history), (0..400).collect::<Vec<u64>>());
+        append_capped_history(&mut history, &perf_samples(400..800), 720);
+        assert_eq!(sample_ids(&history), (80..800).collect::<Vec<u64>>());
+
+        // Legacy zero reads as the initial cap; space grows only past it
+        assert_eq!(effective_history_cap(0), MAX_HISTORY_SAMPLES);
+        assert_eq!(effective_history_cap(720), 720);
+        let base = 8 + NcnPerformanceFeed::INIT_SPACE;
+        assert_eq!(history_account_space(NcnPerformanceFeed::INIT_SPACE, 28, 0), base);
+        assert_eq!(history_account_space(NcnPerformanceFeed::INIT_SPACE, 28, 100), base);
+        assert_eq!(history_account_space(NcnPerformanceFeed::INIT_SPACE, 28, 720), base + 552 * 28);
+
+        // Never below the samples held, never past 30 days
+        assert!(is_valid_history_cap(720, 168));
+        assert!(is_valid_history_cap(168, 168));
+        assert!(is_valid_history_cap(100, 40));
+        assert!(!is_valid_history_cap(167, 168));
+        assert!(!is_valid_history_cap(721, 0));
+        assert!(!is_valid_history_cap(0, 0));
+    }
+
+    #[test]
+    fn test_push_yield_sample_honors_history_cap() {
+        let mut feed = yield_feed(0, 0);
+        feed.history_cap = 300;
+        for i in 0..400u64 {
+            feed.push_yield_sample(YieldSample { apy_bps: 500 + i % 11, variance_bps: 0, timestamp: i as i64 }).unwrap();
+        }
+        assert_eq!(feed.yield_history.len(), 300);
+        assert_eq!(feed.yield_history[0].timestamp, 100);
+        // The running variance evicts in step with the longer window
+        assert_eq!(feed.apy_running_variance.count, 300);
+        let mut fresh = RunningVariance::default();
+        feed.yield_history.iter().for_each(|s| fresh.add(s.apy_bps));
+        assert_eq!(feed.apy_running_variance.stddev_bps(), fresh.stddev_bps());
+    }
+
+    #[test]
+    fn test_append_capped_history_backfill() {
+        // 200 samples into a near-full history: exactly the newest 168 survive, in order
+        let mut history = perf_samples(0..160);
+        append_capped_history(&mut history, &perf_samples(160..360), MAX_HISTORY_SAMPLES);
+        assert_eq!(history.len(), MAX_HISTORY_SAMPLES);
+        assert_eq!(sample_ids(&history), (192..360).collect::<Vec<u64>>());
+
+        // A backfill that fits only evicts the overflow
+        let mut history = perf_samples(0..160);
+        append_capped_history(&mut history, &perf_samples(160..170), MAX_HISTORY_SAMPLES);
+        assert_eq!(sample_ids(&history), (2..170).collect::<Vec<u64>>());
+    }
+
+    // -----------------------------------------------------------------------
+    // Incremental yield variance
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_running_variance_matches_batch() {
+        let mut feed = yield_feed(0, 0);
+        // Deterministic, uneven APY path long enough to evict past the 168-sample cap
+        let mut apy = 800u64;
+        for i in 0..400u64 {
+            apy = (apy * 7 + i * 13) % 1_500 + 200;
+            feed.push_yield_sample(YieldSample { apy_bps: apy, variance_bps: 0, timestamp: i as i64 }).unwrap();
+            let (incremental, batch) = (feed.apy_running_variance.stddev_bps(), feed.calculate_variance());
+            assert!(incremental.abs_diff(batch) <= 1, "sample {}: incremental {} batch {}", i, incremental, batch);
+            if feed.yield_history.len() >= MIN_SAMPLES_FOR_REGIME {
+                assert!(incremental > 0);
+            }
+        }
+        assert_eq!(feed.yield_history.len(), MAX_HISTORY_SAMPLES);
+        assert_eq!(feed.apy_running_variance.count as usize, MAX_HISTORY_SAMPLES);
+
+        // Evicting and re-adding keeps the accumulators close to a fresh pass over the window
+        let mut fresh = RunningVariance::default();
+        feed.yield_history.iter().for_each(|s| fresh.add(s.apy_bps));
+        assert!((feed.apy_running_variance.mean_e6 - fresh.mean_e6).abs() < 1_000);
+        assert_eq!(feed.apy_running_variance.stddev_bps(), fresh.stddev_bps());
+    }
+
+    #[test]
+    fn test_running_variance_settles_to_zero_on_flat_window() {
+        let mut feed = yield_feed(0, 0);
+        for i in 0..MAX_HISTORY_SAMPLES as u64 {
+            feed.push_yield_sample(YieldSample { apy_bps: 100 + (i % 2) * 19_900, variance_bps: 0, timestamp: 0 }).unwrap();
+        }
+        assert!(feed.apy_running_variance.stddev_bps() > 1_000);
+        // A full window of constant APY pushes every volatile sample out
+        for _ in 0..MAX_HISTORY_SAMPLES {
+            feed.push_yield_sample(YieldSample { apy_bps: 800, variance_bps: 0, timestamp: 0 }).unwrap();
+        }
+        assert_eq!(feed.apy_running_variance.stddev_bps(), 0);
+        assert_eq!(feed.calculate_variance(), 0);
+        assert!(feed.apy_running_variance.m2_e12 >= 0);
+
+        // Removing the last sample resets the accumulators
+        let mut single = RunningVariance::default();
+        single.add(800);
+        single.remove(800);
+        assert_eq!(single, RunningVariance::default());
+    }
+
+    #[test]
+    fn test_recompute_derived_restores_recorded_state() {
+        // Record a history the incremental way, then corrupt every derived field
+        let now = 40 * 86400;
+        let mut feed = yield_feed(0, 0);
+        for i in 0..60i64 {
+            let apy_bps = 500 + (i as u64 % 7) * 90;
+            feed.push_yield_sample(YieldSample { apy_bps, variance_bps: 0, timestamp: i * 16 * 3600 }).unwrap();
+        }
+        let expected_stddev = feed.apy_running_variance.stddev_bps();
+        let expected_running = feed.apy_running_variance;
+        feed.apy_7d_avg = 0;
+        feed.apy_30d_avg = 0;
+        feed.yield_variance_bps = 9_999;
+        feed.yield_regime = 4;
+        feed.apy_running_variance = RunningVariance::default();
+
+        feed.recompute_derived(now);
+        let window_avg = |days: i64| {
+            let window: Vec<u64> = feed.yield_history.iter()
+                .filter(|s| now - s.timestamp <= days * 86400)
+                .map(|s| s.apy_bps)
+                .collect();
+            window.iter().sum::<u64>() / window.len() as u64
+        };
+        assert_eq!(feed.apy_7d_avg, window_avg(7));
+        assert_eq!(feed.apy_30d_avg, window_avg(30));
+        assert_ne!(feed.apy_7d_avg, feed.apy_30d_avg);
+        assert_eq!(feed.apy_running_variance, expected_running);
+        assert_eq!(feed.yield_variance_bps, expected_stddev);
+        assert_eq!(feed.yield_regime, feed.classify_regime(expected_stddev));
+
+        // Idempotent
+        let once = feed.clone();
+        feed.recompute_derived(now);
+        assert_eq!((feed.apy_7d_avg, feed.apy_30d_avg, feed.yield_variance_bps), (once.apy_7d_avg, once.apy_30d_avg, once.yield_variance_bps));
+    }
+
+    #[test]
+    fn test_recompute_derived_on_short_or_old_history() {
+        // Below MIN_SAMPLES_FOR_REGIME: no variance, Normal regime
+        let mut feed = yield_feed(3, 200);
+        feed.yield_regime = 0;
+        feed.recompute_derived(0);
+        assert_eq!((feed.yield_variance_bps, feed.yield_regime), (0, 2));
+        assert_eq!(feed.apy_running_variance.count, 3);
+
+        // Samples all older than 30 days leave the averages where they were
+        let mut feed = yield_feed(3, 0);
+        feed.apy_7d_avg = 321;
+        feed.apy_30d_avg = 654;
+        feed.recompute_derived(31 * 86400);
+        assert_eq!((feed.apy_7d_avg, feed.apy_30d_avg), (321, 654));
+        assert_eq!(feed.window_averages(31 * 86400), (None, None));
+    }
+
+    #[test]
+    fn test_push_yield_sample_rejects_out_of_order_timestamp() {
+        let mut feed = yield_feed(0, 0);
+        let sample = |apy_bps: u64, timestamp: i64| YieldSample { apy_bps, variance_bps: 0, timestamp };
+        feed.push_yield_sample(sample(800, 1_000)).unwrap();
+        // Same second is still in order
+        feed.push_yield_sample(sample(810, 1_000)).unwrap();
+        let variance_before = feed.apy_running_variance;
+
+        // A clock regression is rejected without touching the history or the variance
+        assert_eq!(feed.push_yield_sample(sample(5_000, 999)), Err(NcnOracleError::StaleTimestamp.into()));
+        assert_eq!(feed.yield_history.iter().map(|s| s.apy_bps).collect::<Vec<_>>(), vec![800, 810]);
+        assert_eq!(feed.apy_running_variance, variance_before);
+
+        feed.push_yield_sample(sample(820, 1_001)).unwrap();
+        assert_eq!(feed.yield_history.len(), 3);
+    }
+
+    // -----------------------------------------------------------------------
+    // NCN registry
+    // -----------------------------------------------------------------------
+    fn registry_entry(seed: u8) -> NcnRegistryEntry {
+        NcnRegistryEntry {
+            ncn_performance_feed: Pubkey::new_from_array([seed; 32]),
+            ncn_name: format!("NCN {seed}"),
+        }
+    }
+
+    #[test]
+    fn test_registry_page_fills_at_bound() {
+        let mut page = NcnRegistryPage { page_index: 0, entries: Vec::new(), bump: 0 };
+        for seed in 0..MAX_REGISTRY_PAGE_ENTRIES as u8 {
+            assert!(!page.is_full());
+            page.entries.push(registry_entry(seed));
+        }
+        assert!(page.is_full());
+    }
+
+    #[test]
+    fn test_registry_page_position() {
+        let mut page = NcnRegistryPage { page_index: 1, entries: Vec::new(), bump: 0 };
+        page.entries.push(registry_entry(1));
+        page.entries.push(registry_entry(2));
+        assert_eq!(page.position(&registry_entry(2).ncn_performance_feed), Some(1));
+        assert_eq!(page.position(&registry_entry(3).ncn_performance_feed), None);
+
+        page.entries.swap_remove(0);
+        assert_eq!(page.position(&registry_entry(2).ncn_performance_feed), Some(0));
+    }
 }
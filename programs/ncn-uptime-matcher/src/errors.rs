@@ -1,6 +1,11 @@
 use solana_program::program_error::ProgramError;
 
-#[derive(Debug, Clone, Copy)]
+/// Uptime matcher custom error codes occupy 0x300..=0x3FF.
+/// (Yield matcher: 0x30..=0x2FF; ncn-oracle Anchor errors start at 6000.)
+pub const UPTIME_MATCHER_ERROR_BASE: u32 = 0x300;
+pub const UPTIME_MATCHER_ERROR_END: u32 = 0x3FF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UptimeMatcherError {
     MarketResolved = 0x300,
     InvalidProbability = 0x301,
@@ -10,6 +15,125 @@ pub enum UptimeMatcherError {
     InvalidOutcome = 0x305,
     InvalidSignalSeverity = 0x306,
     ArithmeticOverflow = 0x307,
+    InvalidImpactModel = 0x308,
+    InvalidSpreadConfig = 0x309,
+    InvalidLiquidityConfig = 0x30A,
+    InvalidOracleAccount = 0x30B,
+    InsufficientLiquidity = 0x30C,
+    CriticalSignalHalt = 0x30D,
+    OracleSequenceRegressed = 0x30E,
+    MarketExpired = 0x30F,
+    MarketNotExpired = 0x310,
+    AutoResolveDisabled = 0x311,
+    BatchTooLarge = 0x312,
+    OracleInactive = 0x313,
+    SlippageExceeded = 0x314,
+    ResolutionPending = 0x315,
+    GracePeriodActive = 0x316,
+    NoPendingResolution = 0x317,
+    InvalidMode = 0x318,
+    OracleAccountMissing = 0x319,
+    OracleSequenceAhead = 0x31A,
+    DuplicateBatchMarket = 0x31B,
+    FeeAuthorityMismatch = 0x31C,
+    InvalidStalenessConfig = 0x31D,
+    DisputeAuthorityMismatch = 0x31E,
+    CancelLimitReached = 0x31F,
+    InvalidProtocolFeeConfig = 0x320,
+    InvalidPenaltySpread = 0x321,
+    InvalidExpiryMultiplier = 0x322,
+}
+
+impl UptimeMatcherError {
+    /// Every variant, in code order
+    pub const ALL: [UptimeMatcherError; 35] = [
+        Self::MarketResolved,
+        Self::InvalidProbability,
+        Self::ProbabilityNotSet,
+        Self::OracleStale,
+        Self::OracleMismatch,
+        Self::InvalidOutcome,
+        Self::InvalidSignalSeverity,
+        Self::ArithmeticOverflow,
+        Self::InvalidImpactModel,
+        Self::InvalidSpreadConfig,
+        Self::InvalidLiquidityConfig,
+        Self::InvalidOracleAccount,
+        Self::InsufficientLiquidity,
+        Self::CriticalSignalHalt,
+        Self::OracleSequenceRegressed,
+        Self::MarketExpired,
+        Self::MarketNotExpired,
+        Self::AutoResolveDisabled,
+        Self::BatchTooLarge,
+        Self::OracleInactive,
+        Self::SlippageExceeded,
+        Self::ResolutionPending,
+        Self::GracePeriodActive,
+        Self::NoPendingResolution,
+        Self::InvalidMode,
+        Self::OracleAccountMissing,
+        Self::OracleSequenceAhead,
+        Self::DuplicateBatchMarket,
+        Self::FeeAuthorityMismatch,
+        Self::InvalidStalenessConfig,
+        Self::DisputeAuthorityMismatch,
+        Self::CancelLimitReached,
+        Self::InvalidProtocolFeeConfig,
+        Self::InvalidPenaltySpread,
+        Self::InvalidExpiryMultiplier,
+    ];
+
+    /// Custom error code as surfaced in `ProgramError::Custom`
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Map a custom error code back to its variant
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|e| e.code() == code)
+    }
+
+    /// Human-readable description for clients
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::MarketResolved => "Market is resolved",
+            Self::InvalidProbability => "Invalid probability (must be 0-1_000_000, and non-zero at init)",
+            Self::ProbabilityNotSet => "Uptime probability not set",
+            Self::OracleStale => "Oracle data is stale",
+            Self::OracleMismatch => "Oracle account does not match stored oracle",
+            Self::InvalidOutcome => "Invalid resolution outcome (must be 0=SLASHED or 1=SAFE)",
+            Self::InvalidSignalSeverity => "Invalid signal severity (must be 0-3)",
+            Self::ArithmeticOverflow => "Arithmetic overflow",
+            Self::InvalidImpactModel => "Invalid impact model (must be 0=Linear or 1=Sqrt)",
+            Self::InvalidSpreadConfig => "Invalid spread config (min spread exceeds max spread, or max spread exceeds its limit)",
+            Self::InvalidLiquidityConfig => "Invalid liquidity config (impact enabled with zero liquidity notional)",
+            Self::InvalidOracleAccount => "Oracle account is not an NcnPerformanceFeed owned by ncn-oracle",
+            Self::InsufficientLiquidity => "Fill exceeds remaining liquidity (partial fill not allowed)",
+            Self::CriticalSignalHalt => "Quoting halted: signal_actions halts at the current signal severity",
+            Self::OracleSequenceRegressed => "Oracle sequence not greater than the last applied sync (replayed or out of order)",
+            Self::MarketExpired => "Market is past its resolution timestamp and awaiting resolution",
+            Self::MarketNotExpired => "Market has no expiry or has not reached its resolution timestamp",
+            Self::AutoResolveDisabled => "Market expired but auto-resolve on expiry is not enabled (oracle must resolve)",
+            Self::BatchTooLarge => "Batch exceeds the per-instruction item limit (split it across transactions)",
+            Self::OracleInactive => "Referenced NcnPerformanceFeed is deactivated -- quoting halted until the oracle is rotated",
+            Self::SlippageExceeded => "Execution price is above the taker's max acceptable price",
+            Self::ResolutionPending => "A proposed resolution is pending -- trading and other resolution paths are halted",
+            Self::GracePeriodActive => "Resolution grace period applies (propose, then finalize after resolved_after)",
+            Self::NoPendingResolution => "No proposed resolution is pending",
+            Self::InvalidMode => "Invalid mode (must be 0=Continuous or 1=SlashingSettlement)",
+            Self::OracleAccountMissing => "NcnPerformanceFeed account not provided (see the log for the missing index)",
+            Self::OracleSequenceAhead => "Oracle sequence is ahead of the NcnPerformanceFeed's last_updated",
+            Self::DuplicateBatchMarket => "Batch sync lists the same matcher context more than once",
+            Self::FeeAuthorityMismatch => "Signer is not the context's protocol fee authority",
+            Self::InvalidStalenessConfig => "Invalid staleness config (hard-stale limit below the soft-stale threshold)",
+            Self::DisputeAuthorityMismatch => "Signer is not the context's dispute authority (or none is set)",
+            Self::CancelLimitReached => "Pending resolution cancels exhausted -- the current proposal is final",
+            Self::InvalidProtocolFeeConfig => "Invalid protocol fee config (fee above 10000 bps, or a fee without a fee authority)",
+            Self::InvalidPenaltySpread => "Invalid penalty spread (must sit between the min and max spread)",
+            Self::InvalidExpiryMultiplier => "Invalid expiry spread multiplier (below 1x with a widening window set)",
+        }
+    }
 }
 
 impl From<UptimeMatcherError> for ProgramError {
@@ -17,3 +141,24 @@ impl From<UptimeMatcherError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_round_trip() {
+        for e in UptimeMatcherError::ALL {
+            assert_eq!(UptimeMatcherError::from_code(e.code()), Some(e));
+            assert!(!e.description().is_empty());
+        }
+        assert_eq!(UptimeMatcherError::from_code(0x3FF), None);
+    }
+
+    #[test]
+    fn test_error_codes_within_range() {
+        for e in UptimeMatcherError::ALL {
+            assert!((UPTIME_MATCHER_ERROR_BASE..=UPTIME_MATCHER_ERROR_END).contains(&e.code()));
+        }
+    }
+}
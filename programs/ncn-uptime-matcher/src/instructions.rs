@@ -4,12 +4,12 @@ use shank::ShankInstruction;
 pub enum UptimeMatcherInstruction {
     /// Execute match — probability-based pricing with edge spread for NCN uptime
     #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
-    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2)")]
     Match,
 
     /// Initialize NCN uptime matcher context
     #[account(0, name = "lp_pda", desc = "LP PDA to store")]
-    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, writable)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2, writable)")]
     Init,
 
     /// Sync uptime probability from NCN oracle
@@ -21,4 +21,119 @@ pub enum UptimeMatcherInstruction {
     #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
     #[account(1, signer, name = "ncn_oracle", desc = "NCN oracle (must be signer)")]
     Resolve,
+
+    /// Read edge-spread curve at candidate uptime points (writes spreads to return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetSpreadCurve,
+
+    /// Read resolution state: is_resolved, outcome, final price, resolved slot (writes to return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetResolution,
+
+    /// Sync up to 8 markets at once; accounts repeat as (matcher_context, ncn_oracle) pairs
+    #[account(0, writable, name = "matcher_context", desc = "First matcher context account")]
+    #[account(1, name = "ncn_oracle", desc = "NcnPerformanceFeed for the first context")]
+    BatchUptimeSync,
+
+    /// Initialize context after verifying the oracle is a deployed NcnPerformanceFeed
+    #[account(0, name = "lp_pda", desc = "LP PDA to store")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2, writable)")]
+    #[account(2, name = "ncn_oracle", desc = "NcnPerformanceFeed account (owner + discriminator checked)")]
+    InitWithOracle,
+
+    /// Keeper liveness — refresh last-update slot without changing uptime or signal
+    #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, name = "ncn_oracle", desc = "NcnPerformanceFeed account")]
+    Heartbeat,
+
+    /// Compare stored mark with the live oracle uptime (writes deviation bps + alert flag to return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, name = "ncn_oracle", desc = "NcnPerformanceFeed account")]
+    CheckMarkDeviation,
+
+    /// Rotate the stored oracle to a migrated NcnPerformanceFeed (rejected once resolved)
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account")]
+    #[account(2, name = "new_ncn_oracle", desc = "New NcnPerformanceFeed account (owner + discriminator checked)")]
+    SetOracleAccount,
+
+    /// Permissionless: resolve an expired market to its default outcome (requires auto_resolve_on_expiry)
+    #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
+    Expire,
+
+    /// Read all pricing parameters as one packed struct (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetPricingParams,
+
+    /// Initialize context and apply the first uptime sync in one instruction
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2, writable)")]
+    #[account(2, name = "ncn_oracle", desc = "NcnPerformanceFeed account (must equal the init oracle)")]
+    InitAndSync,
+
+    /// Read slots remaining before the oracle data goes stale (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetStaleness,
+
+    /// Read the current mark as decimal and American odds (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetImpliedOdds,
+
+    /// Permissionless: halt quoting if the referenced oracle has been deactivated
+    #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, name = "ncn_oracle", desc = "NcnPerformanceFeed account (must match stored oracle)")]
+    CheckOracleActive,
+
+    /// Compare the stored oracle pubkey against an expected one (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    CheckOracleConfig,
+
+    /// Propose a resolution outcome, finalizable after the grace period (oracle must sign)
+    #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, signer, name = "ncn_oracle", desc = "NCN oracle (must match stored oracle)")]
+    ProposeResolution,
+
+    /// Permissionless: finalize a pending resolution once its grace period has passed
+    #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
+    FinalizeResolution,
+
+    /// Cancel a pending resolution during its grace period (dispute authority must sign)
+    #[account(0, signer, name = "dispute_authority", desc = "Dispute authority set at init or migration")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account")]
+    CancelResolution,
+
+    /// Price a hypothetical mark through the full pricing pipeline (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    QuoteAtMark,
+
+    /// Copy pricing params and mark into a new immutable PDA seeded by (matcher, slot)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, writable, name = "snapshot", desc = "Snapshot PDA [\"snapshot\", matcher_context, slot]")]
+    #[account(2, writable, signer, name = "payer", desc = "Pays rent for the snapshot")]
+    #[account(3, name = "system_program", desc = "System program")]
+    SnapshotMatcher,
+
+    /// Average the spread curve over an uptime range (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetSpreadAverage,
+
+    /// Read the unclaimed protocol fees and fee rate (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetAccruedFees,
+
+    /// Zero the accrued protocol fees, returning the claimed amount (protocol fee authority must sign)
+    #[account(0, signer, name = "protocol_fee_authority", desc = "Protocol fee authority stored at init")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account")]
+    ClaimProtocolFees,
+
+    /// Run the match gating checks without matching; reports OK or the failure reason (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    CanMatch,
+
+    /// Grow a version-1 context to version 2 and write the extension fields (LP PDA must sign)
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (version 1)")]
+    #[account(2, writable, signer, name = "payer", desc = "Pays rent for the larger account")]
+    #[account(3, name = "system_program", desc = "System program")]
+    Migrate,
 }
@@ -3,12 +3,21 @@ use solana_program::{
     program_error::ProgramError, pubkey::Pubkey,
 };
 
-mod errors;
+pub mod errors;
 mod instructions;
+mod pricing;
 mod state;
 mod uptime_pricing;
 
-use uptime_pricing::{process_init, process_match, process_uptime_sync, process_resolve};
+use uptime_pricing::{
+    process_init, process_match, process_uptime_sync, process_resolve, process_get_spread_curve, process_get_resolution,
+    process_batch_uptime_sync, process_init_with_oracle, process_heartbeat, process_check_mark_deviation,
+    process_set_oracle_account, process_expire, process_get_pricing_params, process_init_and_sync,
+    process_get_staleness, process_get_implied_odds, process_check_oracle_active,
+    process_check_oracle_config, process_propose_resolution, process_finalize_resolution,
+    process_cancel_resolution, process_quote_at_mark, process_snapshot_matcher, process_get_spread_average,
+    process_get_accrued_fees, process_claim_protocol_fees, process_can_match, process_migrate,
+};
 
 entrypoint!(process_instruction);
 
@@ -38,6 +47,102 @@ pub fn process_instruction(
             msg!("NCN-UPTIME-MATCHER: Resolve instruction");
             process_resolve(program_id, accounts, instruction_data)
         }
+        0x05 => {
+            msg!("NCN-UPTIME-MATCHER: Get spread curve instruction");
+            process_get_spread_curve(program_id, accounts, instruction_data)
+        }
+        0x06 => {
+            msg!("NCN-UPTIME-MATCHER: Get resolution instruction");
+            process_get_resolution(program_id, accounts, instruction_data)
+        }
+        0x07 => {
+            msg!("NCN-UPTIME-MATCHER: Batch uptime sync instruction");
+            process_batch_uptime_sync(program_id, accounts, instruction_data)
+        }
+        0x08 => {
+            msg!("NCN-UPTIME-MATCHER: Init with oracle instruction");
+            process_init_with_oracle(program_id, accounts, instruction_data)
+        }
+        0x09 => {
+            msg!("NCN-UPTIME-MATCHER: Heartbeat instruction");
+            process_heartbeat(program_id, accounts, instruction_data)
+        }
+        0x0A => {
+            msg!("NCN-UPTIME-MATCHER: Check mark deviation instruction");
+            process_check_mark_deviation(program_id, accounts, instruction_data)
+        }
+        0x0B => {
+            msg!("NCN-UPTIME-MATCHER: Set oracle account instruction");
+            process_set_oracle_account(program_id, accounts, instruction_data)
+        }
+        0x0C => {
+            msg!("NCN-UPTIME-MATCHER: Expire instruction");
+            process_expire(program_id, accounts, instruction_data)
+        }
+        0x0D => {
+            msg!("NCN-UPTIME-MATCHER: Get pricing params instruction");
+            process_get_pricing_params(program_id, accounts, instruction_data)
+        }
+        0x0E => {
+            msg!("NCN-UPTIME-MATCHER: Init and sync instruction");
+            process_init_and_sync(program_id, accounts, instruction_data)
+        }
+        0x0F => {
+            msg!("NCN-UPTIME-MATCHER: Get staleness instruction");
+            process_get_staleness(program_id, accounts, instruction_data)
+        }
+        0x10 => {
+            msg!("NCN-UPTIME-MATCHER: Get implied odds instruction");
+            process_get_implied_odds(program_id, accounts, instruction_data)
+        }
+        0x11 => {
+            msg!("NCN-UPTIME-MATCHER: Check oracle active instruction");
+            process_check_oracle_active(program_id, accounts, instruction_data)
+        }
+        0x12 => {
+            msg!("NCN-UPTIME-MATCHER: Check oracle config instruction");
+            process_check_oracle_config(program_id, accounts, instruction_data)
+        }
+        0x13 => {
+            msg!("NCN-UPTIME-MATCHER: Propose resolution instruction");
+            process_propose_resolution(program_id, accounts, instruction_data)
+        }
+        0x14 => {
+            msg!("NCN-UPTIME-MATCHER: Finalize resolution instruction");
+            process_finalize_resolution(program_id, accounts, instruction_data)
+        }
+        0x15 => {
+            msg!("NCN-UPTIME-MATCHER: Cancel resolution instruction");
+            process_cancel_resolution(program_id, accounts, instruction_data)
+        }
+        0x16 => {
+            msg!("NCN-UPTIME-MATCHER: Quote at mark instruction");
+            process_quote_at_mark(program_id, accounts, instruction_data)
+        }
+        0x17 => {
+            msg!("NCN-UPTIME-MATCHER: Snapshot matcher instruction");
+            process_snapshot_matcher(program_id, accounts, instruction_data)
+        }
+        0x18 => {
+            msg!("NCN-UPTIME-MATCHER: Get spread average instruction");
+            process_get_spread_average(program_id, accounts, instruction_data)
+        }
+        0x19 => {
+            msg!("NCN-UPTIME-MATCHER: Get accrued fees instruction");
+            process_get_accrued_fees(program_id, accounts, instruction_data)
+        }
+        0x1A => {
+            msg!("NCN-UPTIME-MATCHER: Claim protocol fees instruction");
+            process_claim_protocol_fees(program_id, accounts, instruction_data)
+        }
+        0x1B => {
+            msg!("NCN-UPTIME-MATCHER: Can match instruction");
+            process_can_match(program_id, accounts, instruction_data)
+        }
+        0x1C => {
+            msg!("NCN-UPTIME-MATCHER: Migrate instruction");
+            process_migrate(program_id, accounts, instruction_data)
+        }
         _ => {
             msg!("NCN-UPTIME-MATCHER: Unknown instruction tag {}", instruction_data[0]);
             Err(ProgramError::InvalidInstructionData)
@@ -0,0 +1,127 @@
+//! Pricing core for uptime matches: the single source of truth used by
+//! process_match and the unit tests.
+
+use solana_program::program_error::ProgramError;
+
+use crate::errors::UptimeMatcherError;
+use crate::state::{BPS_SCALE, MAX_EDGE_FACTOR_E6, MAX_PROBABILITY};
+use crate::uptime_pricing::{clamp_spread, compute_total_spread, mark_for_uptime, select_edge_spread};
+
+/// Spread inputs for an uptime quote (bps; impact is the fill impact already computed)
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SpreadParams {
+    pub base_spread: u32,
+    pub edge_spread: u32,
+    /// Edge spread at uptime <= 50% (0 = edge_spread on both sides)
+    pub edge_spread_low: u32,
+    pub min_spread: u32,
+    pub max_spread: u32,
+    pub signal_adj: u64,
+    pub impact: u64,
+    /// Near-expiry spread multiplier in bps (0 or 10_000 = no widening)
+    pub expiry_multiplier_bps: u64,
+    /// Floor on the mark the spread is applied to (e6, 0 = none)
+    pub min_mark: u64,
+    /// Cap the ask at par (MAX_PROBABILITY)
+    pub clamp_to_par: bool,
+    /// Edge-factor cap in e6 (0 = MAX_EDGE_FACTOR_E6)
+    pub edge_factor_cap: u128,
+}
+
+
+impl SpreadParams {
+    /// True when the spread does not depend on uptime (no edge spread, no signal adjustment)
+    pub(crate) fn is_static(&self) -> bool {
+        self.edge_spread == 0 && self.edge_spread_low == 0 && self.signal_adj == 0
+    }
+
+    /// Edge-factor cap in force (the 10x default unless overridden)
+    pub(crate) fn edge_factor_cap(&self) -> u128 {
+        if self.edge_factor_cap == 0 { MAX_EDGE_FACTOR_E6 } else { self.edge_factor_cap }
+    }
+}
+
+/// Execution price for an uptime probability. Returns (exec_price, total_spread, edge_factor).
+///
+/// Mark = uptime probability (already e6), or downtime probability when inverted. The
+/// edge spread is symmetric in p and 1-p, so it is unchanged by inversion.
+/// Exec price = mark * (1 + spread/10000)
+///
+/// Static-spread markets (no edge spread, no signal adjustment) take a fast path that
+/// skips the edge-factor division; edge_factor is reported as 0 there. The near-expiry
+/// multiplier scales the clamped spread and is capped again at max_spread.
+///
+/// The mark is floored at `min_mark` so a market whose uptime collapses still quotes a
+/// tradeable price for distressed positions to exit; spreads are still computed from
+/// the real uptime.
+///
+/// With `clamp_to_par` the ask never exceeds MAX_PROBABILITY: a probability-space
+/// claim is worth at most par, so near-certain markets quote exactly 1.0.
+pub(crate) fn uptime_exec_price(
+    uptime_e6: u64,
+    inverted: bool,
+    params: &SpreadParams,
+) -> Result<(u64, u64, u128), ProgramError> {
+    let (total_spread, edge_factor) = if params.is_static() {
+        let spread = clamp_spread(
+            (params.base_spread as u64).saturating_add(params.impact),
+            params.min_spread,
+            params.max_spread,
+        );
+        (spread, 0)
+    } else {
+        compute_total_spread(
+            uptime_e6,
+            params.base_spread,
+            select_edge_spread(uptime_e6, params.edge_spread, params.edge_spread_low),
+            params.min_spread,
+            params.max_spread,
+            params.signal_adj,
+            params.impact,
+            params.edge_factor_cap(),
+        )
+    };
+    let total_spread = apply_expiry_multiplier(total_spread, params.expiry_multiplier_bps, params.max_spread);
+
+    let mark = std::cmp::max(mark_for_uptime(uptime_e6, inverted), params.min_mark);
+    let (_, exec_price) = compute_bid_ask_lp_favorable(mark, total_spread)?;
+    let exec_price = if params.clamp_to_par { std::cmp::min(exec_price, MAX_PROBABILITY) } else { exec_price };
+
+    Ok((exec_price, total_spread, edge_factor))
+}
+
+/// Scale a spread by a bps multiplier, capped at max_spread (multipliers at or below 1x are ignored)
+pub(crate) fn apply_expiry_multiplier(spread: u64, multiplier_bps: u64, max_spread: u32) -> u64 {
+    if multiplier_bps <= BPS_SCALE {
+        return spread;
+    }
+    let widened = (spread as u128 * multiplier_bps as u128 / BPS_SCALE as u128).min(u64::MAX as u128) as u64;
+    std::cmp::min(widened, max_spread as u64)
+}
+
+/// Two-sided quote around a mark with rounding that always favors the LP: the ask
+/// (mark * (1 + spread)) is rounded up and the bid (mark * (1 - spread)) rounded down,
+/// so bid <= fair <= ask. Returns (bid, ask); uptime_exec_price quotes the ask.
+pub(crate) fn compute_bid_ask_lp_favorable(mark: u64, spread_bps: u64) -> Result<(u64, u64), ProgramError> {
+    let scale = BPS_SCALE as u128;
+    let ask_num = (mark as u128)
+        .checked_mul(scale + spread_bps as u128)
+        .ok_or(UptimeMatcherError::ArithmeticOverflow)?;
+    let ask = u64::try_from(ask_num.div_ceil(scale)).map_err(|_| UptimeMatcherError::ArithmeticOverflow)?;
+    // A spread of 100% or more bids zero
+    let bid = (mark as u128 * scale.saturating_sub(spread_bps as u128) / scale) as u64;
+    Ok((bid, ask))
+}
+
+/// Protocol share of a fill's spread revenue, in e6 notional: the spread earns
+/// fill * total_spread / 10_000, of which protocol_fee_bps / 10_000 accrues to the
+/// protocol (rounded down, in the LP's favor). The rest of the spread stays with the LP.
+pub(crate) fn protocol_fee_e6(fill_size: u128, total_spread_bps: u64, protocol_fee_bps: u16) -> Result<u64, ProgramError> {
+    let scale = BPS_SCALE as u128;
+    let fee = fill_size
+        .checked_mul(total_spread_bps as u128)
+        .and_then(|v| v.checked_mul(protocol_fee_bps as u128))
+        .ok_or(UptimeMatcherError::ArithmeticOverflow)?
+        / (scale * scale);
+    u64::try_from(fee).map_err(|_| UptimeMatcherError::ArithmeticOverflow.into())
+}
@@ -1,33 +1,243 @@
 use solana_program::{pubkey::Pubkey, program_error::ProgramError};
 
 // Re-export shared constants and functions from matcher-common
-pub use matcher_common::{CTX_SIZE, RETURN_DATA_OFFSET, RETURN_DATA_SIZE, MAGIC_OFFSET, LP_PDA_OFFSET, verify_magic as verify_magic_generic, read_lp_pda};
+pub use matcher_common::{CTX_SIZE, RETURN_DATA_OFFSET, RETURN_DATA_SIZE, MAGIC_OFFSET, LP_PDA_OFFSET, read_lp_pda};
+// Re-export the versioned header (VERSION_OFFSET u32, MODE_OFFSET u8: 0=Continuous, 1=SlashingSettlement)
+// and fixed-point scales shared with the yield matcher from matcher-utils
+pub use matcher_utils::{BPS_SCALE, CTX_V2_SIZE, CTX_VERSION_1, CTX_VERSION_2, E6_SCALE, MODE_OFFSET, VERSION_OFFSET};
 
 /// Magic bytes: "NCNUMATC" as u64 LE
 pub const UPTIME_MATCHER_MAGIC: u64 = 0x4e43_4e55_4d41_5443;
 
 // Field offsets (ncn-uptime-matcher-specific)
-pub const VERSION_OFFSET: usize = 72;                      // u32
-pub const MODE_OFFSET: usize = 76;                         // u8: 0=Continuous, 1=SlashingSettlement
+pub const PRICE_DECIMALS_OFFSET: usize = 77;               // u8: mark = display value * 10^decimals (see UPTIME_PRICE_DECIMALS)
+pub const QUOTE_KIND_OFFSET: usize = 78;                   // u8: what the mark quotes (see QUOTE_KIND_PROBABILITY)
 pub const BASE_SPREAD_OFFSET: usize = 112;                 // u32
 pub const EDGE_SPREAD_OFFSET: usize = 116;                 // u32: extra spread near 0%/100% uptime
 pub const MAX_SPREAD_OFFSET: usize = 120;                  // u32
 pub const IMPACT_K_OFFSET: usize = 124;                    // u32
 pub const CURRENT_UPTIME_OFFSET: usize = 128;              // u64 (0 - 1_000_000)
-pub const UPTIME_MARK_OFFSET: usize = 136;                 // u64: mark price = uptime prob * 1e6
+pub const UPTIME_MARK_OFFSET: usize = 136;                 // u64: mark price = uptime prob * 1e6 (inverted: 1e6 - uptime)
 pub const LAST_UPDATE_SLOT_OFFSET: usize = 144;            // u64
 pub const RESOLUTION_TIMESTAMP_OFFSET: usize = 152;        // i64 (0 = no expiry)
 pub const IS_RESOLVED_OFFSET: usize = 160;                 // u8
 pub const RESOLUTION_OUTCOME_OFFSET: usize = 161;          // u8: 0=SLASHED, 1=SAFE
+pub const IMPACT_MODEL_OFFSET: usize = 162;                // u8: 0=Linear, 1=Sqrt
+pub const HAS_SYNCED_OFFSET: usize = 163;                  // u8: 1 once a probability has been set
+pub const INVERTED_OFFSET: usize = 164;                    // u8: 1 = mark pays on downtime (1e6 - uptime)
+pub const SIGNAL_ACTIONS_OFFSET: usize = 165;              // u8: per-severity signal actions, 2 bits each (see SIGNAL_ACTION_HALT)
+pub const AUTO_RESOLVE_ON_EXPIRY_OFFSET: usize = 166;      // u8: 1 = anyone may resolve to the default outcome after expiry
+pub const EXPIRY_DEFAULT_OUTCOME_OFFSET: usize = 167;      // u8: outcome used by auto-resolve (0=SLASHED, 1=SAFE)
 pub const SIGNAL_SEVERITY_OFFSET: usize = 168;             // u64 (0-3)
 pub const SIGNAL_ADJUSTED_SPREAD_OFFSET: usize = 176;      // u64
 pub const LIQUIDITY_OFFSET: usize = 184;                   // u128 (16 bytes)
 pub const MAX_FILL_OFFSET: usize = 200;                    // u128 (16 bytes)
 pub const NCN_ORACLE_OFFSET: usize = 216;                  // Pubkey (32): NcnPerformanceFeed account
-// 248..320 = reserved
+pub const MIN_SPREAD_OFFSET: usize = 248;                  // u32: spread floor (bps)
+pub const RESOLVED_SLOT_OFFSET: usize = 252;               // u64: slot the market was resolved in
+pub const ORACLE_SEQUENCE_OFFSET: usize = 260;             // u64: sequence of the last applied sync
+pub const EXPIRY_WIDEN_WINDOW_OFFSET: usize = 268;         // u32: seconds before expiry that spreads start widening (0 = off)
+pub const EXPIRY_MAX_MULTIPLIER_OFFSET: usize = 272;       // u32: spread multiplier at expiry (bps, 10_000 = 1x)
+pub const MIN_MARK_OFFSET: usize = 276;                    // u64: floor on the quoted mark (e6, 0 = none)
+pub const ORACLE_INACTIVE_OFFSET: usize = 284;             // u8: 1 = referenced oracle seen deactivated, quoting halted
+pub const EDGE_SPREAD_LOW_OFFSET: usize = 285;             // u32: edge spread at uptime <= 50% (0 = edge_spread on both sides)
+pub const RESOLUTION_GRACE_OFFSET: usize = 289;            // u32: seconds a proposed resolution waits before finalizing (0 = resolve immediately)
+pub const PENDING_RESOLUTION_OFFSET: usize = 293;          // u8: proposed outcome + 1 (0 = none pending)
+pub const RESOLVED_AFTER_OFFSET: usize = 294;              // i64: earliest time the pending resolution can finalize
+pub const CLAMP_TO_PAR_OFFSET: usize = 302;                // u8: 1 = ask capped at MAX_PROBABILITY (par)
+pub const MAX_SLASHING_BEFORE_RESOLVE_OFFSET: usize = 303; // u8: slashing syncs that force a SLASHED resolution (0 = never)
+pub const SLASHING_COUNT_OFFSET: usize = 304;              // u32: slashing syncs seen so far
+pub const EDGE_CAP_BY_LIQUIDITY_OFFSET: usize = 308;       // u8: 1 = edge-factor cap follows remaining liquidity
+pub const ACCRUED_PROTOCOL_FEES_OFFSET: usize = 309;       // u64: protocol fees accrued by matches, unclaimed (e6 notional)
+pub const PROTOCOL_FEE_BPS_OFFSET: usize = 317;            // u16: protocol share of the spread (bps of the spread, 0 = none)
+pub const LIQUIDITY_CAPPED_OFFSET: usize = 319;            // u8: 1 = fills draw down the liquidity notional (set at init when one is configured)
 
-/// Maximum probability value (100% uptime = 1_000_000)
-pub const MAX_PROBABILITY: u64 = 1_000_000;
+// Context layout versions. Version 1 is the shared CTX_SIZE layout; version 2 appends
+// the extension below and needs a CTX_V2_SIZE account. Init writes version 2 when the
+// account is large enough, and tag 0x1C migrates a version-1 context in place. A
+// version-1 context keeps working and reads every extension field as zero.
+
+// Version-2 extension
+pub const PROTOCOL_FEE_AUTHORITY_OFFSET: usize = 320;      // Pubkey (32): signer of protocol fee claims
+pub const SOFT_STALE_SLOTS_OFFSET: usize = 352;            // u32: oracle age past which quotes are degraded (0 = MAX_STALENESS_SLOTS)
+pub const HARD_STALE_SLOTS_OFFSET: usize = 356;            // u32: oracle age past which matches are rejected (0 = no soft-stale band)
+pub const PENALTY_SPREAD_OFFSET: usize = 360;              // u32: spread floor of a degraded quote (bps, 0 = max spread)
+pub const DISPUTE_AUTHORITY_OFFSET: usize = 364;           // Pubkey (32): signer of pending-resolution cancels (default = none)
+pub const RESOLUTION_CANCELS_OFFSET: usize = 396;          // u8: pending resolutions cancelled so far
+
+/// Reserved tail of a version-2 context: zeroed at init and must stay zero until a
+/// field is allocated there
+pub const RESERVED_OFFSET: usize = 397;
+const _: () = assert!(RESOLUTION_CANCELS_OFFSET < RESERVED_OFFSET, "allocated fields overlap the reserved tail");
+
+/// Debug check that the reserved tail is still zero, so a handler writing past the
+/// allocated layout is caught in tests (and in handlers with `check-reserved`)
+#[cfg(any(test, feature = "check-reserved"))]
+pub fn assert_reserved_zero(ctx_data: &[u8]) {
+    debug_assert!(
+        ctx_data.len() < CTX_V2_SIZE || ctx_data[RESERVED_OFFSET..CTX_V2_SIZE].iter().all(|&b| b == 0),
+        "reserved context bytes {}..{} must stay zero",
+        RESERVED_OFFSET,
+        CTX_V2_SIZE
+    );
+}
+
+/// ncn-oracle program that owns NcnPerformanceFeed accounts
+pub const NCN_ORACLE_PROGRAM_ID: Pubkey = solana_program::pubkey!("NCNRsk1111111111111111111111111111111111111");
+
+/// Anchor discriminator of NcnPerformanceFeed: sha256("account:NcnPerformanceFeed")[..8]
+pub const NCN_PERFORMANCE_FEED_DISCRIMINATOR: [u8; 8] = [6, 52, 172, 160, 196, 213, 224, 156];
+
+/// NcnPerformanceFeed (Borsh) layout: [0..8] discriminator, [8..40] authority,
+/// [40..72] ncn_address, [72..76] ncn_name length (u32), name bytes, then uptime_probability_e6 (u64)
+pub const PERF_FEED_NAME_LEN_OFFSET: usize = 72;
+pub const PERF_FEED_MAX_NAME_LEN: usize = 32;
+
+/// After the name: uptime (u64), slashing events (u32), last slashing time (i64),
+/// restaked SOL (u64), restaker count (u32), then the history Vec (u32 length +
+/// 28-byte samples), signal severity (u8), infra score (u16), is_active (bool) and
+/// last_updated (i64 unix seconds)
+pub const PERF_FEED_FIXED_AFTER_NAME: usize = 32;
+pub const PERF_FEED_SAMPLE_SIZE: usize = 28;
+/// Largest history the oracle allows after resize_performance_history (30 days hourly)
+pub const PERF_FEED_MAX_HISTORY: usize = 720;
+pub const PERF_FEED_SIGNAL_SEVERITY_AFTER_HISTORY: usize = 0;
+pub const PERF_FEED_IS_ACTIVE_AFTER_HISTORY: usize = 3;
+pub const PERF_FEED_LAST_UPDATED_AFTER_HISTORY: usize = 4;
+
+/// Probabilities and edge factors are e6 (E6_SCALE); spreads and fees bps (BPS_SCALE)
+pub const PROBABILITY_SCALE: u64 = 1_000_000;
+
+/// Maximum probability value (100% uptime = PROBABILITY_SCALE)
+pub const MAX_PROBABILITY: u64 = PROBABILITY_SCALE;
+
+/// Display metadata written at init so SDKs can format any matcher's mark without
+/// per-program knowledge: value = mark / 10^price_decimals, read as the quote kind.
+/// Quote kinds are shared with restaking-yield-matcher (0 = probability, its
+/// QUOTE_KIND_YIELD = 1 = annual yield rate); an uptime mark is a probability in e6.
+pub const QUOTE_KIND_PROBABILITY: u8 = 0;
+pub const UPTIME_PRICE_DECIMALS: u8 = 6;
+
+/// Uptime above which the high-side edge spread applies (50%)
+pub const EDGE_SPLIT_UPTIME_E6: u64 = MAX_PROBABILITY / 2;
+
+/// Cap on the edge factor (e6): 10x the base edge spread near 0% / 100% uptime
+pub const MAX_EDGE_FACTOR_E6: u128 = 10 * E6_SCALE as u128;
+
+/// Liquidity-scaled edge-factor caps (opt-in at init), by remaining liquidity notional
+/// (e6): below each bound the paired cap applies, at or above the last bound
+/// DEEP_LIQUIDITY_EDGE_FACTOR_E6. A thin book caps edge pricing low so it stays
+/// tradeable; the middle bucket matches the default 10x cap.
+pub const LIQUIDITY_EDGE_CAP_BUCKETS: [(u128, u128); 3] = [
+    (100_000 * E6_SCALE as u128, 3 * E6_SCALE as u128),      // < 100k notional: 3x
+    (1_000_000 * E6_SCALE as u128, 5 * E6_SCALE as u128),    // < 1M: 5x
+    (10_000_000 * E6_SCALE as u128, MAX_EDGE_FACTOR_E6),     // < 10M: 10x
+];
+pub const DEEP_LIQUIDITY_EDGE_FACTOR_E6: u128 = 20 * E6_SCALE as u128; // >= 10M: 20x
+
+/// Upper bound on the configured max spread (100%): a wider spread only pushes the bid
+/// below zero. With the mark capped at MAX_PROBABILITY the ask stays below 2e6, so the
+/// pricing math cannot overflow.
+pub const MAX_SPREAD_LIMIT_BPS: u32 = 10_000;
+
+/// Match return data: filled size (u128 LE) follows the 8-byte exec price
+pub const RETURN_FILLED_SIZE_OFFSET: usize = RETURN_DATA_OFFSET + 8;
+/// Match return data: u8 1 = priced off soft-stale oracle data at the penalty spread
+pub const RETURN_DEGRADED_OFFSET: usize = RETURN_FILLED_SIZE_OFFSET + 16;
+
+/// Market modes (header mode byte)
+pub const MODE_CONTINUOUS: u8 = 0;
+pub const MODE_SLASHING_SETTLEMENT: u8 = 1;
+
+/// Fill-size impact models
+pub const IMPACT_MODEL_LINEAR: u8 = 0;
+pub const IMPACT_MODEL_SQRT: u8 = 1;
+
+/// Resolution outcomes
+pub const OUTCOME_SLASHED: u8 = 0;
+pub const OUTCOME_SAFE: u8 = 1;
+
+/// Pending resolutions the dispute authority can cancel over a market's life; the
+/// proposal after the last cancel is final
+pub const MAX_RESOLUTION_CANCELS: u8 = 2;
+
+/// GetResolution return data: [0] layout version, [1] is_resolved, [2] outcome,
+/// [3..11] final_price_e6 (u64 LE), [11..19] resolved_slot (u64 LE)
+pub const RESOLUTION_LAYOUT_VERSION: u8 = 1;
+pub const RESOLUTION_RETURN_SIZE: usize = 19;
+
+/// GetPricingParams return data (all integers LE):
+///   [0] layout version, [1] mode, [2..6] base_spread, [6..10] edge_spread,
+///   [10..14] max_spread, [14..18] min_spread, [18..22] impact_k (u32 bps each),
+///   [22] impact_model, [23] inverted, [24..32] mark_e6 (u64), [32..40] uptime_e6 (u64),
+///   [40..48] last_update_slot (u64), [48] is_resolved, [49] price_decimals, [50] quote_kind
+pub const PRICING_PARAMS_LAYOUT_VERSION: u8 = 2;
+pub const PRICING_PARAMS_RETURN_SIZE: usize = 51;
+
+/// Oracle data older than this many slots is stale: by default matches are rejected,
+/// or degraded if the context configures a soft-stale band (see HARD_STALE_SLOTS_OFFSET)
+pub const MAX_STALENESS_SLOTS: u64 = 200;
+
+/// Oldest oracle `last_updated` a heartbeat accepts, in seconds: the staleness window at
+/// ~400 ms per slot. Only the feed's keeper advances it, so permissionless heartbeats
+/// cannot keep a market fresh once the keeper stops updating the feed.
+pub const MAX_HEARTBEAT_FEED_AGE_SECS: i64 = MAX_STALENESS_SLOTS as i64 * 2 / 5;
+
+/// GetStaleness return data: [0..8] slots_until_stale (soft threshold), [8..16]
+/// last_update_slot, [16..24] soft-stale threshold, [24..32] hard-stale limit (u64 LE each)
+pub const STALENESS_RETURN_SIZE: usize = 32;
+
+/// GetImpliedOdds return data: [0..8] mark_e6 (u64), [8..16] decimal_odds_e6 (u64),
+/// [16..24] american_odds_e6 (i64), all LE. A zero mark has no finite odds: decimal is
+/// u64::MAX and American i64::MAX; a certain mark (1e6) has American odds of i64::MIN.
+pub const IMPLIED_ODDS_RETURN_SIZE: usize = 24;
+
+/// CheckOracleConfig return data: [0] stored oracle matches the expected pubkey (u8 0/1),
+/// [1..33] stored oracle pubkey
+pub const ORACLE_CONFIG_RETURN_SIZE: usize = 33;
+
+/// QuoteAtMark return data (all integers LE): [0] layout version, [1..9] exec_price_e6
+/// (u64), [9..17] total_spread_bps (u64), [17..25] edge_factor_e6 (u64), [25..33] impact_bps (u64)
+pub const QUOTE_AT_MARK_LAYOUT_VERSION: u8 = 1;
+pub const QUOTE_AT_MARK_RETURN_SIZE: usize = 33;
+
+/// Snapshot account (written once at creation, never again; all integers LE): [0..8]
+/// UPTIME_SNAPSHOT_MAGIC, [8..40] matcher context pubkey, [40..48] slot (u64),
+/// [48..56] unix_timestamp (i64), [56..107] pricing params (PRICING_PARAMS_RETURN_SIZE layout)
+pub const UPTIME_SNAPSHOT_MAGIC: u64 = 0x4e43_4e55_534e_4150;
+pub const SNAPSHOT_SEED: &[u8] = b"snapshot";
+pub const SNAPSHOT_SIZE: usize = 56 + PRICING_PARAMS_RETURN_SIZE;
+
+/// Per-market sync payload: uptime_e6 (u64) + signal_severity (u64) + signal_adjusted_spread (u64)
+/// + oracle_sequence (u64)
+pub const SYNC_PAYLOAD_SIZE: usize = 32;
+
+/// Maximum markets per batch sync. Each market costs roughly 15k CU (two account
+/// checks, ten field writes, one log line); 8 keeps a full batch under ~120k CU, well
+/// inside the 200k default budget. Larger batches fail up front with BatchTooLarge.
+pub const MAX_BATCH_SYNC_MARKETS: usize = 8;
+
+/// Maximum number of points accepted by the spread curve read instruction
+pub const MAX_SPREAD_CURVE_POINTS: usize = 16;
+
+/// Grid size of the spread average read instruction: 33 points (32 intervals) by
+/// default, at most 64
+pub const DEFAULT_SPREAD_AVERAGE_SAMPLES: usize = 33;
+pub const MAX_SPREAD_AVERAGE_SAMPLES: usize = 64;
+
+/// GetSpreadAverage return data: [0..8] average total_spread_bps (u64 LE, rounded up),
+/// [8] grid samples used (u8; 1 when low == high)
+pub const SPREAD_AVERAGE_RETURN_SIZE: usize = 9;
+
+/// Protocol fee is a share of the spread, so at most all of it
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 10_000;
+
+/// GetAccruedFees return data: [0..8] accrued_fees_e6 (u64 LE), [8..10] protocol_fee_bps (u16 LE)
+pub const ACCRUED_FEES_RETURN_SIZE: usize = 10;
+
+/// CanMatch return data: [0] 1 = a match would currently pass its gates, 0 = it would
+/// fail; [1..5] reason (u32 LE): the UptimeMatcherError code the match would return, 0 when OK
+pub const CAN_MATCH_RETURN_SIZE: usize = 5;
 
 /// Signal severity levels (Kalshify-style)
 pub const SIGNAL_NONE: u64 = 0;
@@ -35,9 +245,21 @@ pub const SIGNAL_LOW: u64 = 1;
 pub const SIGNAL_HIGH: u64 = 2;
 pub const SIGNAL_CRITICAL: u64 = 3;
 
-pub fn verify_magic(ctx_data: &[u8]) -> bool {
-    verify_magic_generic(ctx_data, UPTIME_MATCHER_MAGIC)
-}
+/// Canonical signal-adjusted spread (bps) per oracle signal severity, indexed by level.
+/// Sync derives the charged signal spread from this rather than trusting the keeper.
+pub const SIGNAL_SPREAD_BPS: [u64; 4] = [0, 25, 100, 300];
+
+/// Per-severity signal action table (one byte, two bits per level). Severity s uses bits
+/// 2*(3 - s)..2*(3 - s) + 2, so CRITICAL sits in the low bits and the original
+/// halt_on_critical flag (1) reads as "halt on CRITICAL". An action of 0 widens by the
+/// signal spread; HALT rejects matches; NO_WIDEN quotes without the signal spread.
+pub const SIGNAL_ACTION_HALT: u8 = 0b01;
+pub const SIGNAL_ACTION_NO_WIDEN: u8 = 0b10;
+
+/// A sync that raises the signal from below this level to at or above it counts as one
+/// slashing event toward max_slashing_before_resolve (the oracle escalates to HIGH for
+/// slashes of 1% of stake or more)
+pub const SLASH_SIGNAL_THRESHOLD: u64 = SIGNAL_HIGH;
 
 pub fn read_ncn_oracle(ctx_data: &[u8]) -> Result<Pubkey, ProgramError> {
     Ok(Pubkey::new_from_array(
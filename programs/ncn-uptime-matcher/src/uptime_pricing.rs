@@ -1,17 +1,24 @@
 use solana_program::{
     account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, msg,
-    program_error::ProgramError, pubkey::Pubkey, sysvar::Sysvar,
+    program::{invoke, invoke_signed, set_return_data}, program_error::ProgramError, pubkey::Pubkey,
+    rent::Rent, sysvar::Sysvar,
 };
+use solana_system_interface::{instruction as system_instruction, program as system_program};
 
 use matcher_common::{verify_lp_pda as verify_lp_pda_common, verify_init_preconditions, write_header, write_exec_price};
+use matcher_utils::{
+    has_extension, isqrt, log_match, read_ctx_u32, read_u128, verify_context_owner, write_u128, HeaderView,
+};
 
 use crate::errors::UptimeMatcherError;
+use crate::pricing::{protocol_fee_e6, uptime_exec_price, SpreadParams};
 use crate::state::*;
 
 /// Tag 0x02: Initialize NCN uptime matcher context
 /// Accounts:
 ///   [0] LP PDA (signer)
-///   [1] Matcher context account (writable, 320 bytes)
+///   [1] Matcher context account (writable, CTX_V2_SIZE = 448 bytes for version 2, or
+///       CTX_SIZE = 320 bytes for a version-1 context without the extension)
 /// Data layout:
 ///   [0]    tag (0x02)
 ///   [1]    mode (u8: 0=Continuous, 1=SlashingSettlement)
@@ -19,15 +26,63 @@ use crate::state::*;
 ///   [6..10] edge_spread_bps (u32 LE)
 ///   [10..14] max_spread_bps (u32 LE)
 ///   [14..18] impact_k_bps (u32 LE)
-///   [18..26] initial_uptime_e6 (u64 LE)
+///   [18..26] initial_uptime_e6 (u64 LE, 1-1_000_000)
 ///   [26..34] resolution_timestamp (i64 LE, 0 = no expiry)
-///   [34..50] liquidity_notional_e6 (u128 LE)
+///   [34..50] liquidity_notional_e6 (u128 LE, 0 = fills uncapped; otherwise drawn down by fills)
 ///   [50..66] max_fill_abs (u128 LE)
 ///   [66..98] ncn_oracle pubkey (32 bytes)
+///   [98]   impact_model (u8, optional: 0=Linear (default), 1=Sqrt)
+///   [99..103] min_spread_bps (u32 LE, optional, default 0)
+///   [103]  inverted (u8, optional: 0=pays on uptime (default), 1=pays on downtime)
+///   [104]  signal_actions (u8, optional, default 0 = widen at every severity): per-severity
+///          action table, see SIGNAL_ACTION_HALT. 1 = the original halt_on_critical_signal
+///          (halt on CRITICAL, widen below); e.g. 0x05 halts on HIGH and CRITICAL
+///   [105]  auto_resolve_on_expiry (u8, optional: 0=wait for the oracle (default), 1=enable tag 0x0C)
+///   [106]  expiry_default_outcome (u8, optional: 0=SLASHED, 1=SAFE (default))
+///   [107..111] expiry_widen_window_secs (u32 LE, optional, default 0 = no widening)
+///   [111..115] expiry_max_multiplier_bps (u32 LE, optional, >= 10_000 when a window is set)
+///   [115..123] min_mark_e6 (u64 LE, optional, default 0 = no floor)
+///   [123..127] edge_spread_low_bps (u32 LE, optional, default 0 = edge_spread_bps on both sides;
+///              otherwise edge_spread_bps applies above 50% uptime and this at or below)
+///   [127..131] resolution_grace_secs (u32 LE, optional, default 0 = tag 0x04 resolves
+///              immediately; otherwise resolution goes through tags 0x13/0x14)
+///   [131]      clamp_to_par (u8, optional: 1 = cap the ask at 1_000_000, default 0)
+///   [132]      max_slashing_before_resolve (u8, optional, default 0 = never; SlashingSettlement
+///              mode only: after this many slashing syncs the market resolves SLASHED, or
+///              with a grace period has SLASHED proposed)
+///   [133]      edge_cap_by_liquidity (u8, optional: 1 = scale the 10x edge-factor cap by the
+///              remaining liquidity notional, see LIQUIDITY_EDGE_CAP_BUCKETS; requires a nonzero notional)
+///   [134..136] protocol_fee_bps (u16 LE, optional, default 0: share of each match's spread
+///              revenue accrued as protocol fees, at most 10_000)
+///   [136..]    version-2 extension fields (all optional; a version-1 context rejects any
+///              that are set):
+///   [136..168] protocol_fee_authority pubkey (32 bytes, optional: signer of tag 0x1A claims;
+///              required when protocol_fee_bps > 0)
+///   [168..172] soft_stale_slots (u32 LE, optional, default 0 = MAX_STALENESS_SLOTS: oracle
+///              age past which matches are charged the penalty spread and flagged degraded)
+///   [172..176] hard_stale_slots (u32 LE, optional, default 0 = no soft-stale band: oracle
+///              age past which matches are rejected; at least the soft threshold)
+///   [176..180] penalty_spread_bps (u32 LE, optional, default 0 = max spread: spread floor
+///              of a degraded quote, between min and max spread)
+///   [180..212] dispute_authority pubkey (32 bytes, optional: signer of tag 0x15 cancels;
+///              not the LP PDA. Default none: a pending resolution cannot be cancelled)
+/// An initial uptime of 0 is rejected: the market would refuse every match until the
+/// first sync. Use tag 0x0E to start from the oracle instead.
 pub fn process_init(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    init_context(program_id, accounts, data, false)
+}
+
+/// Shared body of the init instructions; `uptime_from_sync` allows an unset (0) initial
+/// uptime because the caller applies a sync immediately afterwards
+fn init_context(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
+    uptime_from_sync: bool,
 ) -> ProgramResult {
     if accounts.len() < 2 {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -39,7 +94,15 @@ pub fn process_init(
     let lp_pda = &accounts[0];
     let ctx_account = &accounts[1];
 
+    verify_context_owner(ctx_account, program_id, "NCN-UPTIME-MATCHER")?;
     verify_init_preconditions(ctx_account, UPTIME_MATCHER_MAGIC, "NCN-UPTIME-MATCHER")?;
+    let extended = ctx_account.data_len() >= CTX_V2_SIZE;
+
+    let mode = data[1];
+    if mode != MODE_CONTINUOUS && mode != MODE_SLASHING_SETTLEMENT {
+        msg!("NCN-UPTIME-MATCHER: Invalid mode {}", mode);
+        return Err(UptimeMatcherError::InvalidMode.into());
+    }
 
     let initial_uptime = u64::from_le_bytes(
         data[18..26].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
@@ -48,10 +111,120 @@ pub fn process_init(
         msg!("NCN-UPTIME-MATCHER: Initial uptime {} exceeds max {}", initial_uptime, MAX_PROBABILITY);
         return Err(UptimeMatcherError::InvalidProbability.into());
     }
+    if initial_uptime == 0 && !uptime_from_sync {
+        msg!("NCN-UPTIME-MATCHER: Initial uptime is 0 -- the market would reject matches until the first sync");
+        return Err(UptimeMatcherError::InvalidProbability.into());
+    }
+
+    let impact_model = data.get(98).copied().unwrap_or(IMPACT_MODEL_LINEAR);
+    if impact_model > IMPACT_MODEL_SQRT {
+        msg!("NCN-UPTIME-MATCHER: Invalid impact model {}", impact_model);
+        return Err(UptimeMatcherError::InvalidImpactModel.into());
+    }
+
+    let impact_k = u32::from_le_bytes(
+        data[14..18].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let liquidity = u128::from_le_bytes(
+        data[34..50].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    check_liquidity_config(impact_k, liquidity)?;
+
+    let max_spread = u32::from_le_bytes(
+        data[10..14].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    if max_spread > MAX_SPREAD_LIMIT_BPS {
+        msg!("NCN-UPTIME-MATCHER: Max spread {} exceeds limit {}", max_spread, MAX_SPREAD_LIMIT_BPS);
+        return Err(UptimeMatcherError::InvalidSpreadConfig.into());
+    }
+    let min_spread = read_opt_u32(data, 99);
+    if min_spread > max_spread {
+        msg!("NCN-UPTIME-MATCHER: Min spread {} exceeds max spread {}", min_spread, max_spread);
+        return Err(UptimeMatcherError::InvalidSpreadConfig.into());
+    }
+
+    let inverted = data.get(103).copied().unwrap_or(0);
+    if inverted > 1 {
+        msg!("NCN-UPTIME-MATCHER: Invalid inverted flag {}", inverted);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Every byte is a valid action table
+    let signal_actions = data.get(104).copied().unwrap_or(0);
+
+    let auto_resolve = data.get(105).copied().unwrap_or(0);
+    if auto_resolve > 1 {
+        msg!("NCN-UPTIME-MATCHER: Invalid auto_resolve_on_expiry flag {}", auto_resolve);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let expiry_outcome = data.get(106).copied().unwrap_or(OUTCOME_SAFE);
+    if expiry_outcome != OUTCOME_SLASHED && expiry_outcome != OUTCOME_SAFE {
+        msg!("NCN-UPTIME-MATCHER: Invalid expiry default outcome {}", expiry_outcome);
+        return Err(UptimeMatcherError::InvalidOutcome.into());
+    }
+
+    let expiry_window = read_opt_u32(data, 107);
+    let expiry_max_multiplier = read_opt_u32(data, 111);
+    if expiry_window > 0 && (expiry_max_multiplier as u64) < BPS_SCALE {
+        msg!("NCN-UPTIME-MATCHER: Expiry spread multiplier {} below 1x", expiry_max_multiplier);
+        return Err(UptimeMatcherError::InvalidExpiryMultiplier.into());
+    }
+
+    let min_mark = data
+        .get(115..123)
+        .and_then(|b| b.try_into().ok())
+        .map(u64::from_le_bytes)
+        .unwrap_or(0);
+    if min_mark > MAX_PROBABILITY {
+        msg!("NCN-UPTIME-MATCHER: Min mark {} exceeds max {}", min_mark, MAX_PROBABILITY);
+        return Err(UptimeMatcherError::InvalidProbability.into());
+    }
+
+    let clamp_to_par = data.get(131).copied().unwrap_or(0);
+    if clamp_to_par > 1 {
+        msg!("NCN-UPTIME-MATCHER: Invalid clamp_to_par flag {}", clamp_to_par);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Forced settlement only makes sense for a market that settles on slashing
+    let max_slashing = data.get(132).copied().unwrap_or(0);
+    if max_slashing > 0 && mode != MODE_SLASHING_SETTLEMENT {
+        msg!("NCN-UPTIME-MATCHER: max_slashing_before_resolve requires SlashingSettlement mode");
+        return Err(UptimeMatcherError::InvalidMode.into());
+    }
+
+    // Without a liquidity notional there is no depth for the cap to track
+    let edge_cap_by_liquidity = data.get(133).copied().unwrap_or(0);
+    if edge_cap_by_liquidity > 1 {
+        msg!("NCN-UPTIME-MATCHER: Invalid edge_cap_by_liquidity flag {}", edge_cap_by_liquidity);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if edge_cap_by_liquidity == 1 && liquidity == 0 {
+        msg!("NCN-UPTIME-MATCHER: edge_cap_by_liquidity requires a liquidity notional");
+        return Err(UptimeMatcherError::InvalidLiquidityConfig.into());
+    }
+
+    let protocol_fee_bps = data
+        .get(134..136)
+        .and_then(|b| b.try_into().ok())
+        .map(u16::from_le_bytes)
+        .unwrap_or(0);
+    if protocol_fee_bps > MAX_PROTOCOL_FEE_BPS {
+        msg!("NCN-UPTIME-MATCHER: Protocol fee {} bps exceeds max {}", protocol_fee_bps, MAX_PROTOCOL_FEE_BPS);
+        return Err(UptimeMatcherError::InvalidProtocolFeeConfig.into());
+    }
+
+    let extension = parse_extension(data.get(136..).unwrap_or(&[]), lp_pda.key, min_spread, max_spread, protocol_fee_bps)?;
+    if !extended && extension != ContextExtension::default() {
+        msg!("NCN-UPTIME-MATCHER: Version-2 options need a {}-byte context, got {}", CTX_V2_SIZE, ctx_account.data_len());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
 
     let mut ctx_data = ctx_account.try_borrow_mut_data()?;
 
-    write_header(&mut ctx_data, UPTIME_MATCHER_MAGIC, data[1], lp_pda.key);
+    write_header(&mut ctx_data, UPTIME_MATCHER_MAGIC, mode, lp_pda.key);
+    ctx_data[PRICE_DECIMALS_OFFSET] = UPTIME_PRICE_DECIMALS;
+    ctx_data[QUOTE_KIND_OFFSET] = QUOTE_KIND_PROBABILITY;
 
     // Spread params
     ctx_data[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4].copy_from_slice(&data[2..6]);
@@ -63,7 +236,7 @@ pub fn process_init(
     ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
         .copy_from_slice(&initial_uptime.to_le_bytes());
     ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
-        .copy_from_slice(&initial_uptime.to_le_bytes()); // mark = prob in e6
+        .copy_from_slice(&mark_for_uptime(initial_uptime, inverted == 1).to_le_bytes()); // mark = prob in e6
 
     let clock = Clock::get()?;
     ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
@@ -74,7 +247,13 @@ pub fn process_init(
         .copy_from_slice(&data[26..34]);
     ctx_data[IS_RESOLVED_OFFSET] = 0;
     ctx_data[RESOLUTION_OUTCOME_OFFSET] = 0;
-    ctx_data[162..168].fill(0); // padding
+    ctx_data[IMPACT_MODEL_OFFSET] = impact_model;
+    // An initial uptime of 0 (init + sync only) means "not yet set"; a 0% reading from a sync is real
+    ctx_data[HAS_SYNCED_OFFSET] = (initial_uptime > 0) as u8;
+    ctx_data[INVERTED_OFFSET] = inverted;
+    ctx_data[SIGNAL_ACTIONS_OFFSET] = signal_actions;
+    ctx_data[AUTO_RESOLVE_ON_EXPIRY_OFFSET] = auto_resolve;
+    ctx_data[EXPIRY_DEFAULT_OUTCOME_OFFSET] = expiry_outcome;
 
     // Signal (init to none)
     ctx_data[SIGNAL_SEVERITY_OFFSET..SIGNAL_SEVERITY_OFFSET + 8]
@@ -83,14 +262,33 @@ pub fn process_init(
         .copy_from_slice(&0u64.to_le_bytes());
 
     // Liquidity + max fill
-    ctx_data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&data[34..50]);
-    ctx_data[MAX_FILL_OFFSET..MAX_FILL_OFFSET + 16].copy_from_slice(&data[50..66]);
+    write_u128(&mut ctx_data, LIQUIDITY_OFFSET, read_u128(data, 34)?)?;
+    write_u128(&mut ctx_data, MAX_FILL_OFFSET, read_u128(data, 50)?)?;
 
     // NCN oracle
     ctx_data[NCN_ORACLE_OFFSET..NCN_ORACLE_OFFSET + 32].copy_from_slice(&data[66..98]);
 
-    // Zero reserved
+    // Zero reserved, then write extension params
     ctx_data[248..CTX_SIZE].fill(0);
+    ctx_data[LIQUIDITY_CAPPED_OFFSET] = (read_u128(&ctx_data, LIQUIDITY_OFFSET)? > 0) as u8;
+    ctx_data[MIN_SPREAD_OFFSET..MIN_SPREAD_OFFSET + 4].copy_from_slice(&min_spread.to_le_bytes());
+    ctx_data[EXPIRY_WIDEN_WINDOW_OFFSET..EXPIRY_WIDEN_WINDOW_OFFSET + 4]
+        .copy_from_slice(&expiry_window.to_le_bytes());
+    ctx_data[EXPIRY_MAX_MULTIPLIER_OFFSET..EXPIRY_MAX_MULTIPLIER_OFFSET + 4]
+        .copy_from_slice(&expiry_max_multiplier.to_le_bytes());
+    ctx_data[MIN_MARK_OFFSET..MIN_MARK_OFFSET + 8].copy_from_slice(&min_mark.to_le_bytes());
+    ctx_data[EDGE_SPREAD_LOW_OFFSET..EDGE_SPREAD_LOW_OFFSET + 4]
+        .copy_from_slice(&read_opt_u32(data, 123).to_le_bytes());
+    ctx_data[RESOLUTION_GRACE_OFFSET..RESOLUTION_GRACE_OFFSET + 4]
+        .copy_from_slice(&read_opt_u32(data, 127).to_le_bytes());
+    ctx_data[CLAMP_TO_PAR_OFFSET] = clamp_to_par;
+    ctx_data[MAX_SLASHING_BEFORE_RESOLVE_OFFSET] = max_slashing;
+    ctx_data[EDGE_CAP_BY_LIQUIDITY_OFFSET] = edge_cap_by_liquidity;
+    ctx_data[PROTOCOL_FEE_BPS_OFFSET..PROTOCOL_FEE_BPS_OFFSET + 2]
+        .copy_from_slice(&protocol_fee_bps.to_le_bytes());
+    if extended {
+        write_extension(&mut ctx_data, &extension);
+    }
 
     let resolution_ts = i64::from_le_bytes(
         data[26..34].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
@@ -106,14 +304,159 @@ pub fn process_init(
     Ok(())
 }
 
+/// Tag 0x0E: Initialize and apply the first uptime sync in one instruction, so a new
+/// market can quote without a second transaction
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable, 320 bytes, or 448 for version 2)
+///   [2] NCN oracle account (read — must equal the init oracle pubkey)
+/// Data:
+///   [0]     tag (0x0E)
+///   [1..33] sync payload, laid out as tag 0x03 bytes [1..33] (sequence must be > 0)
+///   [33..]  init fields, laid out as tag 0x02 bytes [1..] (optional trailing fields allowed;
+///           initial_uptime_e6 may be 0 since the sync sets it)
+/// The sync payload is validated before anything is written.
+pub fn process_init_and_sync(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    require_sync_accounts(accounts, 1)?;
+    if data.len() < 1 + SYNC_PAYLOAD_SIZE + 97 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let payload = &data[1..1 + SYNC_PAYLOAD_SIZE];
+    let (_, sequence) = parse_sync_payload(payload)?;
+    check_oracle_sequence(0, sequence)?;
+
+    let mut init_data = Vec::with_capacity(data.len() - SYNC_PAYLOAD_SIZE);
+    init_data.push(0x02);
+    init_data.extend_from_slice(&data[1 + SYNC_PAYLOAD_SIZE..]);
+    init_context(program_id, &accounts[..2], &init_data, true)?;
+
+    apply_uptime_sync(program_id, &accounts[1], &accounts[2], payload)
+}
+
+/// Tag 0x08: Initialize, verifying the oracle account exists and is an NcnPerformanceFeed
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable, 320 bytes, or 448 for version 2)
+///   [2] NCN oracle account (read — must equal data[66..98])
+/// Data: same layout as tag 0x02
+/// Use tag 0x02 when the oracle feed is not deployed yet.
+pub fn process_init_with_oracle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 3 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 98 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let expected_oracle = Pubkey::new_from_array(
+        data[66..98].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    verify_oracle_account(&accounts[2], &expected_oracle)?;
+
+    process_init(program_id, &accounts[..2], data)
+}
+
+/// Check that `oracle` is the expected NcnPerformanceFeed: key, owner program, discriminator
+pub fn verify_oracle_account(oracle: &AccountInfo, expected: &Pubkey) -> ProgramResult {
+    if oracle.key != expected {
+        msg!("NCN-UPTIME-MATCHER: Oracle account {} does not match init data {}", oracle.key, expected);
+        return Err(UptimeMatcherError::OracleMismatch.into());
+    }
+    if *oracle.owner != NCN_ORACLE_PROGRAM_ID {
+        msg!("NCN-UPTIME-MATCHER: Oracle owned by {}, expected ncn-oracle", oracle.owner);
+        return Err(UptimeMatcherError::InvalidOracleAccount.into());
+    }
+    let oracle_data = oracle.try_borrow_data()?;
+    if oracle_data.get(..8) != Some(&NCN_PERFORMANCE_FEED_DISCRIMINATOR[..]) {
+        msg!("NCN-UPTIME-MATCHER: Oracle account is not an NcnPerformanceFeed");
+        return Err(UptimeMatcherError::InvalidOracleAccount.into());
+    }
+    Ok(())
+}
+
+/// Tag 0x0B: Rotate the referenced oracle to a migrated NcnPerformanceFeed
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable)
+///   [2] New NCN oracle account (owner + discriminator checked)
+/// Data:
+///   [0] tag (0x0B)
+/// Rejected once the market is resolved.
+pub fn process_set_oracle_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 3 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let lp_pda = &accounts[0];
+    let ctx_account = &accounts[1];
+    let new_oracle = &accounts[2];
+
+    validate_header(ctx_account, program_id)?;
+    verify_lp_pda_common(lp_pda, ctx_account, UPTIME_MATCHER_MAGIC, "NCN-UPTIME-MATCHER")?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    if ctx_data[IS_RESOLVED_OFFSET] == 1 {
+        msg!("NCN-UPTIME-MATCHER: Market is resolved -- oracle is frozen");
+        return Err(UptimeMatcherError::MarketResolved.into());
+    }
+    verify_oracle_account(new_oracle, new_oracle.key)?;
+    if !read_feed_is_active(&new_oracle.try_borrow_data()?)? {
+        msg!("NCN-UPTIME-MATCHER: New oracle {} is deactivated", new_oracle.key);
+        return Err(UptimeMatcherError::OracleInactive.into());
+    }
+
+    let old_oracle = Pubkey::new_from_array(
+        ctx_data[NCN_ORACLE_OFFSET..NCN_ORACLE_OFFSET + 32]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    ctx_data[NCN_ORACLE_OFFSET..NCN_ORACLE_OFFSET + 32].copy_from_slice(new_oracle.key.as_ref());
+    // An oracle-inactive halt applied to the old feed
+    ctx_data[ORACLE_INACTIVE_OFFSET] = 0;
+
+    msg!("ORACLE_ROTATED: old={} new={}", old_oracle, new_oracle.key);
+
+    Ok(())
+}
+
 /// Tag 0x00: Execute match — probability-based pricing with edge spread for NCN uptime
 /// Accounts:
 ///   [0] LP PDA (signer)
 ///   [1] Matcher context account (writable)
+/// Data:
+///   [0]     tag (0x00)
+///   [1..17] fill_size_abs (u128 LE, optional — 0/absent disables impact)
+///   [17]    partial_fill_allowed (u8, optional: 1 = fill what liquidity remains)
+///   [18..26] max_acceptable_price (u64 LE, optional — 0/absent disables the bound)
+/// When a liquidity notional is configured it is consumed by fills: a fill larger
+/// than what remains is rejected unless partial fills are allowed. The filled size is
+/// written to return data after the price. The quote is an ask, so the taker's
+/// slippage bound is a maximum price. Oracle data in the soft-stale band (see
+/// stale_limits) still quotes, at the penalty spread, with return data byte
+/// RETURN_DEGRADED_OFFSET set; past the hard limit the match is rejected.
 pub fn process_match(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _data: &[u8],
+    data: &[u8],
 ) -> ProgramResult {
     if accounts.len() < 2 {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -122,16 +465,21 @@ pub fn process_match(
     let lp_pda = &accounts[0];
     let ctx_account = &accounts[1];
 
+    validate_header(ctx_account, program_id)?;
+
+    // The exec price is written back to the context; fail here rather than at the borrow
+    if !ctx_account.is_writable {
+        msg!("NCN-UPTIME-MATCHER: Matcher context must be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
     // Verify LP PDA signature + context magic + PDA match
     verify_lp_pda_common(lp_pda, ctx_account, UPTIME_MATCHER_MAGIC, "NCN-UPTIME-MATCHER")?;
 
     let ctx_data = ctx_account.try_borrow_data()?;
 
-    // Check if market is resolved
-    if ctx_data[IS_RESOLVED_OFFSET] == 1 {
-        msg!("NCN-UPTIME-MATCHER: Market is resolved -- no more trading");
-        return Err(UptimeMatcherError::MarketResolved.into());
-    }
+    // The same gates, in the same order, that tag 0x1B reports on
+    let (clock, degraded) = check_match_gates(&ctx_data)?;
 
     let base_spread = u32::from_le_bytes(
         ctx_data[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4]
@@ -145,455 +493,4885 @@ pub fn process_match(
         ctx_data[MAX_SPREAD_OFFSET..MAX_SPREAD_OFFSET + 4]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
+    let impact_k = u32::from_le_bytes(
+        ctx_data[IMPACT_K_OFFSET..IMPACT_K_OFFSET + 4]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let liquidity = read_u128(&ctx_data, LIQUIDITY_OFFSET)?;
+    let impact_model = ctx_data[IMPACT_MODEL_OFFSET];
+    let inverted = ctx_data[INVERTED_OFFSET] == 1;
+    let min_spread = u32::from_le_bytes(
+        ctx_data[MIN_SPREAD_OFFSET..MIN_SPREAD_OFFSET + 4]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
     let uptime_e6 = u64::from_le_bytes(
         ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
-    let signal_adj = u64::from_le_bytes(
-        ctx_data[SIGNAL_ADJUSTED_SPREAD_OFFSET..SIGNAL_ADJUSTED_SPREAD_OFFSET + 8]
-            .try_into()
-            .map_err(|_| ProgramError::InvalidAccountData)?,
+    let signal_adj = read_signal_spread(&ctx_data)?;
+
+    let resolution_ts = read_resolution_timestamp(&ctx_data)?;
+    let expiry_multiplier = expiry_spread_multiplier_bps(
+        resolution_ts,
+        clock.unix_timestamp,
+        read_ctx_u32(&ctx_data, EXPIRY_WIDEN_WINDOW_OFFSET)?,
+        read_ctx_u32(&ctx_data, EXPIRY_MAX_MULTIPLIER_OFFSET)?,
+    );
+
+    let requested_fill = read_fill_size(data)?;
+    let partial_fill_allowed = data.get(17).copied().unwrap_or(0) == 1;
+    // A configured notional caps fills whether or not impact prices them
+    let capped = liquidity_capped(&ctx_data)?;
+    let fill_size = if capped {
+        resolve_fill(requested_fill, liquidity, partial_fill_allowed)?
+    } else {
+        requested_fill
+    };
+    let impact = compute_impact_bps(impact_model, impact_k, fill_size, liquidity);
+
+    let spread_params = SpreadParams {
+        base_spread,
+        edge_spread,
+        edge_spread_low: read_ctx_u32(&ctx_data, EDGE_SPREAD_LOW_OFFSET)?,
+        // A degraded quote is floored at the penalty spread (the max unless configured)
+        min_spread: if degraded { degraded_spread_floor(&ctx_data, max_spread)? } else { min_spread },
+        max_spread,
+        signal_adj,
+        impact,
+        expiry_multiplier_bps: expiry_multiplier,
+        min_mark: u64::from_le_bytes(
+            ctx_data[MIN_MARK_OFFSET..MIN_MARK_OFFSET + 8]
+                .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+        ),
+        clamp_to_par: ctx_data[CLAMP_TO_PAR_OFFSET] == 1,
+        // Capped on the depth available before this fill consumes it
+        edge_factor_cap: read_edge_factor_cap(&ctx_data)?,
+    };
+    let (exec_price, total_spread, edge_factor) = uptime_exec_price(uptime_e6, inverted, &spread_params)?;
+    check_slippage(exec_price, read_max_acceptable_price(data))?;
+
+    let fee = protocol_fee_e6(fill_size, total_spread, read_protocol_fee_bps(&ctx_data)?)?;
+    let accrued_fees = accrue_protocol_fee(&ctx_data, fee)?;
+
+    drop(ctx_data);
+
+    // Write execution price to return buffer
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    write_exec_price(&mut ctx_data, exec_price);
+    ctx_data[RETURN_FILLED_SIZE_OFFSET..RETURN_FILLED_SIZE_OFFSET + 16]
+        .copy_from_slice(&fill_size.to_le_bytes());
+    ctx_data[RETURN_DEGRADED_OFFSET] = degraded as u8;
+    if capped && fill_size > 0 {
+        write_u128(&mut ctx_data, LIQUIDITY_OFFSET, liquidity - fill_size)?;
+    }
+    ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8]
+        .copy_from_slice(&accrued_fees.to_le_bytes());
+    #[cfg(feature = "check-reserved")]
+    assert_reserved_zero(&ctx_data);
+
+    let mark = std::cmp::max(mark_for_uptime(uptime_e6, inverted), spread_params.min_mark);
+    log_match(
+        exec_price as i64,
+        total_spread,
+        mark as i64,
+        &format!(
+            "uptime_e6={} edge_factor_e6={} impact_bps={} expiry_mult_bps={} fill={} fee_e6={} degraded={}",
+            uptime_e6, edge_factor, impact, expiry_multiplier, fill_size, fee, degraded as u8
+        ),
     );
 
-    // Reject if uptime probability is 0 (not initialized)
-    if uptime_e6 == 0 {
+    Ok(())
+}
+
+/// Gating checks of a match, in order: resolved, resolution pending, probability set,
+/// signal halt, oracle deactivated, liquidity config, staleness, expiry. Shared
+/// by process_match and the tag 0x1B pre-check so both report the same first failure.
+/// Returns the clock read for the staleness and expiry checks, and whether the oracle
+/// data is in the soft-stale band (quote degraded).
+fn check_match_gates(ctx_data: &[u8]) -> Result<(Clock, bool), ProgramError> {
+    // Check if market is resolved
+    if ctx_data[IS_RESOLVED_OFFSET] == 1 {
+        msg!("NCN-UPTIME-MATCHER: Market is resolved -- no more trading");
+        return Err(UptimeMatcherError::MarketResolved.into());
+    }
+    // The outcome is as good as known while a resolution is pending
+    if ctx_data[PENDING_RESOLUTION_OFFSET] != 0 {
+        msg!("NCN-UPTIME-MATCHER: Resolution pending -- no more trading");
+        return Err(UptimeMatcherError::ResolutionPending.into());
+    }
+
+    // Reject if no probability has ever been set. A synced 0% uptime is a real price;
+    // contexts created before the flag existed fall back to treating 0 as unset.
+    let uptime_e6 = u64::from_le_bytes(
+        ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if ctx_data[HAS_SYNCED_OFFSET] == 0 && uptime_e6 == 0 {
         msg!("NCN-UPTIME-MATCHER: Uptime probability not set");
         return Err(UptimeMatcherError::ProbabilityNotSet.into());
     }
 
-    // Check oracle staleness (reject if > 200 slots old)
-    let last_update = u64::from_le_bytes(
-        ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+    // Conservative LPs stop quoting during an active incident instead of widening
+    let signal_severity = u64::from_le_bytes(
+        ctx_data[SIGNAL_SEVERITY_OFFSET..SIGNAL_SEVERITY_OFFSET + 8]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
-    let clock = Clock::get()?;
-    if clock.slot.saturating_sub(last_update) > 200 {
-        msg!("NCN-UPTIME-MATCHER: Oracle stale -- last update slot {}, current {}", last_update, clock.slot);
-        return Err(UptimeMatcherError::OracleStale.into());
+    if signal_action(ctx_data[SIGNAL_ACTIONS_OFFSET], signal_severity) & SIGNAL_ACTION_HALT != 0 {
+        msg!("NCN-UPTIME-MATCHER: Signal severity {} -- quoting halted", signal_severity);
+        return Err(UptimeMatcherError::CriticalSignalHalt.into());
     }
 
-    // Edge spread calculation:
-    // Edge factor = 1 / (p * (1-p) * 4)
-    // At 50%: factor = 1.0 (no extra spread)
-    // At 99.5% (typical NCN): factor ~100 (wider spread — high confidence zone)
-    // At 10%: factor ~2.78 (wider spread)
-    let p = uptime_e6 as u128;
-    let one_minus_p = MAX_PROBABILITY as u128 - p;
+    // Deactivation seen by process_check_oracle_active halts before staleness would
+    if ctx_data[ORACLE_INACTIVE_OFFSET] == 1 {
+        msg!("NCN-UPTIME-MATCHER: Oracle deactivated -- quoting halted");
+        return Err(UptimeMatcherError::OracleInactive.into());
+    }
 
-    // p * (1-p) * 4 / 1e12 gives us the denominator scaled appropriately
-    let edge_denominator = p
-        .checked_mul(one_minus_p)
-        .unwrap_or(0)
-        .checked_mul(4)
-        .unwrap_or(0)
-        / 1_000_000_000_000u128;
+    // Impact-priced markets never quote flat; a fully consumed notional stops here too
+    check_liquidity_config(read_ctx_u32(ctx_data, IMPACT_K_OFFSET)?, read_u128(ctx_data, LIQUIDITY_OFFSET)?)?;
 
-    let edge_factor = if edge_denominator > 0 {
-        std::cmp::min(1_000_000u128 / edge_denominator, 10_000_000u128) // Cap at 10x
-    } else {
-        10_000_000u128 // Max factor if at exactly 0% or 100%
+    // Check oracle staleness: reject past the hard limit, degrade in the soft-stale band
+    let last_update = u64::from_le_bytes(
+        ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let clock = Clock::get()?;
+    let degraded = match check_staleness(ctx_data, clock.slot.saturating_sub(last_update)) {
+        Ok(degraded) => degraded,
+        Err(e) => {
+            msg!("NCN-UPTIME-MATCHER: Oracle stale -- last update slot {}, current {}", last_update, clock.slot);
+            return Err(e);
+        }
     };
+    if degraded {
+        msg!("NCN-UPTIME-MATCHER: Oracle soft-stale -- last update slot {}, quoting at penalty spread", last_update);
+    }
 
-    let adjusted_edge = (edge_spread as u128)
-        .checked_mul(edge_factor)
-        .unwrap_or(0)
-        / 1_000_000u128;
+    // Past expiry the market only awaits resolution (by the oracle, or tag 0x0C)
+    let resolution_ts = read_resolution_timestamp(ctx_data)?;
+    if is_expired(resolution_ts, clock.unix_timestamp) {
+        msg!("NCN-UPTIME-MATCHER: Market expired at {} -- awaiting resolution", resolution_ts);
+        return Err(UptimeMatcherError::MarketExpired.into());
+    }
 
-    // Total spread = base + edge_adjustment + signal_adjustment
-    let total_spread = std::cmp::min(
-        (base_spread as u64).saturating_add(adjusted_edge as u64).saturating_add(signal_adj),
-        max_spread as u64,
-    );
+    Ok((clock, degraded))
+}
 
-    // Mark price = uptime_probability (already in e6 format)
-    // Exec price = mark * (1 + spread/10000)
-    let spread_mult = 10_000u64.saturating_add(total_spread);
-    let exec_price = ((uptime_e6 as u128)
-        .checked_mul(spread_mult as u128)
-        .ok_or(UptimeMatcherError::ArithmeticOverflow)?
-        / 10_000u128) as u64;
+/// Tag 0x1B: Report whether a match would currently pass its gating checks (no state
+/// change), so front-ends can disable a trade with the exact reason instead of
+/// simulating a match
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]      tag (0x1B)
+///   [1..17]  fill_size_abs (u128 LE, optional — checked against remaining liquidity)
+///   [17]     partial_fill_allowed (u8, optional: 1 = a fill larger than liquidity is OK)
+/// Return data: see CAN_MATCH_RETURN_SIZE layout in state.rs
+/// Gate failures are reported in return data, not as an instruction error. Pricing-time
+/// failures (slippage, arithmetic) are not predicted.
+pub fn process_can_match(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
 
-    drop(ctx_data);
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
 
-    // Write execution price to return buffer
-    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
-    write_exec_price(&mut ctx_data, exec_price);
+    let out = encode_can_match(&ctx_data, data)?;
+    msg!("CAN_MATCH: ok={} reason={}", out[0], u32::from_le_bytes([out[1], out[2], out[3], out[4]]));
+    set_return_data(&out);
 
-    msg!(
-        "MATCH: price={} spread={} uptime={} edge_factor={}",
-        exec_price,
-        total_spread,
-        uptime_e6,
-        edge_factor
-    );
+    Ok(())
+}
 
+/// The match gates, then the requested fill against remaining liquidity
+fn match_precheck(ctx_data: &[u8], data: &[u8]) -> ProgramResult {
+    check_match_gates(ctx_data)?;
+    if liquidity_capped(ctx_data)? {
+        let partial_fill_allowed = data.get(17).copied().unwrap_or(0) == 1;
+        resolve_fill(read_fill_size(data)?, read_u128(ctx_data, LIQUIDITY_OFFSET)?, partial_fill_allowed)?;
+    }
     Ok(())
 }
 
+/// Encode the pre-check as CanMatch return data. Only matcher errors are match
+/// outcomes; anything else (bad account data, no clock) fails the instruction.
+pub fn encode_can_match(ctx_data: &[u8], data: &[u8]) -> Result<[u8; CAN_MATCH_RETURN_SIZE], ProgramError> {
+    let reason = match match_precheck(ctx_data, data) {
+        Ok(()) => 0,
+        Err(ProgramError::Custom(code)) if UptimeMatcherError::from_code(code).is_some() => code,
+        Err(e) => return Err(e),
+    };
+    let mut out = [0u8; CAN_MATCH_RETURN_SIZE];
+    out[0] = (reason == 0) as u8;
+    out[1..5].copy_from_slice(&reason.to_le_bytes());
+    Ok(out)
+}
+
 /// Tag 0x03: Sync uptime probability from NCN oracle
 /// Accounts:
 ///   [0] Matcher context account (writable)
-///   [1] NCN oracle account (read — must match stored oracle)
+///   [1] NCN oracle account (read — must be the stored NcnPerformanceFeed, and active)
 /// Data:
 ///   [0]    tag (0x03)
 ///   [1..9] new_uptime_e6 (u64 LE, 0-1_000_000)
-///   [9..17] signal_severity (u64 LE, 0-3)
-///   [17..25] signal_adjusted_spread (u64 LE)
+///   [9..17] signal_severity (u64 LE, ignored — read from the oracle)
+///   [17..25] signal_adjusted_spread (u64 LE, ignored — derived from the oracle's severity)
+///   [25..33] oracle_sequence (u64 LE, the feed's last_updated read at; must exceed the last applied sequence)
 pub fn process_uptime_sync(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
-    if accounts.len() < 2 {
-        return Err(ProgramError::NotEnoughAccountKeys);
-    }
-    if data.len() < 25 {
+    require_sync_accounts(accounts, 0)?;
+    if data.len() < 1 + SYNC_PAYLOAD_SIZE {
         return Err(ProgramError::InvalidInstructionData);
     }
 
-    let ctx_account = &accounts[0];
-    let oracle = &accounts[1];
+    apply_uptime_sync(program_id, &accounts[0], &accounts[1], &data[1..1 + SYNC_PAYLOAD_SIZE])
+}
 
-    if !ctx_account.is_writable {
-        return Err(ProgramError::InvalidAccountData);
+/// Tag 0x07: Sync several markets in one instruction
+/// Accounts (per market i, in order):
+///   [2i]     Matcher context account (writable)
+///   [2i + 1] NCN oracle account (read — must be that context's stored NcnPerformanceFeed)
+/// Data:
+///   [0]    tag (0x07)
+///   [1]    market count (u8, 1-8)
+///   [2..]  per-market payloads, 32 bytes each, laid out as tag 0x03 bytes [1..33]
+/// Any invalid market fails the whole batch, as does a context listed twice: each entry is
+/// validated against the context as it was before the batch, so a later entry could
+/// otherwise overwrite a market an earlier one had just resolved.
+pub fn process_batch_uptime_sync(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if data.len() < 2 {
+        return Err(ProgramError::InvalidInstructionData);
     }
 
-    // Verify context + oracle
-    {
-        let ctx_data = ctx_account.try_borrow_data()?;
-        if !verify_magic(&ctx_data) {
-            return Err(ProgramError::UninitializedAccount);
-        }
-
-        // Check market not resolved
-        if ctx_data[IS_RESOLVED_OFFSET] == 1 {
-            msg!("NCN-UPTIME-MATCHER: Cannot sync -- market resolved");
-            return Err(UptimeMatcherError::MarketResolved.into());
-        }
-
-        let stored_oracle = read_ncn_oracle(&ctx_data)?;
-        if *oracle.key != stored_oracle {
-            msg!("NCN-UPTIME-MATCHER: Oracle mismatch");
-            return Err(UptimeMatcherError::OracleMismatch.into());
+    let count = data[1] as usize;
+    // Rejected before any work so an oversized batch never runs out of compute mid-loop
+    if count > MAX_BATCH_SYNC_MARKETS {
+        msg!("NCN-UPTIME-MATCHER: Batch sync of {} markets exceeds max {}", count, MAX_BATCH_SYNC_MARKETS);
+        return Err(UptimeMatcherError::BatchTooLarge.into());
+    }
+    if count == 0 {
+        msg!("NCN-UPTIME-MATCHER: Empty batch sync");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if data.len() < 2 + count * SYNC_PAYLOAD_SIZE {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    for i in 0..count {
+        require_sync_accounts(accounts, 2 * i)?;
+        if let Some(j) = (0..i).find(|&j| accounts[2 * j].key == accounts[2 * i].key) {
+            msg!("NCN-UPTIME-MATCHER: Batch entries {} and {} sync the same context", j, i);
+            return Err(UptimeMatcherError::DuplicateBatchMarket.into());
         }
     }
 
-    let new_uptime = u64::from_le_bytes(
-        data[1..9].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    if new_uptime > MAX_PROBABILITY {
-        return Err(UptimeMatcherError::InvalidProbability.into());
+    // Validate every market before writing any, so one bad entry rejects the whole batch
+    // with no context touched
+    let mut validated: Vec<ValidatedSync> = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = 2 + i * SYNC_PAYLOAD_SIZE;
+        let sync = validate_uptime_sync(
+            program_id,
+            &accounts[2 * i],
+            &accounts[2 * i + 1],
+            &data[start..start + SYNC_PAYLOAD_SIZE],
+        )
+        .inspect_err(|_| msg!("NCN-UPTIME-MATCHER: Batch entry {} rejected -- no market synced", i))?;
+        validated.push(sync);
     }
 
-    let signal_severity = u64::from_le_bytes(
-        data[9..17].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    if signal_severity > SIGNAL_CRITICAL {
-        return Err(UptimeMatcherError::InvalidSignalSeverity.into());
+    let clock = Clock::get()?;
+    for (i, sync) in validated.iter().enumerate() {
+        write_uptime_sync(&accounts[2 * i], sync, &clock)?;
     }
 
-    let signal_spread = u64::from_le_bytes(
-        data[17..25].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    let clock = Clock::get()?;
+    Ok(())
+}
 
-    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
-    let old_uptime = u64::from_le_bytes(
-        ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
-            .try_into()
-            .map_err(|_| ProgramError::InvalidAccountData)?,
+/// Validate one context/oracle pair and apply a 32-byte sync payload:
+/// [0..8] new_uptime_e6, [8..16] signal_severity (ignored), [16..24] signal_adjusted_spread
+/// (ignored), [24..32] oracle_sequence. The severity stored, the slashing count and the
+/// signal spread charged all follow the severity the oracle itself recorded, so the keeper
+/// can set none of them.
+fn apply_uptime_sync(
+    program_id: &Pubkey,
+    ctx_account: &AccountInfo,
+    oracle: &AccountInfo,
+    payload: &[u8],
+) -> ProgramResult {
+    let sync = validate_uptime_sync(program_id, ctx_account, oracle, payload)?;
+    write_uptime_sync(ctx_account, &sync, &Clock::get()?)
+}
+
+/// A sync payload that has passed every check against its context and oracle
+struct ValidatedSync {
+    new_uptime: u64,
+    oracle_severity: u64,
+    sequence: u64,
+}
+
+/// Every check of a sync, with no writes: accounts, payload ranges, the oracle's severity
+/// and replay protection
+fn validate_uptime_sync(
+    program_id: &Pubkey,
+    ctx_account: &AccountInfo,
+    oracle: &AccountInfo,
+    payload: &[u8],
+) -> Result<ValidatedSync, ProgramError> {
+    verify_sync_accounts(program_id, ctx_account, oracle)?;
+    require_oracle_active(oracle)?;
+
+    let (new_uptime, sequence) = parse_sync_payload(payload)?;
+    let oracle_severity = read_feed_signal_severity(&oracle.try_borrow_data()?)?;
+
+    let last_sequence = u64::from_le_bytes(
+        ctx_account.try_borrow_data()?[ORACLE_SEQUENCE_OFFSET..ORACLE_SEQUENCE_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    check_oracle_sequence(last_sequence, sequence)?;
+    check_sequence_within_feed(sequence, read_feed_last_updated(&oracle.try_borrow_data()?)?)?;
+
+    Ok(ValidatedSync { new_uptime, oracle_severity, sequence })
+}
+
+/// Write a validated sync to its context at the clock's slot
+fn write_uptime_sync(ctx_account: &AccountInfo, sync: &ValidatedSync, clock: &Clock) -> ProgramResult {
+    let ValidatedSync { new_uptime, oracle_severity, sequence } = *sync;
+    let slot = clock.slot;
+    let signal_spread = SIGNAL_SPREAD_BPS[oracle_severity as usize];
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    let old_uptime = u64::from_le_bytes(
+        ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+
+    let old_severity = u64::from_le_bytes(
+        ctx_data[SIGNAL_SEVERITY_OFFSET..SIGNAL_SEVERITY_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+
+    let inverted = ctx_data[INVERTED_OFFSET] == 1;
+    ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
+        .copy_from_slice(&new_uptime.to_le_bytes());
+    ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
+        .copy_from_slice(&mark_for_uptime(new_uptime, inverted).to_le_bytes());
+    ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+        .copy_from_slice(&slot.to_le_bytes());
+    ctx_data[SIGNAL_SEVERITY_OFFSET..SIGNAL_SEVERITY_OFFSET + 8]
+        .copy_from_slice(&oracle_severity.to_le_bytes());
+    ctx_data[SIGNAL_ADJUSTED_SPREAD_OFFSET..SIGNAL_ADJUSTED_SPREAD_OFFSET + 8]
+        .copy_from_slice(&signal_spread.to_le_bytes());
+    ctx_data[HAS_SYNCED_OFFSET] = 1;
+    ctx_data[ORACLE_SEQUENCE_OFFSET..ORACLE_SEQUENCE_OFFSET + 8].copy_from_slice(&sequence.to_le_bytes());
+
+    msg!(
+        "UPTIME_SYNC: old_uptime={} new_uptime={} signal={} signal_spread={} seq={}",
+        old_uptime,
+        new_uptime,
+        oracle_severity,
+        signal_spread,
+        sequence
+    );
+
+    if record_slashing_signal(&mut ctx_data, old_severity, oracle_severity, slot, clock.unix_timestamp)? {
+        if ctx_data[IS_RESOLVED_OFFSET] == 1 {
+            msg!("NCN-UPTIME-MATCHER: Slashing limit reached -- resolved SLASHED at slot {}", slot);
+        } else {
+            msg!("NCN-UPTIME-MATCHER: Slashing limit reached -- SLASHED proposed, finalizable after the grace period");
+        }
+    }
+
+    Ok(())
+}
+
+/// Count a sync that raises the signal to SLASH_SIGNAL_THRESHOLD or above as a slashing
+/// event and, once max_slashing_before_resolve have been seen, resolve the market SLASHED
+/// (overriding a pending SAFE proposal). A market with a resolution grace period gets a
+/// SLASHED proposal instead, finalized by tag 0x14 once the grace window has passed.
+/// Returns true if the limit was reached.
+pub fn record_slashing_signal(
+    ctx_data: &mut [u8],
+    old_severity: u64,
+    new_severity: u64,
+    slot: u64,
+    now: i64,
+) -> Result<bool, ProgramError> {
+    let max_slashing = ctx_data[MAX_SLASHING_BEFORE_RESOLVE_OFFSET];
+    // A signal that stays elevated across syncs is one incident, not several
+    if max_slashing == 0 || old_severity >= SLASH_SIGNAL_THRESHOLD || new_severity < SLASH_SIGNAL_THRESHOLD {
+        return Ok(false);
+    }
+
+    let count = read_ctx_u32(ctx_data, SLASHING_COUNT_OFFSET)?.saturating_add(1);
+    ctx_data[SLASHING_COUNT_OFFSET..SLASHING_COUNT_OFFSET + 4].copy_from_slice(&count.to_le_bytes());
+    if count < max_slashing as u32 {
+        return Ok(false);
+    }
+
+    if read_ctx_u32(ctx_data, RESOLUTION_GRACE_OFFSET)? > 0 {
+        // A SLASHED proposal already pending keeps its original deadline
+        if ctx_data[PENDING_RESOLUTION_OFFSET] != OUTCOME_SLASHED + 1 {
+            propose_resolution(ctx_data, OUTCOME_SLASHED, now)?;
+        }
+    } else {
+        clear_pending_resolution(ctx_data);
+        apply_resolution(ctx_data, OUTCOME_SLASHED, slot);
+    }
+    Ok(true)
+}
+
+/// Decode and range-check a 32-byte sync payload into (uptime_e6, oracle_sequence); the
+/// keeper's severity and spread fields are skipped
+fn parse_sync_payload(payload: &[u8]) -> Result<(u64, u64), ProgramError> {
+    let field = |i: usize| -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(
+            payload
+                .get(i * 8..i * 8 + 8)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(ProgramError::InvalidInstructionData)?,
+        ))
+    };
+
+    let new_uptime = field(0)?;
+    if new_uptime > MAX_PROBABILITY {
+        return Err(UptimeMatcherError::InvalidProbability.into());
+    }
+
+    Ok((new_uptime, field(3)?))
+}
+
+/// Replay protection: a sync must carry a sequence strictly greater than the last
+/// applied one, so a re-submitted or late transaction cannot regress the mark
+pub fn check_oracle_sequence(last_sequence: u64, sequence: u64) -> ProgramResult {
+    if sequence <= last_sequence {
+        msg!("NCN-UPTIME-MATCHER: Oracle sequence {} not after last applied {}", sequence, last_sequence);
+        return Err(UptimeMatcherError::OracleSequenceRegressed.into());
+    }
+    Ok(())
+}
+
+/// The sequence is the feed's last_updated the payload was read at, so it can never run
+/// ahead of the feed: a caller cannot jump it to u64::MAX and lock out every later sync
+pub fn check_sequence_within_feed(sequence: u64, feed_updated: i64) -> ProgramResult {
+    if sequence > feed_updated.max(0) as u64 {
+        msg!("NCN-UPTIME-MATCHER: Oracle sequence {} ahead of feed last_updated {}", sequence, feed_updated);
+        return Err(UptimeMatcherError::OracleSequenceAhead.into());
+    }
+    Ok(())
+}
+
+/// Version-2 extension fields, laid out in init data from byte 136 and in migrate data
+/// from byte 1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextExtension {
+    pub fee_authority: Pubkey,
+    pub soft_stale_slots: u32,
+    pub hard_stale_slots: u32,
+    pub penalty_spread: u32,
+    pub dispute_authority: Pubkey,
+}
+
+/// Parse and validate extension fields (all optional, absent = 0): [0..32]
+/// protocol_fee_authority, [32..36] soft_stale_slots, [36..40] hard_stale_slots,
+/// [40..44] penalty_spread_bps, [44..76] dispute_authority. A protocol fee needs an
+/// authority to claim it, and the LP PDA cannot dispute its own market's resolution.
+pub fn parse_extension(
+    params: &[u8],
+    lp_pda: &Pubkey,
+    min_spread: u32,
+    max_spread: u32,
+    protocol_fee_bps: u16,
+) -> Result<ContextExtension, ProgramError> {
+    let extension = ContextExtension {
+        fee_authority: params
+            .get(0..32)
+            .and_then(|b| b.try_into().ok())
+            .map(Pubkey::new_from_array)
+            .unwrap_or_default(),
+        soft_stale_slots: read_opt_u32(params, 32),
+        hard_stale_slots: read_opt_u32(params, 36),
+        penalty_spread: read_opt_u32(params, 40),
+        dispute_authority: params
+            .get(44..76)
+            .and_then(|b| b.try_into().ok())
+            .map(Pubkey::new_from_array)
+            .unwrap_or_default(),
+    };
+    let (soft, hard) = (extension.soft_stale_slots, extension.hard_stale_slots);
+    if hard != 0 && (hard as u64) < effective_soft_stale_slots(soft) {
+        msg!("NCN-UPTIME-MATCHER: Hard-stale limit {} below soft-stale threshold {}", hard, soft);
+        return Err(UptimeMatcherError::InvalidStalenessConfig.into());
+    }
+    let penalty = extension.penalty_spread;
+    if penalty != 0 && (penalty < min_spread || penalty > max_spread) {
+        msg!("NCN-UPTIME-MATCHER: Penalty spread {} outside min {} / max {}", penalty, min_spread, max_spread);
+        return Err(UptimeMatcherError::InvalidPenaltySpread.into());
+    }
+    if protocol_fee_bps > 0 && extension.fee_authority == Pubkey::default() {
+        msg!("NCN-UPTIME-MATCHER: Protocol fee {} bps set without a protocol fee authority", protocol_fee_bps);
+        return Err(UptimeMatcherError::InvalidProtocolFeeConfig.into());
+    }
+    if extension.dispute_authority == *lp_pda {
+        msg!("NCN-UPTIME-MATCHER: The LP PDA cannot be the dispute authority");
+        return Err(UptimeMatcherError::DisputeAuthorityMismatch.into());
+    }
+    Ok(extension)
+}
+
+/// Mark the context version 2 and write the extension, zeroing its reserved tail
+pub fn write_extension(ctx_data: &mut [u8], extension: &ContextExtension) {
+    ctx_data[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(&CTX_VERSION_2.to_le_bytes());
+    ctx_data[CTX_SIZE..CTX_V2_SIZE].fill(0);
+    ctx_data[PROTOCOL_FEE_AUTHORITY_OFFSET..PROTOCOL_FEE_AUTHORITY_OFFSET + 32]
+        .copy_from_slice(extension.fee_authority.as_ref());
+    ctx_data[SOFT_STALE_SLOTS_OFFSET..SOFT_STALE_SLOTS_OFFSET + 4]
+        .copy_from_slice(&extension.soft_stale_slots.to_le_bytes());
+    ctx_data[HARD_STALE_SLOTS_OFFSET..HARD_STALE_SLOTS_OFFSET + 4]
+        .copy_from_slice(&extension.hard_stale_slots.to_le_bytes());
+    ctx_data[PENALTY_SPREAD_OFFSET..PENALTY_SPREAD_OFFSET + 4]
+        .copy_from_slice(&extension.penalty_spread.to_le_bytes());
+    ctx_data[DISPUTE_AUTHORITY_OFFSET..DISPUTE_AUTHORITY_OFFSET + 32]
+        .copy_from_slice(extension.dispute_authority.as_ref());
+}
+
+/// Tag 0x1C: Migrate a version-1 context to version 2 in place: grow the account to
+/// CTX_V2_SIZE, with the payer topping up rent, and write the extension. Unmigrated
+/// contexts keep working without the extension's features.
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable)
+///   [2] Payer (signer, writable)
+///   [3] System program
+/// Data:
+///   [0]    tag (0x1C)
+///   [1..]  extension fields, laid out as tag 0x02 bytes [136..] (all optional)
+pub fn process_migrate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let lp_pda = &accounts[0];
+    let ctx_account = &accounts[1];
+    let payer = &accounts[2];
+    let system = &accounts[3];
+
+    validate_header(ctx_account, program_id)?;
+    verify_lp_pda_common(lp_pda, ctx_account, UPTIME_MATCHER_MAGIC, "NCN-UPTIME-MATCHER")?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !payer.is_signer {
+        msg!("NCN-UPTIME-MATCHER: Migration payer must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *system.key != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let extension = {
+        let ctx_data = ctx_account.try_borrow_data()?;
+        if has_extension(&ctx_data) {
+            msg!("NCN-UPTIME-MATCHER: Context is already version {}", CTX_VERSION_2);
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        parse_extension(
+            data.get(1..).unwrap_or(&[]),
+            lp_pda.key,
+            read_ctx_u32(&ctx_data, MIN_SPREAD_OFFSET)?,
+            read_ctx_u32(&ctx_data, MAX_SPREAD_OFFSET)?,
+            read_protocol_fee_bps(&ctx_data)?,
+        )?
+    };
+
+    let shortfall = Rent::get()?.minimum_balance(CTX_V2_SIZE).saturating_sub(ctx_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, ctx_account.key, shortfall),
+            &[payer.clone(), ctx_account.clone(), system.clone()],
+        )?;
+    }
+    ctx_account.resize(CTX_V2_SIZE)?;
+    write_extension(&mut ctx_account.try_borrow_mut_data()?, &extension);
+
+    msg!("MIGRATE: context={} version={}->{} size={}", ctx_account.key, CTX_VERSION_1, CTX_VERSION_2, CTX_V2_SIZE);
+    Ok(())
+}
+
+/// Validate a context against this matcher's magic (see matcher_utils::validate_header).
+/// Every handler taking an initialized context starts here.
+pub fn validate_header(ctx_account: &AccountInfo, program_id: &Pubkey) -> Result<HeaderView, ProgramError> {
+    matcher_utils::validate_header(ctx_account, program_id, UPTIME_MATCHER_MAGIC, "NCN-UPTIME-MATCHER")
+}
+
+/// Check that a sync instruction carries a context at `ctx_index` and its oracle right
+/// after it, logging which slot is missing: a missing context is NotEnoughAccountKeys,
+/// a missing oracle OracleAccountMissing (distinct from a wrong oracle, OracleMismatch)
+fn require_sync_accounts(accounts: &[AccountInfo], ctx_index: usize) -> ProgramResult {
+    if accounts.len() <= ctx_index {
+        msg!("NCN-UPTIME-MATCHER: Missing matcher context at index {}", ctx_index);
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if accounts.len() <= ctx_index + 1 {
+        msg!("NCN-UPTIME-MATCHER: Missing NcnPerformanceFeed at index {}", ctx_index + 1);
+        return Err(UptimeMatcherError::OracleAccountMissing.into());
+    }
+    Ok(())
+}
+
+/// Check that a sync target is a writable, unresolved uptime context bound to `oracle`
+fn verify_sync_accounts(program_id: &Pubkey, ctx_account: &AccountInfo, oracle: &AccountInfo) -> ProgramResult {
+    validate_header(ctx_account, program_id)?;
+
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let ctx_data = ctx_account.try_borrow_data()?;
+
+    // Check market not resolved
+    if ctx_data[IS_RESOLVED_OFFSET] == 1 {
+        msg!("NCN-UPTIME-MATCHER: Cannot sync -- market resolved");
+        return Err(UptimeMatcherError::MarketResolved.into());
+    }
+
+    // The sync reads the signal severity from the oracle, so it must be a genuine feed
+    verify_oracle_account(oracle, &read_ncn_oracle(&ctx_data)?)
+}
+
+/// Refuse to sync or heartbeat from a deactivated oracle: an inactive feed's data is no
+/// longer maintained, so it can neither move nor keep alive the price
+fn require_oracle_active(oracle: &AccountInfo) -> ProgramResult {
+    if !read_feed_is_active(&oracle.try_borrow_data()?)? {
+        msg!("NCN-UPTIME-MATCHER: Oracle {} deactivated -- refused", oracle.key);
+        return Err(UptimeMatcherError::OracleInactive.into());
+    }
+    Ok(())
+}
+
+/// Tag 0x09: Heartbeat — keeper proves liveness when uptime is unchanged.
+/// Refreshes the last-update slot (so the staleness check passes) without touching prices,
+/// only while the oracle itself is active and fresh: its last_updated must be within
+/// MAX_HEARTBEAT_FEED_AGE_SECS (the keeper keeps it so with heartbeat_ncn_performance).
+/// Accounts:
+///   [0] Matcher context account (writable)
+///   [1] NCN oracle account (read — must be the stored NcnPerformanceFeed, and active)
+/// Data:
+///   [0]    tag (0x09)
+pub fn process_heartbeat(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    require_sync_accounts(accounts, 0)?;
+
+    let ctx_account = &accounts[0];
+    verify_sync_accounts(program_id, ctx_account, &accounts[1])?;
+    require_oracle_active(&accounts[1])?;
+
+    let clock = Clock::get()?;
+    let feed_updated = read_feed_last_updated(&accounts[1].try_borrow_data()?)?;
+    check_heartbeat_feed_age(feed_updated, clock.unix_timestamp)?;
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+        .copy_from_slice(&clock.slot.to_le_bytes());
+
+    msg!("HEARTBEAT: slot={} feed_updated={}", clock.slot, feed_updated);
+
+    Ok(())
+}
+
+/// Refuse a heartbeat backed by an oracle not updated within MAX_HEARTBEAT_FEED_AGE_SECS
+pub fn check_heartbeat_feed_age(feed_updated: i64, now: i64) -> ProgramResult {
+    let age = now.saturating_sub(feed_updated);
+    if age > MAX_HEARTBEAT_FEED_AGE_SECS {
+        msg!("NCN-UPTIME-MATCHER: Oracle last updated {}s ago -- heartbeat refused", age);
+        return Err(UptimeMatcherError::OracleStale.into());
+    }
+    Ok(())
+}
+
+/// Tag 0x04: Resolve NCN slashing event — sets final probability to 0 (SLASHED) or 1_000_000 (SAFE)
+/// Accounts:
+///   [0] Matcher context account (writable)
+///   [1] NCN oracle account (signer — must be authorized oracle)
+/// Data:
+///   [0] tag (0x04)
+///   [1] outcome (u8: 0=SLASHED -> prob=0, 1=SAFE -> prob=1_000_000)
+pub fn process_resolve(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 2 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let ctx_account = &accounts[0];
+    let oracle = &accounts[1];
+    let outcome = verify_resolver(program_id, ctx_account, oracle, data[1])?;
+
+    // Markets with a grace period only resolve through propose + finalize
+    if read_ctx_u32(&ctx_account.try_borrow_data()?, RESOLUTION_GRACE_OFFSET)? > 0 {
+        msg!("NCN-UPTIME-MATCHER: Market has a resolution grace period -- use propose/finalize");
+        return Err(UptimeMatcherError::GracePeriodActive.into());
+    }
+
+    let clock = Clock::get()?;
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    let final_price = apply_resolution(&mut ctx_data, outcome, clock.slot);
+
+    msg!(
+        "RESOLVE: outcome={} final_price={}",
+        if outcome == OUTCOME_SAFE { "SAFE" } else { "SLASHED" },
+        final_price
+    );
+
+    Ok(())
+}
+
+/// Shared checks for the oracle-driven resolution paths: the context is an unresolved
+/// uptime market with nothing pending, the oracle is the stored one and has signed, and
+/// the outcome is SLASHED or SAFE. Returns the outcome.
+fn verify_resolver(program_id: &Pubkey, ctx_account: &AccountInfo, oracle: &AccountInfo, outcome: u8) -> Result<u8, ProgramError> {
+    validate_header(ctx_account, program_id)?;
+
+    // Oracle must be signer
+    if !oracle.is_signer {
+        msg!("NCN-UPTIME-MATCHER: Oracle must be signer for resolution");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let ctx_data = ctx_account.try_borrow_data()?;
+    if ctx_data[IS_RESOLVED_OFFSET] == 1 {
+        msg!("NCN-UPTIME-MATCHER: Already resolved");
+        return Err(UptimeMatcherError::MarketResolved.into());
+    }
+    if ctx_data[PENDING_RESOLUTION_OFFSET] != 0 {
+        msg!("NCN-UPTIME-MATCHER: A resolution is already pending");
+        return Err(UptimeMatcherError::ResolutionPending.into());
+    }
+
+    let stored_oracle = read_ncn_oracle(&ctx_data)?;
+    if *oracle.key != stored_oracle {
+        msg!("NCN-UPTIME-MATCHER: Oracle mismatch");
+        return Err(UptimeMatcherError::OracleMismatch.into());
+    }
+
+    if outcome != OUTCOME_SLASHED && outcome != OUTCOME_SAFE {
+        msg!("NCN-UPTIME-MATCHER: Invalid outcome: {} (must be 0=SLASHED or 1=SAFE)", outcome);
+        return Err(UptimeMatcherError::InvalidOutcome.into());
+    }
+    Ok(outcome)
+}
+
+/// Tag 0x13: Propose a resolution — records a pending outcome that can be finalized
+/// (tag 0x14) once the market's grace period has passed, or cancelled (tag 0x15) by
+/// the dispute authority meanwhile. Matches are halted while it is pending.
+/// Accounts:
+///   [0] Matcher context account (writable)
+///   [1] NCN oracle account (signer — must be authorized oracle)
+/// Data:
+///   [0] tag (0x13)
+///   [1] outcome (u8: 0=SLASHED, 1=SAFE)
+pub fn process_propose_resolution(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 2 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let ctx_account = &accounts[0];
+    let outcome = verify_resolver(program_id, ctx_account, &accounts[1], data[1])?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let clock = Clock::get()?;
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    let resolved_after = propose_resolution(&mut ctx_data, outcome, clock.unix_timestamp)?;
+
+    msg!(
+        "RESOLUTION_PROPOSED: outcome={} resolved_after={}",
+        if outcome == OUTCOME_SAFE { "SAFE" } else { "SLASHED" },
+        resolved_after
     );
 
-    ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
-        .copy_from_slice(&new_uptime.to_le_bytes());
-    ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
-        .copy_from_slice(&new_uptime.to_le_bytes());
-    ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
-        .copy_from_slice(&clock.slot.to_le_bytes());
-    ctx_data[SIGNAL_SEVERITY_OFFSET..SIGNAL_SEVERITY_OFFSET + 8]
-        .copy_from_slice(&signal_severity.to_le_bytes());
-    ctx_data[SIGNAL_ADJUSTED_SPREAD_OFFSET..SIGNAL_ADJUSTED_SPREAD_OFFSET + 8]
-        .copy_from_slice(&signal_spread.to_le_bytes());
+    Ok(())
+}
+
+/// Record `outcome` as pending until now + the grace period; returns resolved_after
+pub fn propose_resolution(ctx_data: &mut [u8], outcome: u8, now: i64) -> Result<i64, ProgramError> {
+    let grace = read_ctx_u32(ctx_data, RESOLUTION_GRACE_OFFSET)?;
+    let resolved_after = now.saturating_add(grace as i64);
+    ctx_data[PENDING_RESOLUTION_OFFSET] = outcome + 1;
+    ctx_data[RESOLVED_AFTER_OFFSET..RESOLVED_AFTER_OFFSET + 8].copy_from_slice(&resolved_after.to_le_bytes());
+    Ok(resolved_after)
+}
+
+/// Tag 0x14: Finalize a pending resolution once its grace period has passed
+/// (permissionless crank)
+/// Accounts:
+///   [0] Matcher context account (writable)
+/// Data:
+///   [0] tag (0x14)
+pub fn process_finalize_resolution(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let ctx_account = &accounts[0];
+    validate_header(ctx_account, program_id)?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let clock = Clock::get()?;
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    let (outcome, final_price) = finalize_resolution(&mut ctx_data, clock.unix_timestamp, clock.slot)?;
+
+    msg!(
+        "RESOLVE: outcome={} final_price={} (finalized)",
+        if outcome == OUTCOME_SAFE { "SAFE" } else { "SLASHED" },
+        final_price
+    );
+
+    Ok(())
+}
+
+/// Apply the pending resolution if its grace period has passed; returns (outcome, final price)
+pub fn finalize_resolution(ctx_data: &mut [u8], now: i64, slot: u64) -> Result<(u8, u64), ProgramError> {
+    let outcome = match ctx_data[PENDING_RESOLUTION_OFFSET] {
+        0 => {
+            msg!("NCN-UPTIME-MATCHER: No resolution pending");
+            return Err(UptimeMatcherError::NoPendingResolution.into());
+        }
+        pending => pending - 1,
+    };
+    let resolved_after = i64::from_le_bytes(
+        ctx_data[RESOLVED_AFTER_OFFSET..RESOLVED_AFTER_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if now < resolved_after {
+        msg!("NCN-UPTIME-MATCHER: Grace period runs until {}, now {}", resolved_after, now);
+        return Err(UptimeMatcherError::GracePeriodActive.into());
+    }
+
+    clear_pending_resolution(ctx_data);
+    Ok((outcome, apply_resolution(ctx_data, outcome, slot)))
+}
+
+/// Tag 0x15: Cancel a pending resolution during its grace period (dispute by the
+/// context's dispute authority). At most MAX_RESOLUTION_CANCELS proposals can be
+/// cancelled, so the oracle's next proposal after that is final.
+/// Accounts:
+///   [0] Dispute authority (signer)
+///   [1] Matcher context account (writable)
+/// Data:
+///   [0] tag (0x15)
+pub fn process_cancel_resolution(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority = &accounts[0];
+    let ctx_account = &accounts[1];
+
+    validate_header(ctx_account, program_id)?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    verify_dispute_authority(authority, &ctx_data)?;
+    let (outcome, cancels) = cancel_resolution(&mut ctx_data)?;
+
+    msg!(
+        "RESOLUTION_CANCELLED: outcome={} cancels={}/{}",
+        if outcome == OUTCOME_SAFE { "SAFE" } else { "SLASHED" },
+        cancels,
+        MAX_RESOLUTION_CANCELS
+    );
+
+    Ok(())
+}
+
+/// Check that `authority` signed and is the dispute authority stored at init or
+/// migration. Without one (a version-1 context, or none configured) nobody can cancel.
+pub fn verify_dispute_authority(authority: &AccountInfo, ctx_data: &[u8]) -> ProgramResult {
+    if !authority.is_signer {
+        msg!("NCN-UPTIME-MATCHER: Dispute authority must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !has_extension(ctx_data) {
+        msg!("NCN-UPTIME-MATCHER: Version-1 context has no dispute authority -- migrate with tag 0x1C");
+        return Err(UptimeMatcherError::DisputeAuthorityMismatch.into());
+    }
+    let stored = Pubkey::new_from_array(
+        ctx_data[DISPUTE_AUTHORITY_OFFSET..DISPUTE_AUTHORITY_OFFSET + 32]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if stored == Pubkey::default() || *authority.key != stored {
+        msg!("NCN-UPTIME-MATCHER: {} is not the dispute authority {}", authority.key, stored);
+        return Err(UptimeMatcherError::DisputeAuthorityMismatch.into());
+    }
+    Ok(())
+}
+
+/// Clear the pending resolution and count the cancel; returns (cancelled outcome,
+/// cancels so far). Refused once MAX_RESOLUTION_CANCELS have been spent.
+pub fn cancel_resolution(ctx_data: &mut [u8]) -> Result<(u8, u8), ProgramError> {
+    if ctx_data[PENDING_RESOLUTION_OFFSET] == 0 {
+        msg!("NCN-UPTIME-MATCHER: No resolution pending");
+        return Err(UptimeMatcherError::NoPendingResolution.into());
+    }
+    let cancels = ctx_data[RESOLUTION_CANCELS_OFFSET];
+    if cancels >= MAX_RESOLUTION_CANCELS {
+        msg!("NCN-UPTIME-MATCHER: {} proposals already cancelled -- this one is final", cancels);
+        return Err(UptimeMatcherError::CancelLimitReached.into());
+    }
+    let outcome = ctx_data[PENDING_RESOLUTION_OFFSET] - 1;
+    clear_pending_resolution(ctx_data);
+    ctx_data[RESOLUTION_CANCELS_OFFSET] = cancels + 1;
+    Ok((outcome, cancels + 1))
+}
+
+fn clear_pending_resolution(ctx_data: &mut [u8]) {
+    ctx_data[PENDING_RESOLUTION_OFFSET] = 0;
+    ctx_data[RESOLVED_AFTER_OFFSET..RESOLVED_AFTER_OFFSET + 8].fill(0);
+}
+
+/// Write a terminal outcome (SLASHED or SAFE) into the context and return the final mark
+pub fn apply_resolution(ctx_data: &mut [u8], outcome: u8, slot: u64) -> u64 {
+    let final_probability = if outcome == OUTCOME_SAFE {
+        MAX_PROBABILITY // SAFE -> 100% uptime
+    } else {
+        0u64 // SLASHED -> 0%
+    };
+
+    // Inverted markets settle on the opposite side: SAFE -> 0, SLASHED -> 1_000_000
+    let final_price = mark_for_uptime(final_probability, ctx_data[INVERTED_OFFSET] == 1);
+    ctx_data[IS_RESOLVED_OFFSET] = 1;
+    ctx_data[RESOLUTION_OUTCOME_OFFSET] = outcome;
+    ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
+        .copy_from_slice(&final_probability.to_le_bytes());
+    ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
+        .copy_from_slice(&final_price.to_le_bytes());
+    ctx_data[RESOLVED_SLOT_OFFSET..RESOLVED_SLOT_OFFSET + 8]
+        .copy_from_slice(&slot.to_le_bytes());
+
+    final_price
+}
+
+/// Tag 0x0C: Expire — permissionless crank that resolves an expired market to its
+/// configured default outcome when auto_resolve_on_expiry is set. (process_match cannot
+/// do this itself: a rejected match rolls back any state it wrote.)
+/// Accounts:
+///   [0] Matcher context account (writable)
+/// Data:
+///   [0] tag (0x0C)
+pub fn process_expire(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let ctx_account = &accounts[0];
+
+    validate_header(ctx_account, program_id)?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let clock = Clock::get()?;
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    let outcome = expiry_outcome(&ctx_data, clock.unix_timestamp)?;
+    let final_price = apply_resolution(&mut ctx_data, outcome, clock.slot);
+
+    msg!(
+        "EXPIRE: auto-resolved outcome={} final_price={}",
+        if outcome == OUTCOME_SAFE { "SAFE" } else { "SLASHED" },
+        final_price
+    );
+
+    Ok(())
+}
+
+/// Outcome an expired market auto-resolves to, or why it cannot be auto-resolved yet
+pub fn expiry_outcome(ctx_data: &[u8], now: i64) -> Result<u8, ProgramError> {
+    if ctx_data[IS_RESOLVED_OFFSET] == 1 {
+        msg!("NCN-UPTIME-MATCHER: Already resolved");
+        return Err(UptimeMatcherError::MarketResolved.into());
+    }
+    // A pending (possibly disputed) outcome takes precedence over the expiry default
+    if ctx_data[PENDING_RESOLUTION_OFFSET] != 0 {
+        msg!("NCN-UPTIME-MATCHER: Resolution pending -- finalize or cancel it first");
+        return Err(UptimeMatcherError::ResolutionPending.into());
+    }
+    let resolution_ts = read_resolution_timestamp(ctx_data)?;
+    if !is_expired(resolution_ts, now) {
+        msg!("NCN-UPTIME-MATCHER: Not expired (resolution_ts={}, now={})", resolution_ts, now);
+        return Err(UptimeMatcherError::MarketNotExpired.into());
+    }
+    if ctx_data[AUTO_RESOLVE_ON_EXPIRY_OFFSET] != 1 {
+        msg!("NCN-UPTIME-MATCHER: Auto-resolve on expiry disabled -- oracle must resolve");
+        return Err(UptimeMatcherError::AutoResolveDisabled.into());
+    }
+    Ok(ctx_data[EXPIRY_DEFAULT_OUTCOME_OFFSET])
+}
+
+/// A market with a resolution timestamp is expired once that time is reached (0 = no expiry)
+pub fn is_expired(resolution_ts: i64, now: i64) -> bool {
+    resolution_ts > 0 && now >= resolution_ts
+}
+
+/// Spread multiplier (bps) for the time left to expiry: 1x outside the widening window,
+/// rising linearly to `max_multiplier_bps` at the resolution timestamp. Markets without
+/// an expiry or a window always quote at 1x.
+pub fn expiry_spread_multiplier_bps(resolution_ts: i64, now: i64, window_secs: u32, max_multiplier_bps: u32) -> u64 {
+    let max_multiplier = max_multiplier_bps as u64;
+    if resolution_ts <= 0 || window_secs == 0 || max_multiplier <= BPS_SCALE {
+        return BPS_SCALE;
+    }
+    let remaining = resolution_ts.saturating_sub(now);
+    if remaining >= window_secs as i64 {
+        return BPS_SCALE;
+    }
+    if remaining <= 0 {
+        return max_multiplier;
+    }
+    let elapsed = (window_secs as u128) - remaining as u128;
+    BPS_SCALE + ((max_multiplier - BPS_SCALE) as u128 * elapsed / window_secs as u128) as u64
+}
+
+/// Action bits (SIGNAL_ACTION_*) configured for `severity` in a signal action table
+pub fn signal_action(actions: u8, severity: u64) -> u8 {
+    let shift = 2 * (SIGNAL_CRITICAL - severity.min(SIGNAL_CRITICAL));
+    (actions >> shift) & 0b11
+}
+
+/// Signal spread charged at the current severity: the synced signal-adjusted spread, or
+/// 0 when the severity's action quotes without widening
+fn read_signal_spread(ctx_data: &[u8]) -> Result<u64, ProgramError> {
+    let read_u64 = |at: usize| -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(
+            ctx_data.get(at..at + 8).and_then(|b| b.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?,
+        ))
+    };
+    let severity = read_u64(SIGNAL_SEVERITY_OFFSET)?;
+    if signal_action(ctx_data[SIGNAL_ACTIONS_OFFSET], severity) & SIGNAL_ACTION_NO_WIDEN != 0 {
+        return Ok(0);
+    }
+    read_u64(SIGNAL_ADJUSTED_SPREAD_OFFSET)
+}
+
+/// Unclaimed protocol fees (e6 notional)
+fn read_accrued_fees(ctx_data: &[u8]) -> Result<u64, ProgramError> {
+    Ok(u64::from_le_bytes(
+        ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
+
+/// Accrued protocol fees after adding `fee`. Saturates at u64::MAX rather than failing,
+/// so an unclaimed balance can never halt matching; the fee authority claims to reset it.
+fn accrue_protocol_fee(ctx_data: &[u8], fee: u64) -> Result<u64, ProgramError> {
+    Ok(read_accrued_fees(ctx_data)?.saturating_add(fee))
+}
+
+fn read_protocol_fee_bps(ctx_data: &[u8]) -> Result<u16, ProgramError> {
+    Ok(u16::from_le_bytes(
+        ctx_data[PROTOCOL_FEE_BPS_OFFSET..PROTOCOL_FEE_BPS_OFFSET + 2]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
+
+fn read_resolution_timestamp(ctx_data: &[u8]) -> Result<i64, ProgramError> {
+    Ok(i64::from_le_bytes(
+        ctx_data[RESOLUTION_TIMESTAMP_OFFSET..RESOLUTION_TIMESTAMP_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
+
+/// Tag 0x05: Read the edge-spread curve at candidate uptime points (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x05)
+///   [1]    point count (u8, 1-16)
+///   [2..]  uptime_e6 points (u64 LE each, 0-1_000_000)
+/// Return data: total_spread_bps (u64 LE) per point, in input order
+pub fn process_get_spread_curve(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 2 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let count = data[1] as usize;
+    if count > MAX_SPREAD_CURVE_POINTS {
+        msg!("NCN-UPTIME-MATCHER: Spread curve of {} points exceeds max {}", count, MAX_SPREAD_CURVE_POINTS);
+        return Err(UptimeMatcherError::BatchTooLarge.into());
+    }
+    if count == 0 {
+        msg!("NCN-UPTIME-MATCHER: Empty spread curve request");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if data.len() < 2 + count * 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+    let params = curve_spread_params(&ctx_data)?;
+
+    let mut out = [0u8; MAX_SPREAD_CURVE_POINTS * 8];
+    for i in 0..count {
+        let start = 2 + i * 8;
+        let uptime_e6 = u64::from_le_bytes(
+            data[start..start + 8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+        );
+        if uptime_e6 > MAX_PROBABILITY {
+            return Err(UptimeMatcherError::InvalidProbability.into());
+        }
+        out[i * 8..i * 8 + 8].copy_from_slice(&curve_spread(&params, uptime_e6).to_le_bytes());
+    }
+
+    set_return_data(&out[..count * 8]);
+
+    Ok(())
+}
+
+/// Spread inputs of the uptime curve read by tags 0x05 and 0x18: the context's spreads
+/// and signal adjustment, with no fill impact or expiry widening
+fn curve_spread_params(ctx_data: &[u8]) -> Result<SpreadParams, ProgramError> {
+    Ok(SpreadParams {
+        base_spread: read_ctx_u32(ctx_data, BASE_SPREAD_OFFSET)?,
+        edge_spread: read_ctx_u32(ctx_data, EDGE_SPREAD_OFFSET)?,
+        edge_spread_low: read_ctx_u32(ctx_data, EDGE_SPREAD_LOW_OFFSET)?,
+        min_spread: read_ctx_u32(ctx_data, MIN_SPREAD_OFFSET)?,
+        max_spread: read_ctx_u32(ctx_data, MAX_SPREAD_OFFSET)?,
+        signal_adj: read_signal_spread(ctx_data)?,
+        edge_factor_cap: read_edge_factor_cap(ctx_data)?,
+        ..SpreadParams::default()
+    })
+}
+
+/// Total spread of the curve at one uptime point (the match spread before impact)
+fn curve_spread(params: &SpreadParams, uptime_e6: u64) -> u64 {
+    let edge = select_edge_spread(uptime_e6, params.edge_spread, params.edge_spread_low);
+    compute_total_spread(
+        uptime_e6,
+        params.base_spread,
+        edge,
+        params.min_spread,
+        params.max_spread,
+        params.signal_adj,
+        params.impact,
+        params.edge_factor_cap(),
+    )
+    .0
+}
+
+/// Tag 0x18: Average the spread curve over an uptime range (no state change), so LPs can
+/// size inventory against the spread they would charge across plausible outcomes
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]      tag (0x18)
+///   [1..9]   low uptime_e6 (u64 LE)
+///   [9..17]  high uptime_e6 (u64 LE, low <= high <= 1_000_000)
+///   [17]     grid samples (u8, optional — 0/absent uses DEFAULT_SPREAD_AVERAGE_SAMPLES;
+///            2-MAX_SPREAD_AVERAGE_SAMPLES otherwise)
+/// Return data: see SPREAD_AVERAGE_RETURN_SIZE layout in state.rs
+pub fn process_get_spread_average(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let read_u64 = |at: usize| -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(
+            data.get(at..at + 8).and_then(|b| b.try_into().ok()).ok_or(ProgramError::InvalidInstructionData)?,
+        ))
+    };
+    let (low, high) = (read_u64(1)?, read_u64(9)?);
+    let samples = match data.get(17).copied().unwrap_or(0) {
+        0 => DEFAULT_SPREAD_AVERAGE_SAMPLES,
+        n => n as usize,
+    };
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let out = encode_spread_average(&ctx_data, low, high, samples)?;
+    msg!(
+        "SPREAD_AVERAGE: low={} high={} samples={} avg_bps={}",
+        low,
+        high,
+        out[8],
+        u64::from_le_bytes(out[0..8].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+    );
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Mean of the curve spread over `samples` evenly spaced uptime points from `low` to
+/// `high` inclusive (a single point when the range is empty), rounded up so the figure
+/// never understates what the LP would charge
+pub fn encode_spread_average(
+    ctx_data: &[u8],
+    low: u64,
+    high: u64,
+    samples: usize,
+) -> Result<[u8; SPREAD_AVERAGE_RETURN_SIZE], ProgramError> {
+    if low > high || high > MAX_PROBABILITY {
+        msg!("NCN-UPTIME-MATCHER: Invalid uptime range {}..={}", low, high);
+        return Err(UptimeMatcherError::InvalidProbability.into());
+    }
+    if !(2..=MAX_SPREAD_AVERAGE_SAMPLES).contains(&samples) {
+        msg!("NCN-UPTIME-MATCHER: Spread average of {} samples outside 2..={}", samples, MAX_SPREAD_AVERAGE_SAMPLES);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let params = curve_spread_params(ctx_data)?;
+
+    let samples = if low == high { 1 } else { samples };
+    let step_span = (high - low) as u128;
+    let mut sum: u128 = 0;
+    for i in 0..samples {
+        // Grid points stay inside [low, high]: i / (samples - 1) <= 1
+        let offset = if samples == 1 { 0 } else { step_span * i as u128 / (samples as u128 - 1) };
+        sum += curve_spread(&params, low + offset as u64) as u128;
+    }
+    // At most 64 spreads, each a u64: the sum fits u128 and the mean fits u64
+    let average = sum.div_ceil(samples as u128) as u64;
+
+    let mut out = [0u8; SPREAD_AVERAGE_RETURN_SIZE];
+    out[0..8].copy_from_slice(&average.to_le_bytes());
+    out[8] = samples as u8;
+    Ok(out)
+}
+
+/// Tag 0x19: Read the unclaimed protocol fees and the fee rate (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0] tag (0x19)
+/// Return data: see ACCRUED_FEES_RETURN_SIZE layout in state.rs
+pub fn process_get_accrued_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let out = encode_accrued_fees(&ctx_data)?;
+    msg!(
+        "ACCRUED_FEES: fees_e6={} protocol_fee_bps={}",
+        read_accrued_fees(&ctx_data)?,
+        read_protocol_fee_bps(&ctx_data)?
+    );
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Encode the GetAccruedFees return data (see ACCRUED_FEES_RETURN_SIZE)
+pub fn encode_accrued_fees(ctx_data: &[u8]) -> Result<[u8; ACCRUED_FEES_RETURN_SIZE], ProgramError> {
+    let mut out = [0u8; ACCRUED_FEES_RETURN_SIZE];
+    out[0..8].copy_from_slice(&read_accrued_fees(ctx_data)?.to_le_bytes());
+    out[8..10].copy_from_slice(&read_protocol_fee_bps(ctx_data)?.to_le_bytes());
+    Ok(out)
+}
+
+/// Tag 0x1A: Claim the accrued protocol fees. The matcher holds no funds, so claiming
+/// zeroes the counter and reports the amount for the caller to settle. Allowed after
+/// resolution, since fees accrued before it are still owed. Only the protocol fee
+/// authority stored at init can claim; the LP cannot.
+/// Accounts:
+///   [0] Protocol fee authority (signer)
+///   [1] Matcher context account (writable)
+/// Data:
+///   [0] tag (0x1A)
+/// Return data: [0..8] claimed fees_e6 (u64 LE)
+pub fn process_claim_protocol_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let fee_authority = &accounts[0];
+    let ctx_account = &accounts[1];
+
+    validate_header(ctx_account, program_id)?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    verify_fee_authority(fee_authority, &ctx_data)?;
+    let claimed = claim_protocol_fees(&mut ctx_data)?;
+
+    msg!("FEES_CLAIMED: fees_e6={}", claimed);
+    set_return_data(&claimed.to_le_bytes());
+
+    Ok(())
+}
+
+/// Check that `authority` signed and is the protocol fee authority stored at init or
+/// migration. A version-1 context has no authority, so nobody can claim until it migrates.
+pub fn verify_fee_authority(authority: &AccountInfo, ctx_data: &[u8]) -> ProgramResult {
+    if !authority.is_signer {
+        msg!("NCN-UPTIME-MATCHER: Protocol fee authority must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !has_extension(ctx_data) {
+        msg!("NCN-UPTIME-MATCHER: Version-1 context has no protocol fee authority -- migrate with tag 0x1C");
+        return Err(UptimeMatcherError::FeeAuthorityMismatch.into());
+    }
+    let stored = Pubkey::new_from_array(
+        ctx_data[PROTOCOL_FEE_AUTHORITY_OFFSET..PROTOCOL_FEE_AUTHORITY_OFFSET + 32]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if *authority.key != stored {
+        msg!("NCN-UPTIME-MATCHER: {} is not the protocol fee authority {}", authority.key, stored);
+        return Err(UptimeMatcherError::FeeAuthorityMismatch.into());
+    }
+    Ok(())
+}
+
+/// Zero the accrued protocol fees, returning what was accrued
+pub fn claim_protocol_fees(ctx_data: &mut [u8]) -> Result<u64, ProgramError> {
+    let claimed = read_accrued_fees(ctx_data)?;
+    ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8].fill(0);
+    Ok(claimed)
+}
+
+/// Tag 0x06: Read the market's resolution state (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x06)
+/// Return data: see RESOLUTION_RETURN_SIZE layout in state.rs
+pub fn process_get_resolution(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    set_return_data(&encode_resolution(&ctx_data)?);
+
+    Ok(())
+}
+
+/// Encode the resolution fields of a context. Unresolved markets report
+/// is_resolved=0 with zero outcome, price and slot.
+pub fn encode_resolution(ctx_data: &[u8]) -> Result<[u8; RESOLUTION_RETURN_SIZE], ProgramError> {
+    let mut out = [0u8; RESOLUTION_RETURN_SIZE];
+    out[0] = RESOLUTION_LAYOUT_VERSION;
+    if ctx_data[IS_RESOLVED_OFFSET] != 1 {
+        return Ok(out);
+    }
+
+    out[1] = 1;
+    out[2] = ctx_data[RESOLUTION_OUTCOME_OFFSET];
+    out[3..11].copy_from_slice(
+        ctx_data
+            .get(UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8)
+            .ok_or(ProgramError::InvalidAccountData)?,
+    );
+    out[11..19].copy_from_slice(
+        ctx_data
+            .get(RESOLVED_SLOT_OFFSET..RESOLVED_SLOT_OFFSET + 8)
+            .ok_or(ProgramError::InvalidAccountData)?,
+    );
+    Ok(out)
+}
+
+/// Tag 0x0D: Read all pricing parameters in one packed struct (no state change),
+/// so clients need not reimplement the context offset table
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x0D)
+/// Return data: see PRICING_PARAMS_RETURN_SIZE layout in state.rs
+pub fn process_get_pricing_params(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    set_return_data(&encode_pricing_params(&ctx_data)?);
+
+    Ok(())
+}
+
+/// Pack the pricing fields of a context into the GetPricingParams layout
+pub fn encode_pricing_params(ctx_data: &[u8]) -> Result<[u8; PRICING_PARAMS_RETURN_SIZE], ProgramError> {
+    // (context offset, return offset, length)
+    const FIELDS: [(usize, usize, usize); 14] = [
+        (MODE_OFFSET, 1, 1),
+        (BASE_SPREAD_OFFSET, 2, 4),
+        (EDGE_SPREAD_OFFSET, 6, 4),
+        (MAX_SPREAD_OFFSET, 10, 4),
+        (MIN_SPREAD_OFFSET, 14, 4),
+        (IMPACT_K_OFFSET, 18, 4),
+        (IMPACT_MODEL_OFFSET, 22, 1),
+        (INVERTED_OFFSET, 23, 1),
+        (UPTIME_MARK_OFFSET, 24, 8),
+        (CURRENT_UPTIME_OFFSET, 32, 8),
+        (LAST_UPDATE_SLOT_OFFSET, 40, 8),
+        (IS_RESOLVED_OFFSET, 48, 1),
+        (PRICE_DECIMALS_OFFSET, 49, 1),
+        (QUOTE_KIND_OFFSET, 50, 1),
+    ];
+    let mut out = [0u8; PRICING_PARAMS_RETURN_SIZE];
+    out[0] = PRICING_PARAMS_LAYOUT_VERSION;
+    for (src, dst, len) in FIELDS {
+        out[dst..dst + len].copy_from_slice(ctx_data.get(src..src + len).ok_or(ProgramError::InvalidAccountData)?);
+    }
+    // Contexts initialized before the display metadata have zeros there
+    if out[49] == 0 {
+        out[49] = UPTIME_PRICE_DECIMALS;
+        out[50] = QUOTE_KIND_PROBABILITY;
+    }
+    Ok(out)
+}
+
+/// Tag 0x0F: Read how many slots remain before the matcher goes stale and stops quoting
+/// (no state change), so monitoring can alert before matches start failing
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x0F)
+/// Return data: see STALENESS_RETURN_SIZE layout in state.rs
+pub fn process_get_staleness(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let last_update = u64::from_le_bytes(
+        ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let current_slot = Clock::get()?.slot;
+    let (soft_stale, hard_stale) = stale_limits(&ctx_data)?;
+    let remaining = slots_until_stale(last_update, current_slot, soft_stale);
+
+    let mut out = [0u8; STALENESS_RETURN_SIZE];
+    out[0..8].copy_from_slice(&remaining.to_le_bytes());
+    out[8..16].copy_from_slice(&last_update.to_le_bytes());
+    out[16..24].copy_from_slice(&soft_stale.to_le_bytes());
+    out[24..32].copy_from_slice(&hard_stale.to_le_bytes());
+    msg!("STALENESS: slots_until_stale={} last_update={} current={}", remaining, last_update, current_slot);
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Slots left before a context last updated at `last_update` goes stale under a
+/// `max_staleness` slot limit (0 once the limit is reached)
+pub fn slots_until_stale(last_update: u64, current_slot: u64, max_staleness: u64) -> u64 {
+    max_staleness.saturating_sub(current_slot.saturating_sub(last_update))
+}
+
+/// Stored soft-stale threshold in slots, with 0 meaning MAX_STALENESS_SLOTS
+fn effective_soft_stale_slots(stored: u32) -> u64 {
+    if stored == 0 { MAX_STALENESS_SLOTS } else { stored as u64 }
+}
+
+/// (soft, hard) staleness limits in slots: oracle data older than `soft` quotes
+/// degraded at the penalty spread, older than `hard` is rejected. Without a hard
+/// limit (or the version-2 extension) the two coincide and there is no soft-stale band.
+pub fn stale_limits(ctx_data: &[u8]) -> Result<(u64, u64), ProgramError> {
+    if !has_extension(ctx_data) {
+        return Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS));
+    }
+    let soft = effective_soft_stale_slots(read_ctx_u32(ctx_data, SOFT_STALE_SLOTS_OFFSET)?);
+    let hard = (read_ctx_u32(ctx_data, HARD_STALE_SLOTS_OFFSET)? as u64).max(soft);
+    Ok((soft, hard))
+}
+
+/// Spread floor of a degraded quote: the configured penalty spread, or the max
+/// spread when none is set
+pub fn degraded_spread_floor(ctx_data: &[u8], max_spread: u32) -> Result<u32, ProgramError> {
+    if !has_extension(ctx_data) {
+        return Ok(max_spread);
+    }
+    let penalty = read_ctx_u32(ctx_data, PENALTY_SPREAD_OFFSET)?;
+    Ok(if penalty == 0 { max_spread } else { penalty })
+}
+
+/// Classify oracle data `age` slots old: Ok(false) fresh, Ok(true) soft-stale (quote
+/// degraded), OracleStale past the hard limit
+pub fn check_staleness(ctx_data: &[u8], age: u64) -> Result<bool, ProgramError> {
+    let (soft, hard) = stale_limits(ctx_data)?;
+    if age > hard {
+        return Err(UptimeMatcherError::OracleStale.into());
+    }
+    Ok(age > soft)
+}
+
+/// Tag 0x10: Read the current mark as decimal and American odds (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x10)
+/// Return data: see IMPLIED_ODDS_RETURN_SIZE layout in state.rs
+pub fn process_get_implied_odds(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let mark = u64::from_le_bytes(
+        ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let (decimal, american) = implied_odds_e6(mark).ok_or(ProgramError::InvalidAccountData)?;
+
+    let mut out = [0u8; IMPLIED_ODDS_RETURN_SIZE];
+    out[0..8].copy_from_slice(&mark.to_le_bytes());
+    out[8..16].copy_from_slice(&decimal.to_le_bytes());
+    out[16..24].copy_from_slice(&american.to_le_bytes());
+    msg!("IMPLIED_ODDS: mark={} decimal_e6={} american_e6={}", mark, decimal, american);
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Convert a probability mark (e6) to (decimal_odds_e6, american_odds_e6).
+/// Decimal odds are 1/p; American odds are +100*(1-p)/p for p <= 0.5 and
+/// -100*p/(1-p) above it. Both truncate toward zero. The extremes use the sentinels
+/// documented on IMPLIED_ODDS_RETURN_SIZE. None for a mark above MAX_PROBABILITY.
+pub fn implied_odds_e6(mark: u64) -> Option<(u64, i64)> {
+    if mark > MAX_PROBABILITY {
+        return None;
+    }
+    if mark == 0 {
+        return Some((u64::MAX, i64::MAX));
+    }
+    let (p, q) = (mark as u128, (MAX_PROBABILITY - mark) as u128);
+    let scale = PROBABILITY_SCALE as u128;
+    // 1e12 / p is at most 1e12 for p >= 1
+    let decimal = (scale * scale / p) as u64;
+    // 100 * 1e6 * (1e6 - 1) is the largest magnitude, well inside i64
+    let american = if q == 0 {
+        i64::MIN
+    } else if p <= q {
+        (100 * scale * q / p) as i64
+    } else {
+        -((100 * scale * p / q) as i64)
+    };
+    Some((decimal, american))
+}
+
+/// Tag 0x0A: Compare the stored mark against the live oracle (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+///   [1] NCN oracle account (read — must be the stored NcnPerformanceFeed)
+/// Data:
+///   [0]    tag (0x0A)
+///   [1..5] alert threshold (u32 LE, bps)
+/// Return data: [0..8] deviation_bps (u64 LE), [8] exceeds threshold (u8 0/1)
+pub fn process_check_mark_deviation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 5 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let threshold = u32::from_le_bytes(
+        data[1..5].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let oracle = &accounts[1];
+    verify_oracle_account(oracle, &read_ncn_oracle(&ctx_data)?)?;
+    let live_uptime = read_feed_uptime_e6(&oracle.try_borrow_data()?)?;
+
+    let stored_mark = u64::from_le_bytes(
+        ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let live_mark = mark_for_uptime(live_uptime, ctx_data[INVERTED_OFFSET] == 1);
+    let deviation = mark_deviation_bps(stored_mark, live_mark);
+    let exceeds = deviation > threshold as u64;
+    if exceeds {
+        msg!(
+            "NCN-UPTIME-MATCHER: Mark deviation {} bps exceeds {} (stored={} live={})",
+            deviation, threshold, stored_mark, live_mark
+        );
+    }
+
+    let mut out = [0u8; 9];
+    out[..8].copy_from_slice(&deviation.to_le_bytes());
+    out[8] = exceeds as u8;
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Tag 0x11: Check whether the referenced oracle is still active; if it has been
+/// deactivated, set the context's halt flag so matches stop immediately instead of
+/// at staleness. Permissionless, meant for keepers reacting to FeedDeactivated.
+/// Accounts:
+///   [0] Matcher context account (writable)
+///   [1] NCN oracle account (read — must be the stored NcnPerformanceFeed)
+/// Data:
+///   [0] tag (0x11)
+/// Return data: [0] oracle is_active (u8 0/1)
+pub fn process_check_oracle_active(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let ctx_account = &accounts[0];
+    let oracle = &accounts[1];
+
+    validate_header(ctx_account, program_id)?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    verify_oracle_account(oracle, &read_ncn_oracle(&ctx_data)?)?;
+    let active = read_feed_is_active(&oracle.try_borrow_data()?)?;
+    if !active && ctx_data[ORACLE_INACTIVE_OFFSET] == 0 {
+        ctx_data[ORACLE_INACTIVE_OFFSET] = 1;
+        msg!("ORACLE_INACTIVE: oracle={} -- quoting halted", oracle.key);
+    }
+    set_return_data(&[active as u8]);
+
+    Ok(())
+}
+
+/// Tag 0x12: Compare the stored oracle pubkey against an expected one (no state change),
+/// so a monitoring job can sweep a fleet of matchers against its oracle registry
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]     tag (0x12)
+///   [1..33] expected NcnPerformanceFeed pubkey
+/// Return data: see ORACLE_CONFIG_RETURN_SIZE layout in state.rs
+pub fn process_check_oracle_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let expected = Pubkey::new_from_array(
+        data.get(1..33)
+            .ok_or(ProgramError::InvalidInstructionData)?
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let out = encode_oracle_config(&ctx_data, &expected)?;
+    if out[0] == 0 {
+        msg!("ORACLE_CONFIG: mismatch stored={} expected={}", read_ncn_oracle(&ctx_data)?, expected);
+    }
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Pack the oracle-config check for `expected` into the CheckOracleConfig layout
+pub fn encode_oracle_config(ctx_data: &[u8], expected: &Pubkey) -> Result<[u8; ORACLE_CONFIG_RETURN_SIZE], ProgramError> {
+    let stored = read_ncn_oracle(ctx_data)?;
+    let mut out = [0u8; ORACLE_CONFIG_RETURN_SIZE];
+    out[0] = (stored == *expected) as u8;
+    out[1..33].copy_from_slice(stored.as_ref());
+    Ok(out)
+}
+
+/// Tag 0x16: Quote a hypothetical mark through the full match pricing pipeline (no state
+/// change), so LP strategies can be backtested independently of the live oracle value
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]      tag (0x16)
+///   [1..9]   mark_e6 (u64 LE, 0-1_000_000; the downtime probability on inverted markets)
+///   [9..25]  fill_size_abs (u128 LE, optional — 0/absent disables impact)
+///   [25..33] unix_timestamp (i64 LE, optional — 0/absent applies no expiry widening)
+/// Return data: see QUOTE_AT_MARK_RETURN_SIZE layout in state.rs
+/// Spreads, impact, liquidity and the mark floor come from the context as a match would
+/// see them; the resolved, halt and staleness checks are skipped.
+pub fn process_quote_at_mark(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let mark_e6 = u64::from_le_bytes(
+        data.get(1..9)
+            .ok_or(ProgramError::InvalidInstructionData)?
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let fill_size = data
+        .get(9..25)
+        .and_then(|b| b.try_into().ok())
+        .map(u128::from_le_bytes)
+        .unwrap_or(0);
+    let now = data
+        .get(25..33)
+        .and_then(|b| b.try_into().ok())
+        .map(i64::from_le_bytes)
+        .unwrap_or(0);
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    set_return_data(&quote_at_mark(&ctx_data, mark_e6, fill_size, now)?);
+
+    Ok(())
+}
+
+/// Price `mark_e6` against the context's pricing config, as process_match would with the
+/// uptime that produces that mark. `now` of 0 skips the expiry multiplier.
+pub fn quote_at_mark(
+    ctx_data: &[u8],
+    mark_e6: u64,
+    fill_size: u128,
+    now: i64,
+) -> Result<[u8; QUOTE_AT_MARK_RETURN_SIZE], ProgramError> {
+    if mark_e6 > MAX_PROBABILITY {
+        msg!("NCN-UPTIME-MATCHER: Mark {} exceeds max {}", mark_e6, MAX_PROBABILITY);
+        return Err(UptimeMatcherError::InvalidProbability.into());
+    }
+    let read_u64 = |at: usize| -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(
+            ctx_data.get(at..at + 8).and_then(|b| b.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?,
+        ))
+    };
+    let inverted = ctx_data[INVERTED_OFFSET] == 1;
+    // Inversion is its own inverse: the uptime behind a mark is the mark of that mark
+    let uptime_e6 = mark_for_uptime(mark_e6, inverted);
+
+    let impact_k = read_ctx_u32(ctx_data, IMPACT_K_OFFSET)?;
+    let liquidity = read_u128(ctx_data, LIQUIDITY_OFFSET)?;
+    // A fill the live match would reject is rejected here too
+    check_liquidity_config(impact_k, liquidity)?;
+    let fill_size = if liquidity_capped(ctx_data)? { resolve_fill(fill_size, liquidity, false)? } else { fill_size };
+    let impact = compute_impact_bps(ctx_data[IMPACT_MODEL_OFFSET], impact_k, fill_size, liquidity);
+
+    let expiry_multiplier = if now == 0 {
+        BPS_SCALE
+    } else {
+        expiry_spread_multiplier_bps(
+            read_resolution_timestamp(ctx_data)?,
+            now,
+            read_ctx_u32(ctx_data, EXPIRY_WIDEN_WINDOW_OFFSET)?,
+            read_ctx_u32(ctx_data, EXPIRY_MAX_MULTIPLIER_OFFSET)?,
+        )
+    };
+
+    let spread_params = SpreadParams {
+        base_spread: read_ctx_u32(ctx_data, BASE_SPREAD_OFFSET)?,
+        edge_spread: read_ctx_u32(ctx_data, EDGE_SPREAD_OFFSET)?,
+        edge_spread_low: read_ctx_u32(ctx_data, EDGE_SPREAD_LOW_OFFSET)?,
+        min_spread: read_ctx_u32(ctx_data, MIN_SPREAD_OFFSET)?,
+        max_spread: read_ctx_u32(ctx_data, MAX_SPREAD_OFFSET)?,
+        signal_adj: read_signal_spread(ctx_data)?,
+        impact,
+        expiry_multiplier_bps: expiry_multiplier,
+        min_mark: read_u64(MIN_MARK_OFFSET)?,
+        clamp_to_par: ctx_data[CLAMP_TO_PAR_OFFSET] == 1,
+        edge_factor_cap: read_edge_factor_cap(ctx_data)?,
+    };
+    let (exec_price, total_spread, edge_factor) = uptime_exec_price(uptime_e6, inverted, &spread_params)?;
+
+    let mut out = [0u8; QUOTE_AT_MARK_RETURN_SIZE];
+    out[0] = QUOTE_AT_MARK_LAYOUT_VERSION;
+    out[1..9].copy_from_slice(&exec_price.to_le_bytes());
+    out[9..17].copy_from_slice(&total_spread.to_le_bytes());
+    // The edge factor is capped at 20x (2e7) at most, well inside u64
+    out[17..25].copy_from_slice(&(edge_factor as u64).to_le_bytes());
+    out[25..33].copy_from_slice(&impact.to_le_bytes());
+    Ok(out)
+}
+
+/// Tag 0x17: Snapshot the pricing params and mark into a new immutable account, so
+/// auditors can prove the matcher's configuration at a given slot
+/// Accounts:
+///   [0] Matcher context account (read)
+///   [1] Snapshot account (writable, PDA of [SNAPSHOT_SEED, matcher context, slot (u64 LE)])
+///   [2] Payer (signer, writable)
+///   [3] System program
+/// Data:
+///   [0]    tag (0x17)
+/// Layout: see SNAPSHOT_SIZE in state.rs. The snapshot is owned by this program and no
+/// instruction writes to it after creation; one snapshot per matcher per slot.
+pub fn process_snapshot_matcher(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let ctx_account = &accounts[0];
+    let snapshot = &accounts[1];
+    let payer = &accounts[2];
+    let system = &accounts[3];
+
+    validate_header(ctx_account, program_id)?;
+    if !payer.is_signer {
+        msg!("NCN-UPTIME-MATCHER: Snapshot payer must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !snapshot.is_writable {
+        msg!("NCN-UPTIME-MATCHER: Snapshot account must be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if *system.key != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let clock = Clock::get()?;
+    let slot_bytes = clock.slot.to_le_bytes();
+    let (expected, bump) =
+        Pubkey::find_program_address(&[SNAPSHOT_SEED, ctx_account.key.as_ref(), &slot_bytes], program_id);
+    if *snapshot.key != expected {
+        msg!("NCN-UPTIME-MATCHER: Snapshot account is not the PDA for slot {}", clock.slot);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let out = encode_snapshot(&ctx_account.try_borrow_data()?, ctx_account.key, clock.slot, clock.unix_timestamp)?;
+
+    // create_account fails if the PDA already exists, so a snapshot is never overwritten
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            snapshot.key,
+            Rent::get()?.minimum_balance(SNAPSHOT_SIZE),
+            SNAPSHOT_SIZE as u64,
+            program_id,
+        ),
+        &[payer.clone(), snapshot.clone(), system.clone()],
+        &[&[SNAPSHOT_SEED, ctx_account.key.as_ref(), &slot_bytes, &[bump]]],
+    )?;
+    snapshot.try_borrow_mut_data()?.copy_from_slice(&out);
+
+    msg!("SNAPSHOT: matcher={} slot={}", ctx_account.key, clock.slot);
+    Ok(())
+}
+
+/// Serialize a snapshot of the context's pricing params and mark (see SNAPSHOT_SIZE)
+pub fn encode_snapshot(
+    ctx_data: &[u8],
+    matcher: &Pubkey,
+    slot: u64,
+    unix_timestamp: i64,
+) -> Result<[u8; SNAPSHOT_SIZE], ProgramError> {
+    let mut out = [0u8; SNAPSHOT_SIZE];
+    out[0..8].copy_from_slice(&UPTIME_SNAPSHOT_MAGIC.to_le_bytes());
+    out[8..40].copy_from_slice(matcher.as_ref());
+    out[40..48].copy_from_slice(&slot.to_le_bytes());
+    out[48..56].copy_from_slice(&unix_timestamp.to_le_bytes());
+    out[56..].copy_from_slice(&encode_pricing_params(ctx_data)?);
+    Ok(out)
+}
+
+/// Offset of the first field after the performance history in raw NcnPerformanceFeed
+/// account data (see PERF_FEED_* layout)
+fn perf_feed_after_history_at(feed_data: &[u8]) -> Result<usize, ProgramError> {
+    let read_u32 = |at: usize| -> Result<usize, ProgramError> {
+        let bytes = feed_data.get(at..at + 4).ok_or(ProgramError::InvalidAccountData)?;
+        Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| ProgramError::InvalidAccountData)?) as usize)
+    };
+    let name_len = read_u32(PERF_FEED_NAME_LEN_OFFSET)?;
+    if name_len > PERF_FEED_MAX_NAME_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let history_len_at = PERF_FEED_NAME_LEN_OFFSET + 4 + name_len + PERF_FEED_FIXED_AFTER_NAME;
+    let history_len = read_u32(history_len_at)?;
+    if history_len > PERF_FEED_MAX_HISTORY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(history_len_at + 4 + history_len * PERF_FEED_SAMPLE_SIZE)
+}
+
+/// Read is_active from raw NcnPerformanceFeed account data
+pub fn read_feed_is_active(feed_data: &[u8]) -> Result<bool, ProgramError> {
+    let at = perf_feed_after_history_at(feed_data)? + PERF_FEED_IS_ACTIVE_AFTER_HISTORY;
+    match feed_data.get(at) {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Read last_updated (unix seconds) from raw NcnPerformanceFeed account data
+pub fn read_feed_last_updated(feed_data: &[u8]) -> Result<i64, ProgramError> {
+    let at = perf_feed_after_history_at(feed_data)? + PERF_FEED_LAST_UPDATED_AFTER_HISTORY;
+    let bytes = feed_data.get(at..at + 8).ok_or(ProgramError::InvalidAccountData)?;
+    Ok(i64::from_le_bytes(bytes.try_into().map_err(|_| ProgramError::InvalidAccountData)?))
+}
+
+/// Read signal_severity (0-3) from raw NcnPerformanceFeed account data
+pub fn read_feed_signal_severity(feed_data: &[u8]) -> Result<u64, ProgramError> {
+    let at = perf_feed_after_history_at(feed_data)? + PERF_FEED_SIGNAL_SEVERITY_AFTER_HISTORY;
+    match feed_data.get(at) {
+        Some(&severity) if severity as u64 <= SIGNAL_CRITICAL => Ok(severity as u64),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Read uptime_probability_e6 from raw NcnPerformanceFeed account data
+pub fn read_feed_uptime_e6(feed_data: &[u8]) -> Result<u64, ProgramError> {
+    let name_len = feed_data
+        .get(PERF_FEED_NAME_LEN_OFFSET..PERF_FEED_NAME_LEN_OFFSET + 4)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let name_len = u32::from_le_bytes(name_len.try_into().map_err(|_| ProgramError::InvalidAccountData)?) as usize;
+    if name_len > PERF_FEED_MAX_NAME_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let start = PERF_FEED_NAME_LEN_OFFSET + 4 + name_len;
+    let uptime = feed_data.get(start..start + 8).ok_or(ProgramError::InvalidAccountData)?;
+    Ok(u64::from_le_bytes(uptime.try_into().map_err(|_| ProgramError::InvalidAccountData)?))
+}
+
+/// Relative deviation of the stored mark from the live mark, in bps of the live mark.
+/// A zero live mark yields 0 if the stored mark is also 0, otherwise u64::MAX.
+pub fn mark_deviation_bps(stored_mark: u64, live_mark: u64) -> u64 {
+    if live_mark == 0 {
+        return if stored_mark == 0 { 0 } else { u64::MAX };
+    }
+    let deviation = (stored_mark.abs_diff(live_mark) as u128) * BPS_SCALE as u128 / live_mark as u128;
+    u64::try_from(deviation).unwrap_or(u64::MAX)
+}
+
+/// Edge spread for the side of 50% the uptime is on: `edge_spread_high` above it,
+/// `edge_spread_low` at or below. A zero low side keeps a single symmetric edge spread.
+pub fn select_edge_spread(uptime_e6: u64, edge_spread_high: u32, edge_spread_low: u32) -> u32 {
+    if edge_spread_low == 0 || uptime_e6 > EDGE_SPLIT_UPTIME_E6 {
+        edge_spread_high
+    } else {
+        edge_spread_low
+    }
+}
+
+/// Total spread in bps for a given uptime: base + edge adjustment + signal adjustment
+/// + fill impact, clamped to [min, max]. Returns (total_spread, edge_factor).
+///
+/// Edge factor = 1 / (p * (1-p) * 4)
+/// At 50%: factor = 1.0 (no extra spread)
+/// At 99.5% (typical NCN): factor ~100 (wider spread — high confidence zone)
+/// At 10%: factor ~2.78 (wider spread)
+/// The factor is capped at `max_edge_factor` (e6): MAX_EDGE_FACTOR_E6 (10x) unless the
+/// market scales it by liquidity (see edge_factor_cap_for_liquidity).
+#[allow(clippy::too_many_arguments)]
+pub fn compute_total_spread(
+    uptime_e6: u64,
+    base_spread: u32,
+    edge_spread: u32,
+    min_spread: u32,
+    max_spread: u32,
+    signal_adj: u64,
+    impact: u64,
+    max_edge_factor: u128,
+) -> (u64, u128) {
+    let p = uptime_e6 as u128;
+    let one_minus_p = MAX_PROBABILITY as u128 - p;
+
+    // p * (1-p) * 4 / scale^2 gives us the denominator scaled appropriately
+    let edge_denominator = p
+        .checked_mul(one_minus_p)
+        .unwrap_or(0)
+        .checked_mul(4)
+        .unwrap_or(0)
+        / (PROBABILITY_SCALE as u128 * PROBABILITY_SCALE as u128);
+
+    // Edge factor is in e6 (1x = E6_SCALE)
+    let edge_factor = if edge_denominator > 0 {
+        std::cmp::min(E6_SCALE as u128 / edge_denominator, max_edge_factor)
+    } else {
+        max_edge_factor // Max factor if at exactly 0% or 100%
+    };
+
+    let adjusted_edge = (edge_spread as u128)
+        .checked_mul(edge_factor)
+        .unwrap_or(0)
+        / E6_SCALE as u128;
+
+    // Total spread = base + edge_adjustment + signal_adjustment + impact
+    let total_spread = clamp_spread(
+        (base_spread as u64)
+            .saturating_add(adjusted_edge as u64)
+            .saturating_add(signal_adj)
+            .saturating_add(impact),
+        min_spread,
+        max_spread,
+    );
+
+    (total_spread, edge_factor)
+}
+
+/// Edge-factor cap (e6) for a market with `liquidity` notional remaining: the first
+/// LIQUIDITY_EDGE_CAP_BUCKETS bound it falls below, else DEEP_LIQUIDITY_EDGE_FACTOR_E6.
+/// Fills consume liquidity, so a market's cap tightens as its book thins.
+pub fn edge_factor_cap_for_liquidity(liquidity: u128) -> u128 {
+    LIQUIDITY_EDGE_CAP_BUCKETS
+        .iter()
+        .find(|(bound, _)| liquidity < *bound)
+        .map(|(_, cap)| *cap)
+        .unwrap_or(DEEP_LIQUIDITY_EDGE_FACTOR_E6)
+}
+
+/// Edge-factor cap for a context: liquidity-scaled when the market opted in at init,
+/// else 0 (the default MAX_EDGE_FACTOR_E6)
+fn read_edge_factor_cap(ctx_data: &[u8]) -> Result<u128, ProgramError> {
+    if ctx_data.get(EDGE_CAP_BY_LIQUIDITY_OFFSET).copied() != Some(1) {
+        return Ok(0);
+    }
+    Ok(edge_factor_cap_for_liquidity(read_u128(ctx_data, LIQUIDITY_OFFSET)?))
+}
+
+/// Mark price for an uptime probability: uptime itself, or downtime (1e6 - uptime) when inverted
+pub fn mark_for_uptime(uptime_e6: u64, inverted: bool) -> u64 {
+    if inverted {
+        MAX_PROBABILITY.saturating_sub(uptime_e6)
+    } else {
+        uptime_e6
+    }
+}
+
+/// Apply the spread floor, then the max cap (the cap wins if misconfigured below the floor)
+pub fn clamp_spread(spread: u64, min_spread: u32, max_spread: u32) -> u64 {
+    std::cmp::min(std::cmp::max(spread, min_spread as u64), max_spread as u64)
+}
+
+/// Read an optional trailing u32 extension field from instruction data (0 when absent)
+fn read_opt_u32(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4)
+        .and_then(|b| b.try_into().ok())
+        .map(u32::from_le_bytes)
+        .unwrap_or(0)
+}
+
+/// Read the optional fill size from match instruction data (0 when absent)
+fn read_fill_size(data: &[u8]) -> Result<u128, ProgramError> {
+    if data.len() < 17 {
+        return Ok(0);
+    }
+    Ok(u128::from_le_bytes(
+        data[1..17].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    ))
+}
+
+/// Read the optional taker price bound from match instruction data (0 when absent)
+fn read_max_acceptable_price(data: &[u8]) -> u64 {
+    data.get(18..26)
+        .and_then(|b| b.try_into().ok())
+        .map(u64::from_le_bytes)
+        .unwrap_or(0)
+}
+
+/// Reject a fill priced above the taker's bound (0 = no bound)
+pub fn check_slippage(exec_price: u64, max_acceptable_price: u64) -> ProgramResult {
+    if max_acceptable_price > 0 && exec_price > max_acceptable_price {
+        msg!("NCN-UPTIME-MATCHER: Exec price {} exceeds taker max {}", exec_price, max_acceptable_price);
+        return Err(UptimeMatcherError::SlippageExceeded.into());
+    }
+    Ok(())
+}
+
+/// Impact pricing divides by the liquidity notional: with impact enabled a zero notional
+/// would quote flat, so it is an error wherever a quote is priced (init, match, quote)
+pub fn check_liquidity_config(impact_k: u32, liquidity: u128) -> ProgramResult {
+    if impact_k > 0 && liquidity == 0 {
+        msg!("NCN-UPTIME-MATCHER: Impact enabled (k={}) with zero liquidity notional", impact_k);
+        return Err(UptimeMatcherError::InvalidLiquidityConfig.into());
+    }
+    Ok(())
+}
+
+/// Whether fills draw down the liquidity notional: a context initialized with one, or an
+/// impact-priced context from before LIQUIDITY_CAPPED_OFFSET was set at init
+pub fn liquidity_capped(ctx_data: &[u8]) -> Result<bool, ProgramError> {
+    Ok(ctx_data.get(LIQUIDITY_CAPPED_OFFSET).copied() == Some(1) || read_ctx_u32(ctx_data, IMPACT_K_OFFSET)? > 0)
+}
+
+/// Size actually filled against `available` liquidity: the full request when it fits,
+/// otherwise what remains if partial fills are allowed, else an error
+pub fn resolve_fill(requested: u128, available: u128, partial_fill_allowed: bool) -> Result<u128, ProgramError> {
+    if requested <= available {
+        Ok(requested)
+    } else if partial_fill_allowed {
+        Ok(available)
+    } else {
+        msg!("NCN-UPTIME-MATCHER: Fill {} exceeds remaining liquidity {}", requested, available);
+        Err(UptimeMatcherError::InsufficientLiquidity.into())
+    }
+}
+
+/// Fill-size impact in bps. `impact_k` is the impact charged when the fill equals
+/// the configured liquidity notional; both models agree at that point.
+///   Linear: impact = k * fill / liquidity
+///   Sqrt:   impact = k * sqrt(fill / liquidity)   (Almgren-style square-root impact)
+/// Returns 0 if impact is disabled (k == 0), the fill is empty, or no liquidity is configured.
+pub fn compute_impact_bps(model: u8, impact_k: u32, fill_size: u128, liquidity: u128) -> u64 {
+    if impact_k == 0 || fill_size == 0 {
+        return 0;
+    }
+    let impact = if model == IMPACT_MODEL_SQRT {
+        // sqrt(fill / liquidity) in e6 = isqrt(fill * 1e12 / liquidity)
+        let e12 = E6_SCALE as u128 * E6_SCALE as u128;
+        let ratio_e12 = match fill_size.checked_mul(e12) {
+            Some(v) => v.checked_div(liquidity),
+            None => fill_size.checked_div(liquidity).map(|r| r.saturating_mul(e12)),
+        };
+        ratio_e12.map(|r| (impact_k as u128).saturating_mul(isqrt(r)) / E6_SCALE as u128)
+    } else {
+        match (impact_k as u128).checked_mul(fill_size) {
+            Some(v) => v.checked_div(liquidity),
+            None => fill_size.checked_div(liquidity).map(|r| (impact_k as u128).saturating_mul(r)),
+        }
+    };
+    // Zero liquidity (rejected by check_liquidity_config when impact is enabled) prices no impact rather than panicking
+    impact.map_or(0, |v| u64::try_from(v).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::UptimeMatcherError;
+    use crate::pricing::{compute_bid_ask_lp_favorable, protocol_fee_e6, uptime_exec_price, SpreadParams};
+    use crate::state::*;
+    use super::{
+        check_oracle_sequence, check_slippage, clamp_spread, encode_oracle_config, validate_header, compute_impact_bps, compute_total_spread, encode_resolution, isqrt, mark_for_uptime,
+        mark_deviation_bps, select_edge_spread, read_feed_uptime_e6, read_feed_is_active, read_feed_signal_severity, verify_oracle_account,
+        process_batch_uptime_sync, process_check_oracle_active, process_heartbeat, process_set_oracle_account, resolve_fill,
+        apply_resolution, expiry_outcome, slots_until_stale, expiry_spread_multiplier_bps, is_expired, encode_pricing_params,
+        process_get_resolution, process_get_spread_curve, process_init_and_sync, process_match, process_resolve, process_uptime_sync,
+        propose_resolution, finalize_resolution, process_cancel_resolution, process_quote_at_mark, quote_at_mark, process_get_spread_average, encode_spread_average, edge_factor_cap_for_liquidity,
+        check_liquidity_config, liquidity_capped, read_u128, write_u128, record_slashing_signal, encode_snapshot, process_snapshot_matcher,
+        encode_accrued_fees, claim_protocol_fees, accrue_protocol_fee, process_claim_protocol_fees, encode_can_match, signal_action,
+        read_feed_last_updated, check_heartbeat_feed_age, check_sequence_within_feed,
+        validate_uptime_sync, write_uptime_sync, has_extension, parse_extension, write_extension, process_migrate,
+        verify_fee_authority, ContextExtension, stale_limits, check_staleness, degraded_spread_floor, verify_dispute_authority,
+    };
+    use matcher_utils::{format_match_log, testing::MockContext};
+    use solana_program::{
+        account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program_error::ProgramError,
+        pubkey::Pubkey,
+    };
+    use solana_system_interface::program as system_program;
+
+    /// Quote through the same pricing core as process_match (no spread floor, no impact).
+    /// Returns (exec_price, total_spread, edge_factor).
+    fn compute_exec_price_edge(
+        uptime_e6: u64,
+        base_spread: u32,
+        edge_spread: u32,
+        max_spread: u32,
+        signal_adj: u64,
+    ) -> (u64, u64, u128) {
+        let params = SpreadParams { base_spread, edge_spread, max_spread, signal_adj, ..Default::default() };
+        uptime_exec_price(uptime_e6, false, &params).unwrap()
+    }
+
+    /// Shared context builder (header, put, version_1, build, run); the uptime defaults
+    /// and setters come from UptimeContext
+    type MockContextBuilder = MockContext;
+
+    /// Builds a valid LP-owned uptime context (spreads 20/30/500, synced at 99.5%,
+    /// unresolved) so handler tests only spell out the fields they exercise
+    trait UptimeContext: Sized {
+        fn new() -> Self;
+        fn for_lp(lp_key: Pubkey) -> Self;
+        fn spreads(self, base: u32, edge: u32, max: u32) -> Self;
+        /// Synced uptime, with the mark following the stored inversion flag
+        fn uptime(self, uptime_e6: u64) -> Self;
+        fn oracle(self, oracle_key: &Pubkey) -> Self;
+        fn liquidity(self, impact_k: u32, liquidity: u128) -> Self;
+        fn resolved(self, outcome: u8) -> Self;
+        fn pending_resolution(self, outcome: u8, resolved_after: i64) -> Self;
+    }
+
+    impl UptimeContext for MockContext {
+        fn new() -> Self {
+            Self::for_lp(Pubkey::new_unique())
+        }
+
+        fn for_lp(lp_key: Pubkey) -> Self {
+            let mut ctx = Self::with_header(UPTIME_MATCHER_MAGIC, MODE_CONTINUOUS, lp_key);
+            write_extension(&mut ctx.data, &ContextExtension::default());
+            ctx.spreads(20, 30, 500).uptime(995_000)
+        }
+
+        fn spreads(self, base: u32, edge: u32, max: u32) -> Self {
+            self.put(BASE_SPREAD_OFFSET, &base.to_le_bytes())
+                .put(EDGE_SPREAD_OFFSET, &edge.to_le_bytes())
+                .put(MAX_SPREAD_OFFSET, &max.to_le_bytes())
+        }
+
+        fn uptime(self, uptime_e6: u64) -> Self {
+            let mark = mark_for_uptime(uptime_e6, self.data[INVERTED_OFFSET] == 1);
+            self.put(CURRENT_UPTIME_OFFSET, &uptime_e6.to_le_bytes())
+                .put(UPTIME_MARK_OFFSET, &mark.to_le_bytes())
+                .put(HAS_SYNCED_OFFSET, &[1])
+        }
+
+        fn oracle(self, oracle_key: &Pubkey) -> Self {
+            self.put(NCN_ORACLE_OFFSET, oracle_key.as_ref())
+        }
+
+        fn liquidity(self, impact_k: u32, liquidity: u128) -> Self {
+            self.put(IMPACT_K_OFFSET, &impact_k.to_le_bytes())
+                .put(LIQUIDITY_OFFSET, &liquidity.to_le_bytes())
+                .put(LIQUIDITY_CAPPED_OFFSET, &[(liquidity > 0) as u8])
+        }
+
+        fn resolved(self, outcome: u8) -> Self {
+            self.put(IS_RESOLVED_OFFSET, &[1]).put(RESOLUTION_OUTCOME_OFFSET, &[outcome])
+        }
+
+        fn pending_resolution(self, outcome: u8, resolved_after: i64) -> Self {
+            self.put(PENDING_RESOLUTION_OFFSET, &[outcome + 1]).put(RESOLVED_AFTER_OFFSET, &resolved_after.to_le_bytes())
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 1. 50% uptime (unlikely but tests base case)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_50_percent_uptime() {
+        let (price, spread, factor) = compute_exec_price_edge(500_000, 20, 30, 500, 0);
+        assert_eq!(factor, E6_SCALE as u128);
+        assert_eq!(spread, 50);
+        assert_eq!(price, 502_500);
+    }
+
+    // -----------------------------------------------------------------------
+    // 2. 99.5% uptime (typical healthy NCN)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_995_percent_uptime() {
+        let (price, spread, factor) = compute_exec_price_edge(995_000, 20, 30, 500, 0);
+        // p=995000, 1-p=5000
+        // edge_denom = 995000*5000*4 / 1e12 = 19_900_000_000 / 1e12 = 0 (integer truncation!)
+        // edge_factor = 10_000_000 (max, since denominator is 0)
+        // adjusted_edge = 30 * 10_000_000 / 1_000_000 = 300
+        // total_spread = min(20 + 300, 500) = 320
+        assert_eq!(factor, 10 * E6_SCALE as u128);
+        assert_eq!(spread, 320);
+        // exec_price = 995000 * 10320 / 10000 = 1_026_840
+        assert_eq!(price, 1_026_840);
+    }
+
+    // -----------------------------------------------------------------------
+    // 3. 10% uptime (NCN in trouble)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_10_percent_uptime() {
+        let (price, spread, factor) = compute_exec_price_edge(100_000, 20, 30, 500, 0);
+        assert_eq!(factor, 10 * E6_SCALE as u128);
+        assert_eq!(spread, 320);
+        assert_eq!(price, 103_200);
+    }
+
+    // -----------------------------------------------------------------------
+    // 4. 90% uptime
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_90_percent_uptime() {
+        let (price, spread, factor) = compute_exec_price_edge(900_000, 20, 30, 500, 0);
+        assert_eq!(factor, 10 * E6_SCALE as u128);
+        assert_eq!(spread, 320);
+        assert_eq!(price, 928_800);
+    }
+
+    // -----------------------------------------------------------------------
+    // 5. 1% uptime (NCN nearly dead)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_1_percent_uptime() {
+        let (price, spread, factor) = compute_exec_price_edge(10_000, 20, 30, 500, 0);
+        assert_eq!(factor, 10 * E6_SCALE as u128);
+        assert_eq!(spread, 320);
+        assert_eq!(price, 10_320);
+    }
+
+    // -----------------------------------------------------------------------
+    // 6. 99% uptime
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_99_percent_uptime() {
+        let (price, spread, factor) = compute_exec_price_edge(990_000, 20, 30, 500, 0);
+        assert_eq!(factor, 10 * E6_SCALE as u128);
+        assert_eq!(spread, 320);
+        assert_eq!(price, 1_021_680);
+    }
+
+    // -----------------------------------------------------------------------
+    // 7. Signal adjustment (Kalshify-style spread widening)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_signal_adjustment() {
+        let (price, spread, _) = compute_exec_price_edge(500_000, 20, 300, 500, 50);
+        assert_eq!(spread, 370);
+        assert_eq!(price, 518_500);
+    }
+
+    // -----------------------------------------------------------------------
+    // 8. Signal CRITICAL widens spread significantly
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_critical_signal_spread() {
+        let (price, spread, _) = compute_exec_price_edge(995_000, 20, 30, 500, 200);
+        // edge_factor = 10_000_000 (same reason as 99.5% test above)
+        // adjusted_edge = 30 * 10_000_000 / 1_000_000 = 300
+        // base(20) + edge(300) + signal(200) = 520, capped to max(500)
+        assert_eq!(spread, 500);
+        // exec_price = 995000 * 10500 / 10000 = 1_044_750
+        assert_eq!(price, 1_044_750);
+    }
+
+    // -----------------------------------------------------------------------
+    // 9. Max spread capping
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_max_spread_capping() {
+        let (price, spread, _) = compute_exec_price_edge(500_000, 20, 1000, 500, 500);
+        assert_eq!(spread, 500);
+        assert_eq!(price, 525_000);
+
+        let (price2, spread2, _) = compute_exec_price_edge(10_000, 100, 200, 500, 300);
+        assert_eq!(spread2, 500);
+        assert_eq!(price2, 10_000 * 10_500 / 10_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 10. Constants
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_max_probability_constant() {
+        assert_eq!(MAX_PROBABILITY, 1_000_000);
+    }
+
+    #[test]
+    fn test_signal_severity_constants() {
+        assert_eq!(SIGNAL_NONE, 0);
+        assert_eq!(SIGNAL_LOW, 1);
+        assert_eq!(SIGNAL_HIGH, 2);
+        assert_eq!(SIGNAL_CRITICAL, 3);
+    }
+
+    // -----------------------------------------------------------------------
+    // 11. Resolution test — SLASHED snaps probability to 0
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_resolution_slashed() {
+        // After SLASHED resolution, price should be 0
+        let final_prob: u64 = 0;
+        // No pricing possible (process_match reports MarketResolved, see test 20)
+        assert_eq!(final_prob, 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // 12. Resolution test — SAFE snaps probability to 1_000_000
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_resolution_safe() {
+        let final_prob = MAX_PROBABILITY;
+        assert_eq!(final_prob, 1_000_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 13. Spread curve — shared spread function agrees with the match math
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_spread_curve_matches_exec_pricing() {
+        for uptime in [10_000u64, 100_000, 500_000, 900_000, 995_000, 1_000_000] {
+            let (_, spread, factor) = compute_exec_price_edge(uptime, 20, 30, 500, 50);
+            assert_eq!(compute_total_spread(uptime, 20, 30, 0, 500, 50, 0, MAX_EDGE_FACTOR_E6), (spread, factor));
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 14. Impact models — linear vs square-root at several fill sizes
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_linear_impact() {
+        let liq = 1_000_000_000_000u128; // 1M notional (e6)
+        // k = 100 bps at fill == liquidity
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, 0, liq), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, liq / 100, liq), 1);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, liq / 4, liq), 25);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, liq, liq), 100);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, liq * 4, liq), 400);
+    }
+
+    #[test]
+    fn test_sqrt_impact() {
+        let liq = 1_000_000_000_000u128;
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, 0, liq), 0);
+        // sqrt(1/100) = 0.1 -> 10 bps
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, liq / 100, liq), 10);
+        // sqrt(1/4) = 0.5 -> 50 bps
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, liq / 4, liq), 50);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, liq, liq), 100);
+        // sqrt(4) = 2 -> 200 bps
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, liq * 4, liq), 200);
+    }
+
+    #[test]
+    fn test_impact_disabled_without_k_or_liquidity() {
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 0, 1_000, 1_000), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, 1_000, 0), 0);
+    }
+
+    #[test]
+    fn test_impact_counts_toward_max_spread() {
+        // 50% uptime: base 20 + edge 30 + impact 25 = 75
+        assert_eq!(compute_total_spread(500_000, 20, 30, 0, 500, 0, 25, MAX_EDGE_FACTOR_E6).0, 75);
+        // Capped at max
+        assert_eq!(compute_total_spread(500_000, 20, 30, 0, 60, 0, 25, MAX_EDGE_FACTOR_E6).0, 60);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(3), 1);
+        assert_eq!(isqrt(4), 2);
+        assert_eq!(isqrt(1_000_000_000_000), 1_000_000);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        // Floor of the true root over every small input
+        for x in 0u128..10_000 {
+            let r = isqrt(x);
+            assert!(r * r <= x && (r + 1) * (r + 1) > x, "isqrt({}) = {}", x, r);
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 15. Cross-program confusion — a yield matcher context is rejected
+    // -----------------------------------------------------------------------
+    const YIELD_MATCHER_MAGIC: u64 = 0x5253_544b_4d41_5443; // "RSTKMATC"
+
+    fn yield_ctx() -> Vec<u8> {
+        MockContext::with_header(YIELD_MATCHER_MAGIC, 0, Pubkey::new_unique()).build()
+    }
+
+    /// Run a two-account handler (context first unless `ctx_second`) against a yield context
+    fn run_with_yield_ctx(
+        handler: fn(&Pubkey, &[AccountInfo], &[u8]) -> ProgramResult,
+        ctx_second: bool,
+        data: &[u8],
+    ) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, other_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut ctx_data = yield_ctx();
+        let mut other_data = [0u8; 0];
+        let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+        let other = AccountInfo::new(&other_key, true, false, &mut l1, &mut other_data, &program_id, false, 0);
+        let accounts = if ctx_second { [other, ctx] } else { [ctx, other] };
+        handler(&program_id, &accounts, data)
+    }
+
+    #[test]
+    fn test_handlers_reject_yield_context() {
+        let err = Err(ProgramError::UninitializedAccount);
+        assert_eq!(run_with_yield_ctx(process_match, true, &[0x00]), err);
+        assert_eq!(run_with_yield_ctx(process_uptime_sync, false, &[0x03; 1 + SYNC_PAYLOAD_SIZE]), err);
+        assert_eq!(run_with_yield_ctx(process_resolve, false, &[0x04, 1]), err);
+        assert_eq!(run_with_yield_ctx(process_get_spread_curve, false, &[0x05, 1, 0, 0, 0, 0, 0, 0, 0, 0]), err);
+        assert_eq!(run_with_yield_ctx(process_get_resolution, false, &[0x06]), err);
+        assert_eq!(run_with_yield_ctx(process_heartbeat, false, &[0x09]), err);
+    }
+
+    // -----------------------------------------------------------------------
+    // 16. Minimum spread floor
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_clamp_spread() {
+        assert_eq!(clamp_spread(5, 25, 500), 25);   // floor applies
+        assert_eq!(clamp_spread(100, 25, 500), 100); // between bounds
+        assert_eq!(clamp_spread(900, 25, 500), 500); // cap applies
+        assert_eq!(clamp_spread(0, 0, 500), 0);      // no floor configured
+    }
+
+    #[test]
+    fn test_min_spread_floor_in_pricing() {
+        // 50% uptime, base 5 + edge 5 = 10 < floor 40
+        assert_eq!(compute_total_spread(500_000, 5, 5, 40, 500, 0, 0, MAX_EDGE_FACTOR_E6).0, 40);
+        // Adjustments above the floor are unaffected
+        assert_eq!(compute_total_spread(500_000, 20, 30, 40, 500, 0, 0, MAX_EDGE_FACTOR_E6).0, 50);
+    }
+
+    // -----------------------------------------------------------------------
+    // 17. Resolution read-out
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_resolution_unresolved() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8].copy_from_slice(&995_000u64.to_le_bytes());
+
+        let out = encode_resolution(&ctx).unwrap();
+        assert_eq!(out[0], RESOLUTION_LAYOUT_VERSION);
+        assert_eq!(&out[1..], &[0u8; RESOLUTION_RETURN_SIZE - 1]);
+    }
+
+    #[test]
+    fn test_encode_resolution_outcomes() {
+        for (outcome, price) in [(OUTCOME_SLASHED, 0u64), (OUTCOME_SAFE, MAX_PROBABILITY)] {
+            let mut ctx = vec![0u8; CTX_V2_SIZE];
+            ctx[IS_RESOLVED_OFFSET] = 1;
+            ctx[RESOLUTION_OUTCOME_OFFSET] = outcome;
+            ctx[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+            ctx[RESOLVED_SLOT_OFFSET..RESOLVED_SLOT_OFFSET + 8].copy_from_slice(&12_345u64.to_le_bytes());
+
+            let out = encode_resolution(&ctx).unwrap();
+            assert_eq!(out[1], 1);
+            assert_eq!(out[2], outcome);
+            assert_eq!(u64::from_le_bytes(out[3..11].try_into().unwrap()), price);
+            assert_eq!(u64::from_le_bytes(out[11..19].try_into().unwrap()), 12_345);
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 18. Zero liquidity never divides by zero
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_impact_zero_liquidity_is_zero() {
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, 1_000_000, 0), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, 1_000_000, 0), 0);
+        // Overflowing fill sizes take the fallback path and still guard the division
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, u32::MAX, u128::MAX, 0), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, u32::MAX, u128::MAX, 0), 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // 19. Batch sync — count bounds and per-market validation
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_batch_sync_count_bounds() {
+        let program_id = Pubkey::new_unique();
+        assert_eq!(
+            process_batch_uptime_sync(&program_id, &[], &[0x07, 0]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        // Oversized batches fail before payload or account checks
+        for count in [MAX_BATCH_SYNC_MARKETS + 1, u8::MAX as usize] {
+            assert_eq!(
+                process_batch_uptime_sync(&program_id, &[], &[0x07, count as u8]),
+                Err(UptimeMatcherError::BatchTooLarge.into())
+            );
+        }
+        // Payloads present but accounts missing
+        let mut data = vec![0x07, 2];
+        data.resize(2 + 2 * SYNC_PAYLOAD_SIZE, 0);
+        assert_eq!(
+            process_batch_uptime_sync(&program_id, &[], &data),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+
+    #[test]
+    fn test_batch_sync_rejects_foreign_context() {
+        let mut data = vec![0x07, 1];
+        data.resize(2 + SYNC_PAYLOAD_SIZE, 0);
+        assert_eq!(
+            run_with_yield_ctx(process_batch_uptime_sync, false, &data),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 20. Zero uptime — never-synced vs resolved SLASHED vs synced 0%
+    // -----------------------------------------------------------------------
+    /// Run process_match against an uptime context with 0 stored uptime
+    fn match_zero_uptime(is_resolved: u8, has_synced: u8) -> ProgramResult {
+        MockContextBuilder::new()
+            .uptime(0)
+            .put(HAS_SYNCED_OFFSET, &[has_synced])
+            .put(IS_RESOLVED_OFFSET, &[is_resolved])
+            .run(process_match, &[0x00])
+            .0
+    }
+
+    #[test]
+    fn test_zero_uptime_never_synced() {
+        assert_eq!(
+            match_zero_uptime(0, 0),
+            Err(UptimeMatcherError::ProbabilityNotSet.into())
+        );
+    }
+
+    #[test]
+    fn test_zero_uptime_resolved_slashed() {
+        // Resolution wins regardless of the synced flag
+        for has_synced in [0, 1] {
+            assert_eq!(
+                match_zero_uptime(1, has_synced),
+                Err(UptimeMatcherError::MarketResolved.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_zero_uptime_synced_passes_probability_check() {
+        // A synced 0% reading gets past the probability check; off-chain the next
+        // step (the Clock sysvar read) fails instead
+        let result = match_zero_uptime(0, 1);
+        assert_ne!(result, Err(UptimeMatcherError::ProbabilityNotSet.into()));
+        assert!(result.is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // 21. Init with oracle verification
+    // -----------------------------------------------------------------------
+    fn check_oracle(owner: &Pubkey, data: &[u8], key_matches: bool) -> ProgramResult {
+        let key = Pubkey::new_unique();
+        let expected = if key_matches { key } else { Pubkey::new_unique() };
+        let mut lamports = 0u64;
+        let mut oracle_data = data.to_vec();
+        let oracle = AccountInfo::new(&key, false, false, &mut lamports, &mut oracle_data, owner, false, 0);
+        verify_oracle_account(&oracle, &expected)
+    }
+
+    #[test]
+    fn test_verify_oracle_account() {
+        let mut feed = vec![0u8; 64];
+        feed[..8].copy_from_slice(&NCN_PERFORMANCE_FEED_DISCRIMINATOR);
+
+        assert_eq!(check_oracle(&NCN_ORACLE_PROGRAM_ID, &feed, true), Ok(()));
+        assert_eq!(
+            check_oracle(&NCN_ORACLE_PROGRAM_ID, &feed, false),
+            Err(UptimeMatcherError::OracleMismatch.into())
+        );
+        // Wrong owner (e.g. a system-owned or nonexistent account)
+        assert_eq!(
+            check_oracle(&Pubkey::default(), &[], true),
+            Err(UptimeMatcherError::InvalidOracleAccount.into())
+        );
+        // Right owner, wrong account type
+        let mut other = feed.clone();
+        other[0] ^= 0xff;
+        assert_eq!(
+            check_oracle(&NCN_ORACLE_PROGRAM_ID, &other, true),
+            Err(UptimeMatcherError::InvalidOracleAccount.into())
+        );
+        assert_eq!(
+            check_oracle(&NCN_ORACLE_PROGRAM_ID, &feed[..4], true),
+            Err(UptimeMatcherError::InvalidOracleAccount.into())
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 22. Inverted (downtime) markets mirror direct pricing
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_inverted_pricing_mirrors_direct() {
+        for uptime in [10_000u64, 250_000, 500_000, 900_000, 995_000] {
+            let mirrored = MAX_PROBABILITY - uptime;
+
+            // Inverted mark at p equals direct mark at 1-p
+            assert_eq!(mark_for_uptime(uptime, true), mark_for_uptime(mirrored, false));
+
+            // Edge spread is symmetric, so exec prices mirror too
+            let params = SpreadParams { base_spread: 20, edge_spread: 30, max_spread: 500, signal_adj: 50, ..Default::default() };
+            let (price, spread, _) = uptime_exec_price(uptime, true, &params).unwrap();
+            let (mirrored_price, mirrored_spread, _) = uptime_exec_price(mirrored, false, &params).unwrap();
+            assert_eq!(spread, mirrored_spread);
+            assert_eq!(price, mirrored_price);
+        }
+    }
+
+    #[test]
+    fn test_inverted_resolution_flips() {
+        // SAFE settles the downtime instrument at 0, SLASHED at 1_000_000
+        assert_eq!(mark_for_uptime(MAX_PROBABILITY, true), 0);
+        assert_eq!(mark_for_uptime(0, true), MAX_PROBABILITY);
+        assert_eq!(mark_for_uptime(MAX_PROBABILITY, false), MAX_PROBABILITY);
+    }
+
+    // -----------------------------------------------------------------------
+    // 23. Mark deviation against the live oracle
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_mark_deviation_bps() {
+        assert_eq!(mark_deviation_bps(995_000, 995_000), 0);
+        assert_eq!(mark_deviation_bps(985_050, 995_000), 100);  // 1% below
+        assert_eq!(mark_deviation_bps(1_000_000, 500_000), 10_000);
+        assert_eq!(mark_deviation_bps(0, 0), 0);
+        assert_eq!(mark_deviation_bps(5_000, 0), u64::MAX);
+    }
+
+    #[test]
+    fn test_read_feed_uptime_e6() {
+        let name = b"Pyth Oracle NCN";
+        let mut feed = vec![0u8; PERF_FEED_NAME_LEN_OFFSET];
+        feed[..8].copy_from_slice(&NCN_PERFORMANCE_FEED_DISCRIMINATOR);
+        feed.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        feed.extend_from_slice(name);
+        feed.extend_from_slice(&995_000u64.to_le_bytes());
+        assert_eq!(read_feed_uptime_e6(&feed), Ok(995_000));
+
+        // Truncated account
+        assert_eq!(read_feed_uptime_e6(&feed[..feed.len() - 1]), Err(ProgramError::InvalidAccountData));
+        // Corrupt name length
+        feed[PERF_FEED_NAME_LEN_OFFSET..PERF_FEED_NAME_LEN_OFFSET + 4].copy_from_slice(&33u32.to_le_bytes());
+        assert_eq!(read_feed_uptime_e6(&feed), Err(ProgramError::InvalidAccountData));
+    }
+
+    // -----------------------------------------------------------------------
+    // 24. Partial fills against remaining liquidity
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_resolve_fill_within_liquidity() {
+        assert_eq!(resolve_fill(0, 0, false), Ok(0));
+        assert_eq!(resolve_fill(400, 1_000, false), Ok(400));
+        assert_eq!(resolve_fill(1_000, 1_000, false), Ok(1_000));
+    }
+
+    #[test]
+    fn test_resolve_fill_oversized() {
+        assert_eq!(
+            resolve_fill(1_500, 1_000, false),
+            Err(crate::errors::UptimeMatcherError::InsufficientLiquidity.into())
+        );
+        // Partial fill takes what remains; nothing left means a zero fill
+        assert_eq!(resolve_fill(1_500, 1_000, true), Ok(1_000));
+        assert_eq!(resolve_fill(1_500, 0, true), Ok(0));
+    }
+
+    // -----------------------------------------------------------------------
+    // 25. Halt on CRITICAL signal
+    // -----------------------------------------------------------------------
+    /// Run process_match against a synced context with the given signal and action table
+    fn match_with_signal(severity: u64, signal_actions: u8) -> ProgramResult {
+        MockContextBuilder::new()
+            .put(SIGNAL_SEVERITY_OFFSET, &severity.to_le_bytes())
+            .put(SIGNAL_ACTIONS_OFFSET, &[signal_actions])
+            .run(process_match, &[0x00])
+            .0
+    }
+
+    #[test]
+    fn test_critical_signal_halts_when_flag_set() {
+        assert_eq!(
+            match_with_signal(SIGNAL_CRITICAL, 1),
+            Err(UptimeMatcherError::CriticalSignalHalt.into())
+        );
+    }
+
+    #[test]
+    fn test_critical_signal_widens_by_default() {
+        // Without the flag (or below CRITICAL) matching proceeds past the guard; off-chain
+        // the next step (the Clock sysvar read) fails instead
+        for (severity, halt) in [(SIGNAL_CRITICAL, 0), (SIGNAL_HIGH, 1)] {
+            let result = match_with_signal(severity, halt);
+            assert_ne!(result, Err(UptimeMatcherError::CriticalSignalHalt.into()));
+            assert!(result.is_err());
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 26. Reserved context tail stays zero
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_reserved_tail_after_allocated_fields() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[..RESERVED_OFFSET].fill(0xFF);
+        assert_reserved_zero(&ctx);
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved context bytes")]
+    fn test_reserved_tail_write_detected() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[CTX_V2_SIZE - 1] = 1;
+        assert_reserved_zero(&ctx);
+    }
+
+    // -----------------------------------------------------------------------
+    // 27. Oracle sync replay protection
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_oracle_sequence_must_increase() {
+        assert_eq!(check_oracle_sequence(0, 1), Ok(()));
+        assert_eq!(check_oracle_sequence(41, 42), Ok(()));
+        // Replayed (equal) and late (older) syncs are rejected
+        assert_eq!(check_oracle_sequence(42, 42), Err(UptimeMatcherError::OracleSequenceRegressed.into()));
+        assert_eq!(check_oracle_sequence(42, 7), Err(UptimeMatcherError::OracleSequenceRegressed.into()));
+        // A fresh context (sequence 0) rejects a zero sequence
+        assert_eq!(check_oracle_sequence(0, 0), Err(UptimeMatcherError::OracleSequenceRegressed.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 28. Oracle account rotation
+    // -----------------------------------------------------------------------
+    /// Rotate a context (LP-signed) to a new oracle with the given owner and data;
+    /// returns the result and the stored oracle key afterwards
+    fn rotate_oracle(resolved: u8, oracle_owner: &Pubkey, oracle_bytes: &[u8]) -> (ProgramResult, Pubkey, Pubkey) {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, new_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let old_key = Pubkey::new_unique();
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key)
+            .oracle(&old_key)
+            .put(IS_RESOLVED_OFFSET, &[resolved])
+            .build();
+        let mut oracle_data = oracle_bytes.to_vec();
+        let result = {
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&new_key, false, false, &mut l2, &mut oracle_data, oracle_owner, false, 0);
+            process_set_oracle_account(&program_id, &[lp, ctx, oracle], &[0x0B])
+        };
+        let stored = Pubkey::new_from_array(ctx_data[NCN_ORACLE_OFFSET..NCN_ORACLE_OFFSET + 32].try_into().unwrap());
+        let expected = if result.is_ok() { new_key } else { old_key };
+        (result, stored, expected)
+    }
+
+    #[test]
+    fn test_set_oracle_account_rewrites_key() {
+        let (result, stored, expected) = rotate_oracle(0, &NCN_ORACLE_PROGRAM_ID, &perf_feed(0, true));
+        assert_eq!(result, Ok(()));
+        assert_eq!(stored, expected);
+
+        // A deactivated feed is not a valid rotation target
+        let (result, stored, expected) = rotate_oracle(0, &NCN_ORACLE_PROGRAM_ID, &perf_feed(0, false));
+        assert_eq!(result, Err(UptimeMatcherError::OracleInactive.into()));
+        assert_eq!(stored, expected);
+    }
+
+    #[test]
+    fn test_set_oracle_account_rejections() {
+        let mut feed = vec![0u8; 64];
+        feed[..8].copy_from_slice(&NCN_PERFORMANCE_FEED_DISCRIMINATOR);
+
+        // Resolved markets keep their oracle
+        let (result, stored, expected) = rotate_oracle(1, &NCN_ORACLE_PROGRAM_ID, &feed);
+        assert_eq!(result, Err(UptimeMatcherError::MarketResolved.into()));
+        assert_eq!(stored, expected);
+
+        // Wrong owner, or right owner with the wrong account type
+        let mut other = feed.clone();
+        other[0] ^= 0xff;
+        for (owner, bytes) in [(Pubkey::default(), &feed), (NCN_ORACLE_PROGRAM_ID, &other)] {
+            let (result, stored, expected) = rotate_oracle(0, &owner, bytes);
+            assert_eq!(result, Err(UptimeMatcherError::InvalidOracleAccount.into()));
+            assert_eq!(stored, expected);
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 29. Static-spread fast path agrees with the full edge computation
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_static_spread_fast_path_matches_slow_path() {
+        for uptime in [0u64, 10_000, 500_000, 995_000, 1_000_000] {
+            for (base, min, max, impact) in [(20u32, 0u32, 500u32, 0u64), (5, 40, 500, 0), (20, 0, 60, 75), (0, 0, 0, 0)] {
+                for inverted in [false, true] {
+                    let params = SpreadParams { base_spread: base, min_spread: min, max_spread: max, impact, ..Default::default() };
+                    assert!(params.is_static());
+                    let (fast_price, fast_spread, fast_factor) = uptime_exec_price(uptime, inverted, &params).unwrap();
+
+                    let (slow_spread, _) = compute_total_spread(uptime, base, 0, min, max, 0, impact, MAX_EDGE_FACTOR_E6);
+                    let mark = mark_for_uptime(uptime, inverted) as u128;
+                    let slow_price = (mark * (BPS_SCALE + slow_spread) as u128).div_ceil(BPS_SCALE as u128) as u64;
+
+                    assert_eq!((fast_price, fast_spread, fast_factor), (slow_price, slow_spread, 0));
+                }
+            }
+        }
+        // Any edge spread or signal adjustment takes the full path
+        assert!(!SpreadParams { edge_spread: 1, ..Default::default() }.is_static());
+        assert!(!SpreadParams { signal_adj: 1, ..Default::default() }.is_static());
+    }
+
+    // -----------------------------------------------------------------------
+    // 30. LP-favorable rounding on both sides
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_bid_ask_rounding_favors_lp() {
+        // Marks and spreads chosen so mark * (10000 +/- spread) is rarely a multiple of 10000
+        for mark in [0u64, 1, 3, 7, 9_999, 10_001, 333_333, 995_001, 999_999] {
+            for spread in [0u64, 1, 3, 7, 33, 9_999, 10_000, 12_345] {
+                let (bid, ask) = compute_bid_ask_lp_favorable(mark, spread).unwrap();
+                let ask_fair = mark as u128 * (BPS_SCALE + spread) as u128;
+                let bid_fair = mark as u128 * BPS_SCALE.saturating_sub(spread) as u128;
+                // bid <= fair <= ask, each within one unit of the exact value
+                assert!(ask as u128 * BPS_SCALE as u128 >= ask_fair);
+                assert!(ask == 0 || (ask as u128 - 1) * (BPS_SCALE as u128) < ask_fair);
+                assert!(bid as u128 * BPS_SCALE as u128 <= bid_fair);
+                assert!((bid as u128 + 1) * (BPS_SCALE as u128) > bid_fair);
+                assert!(bid <= mark && mark <= ask);
+            }
+        }
+        // 1 unit at 1 bp: fair ask is 1.0001 -> 2, fair bid 0.9999 -> 0
+        assert_eq!(compute_bid_ask_lp_favorable(1, 1).unwrap(), (0, 2));
+        // Exact multiples are not rounded
+        assert_eq!(compute_bid_ask_lp_favorable(500_000, 20).unwrap(), (499_000, 501_000));
+    }
+
+    #[test]
+    fn test_bid_ask_ask_overflow_is_rejected() {
+        assert!(compute_bid_ask_lp_favorable(u64::MAX, 1).is_err());
+        assert_eq!(compute_bid_ask_lp_favorable(u64::MAX, 0).unwrap(), (u64::MAX, u64::MAX));
+    }
+
+    // -----------------------------------------------------------------------
+    // 31. Expiry: matches stop, auto-resolve to the configured default
+    // -----------------------------------------------------------------------
+    fn expiring_ctx(resolution_ts: i64, auto_resolve: u8, default_outcome: u8) -> Vec<u8> {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[RESOLUTION_TIMESTAMP_OFFSET..RESOLUTION_TIMESTAMP_OFFSET + 8].copy_from_slice(&resolution_ts.to_le_bytes());
+        ctx[AUTO_RESOLVE_ON_EXPIRY_OFFSET] = auto_resolve;
+        ctx[EXPIRY_DEFAULT_OUTCOME_OFFSET] = default_outcome;
+        ctx
+    }
+
+    #[test]
+    fn test_is_expired() {
+        assert!(!is_expired(0, i64::MAX)); // no expiry configured
+        assert!(!is_expired(1_000, 999));
+        assert!(is_expired(1_000, 1_000));
+        assert!(is_expired(1_000, 5_000));
+    }
+
+    #[test]
+    fn test_expiry_outcome_requires_expiry_and_opt_in() {
+        assert_eq!(expiry_outcome(&expiring_ctx(1_000, 1, OUTCOME_SAFE), 1_000), Ok(OUTCOME_SAFE));
+        assert_eq!(expiry_outcome(&expiring_ctx(1_000, 1, OUTCOME_SLASHED), 2_000), Ok(OUTCOME_SLASHED));
+        assert_eq!(
+            expiry_outcome(&expiring_ctx(1_000, 1, OUTCOME_SAFE), 999),
+            Err(UptimeMatcherError::MarketNotExpired.into())
+        );
+        assert_eq!(
+            expiry_outcome(&expiring_ctx(0, 1, OUTCOME_SAFE), 2_000),
+            Err(UptimeMatcherError::MarketNotExpired.into())
+        );
+        assert_eq!(
+            expiry_outcome(&expiring_ctx(1_000, 0, OUTCOME_SAFE), 2_000),
+            Err(UptimeMatcherError::AutoResolveDisabled.into())
+        );
+        let mut resolved = expiring_ctx(1_000, 1, OUTCOME_SAFE);
+        resolved[IS_RESOLVED_OFFSET] = 1;
+        assert_eq!(expiry_outcome(&resolved, 2_000), Err(UptimeMatcherError::MarketResolved.into()));
+    }
+
+    #[test]
+    fn test_auto_resolve_writes_terminal_state() {
+        for (inverted, outcome, price) in [(0, OUTCOME_SAFE, MAX_PROBABILITY), (1, OUTCOME_SAFE, 0), (0, OUTCOME_SLASHED, 0)] {
+            let mut ctx = expiring_ctx(1_000, 1, outcome);
+            ctx[INVERTED_OFFSET] = inverted;
+            let outcome = expiry_outcome(&ctx, 1_000).unwrap();
+            assert_eq!(apply_resolution(&mut ctx, outcome, 77), price);
+            assert_eq!((ctx[IS_RESOLVED_OFFSET], ctx[RESOLUTION_OUTCOME_OFFSET]), (1, outcome));
+            assert_eq!(u64::from_le_bytes(ctx[RESOLVED_SLOT_OFFSET..RESOLVED_SLOT_OFFSET + 8].try_into().unwrap()), 77);
+            // A second crank finds the market resolved
+            assert_eq!(expiry_outcome(&ctx, 1_000), Err(UptimeMatcherError::MarketResolved.into()));
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 32. Bulk pricing-params read-out
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_pricing_params_layout() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[MODE_OFFSET] = 1;
+        for (offset, value) in [(BASE_SPREAD_OFFSET, 20u32), (EDGE_SPREAD_OFFSET, 30), (MAX_SPREAD_OFFSET, 500), (MIN_SPREAD_OFFSET, 5), (IMPACT_K_OFFSET, 7)] {
+            ctx[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        ctx[IMPACT_MODEL_OFFSET] = IMPACT_MODEL_SQRT;
+        ctx[INVERTED_OFFSET] = 1;
+        ctx[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8].copy_from_slice(&5_000u64.to_le_bytes());
+        ctx[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8].copy_from_slice(&995_000u64.to_le_bytes());
+        ctx[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8].copy_from_slice(&42u64.to_le_bytes());
+        ctx[IS_RESOLVED_OFFSET] = 1;
+
+        let out = encode_pricing_params(&ctx).unwrap();
+        let u32_at = |o: usize| u32::from_le_bytes(out[o..o + 4].try_into().unwrap());
+        let u64_at = |o: usize| u64::from_le_bytes(out[o..o + 8].try_into().unwrap());
+        assert_eq!((out[0], out[1]), (PRICING_PARAMS_LAYOUT_VERSION, 1));
+        assert_eq!((u32_at(2), u32_at(6), u32_at(10), u32_at(14), u32_at(18)), (20, 30, 500, 5, 7));
+        assert_eq!((out[22], out[23]), (IMPACT_MODEL_SQRT, 1));
+        assert_eq!((u64_at(24), u64_at(32), u64_at(40)), (5_000, 995_000, 42));
+        assert_eq!(out[48], 1);
+        // A context from before the display metadata reports the program's scale
+        assert_eq!((out[49], out[50]), (UPTIME_PRICE_DECIMALS, QUOTE_KIND_PROBABILITY));
+        ctx[PRICE_DECIMALS_OFFSET] = 8;
+        ctx[QUOTE_KIND_OFFSET] = 1;
+        let out = encode_pricing_params(&ctx).unwrap();
+        assert_eq!((out[49], out[50]), (8, 1));
+    }
+
+    // -----------------------------------------------------------------------
+    // 33. Spreads widen as expiry approaches
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_expiry_multiplier_schedule() {
+        // One-hour window ramping to 3x at expiry 10_000
+        let mult = |now: i64| expiry_spread_multiplier_bps(10_000, now, 3_600, 30_000);
+        assert_eq!(mult(0), 10_000); // far from expiry
+        assert_eq!(mult(10_000 - 3_600), 10_000); // window edge
+        assert_eq!(mult(10_000 - 1_800), 20_000); // halfway through the window
+        assert_eq!(mult(10_000 - 360), 28_000);
+        assert_eq!(mult(10_000), 30_000);
+        assert!(mult(10_000 - 1_000) > mult(10_000 - 2_000));
+
+        // No expiry, no window, or a sub-1x cap disables the schedule
+        assert_eq!(expiry_spread_multiplier_bps(0, 9_999, 3_600, 30_000), 10_000);
+        assert_eq!(expiry_spread_multiplier_bps(10_000, 9_999, 0, 30_000), 10_000);
+        assert_eq!(expiry_spread_multiplier_bps(10_000, 9_999, 3_600, 5_000), 10_000);
+        assert_eq!(expiry_spread_multiplier_bps(i64::MAX, 0, u32::MAX, u32::MAX), 10_000);
+        assert!(expiry_spread_multiplier_bps(u32::MAX as i64, u32::MAX as i64 - 1, u32::MAX, u32::MAX) > u32::MAX as u64 - 10_000);
+    }
+
+    #[test]
+    fn test_expiry_multiplier_widens_quote_up_to_max() {
+        let params = |expiry_multiplier_bps| SpreadParams {
+            base_spread: 40,
+            max_spread: 100,
+            expiry_multiplier_bps,
+            ..Default::default()
+        };
+        let (far_price, far_spread, _) = uptime_exec_price(500_000, false, &params(10_000)).unwrap();
+        let (near_price, near_spread, _) = uptime_exec_price(500_000, false, &params(20_000)).unwrap();
+        assert_eq!((far_spread, near_spread), (40, 80));
+        assert!(near_price > far_price);
+        // Unset multiplier is 1x; a large one is capped at max_spread
+        assert_eq!(uptime_exec_price(500_000, false, &params(0)).unwrap().1, 40);
+        assert_eq!(uptime_exec_price(500_000, false, &params(50_000)).unwrap().1, 100);
+    }
+
+    // -----------------------------------------------------------------------
+    // 34. Match requires a writable context
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_match_rejects_read_only_context() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+        let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+        let ctx = AccountInfo::new(&ctx_key, false, false, &mut l1, &mut ctx_data, &program_id, false, 0);
+        assert_eq!(process_match(&program_id, &[lp, ctx], &[0x00]), Err(ProgramError::InvalidAccountData));
+    }
+
+    // -----------------------------------------------------------------------
+    // 35. Mark floor keeps collapsing markets tradeable
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_min_mark_floor_near_zero_uptime() {
+        let params = |min_mark| SpreadParams { base_spread: 50, edge_spread: 30, max_spread: 500, min_mark, ..Default::default() };
+
+        // No floor: the quote collapses with uptime
+        let (price, spread, _) = uptime_exec_price(100, false, &params(0)).unwrap();
+        assert_eq!(spread, 350); // edge factor capped at 10x
+        assert_eq!(price, 104); // 100 * 1.035, rounded up
+        assert_eq!(uptime_exec_price(0, false, &params(0)).unwrap().0, 0);
+
+        // 5% floor: price holds at floor * (1 + spread), spread still from real uptime
+        for uptime in [0, 100, 10_000] {
+            let (price, floored_spread, _) = uptime_exec_price(uptime, false, &params(50_000)).unwrap();
+            assert_eq!(floored_spread, uptime_exec_price(uptime, false, &params(0)).unwrap().1);
+            assert_eq!(price, (50_000u128 * (BPS_SCALE + floored_spread) as u128).div_ceil(BPS_SCALE as u128) as u64);
+        }
+
+        // Above the floor the quote is unchanged
+        assert_eq!(
+            uptime_exec_price(995_000, false, &params(50_000)).unwrap(),
+            uptime_exec_price(995_000, false, &params(0)).unwrap()
+        );
+        // The floor applies to the quoted (downtime) mark on inverted markets
+        assert!(uptime_exec_price(1_000_000, true, &params(50_000)).unwrap().0 >= 50_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 36. Combined init + first sync
+    // -----------------------------------------------------------------------
+    fn init_and_sync(uptime_e6: u64, severity: u64, sequence: u64, init_len: usize) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut d0, mut d2) = ([0u8; 0], [0u8; 0]);
+        let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+        let accounts = [
+            AccountInfo::new(&keys[0], true, false, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&keys[1], false, true, &mut l1, &mut ctx_data, &program_id, false, 0),
+            AccountInfo::new(&keys[2], false, false, &mut l2, &mut d2, &program_id, false, 0),
+        ];
+        let mut data = vec![0x0E];
+        for field in [uptime_e6, severity, 0, sequence] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.resize(1 + SYNC_PAYLOAD_SIZE + init_len, 0);
+        process_init_and_sync(&program_id, &accounts, &data)
+    }
+
+    #[test]
+    fn test_init_and_sync_validates_sync_payload_first() {
+        assert_eq!(init_and_sync(995_000, 0, 1, 96), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(init_and_sync(MAX_PROBABILITY + 1, 0, 1, 97), Err(UptimeMatcherError::InvalidProbability.into()));
+        // A fresh context has applied no sequence yet, so 0 can never be the first sync
+        assert_eq!(init_and_sync(995_000, 0, 0, 97), Err(UptimeMatcherError::OracleSequenceRegressed.into()));
+        // The keeper's severity is not read, so even an out-of-range one is no error
+        assert_eq!(init_and_sync(995_000, SIGNAL_CRITICAL + 1, 0, 97), Err(UptimeMatcherError::OracleSequenceRegressed.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 37. Slots until stale
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_slots_until_stale() {
+        assert_eq!(MAX_STALENESS_SLOTS, 200);
+        assert_eq!(slots_until_stale(1_000, 1_000, MAX_STALENESS_SLOTS), MAX_STALENESS_SLOTS);
+        assert_eq!(slots_until_stale(1_000, 1_040, MAX_STALENESS_SLOTS), MAX_STALENESS_SLOTS - 40);
+        // Still quotable at exactly the limit, stale one slot later
+        assert_eq!(slots_until_stale(1_000, 1_000 + MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS), 0);
+        assert_eq!(slots_until_stale(1_000, 1_001 + MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS), 0);
+        assert_eq!(slots_until_stale(0, u64::MAX, MAX_STALENESS_SLOTS), 0);
+        // An update slot ahead of the clock counts as fresh
+        assert_eq!(slots_until_stale(2_000, 1_000, MAX_STALENESS_SLOTS), MAX_STALENESS_SLOTS);
+        // A configured soft threshold replaces the default
+        assert_eq!(slots_until_stale(1_000, 1_040, 500), 460);
+    }
+
+    // -----------------------------------------------------------------------
+    // 38. Context must be owned by the matcher program
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_foreign_owned_context_rejected() {
+        let (program_id, foreign_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (lp_key, ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut lp_data, mut oracle_data) = ([0u8; 0], [0u8; 0]);
+        // Valid magic and LP binding, but written by some other program
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+        let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+        let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &foreign_program, false, 0);
+        let oracle = AccountInfo::new(&oracle_key, false, false, &mut l2, &mut oracle_data, &program_id, false, 0);
+
+        let err = Err(ProgramError::IllegalOwner);
+        assert_eq!(process_match(&program_id, &[lp.clone(), ctx.clone()], &[0x00]), err);
+        assert_eq!(super::process_init(&program_id, &[lp, ctx.clone()], &[0x02; 98]), err);
+        assert_eq!(super::process_uptime_sync(&program_id, &[ctx.clone(), oracle.clone()], &[0x03; 33]), err);
+        assert_eq!(super::process_heartbeat(&program_id, &[ctx.clone(), oracle], &[0x09]), err);
+        assert_eq!(super::process_expire(&program_id, &[ctx], &[0x0C]), err);
+    }
+
+    // -----------------------------------------------------------------------
+    // 39. Implied odds
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_implied_odds() {
+        use super::implied_odds_e6;
+        // 80%: decimal 1.25, favorite at -400
+        assert_eq!(implied_odds_e6(800_000), Some((1_250_000, -400_000_000)));
+        // 25%: decimal 4.0, underdog at +300
+        assert_eq!(implied_odds_e6(250_000), Some((4_000_000, 300_000_000)));
+        // Even money quotes +100
+        assert_eq!(implied_odds_e6(500_000), Some((2_000_000, 100_000_000)));
+        // Truncation toward zero on both sides
+        assert_eq!(implied_odds_e6(300_000), Some((3_333_333, 233_333_333)));
+        assert_eq!(implied_odds_e6(700_000), Some((1_428_571, -233_333_333)));
+
+        // Extremes never divide by zero
+        assert_eq!(implied_odds_e6(0), Some((u64::MAX, i64::MAX)));
+        assert_eq!(implied_odds_e6(MAX_PROBABILITY), Some((1_000_000, i64::MIN)));
+        assert_eq!(implied_odds_e6(1), Some((1_000_000_000_000, 99_999_900_000_000)));
+        assert_eq!(implied_odds_e6(MAX_PROBABILITY - 1), Some((1_000_001, -99_999_900_000_000)));
+        assert_eq!(implied_odds_e6(MAX_PROBABILITY + 1), None);
+    }
+
+    // -----------------------------------------------------------------------
+    // 40. Max spread bound keeps the pricing math overflow-free
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_max_spread_limit() {
+        let init = |max_spread: u32| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 98];
+            data[0] = 0x02;
+            data[10..14].copy_from_slice(&max_spread.to_le_bytes());
+            data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+            // min spread above max, so an accepted max stops at the next check
+            data.extend_from_slice(&[0]);
+            data.extend_from_slice(&u32::MAX.to_le_bytes());
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        let err: ProgramError = UptimeMatcherError::InvalidSpreadConfig.into();
+        assert_eq!(init(MAX_SPREAD_LIMIT_BPS + 1), Err(err.clone()));
+        assert_eq!(init(u32::MAX), Err(err.clone()));
+        // At the limit the max passes and only the min/max ordering fails
+        assert_eq!(init(MAX_SPREAD_LIMIT_BPS), Err(err));
+
+        // Worst case at the limit: certain mark, full spread, every component maxed
+        let params = SpreadParams {
+            base_spread: u32::MAX,
+            edge_spread: u32::MAX,
+            max_spread: MAX_SPREAD_LIMIT_BPS,
+            signal_adj: u64::MAX,
+            impact: u64::MAX,
+            expiry_multiplier_bps: u64::MAX,
+            ..Default::default()
+        };
+        for uptime in [0, 500_000, MAX_PROBABILITY] {
+            for inverted in [false, true] {
+                let (price, spread, _) = uptime_exec_price(uptime, inverted, &params).unwrap();
+                assert_eq!(spread, MAX_SPREAD_LIMIT_BPS as u64);
+                assert!(price <= 2 * MAX_PROBABILITY);
+            }
+        }
+
+        // The overflow branch itself is only reachable with an out-of-range mark floor
+        let floor = SpreadParams { base_spread: 1, max_spread: 1, min_mark: u64::MAX, ..Default::default() };
+        assert_eq!(uptime_exec_price(0, false, &floor), Err(UptimeMatcherError::ArithmeticOverflow.into()));
+        assert_eq!(uptime_exec_price(0, false, &SpreadParams { base_spread: 0, ..floor }), Ok((u64::MAX, 0, 0)));
+    }
+
+    // -----------------------------------------------------------------------
+    // 41. Halt on a deactivated oracle
+    // -----------------------------------------------------------------------
+    const FEED_UPDATED: i64 = 1_700_000_000;
+
+    /// Serialized NcnPerformanceFeed with `history_len` samples and the given is_active
+    fn perf_feed(history_len: u32, is_active: bool) -> Vec<u8> {
+        let name = b"Pyth Oracle NCN";
+        let mut feed = vec![0u8; PERF_FEED_NAME_LEN_OFFSET];
+        feed[..8].copy_from_slice(&NCN_PERFORMANCE_FEED_DISCRIMINATOR);
+        feed.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        feed.extend_from_slice(name);
+        feed.extend_from_slice(&[0u8; PERF_FEED_FIXED_AFTER_NAME]);
+        feed.extend_from_slice(&history_len.to_le_bytes());
+        feed.extend(std::iter::repeat_n(0u8, history_len as usize * PERF_FEED_SAMPLE_SIZE));
+        feed.extend_from_slice(&[0u8; PERF_FEED_IS_ACTIVE_AFTER_HISTORY]);
+        feed.push(is_active as u8);
+        feed.extend_from_slice(&FEED_UPDATED.to_le_bytes());
+        feed
+    }
+
+    #[test]
+    fn test_read_feed_is_active() {
+        assert_eq!(read_feed_is_active(&perf_feed(0, true)), Ok(true));
+        assert_eq!(read_feed_is_active(&perf_feed(5, false)), Ok(false));
+
+        let feed = perf_feed(3, true);
+        assert_eq!(read_feed_is_active(&feed[..feed.len() - 9]), Err(ProgramError::InvalidAccountData));
+        // Non-bool byte, and a history longer than the oracle allows
+        let mut bad = feed.clone();
+        bad[feed.len() - 9] = 2;
+        assert_eq!(read_feed_is_active(&bad), Err(ProgramError::InvalidAccountData));
+        let mut long = perf_feed(0, true);
+        let at = PERF_FEED_NAME_LEN_OFFSET + 4 + 15 + PERF_FEED_FIXED_AFTER_NAME;
+        long[at..at + 4].copy_from_slice(&(PERF_FEED_MAX_HISTORY as u32 + 1).to_le_bytes());
+        assert_eq!(read_feed_is_active(&long), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_check_oracle_active_halts_matches() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).oracle(&oracle_key).build();
+
+        let check = |is_active: bool, ctx_data: &mut Vec<u8>| {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut feed = perf_feed(2, is_active);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0);
+            process_check_oracle_active(&program_id, &[ctx, oracle], &[0x11])
+        };
+
+        // An active oracle leaves the context untouched
+        assert_eq!(check(true, &mut ctx_data), Ok(()));
+        assert_eq!(ctx_data[ORACLE_INACTIVE_OFFSET], 0);
+        assert_eq!(check(false, &mut ctx_data), Ok(()));
+        assert_eq!(ctx_data[ORACLE_INACTIVE_OFFSET], 1);
+
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+        let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+        assert_eq!(
+            process_match(&program_id, &[lp, ctx], &[0x00]),
+            Err(UptimeMatcherError::OracleInactive.into())
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 42. Zero initial uptime is rejected at init
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_init_rejects_zero_initial_uptime() {
+        let init = |uptime_e6: u64| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 98];
+            data[0] = 0x02;
+            data[18..26].copy_from_slice(&uptime_e6.to_le_bytes());
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        let err: ProgramError = UptimeMatcherError::InvalidProbability.into();
+        assert_eq!(init(0), Err(err.clone()));
+        // A non-zero uptime passes validation; off-chain the Clock sysvar read fails instead
+        for uptime in [1, MAX_PROBABILITY] {
+            let result = init(uptime);
+            assert_ne!(result, Err(err.clone()));
+            assert!(result.is_err());
+        }
+
+        // Init + sync takes its uptime from the sync, so a zero init field is fine there
+        let result = init_and_sync(995_000, 0, 1, 97);
+        assert_ne!(result, Err(err));
+        assert!(result.is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // 43. Taker slippage bound
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_check_slippage() {
+        assert_eq!(check_slippage(1_000_000, 0), Ok(()));
+        assert_eq!(check_slippage(995_500, 995_500), Ok(()));
+        assert_eq!(check_slippage(995_500, 1_000_000), Ok(()));
+        assert_eq!(check_slippage(995_501, 995_500), Err(UptimeMatcherError::SlippageExceeded.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 44. Asymmetric edge spread
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_asymmetric_edge_spread() {
+        let params = |edge_spread_low| SpreadParams {
+            edge_spread: 30,
+            edge_spread_low,
+            max_spread: 5_000,
+            ..Default::default()
+        };
+        let spread = |uptime, low| uptime_exec_price(uptime, false, &params(low)).unwrap().1;
+
+        // Single-parameter mode: 90% and 10% are mirror images, so the spreads match
+        assert_eq!(spread(900_000, 0), spread(100_000, 0));
+
+        // High side keeps edge_spread; the low side switches to edge_spread_low
+        assert_eq!(spread(900_000, 100), compute_total_spread(900_000, 0, 30, 0, 5_000, 0, 0, MAX_EDGE_FACTOR_E6).0);
+        assert_eq!(spread(100_000, 100), compute_total_spread(100_000, 0, 100, 0, 5_000, 0, 0, MAX_EDGE_FACTOR_E6).0);
+        assert!(spread(100_000, 100) > spread(900_000, 100));
+        // Exactly 50% counts as the low side
+        assert_eq!(select_edge_spread(EDGE_SPLIT_UPTIME_E6, 30, 100), 100);
+        assert_eq!(select_edge_spread(EDGE_SPLIT_UPTIME_E6 + 1, 30, 100), 30);
+
+        // A low-side-only edge is not a static spread
+        assert!(!SpreadParams { edge_spread_low: 1, ..Default::default() }.is_static());
+    }
+
+    // -----------------------------------------------------------------------
+    // 45. Oracle config check against a registry
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_oracle_config() {
+        let oracle = Pubkey::new_unique();
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[NCN_ORACLE_OFFSET..NCN_ORACLE_OFFSET + 32].copy_from_slice(oracle.as_ref());
+
+        let out = encode_oracle_config(&ctx, &oracle).unwrap();
+        assert_eq!(out[0], 1);
+        assert_eq!(&out[1..33], oracle.as_ref());
+
+        let out = encode_oracle_config(&ctx, &Pubkey::new_unique()).unwrap();
+        assert_eq!(out[0], 0);
+        assert_eq!(&out[1..33], oracle.as_ref());
+    }
+
+    // -----------------------------------------------------------------------
+    // 46. One-call header validation
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_validate_header() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, lp_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let check = |owner: &Pubkey, data: &mut Vec<u8>| {
+            let mut lamports = 0u64;
+            let ctx = AccountInfo::new(&ctx_key, false, false, &mut lamports, data, owner, false, 0);
+            validate_header(&ctx, &program_id)
+        };
+
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).put(MODE_OFFSET, &[1]).build();
+        let header = check(&program_id, &mut ctx_data).unwrap();
+        assert_eq!((header.mode, header.lp_pda), (1, lp_key));
+
+        // Owner is checked before size, size before magic
+        assert_eq!(check(&Pubkey::new_unique(), &mut vec![0u8; 8]), Err(ProgramError::IllegalOwner));
+        assert_eq!(check(&program_id, &mut ctx_data[..CTX_SIZE - 1].to_vec()), Err(ProgramError::AccountDataTooSmall));
+        assert_eq!(check(&program_id, &mut vec![0u8; CTX_V2_SIZE]), Err(ProgramError::UninitializedAccount));
+
+        // A version-1 context keeps the shared size; version 2 needs the extension bytes
+        let mut v1 = MockContextBuilder::for_lp(lp_key).version_1().build();
+        assert_eq!(check(&program_id, &mut v1).map(|h| h.version), Ok(1));
+        v1[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(&CTX_VERSION_2.to_le_bytes());
+        assert_eq!(check(&program_id, &mut v1), Err(ProgramError::AccountDataTooSmall));
+    }
+
+    // -----------------------------------------------------------------------
+    // 47. Canonical match log line
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_format_match_log() {
+        assert_eq!(
+            format_match_log(995_500, 50, 995_000, "uptime_e6=995000 fill=0"),
+            "MATCH v=1 price=995500 spread_bps=50 mark_e6=995000 uptime_e6=995000 fill=0"
+        );
+        assert_eq!(format_match_log(-20_000, 0, -20_000, ""), "MATCH v=1 price=-20000 spread_bps=0 mark_e6=-20000");
+    }
+
+    // -----------------------------------------------------------------------
+    // 48. Two-phase resolution with a grace period
+    // -----------------------------------------------------------------------
+    fn grace_ctx(grace_secs: u32, lp_key: &Pubkey, oracle_key: &Pubkey) -> Vec<u8> {
+        MockContextBuilder::for_lp(*lp_key)
+            .oracle(oracle_key)
+            .put(RESOLUTION_GRACE_OFFSET, &grace_secs.to_le_bytes())
+            .build()
+    }
+
+    #[test]
+    fn test_propose_then_finalize_after_grace() {
+        let mut ctx = grace_ctx(3_600, &Pubkey::new_unique(), &Pubkey::new_unique());
+        assert_eq!(finalize_resolution(&mut ctx, 0, 1), Err(UptimeMatcherError::NoPendingResolution.into()));
+
+        assert_eq!(propose_resolution(&mut ctx, OUTCOME_SLASHED, 1_000), Ok(4_600));
+        assert_eq!(ctx[PENDING_RESOLUTION_OFFSET], OUTCOME_SLASHED + 1);
+        assert_eq!(finalize_resolution(&mut ctx, 4_599, 1), Err(UptimeMatcherError::GracePeriodActive.into()));
+        assert_eq!(ctx[IS_RESOLVED_OFFSET], 0);
+
+        assert_eq!(finalize_resolution(&mut ctx, 4_600, 9), Ok((OUTCOME_SLASHED, 0)));
+        assert_eq!((ctx[IS_RESOLVED_OFFSET], ctx[RESOLUTION_OUTCOME_OFFSET]), (1, OUTCOME_SLASHED));
+        assert_eq!(ctx[PENDING_RESOLUTION_OFFSET], 0);
+        assert!(ctx[RESOLVED_AFTER_OFFSET..RESOLVED_AFTER_OFFSET + 8].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_pending_resolution_blocks_other_paths() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut oracle_data = [0u8; 0];
+
+        // With a grace period, a direct resolve is refused before the clock is read
+        let mut ctx_data = grace_ctx(60, &lp_key, &oracle_key);
+        {
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, true, false, &mut l1, &mut oracle_data, &program_id, false, 0);
+            assert_eq!(
+                process_resolve(&program_id, &[ctx, oracle], &[0x04, OUTCOME_SAFE]),
+                Err(UptimeMatcherError::GracePeriodActive.into())
+            );
+        }
+
+        propose_resolution(&mut ctx_data, OUTCOME_SAFE, 0).unwrap();
+        {
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, true, false, &mut l1, &mut oracle_data, &program_id, false, 0);
+            assert_eq!(
+                process_resolve(&program_id, &[ctx, oracle], &[0x04, OUTCOME_SAFE]),
+                Err(UptimeMatcherError::ResolutionPending.into())
+            );
+        }
+        {
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l1, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+            assert_eq!(process_match(&program_id, &[lp, ctx], &[0x00]), Err(UptimeMatcherError::ResolutionPending.into()));
+        }
+
+        let mut expiring = expiring_ctx(1_000, 1, OUTCOME_SLASHED);
+        expiring[PENDING_RESOLUTION_OFFSET] = OUTCOME_SAFE + 1;
+        assert_eq!(expiry_outcome(&expiring, 2_000), Err(UptimeMatcherError::ResolutionPending.into()));
+    }
+
+    #[test]
+    fn test_dispute_authority_cancels_pending_resolution() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, disputer) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key)
+            .put(RESOLUTION_GRACE_OFFSET, &60u32.to_le_bytes())
+            .put(DISPUTE_AUTHORITY_OFFSET, disputer.as_ref())
+            .build();
+        let cancel = |ctx_data: &mut Vec<u8>, signer: &Pubkey, is_signer: bool| {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut signer_data = [0u8; 0];
+            let authority = AccountInfo::new(signer, is_signer, false, &mut l0, &mut signer_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, ctx_data, &program_id, false, 0);
+            process_cancel_resolution(&program_id, &[authority, ctx], &[0x15])
+        };
+        let mismatch = Err(UptimeMatcherError::DisputeAuthorityMismatch.into());
+
+        assert_eq!(cancel(&mut ctx_data, &disputer, true), Err(UptimeMatcherError::NoPendingResolution.into()));
+        propose_resolution(&mut ctx_data, OUTCOME_SLASHED, 0).unwrap();
+        // Neither the LP PDA nor an unsigned dispute authority can cancel
+        assert_eq!(cancel(&mut ctx_data, &lp_key, true), mismatch);
+        assert_eq!(cancel(&mut ctx_data, &disputer, false), Err(ProgramError::MissingRequiredSignature));
+        assert_eq!(ctx_data[PENDING_RESOLUTION_OFFSET], OUTCOME_SLASHED + 1);
+
+        assert_eq!(cancel(&mut ctx_data, &disputer, true), Ok(()));
+        assert_eq!((ctx_data[PENDING_RESOLUTION_OFFSET], ctx_data[IS_RESOLVED_OFFSET]), (0, 0));
+        assert_eq!(ctx_data[RESOLUTION_CANCELS_OFFSET], 1);
+
+        // Re-proposals are bounded: after MAX_RESOLUTION_CANCELS the proposal stands
+        for _ in 1..MAX_RESOLUTION_CANCELS {
+            propose_resolution(&mut ctx_data, OUTCOME_SAFE, 0).unwrap();
+            assert_eq!(cancel(&mut ctx_data, &disputer, true), Ok(()));
+        }
+        propose_resolution(&mut ctx_data, OUTCOME_SAFE, 0).unwrap();
+        assert_eq!(cancel(&mut ctx_data, &disputer, true), Err(UptimeMatcherError::CancelLimitReached.into()));
+        assert_eq!(finalize_resolution(&mut ctx_data, 60, 1), Ok((OUTCOME_SAFE, MAX_PROBABILITY)));
+    }
+
+    #[test]
+    fn test_cancel_needs_a_configured_dispute_authority() {
+        let program_id = Pubkey::new_unique();
+        let signer_key = Pubkey::new_unique();
+        let mismatch: ProgramError = UptimeMatcherError::DisputeAuthorityMismatch.into();
+        let (mut lamports, mut data) = (0u64, [0u8; 0]);
+        let signer = AccountInfo::new(&signer_key, true, false, &mut lamports, &mut data, &program_id, false, 0);
+        // None configured, or a version-1 context without the extension
+        assert_eq!(verify_dispute_authority(&signer, &MockContextBuilder::new().build()), Err(mismatch.clone()));
+        let v1 = MockContextBuilder::new().put(DISPUTE_AUTHORITY_OFFSET, signer_key.as_ref()).version_1().build();
+        assert_eq!(verify_dispute_authority(&signer, &v1), Err(mismatch.clone()));
+        let v2 = MockContextBuilder::new().put(DISPUTE_AUTHORITY_OFFSET, signer_key.as_ref()).build();
+        assert_eq!(verify_dispute_authority(&signer, &v2), Ok(()));
+
+        // The LP PDA cannot be configured as its own market's dispute authority
+        let lp_key = Pubkey::new_unique();
+        let mut params = vec![0u8; 76];
+        params[44..76].copy_from_slice(lp_key.as_ref());
+        assert_eq!(parse_extension(&params, &lp_key, 0, 500, 0), Err(mismatch));
+        let extension = parse_extension(&params, &Pubkey::new_unique(), 0, 500, 0).unwrap();
+        assert_eq!(extension.dispute_authority, lp_key);
+    }
+
+    // -----------------------------------------------------------------------
+    // 49. Mode byte is range-checked at init
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_init_rejects_out_of_range_mode() {
+        let init = |mode: u8| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 98];
+            data[0] = 0x02;
+            data[1] = mode;
+            data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        let err: ProgramError = UptimeMatcherError::InvalidMode.into();
+        assert_eq!(init(99), Err(err.clone()));
+        assert_eq!(init(MODE_SLASHING_SETTLEMENT + 1), Err(err.clone()));
+        // Valid modes pass validation; off-chain the Clock sysvar read fails instead
+        for mode in [MODE_CONTINUOUS, MODE_SLASHING_SETTLEMENT] {
+            let result = init(mode);
+            assert_ne!(result, Err(err.clone()));
+            assert!(result.is_err());
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 50. Quote at a hypothetical mark
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_quote_at_mark_runs_match_pipeline() {
+        let decode = |out: [u8; QUOTE_AT_MARK_RETURN_SIZE]| {
+            let at = |o: usize| u64::from_le_bytes(out[o..o + 8].try_into().unwrap());
+            assert_eq!(out[0], QUOTE_AT_MARK_LAYOUT_VERSION);
+            (at(1), at(9), at(17), at(25))
+        };
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4].copy_from_slice(&50u32.to_le_bytes());
+        ctx[EDGE_SPREAD_OFFSET..EDGE_SPREAD_OFFSET + 4].copy_from_slice(&30u32.to_le_bytes());
+        ctx[MAX_SPREAD_OFFSET..MAX_SPREAD_OFFSET + 4].copy_from_slice(&500u32.to_le_bytes());
+        let params = SpreadParams { base_spread: 50, edge_spread: 30, max_spread: 500, ..Default::default() };
+
+        // The live uptime plays no part
+        ctx[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8].copy_from_slice(&1u64.to_le_bytes());
+        for mark in [0, 500_000, 995_000, MAX_PROBABILITY] {
+            let (price, spread, edge_factor) = uptime_exec_price(mark, false, &params).unwrap();
+            assert_eq!(decode(quote_at_mark(&ctx, mark, 0, 0).unwrap()), (price, spread, edge_factor as u64, 0));
+        }
+        assert_eq!(
+            quote_at_mark(&ctx, MAX_PROBABILITY + 1, 0, 0),
+            Err(UptimeMatcherError::InvalidProbability.into())
+        );
+
+        // On an inverted market the mark is the downtime probability
+        ctx[INVERTED_OFFSET] = 1;
+        let (price, spread, _) = uptime_exec_price(995_000, true, &params).unwrap();
+        let (q_price, q_spread, _, _) = decode(quote_at_mark(&ctx, 5_000, 0, 0).unwrap());
+        assert_eq!((q_price, q_spread), (price, spread));
+        ctx[INVERTED_OFFSET] = 0;
+
+        // Impact uses the remaining liquidity, and an oversized fill fails like a match
+        let liq = 1_000_000_000u128;
+        ctx[IMPACT_K_OFFSET..IMPACT_K_OFFSET + 4].copy_from_slice(&100u32.to_le_bytes());
+        ctx[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&liq.to_le_bytes());
+        let (_, _, _, impact) = decode(quote_at_mark(&ctx, 995_000, liq / 100, 0).unwrap());
+        assert_eq!(impact, compute_impact_bps(IMPACT_MODEL_LINEAR, 100, liq / 100, liq));
+        assert!(impact > 0);
+        assert_eq!(
+            quote_at_mark(&ctx, 995_000, liq + 1, 0),
+            Err(UptimeMatcherError::InsufficientLiquidity.into())
+        );
+
+        // A timestamp inside the expiry window widens the quote; none leaves it at 1x
+        let ctx = {
+            let mut c = ctx.clone();
+            c[IMPACT_K_OFFSET..IMPACT_K_OFFSET + 4].fill(0);
+            c[RESOLUTION_TIMESTAMP_OFFSET..RESOLUTION_TIMESTAMP_OFFSET + 8].copy_from_slice(&10_000i64.to_le_bytes());
+            c[EXPIRY_WIDEN_WINDOW_OFFSET..EXPIRY_WIDEN_WINDOW_OFFSET + 4].copy_from_slice(&1_000u32.to_le_bytes());
+            c[EXPIRY_MAX_MULTIPLIER_OFFSET..EXPIRY_MAX_MULTIPLIER_OFFSET + 4].copy_from_slice(&30_000u32.to_le_bytes());
+            c
+        };
+        let (_, base, _, _) = decode(quote_at_mark(&ctx, 500_000, 0, 0).unwrap());
+        let (_, widened, _, _) = decode(quote_at_mark(&ctx, 500_000, 0, 9_900).unwrap());
+        assert!(widened > base);
+    }
+
+    #[test]
+    fn test_quote_at_mark_is_read_only() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, lp_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0u64;
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+        let before = ctx_data.clone();
+        {
+            let ctx = AccountInfo::new(&ctx_key, false, false, &mut lamports, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0x16];
+            data.extend_from_slice(&995_000u64.to_le_bytes());
+            assert_eq!(process_quote_at_mark(&program_id, std::slice::from_ref(&ctx), &data), Ok(()));
+            assert_eq!(process_quote_at_mark(&program_id, &[ctx], &[0x16, 0]), Err(ProgramError::InvalidInstructionData));
+        }
+        assert_eq!(ctx_data, before);
+    }
+
+    // -----------------------------------------------------------------------
+    // 51. Impact with zero liquidity fails instead of quoting flat
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_zero_liquidity_with_impact_rejected() {
+        let err: ProgramError = UptimeMatcherError::InvalidLiquidityConfig.into();
+        assert_eq!(check_liquidity_config(100, 0), Err(err.clone()));
+        assert_eq!(check_liquidity_config(0, 0), Ok(()));
+        assert_eq!(check_liquidity_config(100, 1), Ok(()));
+
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        // A synced market whose notional slipped past init as zero
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).put(IMPACT_K_OFFSET, &100u32.to_le_bytes()).build();
+        assert_eq!(quote_at_mark(&ctx_data, 995_000, 0, 0), Err(err.clone()));
+
+        let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+        let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+        // Rejected even for a zero-size fill, which would otherwise price no impact
+        assert_eq!(process_match(&program_id, &[lp, ctx], &[0x00]), Err(err));
+    }
+
+    // -----------------------------------------------------------------------
+    // 52. Opt-in clamp of the ask at par
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_clamp_to_par() {
+        let params = |clamp_to_par| SpreadParams { base_spread: 20, edge_spread: 30, max_spread: 500, clamp_to_par, ..Default::default() };
+
+        // Off by default: 99.5% uptime quotes above par
+        assert_eq!(uptime_exec_price(995_000, false, &params(false)).unwrap().0, 1_026_840);
+        // On: the ask is capped at 1.0, the reported spread is unchanged
+        let (price, spread, _) = uptime_exec_price(995_000, false, &params(true)).unwrap();
+        assert_eq!((price, spread), (MAX_PROBABILITY, 320));
+        assert_eq!(uptime_exec_price(MAX_PROBABILITY, false, &params(true)).unwrap().0, MAX_PROBABILITY);
+
+        // Quotes already below par are untouched
+        for uptime in [0, 500_000, 900_000] {
+            assert_eq!(
+                uptime_exec_price(uptime, false, &params(true)).unwrap(),
+                uptime_exec_price(uptime, false, &params(false)).unwrap()
+            );
+        }
+
+        // The stored flag reaches the quote path
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4].copy_from_slice(&20u32.to_le_bytes());
+        ctx[EDGE_SPREAD_OFFSET..EDGE_SPREAD_OFFSET + 4].copy_from_slice(&30u32.to_le_bytes());
+        ctx[MAX_SPREAD_OFFSET..MAX_SPREAD_OFFSET + 4].copy_from_slice(&500u32.to_le_bytes());
+        ctx[CLAMP_TO_PAR_OFFSET] = 1;
+        let out = quote_at_mark(&ctx, 995_000, 0, 0).unwrap();
+        assert_eq!(u64::from_le_bytes(out[1..9].try_into().unwrap()), MAX_PROBABILITY);
+    }
+
+    // -----------------------------------------------------------------------
+    // 53. Handler-level match gates on builder contexts
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_match_gates_on_built_context() {
+        // A valid context clears every pre-clock gate; off-chain the Clock read fails instead
+        let (valid, _) = MockContextBuilder::new().run(process_match, &[0x00]);
+        assert!(valid.is_err());
+
+        let cases = [
+            (MockContextBuilder::new().resolved(OUTCOME_SAFE), UptimeMatcherError::MarketResolved),
+            (MockContextBuilder::new().pending_resolution(OUTCOME_SLASHED, 0), UptimeMatcherError::ResolutionPending),
+            (MockContextBuilder::new().uptime(0).put(HAS_SYNCED_OFFSET, &[0]), UptimeMatcherError::ProbabilityNotSet),
+            (
+                MockContextBuilder::new()
+                    .put(SIGNAL_ACTIONS_OFFSET, &[1])
+                    .put(SIGNAL_SEVERITY_OFFSET, &SIGNAL_CRITICAL.to_le_bytes()),
+                UptimeMatcherError::CriticalSignalHalt,
+            ),
+            (MockContextBuilder::new().put(ORACLE_INACTIVE_OFFSET, &[1]), UptimeMatcherError::OracleInactive),
+            (MockContextBuilder::new().liquidity(100, 0), UptimeMatcherError::InvalidLiquidityConfig),
+        ];
+        for (builder, err) in cases {
+            let err: ProgramError = err.into();
+            assert_ne!(valid, Err(err.clone()));
+            let before = builder.data.clone();
+            let (result, after) = builder.run(process_match, &[0x00]);
+            assert_eq!(result, Err(err));
+            // Rejected matches leave the return buffer untouched
+            assert_eq!(after, before);
+        }
+    }
+
+    #[test]
+    fn test_quote_at_mark_on_built_context() {
+        let ctx = MockContextBuilder::new().spreads(20, 30, 500).liquidity(100, 1_000_000_000).build();
+        let out = quote_at_mark(&ctx, 995_000, 0, 0).unwrap();
+        let (price, spread, _) = compute_exec_price_edge(995_000, 20, 30, 500, 0);
+        assert_eq!(u64::from_le_bytes(out[1..9].try_into().unwrap()), price);
+        assert_eq!(u64::from_le_bytes(out[9..17].try_into().unwrap()), spread);
+    }
+
+    // -----------------------------------------------------------------------
+    // 54. Bounds-checked u128 context fields
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_u128_accessors_are_bounds_checked() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        write_u128(&mut ctx, LIQUIDITY_OFFSET, u128::MAX - 1).unwrap();
+        write_u128(&mut ctx, MAX_FILL_OFFSET, 7).unwrap();
+        assert_eq!(read_u128(&ctx, LIQUIDITY_OFFSET), Ok(u128::MAX - 1));
+        assert_eq!(read_u128(&ctx, MAX_FILL_OFFSET), Ok(7));
+
+        // A field running past the end of a short account errors instead of panicking
+        let mut short = vec![0u8; MAX_FILL_OFFSET + 15];
+        assert_eq!(read_u128(&short, MAX_FILL_OFFSET), Err(ProgramError::InvalidAccountData));
+        assert_eq!(write_u128(&mut short, MAX_FILL_OFFSET, 1), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_u128(&short, usize::MAX - 8), Err(ProgramError::InvalidAccountData));
+        assert!(short.iter().all(|&b| b == 0));
+    }
+
+    // -----------------------------------------------------------------------
+    // 55. Forced SLASHED resolution after repeated slashing syncs
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_slashing_limit_forces_resolution() {
+        let count = |ctx: &[u8]| u32::from_le_bytes(ctx[SLASHING_COUNT_OFFSET..SLASHING_COUNT_OFFSET + 4].try_into().unwrap());
+        let mut ctx = MockContextBuilder::new()
+            .put(MAX_SLASHING_BEFORE_RESOLVE_OFFSET, &[2])
+            .pending_resolution(OUTCOME_SAFE, 1_000)
+            .build();
+
+        // Only escalations into slashing territory count, once per incident
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_NONE, SIGNAL_LOW, 1, 0), Ok(false));
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_LOW, SIGNAL_HIGH, 1, 0), Ok(false));
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_HIGH, SIGNAL_CRITICAL, 1, 0), Ok(false));
+        assert_eq!(count(&ctx), 1);
+        assert_eq!(ctx[IS_RESOLVED_OFFSET], 0);
+
+        // The second incident reaches the limit and overrides the pending SAFE proposal
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_NONE, SIGNAL_CRITICAL, 42, 0), Ok(true));
+        assert_eq!(count(&ctx), 2);
+        assert_eq!((ctx[IS_RESOLVED_OFFSET], ctx[RESOLUTION_OUTCOME_OFFSET]), (1, OUTCOME_SLASHED));
+        assert_eq!(ctx[PENDING_RESOLUTION_OFFSET], 0);
+        assert_eq!(u64::from_le_bytes(ctx[RESOLVED_SLOT_OFFSET..RESOLVED_SLOT_OFFSET + 8].try_into().unwrap()), 42);
+
+        // Disabled (the default): slashes are never counted
+        let mut ctx = MockContextBuilder::new().build();
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_NONE, SIGNAL_CRITICAL, 1, 0), Ok(false));
+        assert_eq!(count(&ctx), 0);
+        assert_reserved_zero(&ctx);
+    }
+
+    #[test]
+    fn test_slashing_limit_respects_grace_period() {
+        let mut ctx = MockContextBuilder::new()
+            .put(MAX_SLASHING_BEFORE_RESOLVE_OFFSET, &[1])
+            .put(RESOLUTION_GRACE_OFFSET, &3_600u32.to_le_bytes())
+            .pending_resolution(OUTCOME_SAFE, 1_000)
+            .build();
+        let resolved_after = |ctx: &[u8]| i64::from_le_bytes(ctx[RESOLVED_AFTER_OFFSET..RESOLVED_AFTER_OFFSET + 8].try_into().unwrap());
+
+        // The limit proposes SLASHED over the pending SAFE instead of resolving at once
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_NONE, SIGNAL_CRITICAL, 42, 10_000), Ok(true));
+        assert_eq!(ctx[IS_RESOLVED_OFFSET], 0);
+        assert_eq!(ctx[PENDING_RESOLUTION_OFFSET], OUTCOME_SLASHED + 1);
+        assert_eq!(resolved_after(&ctx), 13_600);
+
+        // A later incident leaves the pending SLASHED proposal's deadline alone
+        assert_eq!(record_slashing_signal(&mut ctx, SIGNAL_NONE, SIGNAL_CRITICAL, 43, 12_000), Ok(true));
+        assert_eq!(resolved_after(&ctx), 13_600);
+
+        assert_eq!(finalize_resolution(&mut ctx, 13_599, 50), Err(UptimeMatcherError::GracePeriodActive.into()));
+        assert_eq!(finalize_resolution(&mut ctx, 13_600, 50), Ok((OUTCOME_SLASHED, 0)));
+        assert_eq!((ctx[IS_RESOLVED_OFFSET], ctx[RESOLUTION_OUTCOME_OFFSET]), (1, OUTCOME_SLASHED));
+    }
+
+    #[test]
+    fn test_slashing_limit_requires_settlement_mode() {
+        let init = |mode: u8, max_slashing: u8| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 133];
+            data[0] = 0x02;
+            data[1] = mode;
+            data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+            data[132] = max_slashing;
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        let err: ProgramError = UptimeMatcherError::InvalidMode.into();
+        assert_eq!(init(MODE_CONTINUOUS, 3), Err(err.clone()));
+        // Off-chain the Clock sysvar read fails once validation passes
+        for (mode, max_slashing) in [(MODE_SLASHING_SETTLEMENT, 3), (MODE_CONTINUOUS, 0)] {
+            let result = init(mode, max_slashing);
+            assert_ne!(result, Err(err.clone()));
+            assert!(result.is_err());
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 56. Immutable pricing snapshots
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_snapshot_layout() {
+        let matcher = Pubkey::new_unique();
+        let ctx = MockContextBuilder::new().spreads(25, 30, 400).build();
+        let out = encode_snapshot(&ctx, &matcher, 1_234, -5).unwrap();
+        assert_eq!(u64::from_le_bytes(out[0..8].try_into().unwrap()), UPTIME_SNAPSHOT_MAGIC);
+        assert_eq!(&out[8..40], matcher.as_ref());
+        assert_eq!(u64::from_le_bytes(out[40..48].try_into().unwrap()), 1_234);
+        assert_eq!(i64::from_le_bytes(out[48..56].try_into().unwrap()), -5);
+        assert_eq!(out[56..], encode_pricing_params(&ctx).unwrap());
+        // The snapshot can never pass as a matcher context
+        assert_ne!(UPTIME_SNAPSHOT_MAGIC, UPTIME_MATCHER_MAGIC);
+        assert_eq!(encode_snapshot(&ctx[..100], &matcher, 0, 0), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_snapshot_validates_accounts_before_clock() {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), solana_system_interface::program::ID];
+        let snapshot = |ctx_data: &mut Vec<u8>, payer_signs: bool, system_key: &Pubkey| {
+            let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+            let (mut d1, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+            let accounts = [
+                AccountInfo::new(&keys[0], false, false, &mut l0, ctx_data, &program_id, false, 0),
+                AccountInfo::new(&keys[1], false, true, &mut l1, &mut d1, &keys[3], false, 0),
+                AccountInfo::new(&keys[2], payer_signs, true, &mut l2, &mut d2, &keys[3], false, 0),
+                AccountInfo::new(system_key, false, false, &mut l3, &mut d3, &keys[3], false, 0),
+            ];
+            process_snapshot_matcher(&program_id, &accounts, &[0x17])
+        };
+        let mut ctx = MockContextBuilder::new().build();
+        assert_eq!(snapshot(&mut ctx, false, &keys[3]), Err(ProgramError::MissingRequiredSignature));
+        assert_eq!(snapshot(&mut ctx, true, &Pubkey::new_unique()), Err(ProgramError::IncorrectProgramId));
+        let mut foreign = yield_ctx();
+        assert_eq!(snapshot(&mut foreign, true, &keys[3]), Err(ProgramError::UninitializedAccount));
+    }
+
+    // -----------------------------------------------------------------------
+    // 57. Spread curve average over an uptime range
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_spread_average_matches_curve_mean() {
+        let decode = |out: [u8; SPREAD_AVERAGE_RETURN_SIZE]| (u64::from_le_bytes(out[0..8].try_into().unwrap()), out[8]);
+        let ctx = MockContextBuilder::new().spreads(20, 30, 500).build();
+        // Five points 0, 250_000, ..., 1_000_000 on the exact curve the match prices off
+        let points = [0u64, 250_000, 500_000, 750_000, 1_000_000];
+        let sum: u64 = points.iter().map(|&p| compute_total_spread(p, 20, 30, 0, 500, 0, 0, MAX_EDGE_FACTOR_E6).0).sum();
+        assert_eq!(decode(encode_spread_average(&ctx, 0, 1_000_000, 5).unwrap()), (sum.div_ceil(5), 5));
+        // The default grid passes through 50%, where the edge factor is lowest, so the
+        // average sits strictly between the curve's minimum and its capped edges
+        let (avg, _) = decode(encode_spread_average(&ctx, 0, 1_000_000, DEFAULT_SPREAD_AVERAGE_SAMPLES).unwrap());
+        assert!(avg > compute_total_spread(500_000, 20, 30, 0, 500, 0, 0, MAX_EDGE_FACTOR_E6).0);
+        assert!(avg < compute_total_spread(1_000_000, 20, 30, 0, 500, 0, 0, MAX_EDGE_FACTOR_E6).0);
+        // An empty range is the single point, whatever the grid size
+        let point = compute_total_spread(995_000, 20, 30, 0, 500, 0, 0, MAX_EDGE_FACTOR_E6).0;
+        assert_eq!(decode(encode_spread_average(&ctx, 995_000, 995_000, 64).unwrap()), (point, 1));
+        // A static-spread market averages to its base spread
+        let flat = MockContextBuilder::new().spreads(40, 0, 500).build();
+        assert_eq!(decode(encode_spread_average(&flat, 0, 1_000_000, 64).unwrap()).0, 40);
+    }
+
+    #[test]
+    fn test_spread_average_rejects_bad_ranges() {
+        let ctx = MockContextBuilder::new().build();
+        let probability = Err(UptimeMatcherError::InvalidProbability.into());
+        assert_eq!(encode_spread_average(&ctx, 600_000, 500_000, 8), probability);
+        assert_eq!(encode_spread_average(&ctx, 0, MAX_PROBABILITY + 1, 8), probability);
+        assert_eq!(encode_spread_average(&ctx, 0, 1_000_000, 1), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(encode_spread_average(&ctx, 0, 1_000_000, MAX_SPREAD_AVERAGE_SAMPLES + 1), Err(ProgramError::InvalidInstructionData));
+        // The handler needs both bounds and rejects a foreign context
+        let mut data = vec![0x18];
+        data.extend_from_slice(&0u64.to_le_bytes());
+        assert_eq!(run_with_yield_ctx(process_get_spread_average, false, &data), Err(ProgramError::InvalidInstructionData));
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        assert_eq!(run_with_yield_ctx(process_get_spread_average, false, &data), Err(ProgramError::UninitializedAccount));
+    }
 
-    msg!(
-        "UPTIME_SYNC: old_uptime={} new_uptime={} signal={}",
-        old_uptime,
-        new_uptime,
-        signal_severity
-    );
+    // -----------------------------------------------------------------------
+    // 58. Liquidity-scaled edge-factor cap
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_edge_factor_cap_buckets() {
+        let notional = |n: u128| n * E6_SCALE as u128;
+        assert_eq!(edge_factor_cap_for_liquidity(0), 3 * E6_SCALE as u128);
+        assert_eq!(edge_factor_cap_for_liquidity(notional(100_000) - 1), 3 * E6_SCALE as u128);
+        assert_eq!(edge_factor_cap_for_liquidity(notional(100_000)), 5 * E6_SCALE as u128);
+        assert_eq!(edge_factor_cap_for_liquidity(notional(1_000_000)), MAX_EDGE_FACTOR_E6);
+        assert_eq!(edge_factor_cap_for_liquidity(notional(10_000_000) - 1), MAX_EDGE_FACTOR_E6);
+        assert_eq!(edge_factor_cap_for_liquidity(notional(10_000_000)), DEEP_LIQUIDITY_EDGE_FACTOR_E6);
+        assert_eq!(edge_factor_cap_for_liquidity(u128::MAX), DEEP_LIQUIDITY_EDGE_FACTOR_E6);
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_edge_cap_follows_liquidity_when_enabled() {
+        // (total_spread, edge_factor) quoted at certainty, where the cap always binds
+        let quote = |liquidity_notional: u128, by_liquidity: u8| {
+            let ctx = MockContextBuilder::new()
+                .spreads(20, 30, 1_000)
+                .liquidity(10, liquidity_notional * E6_SCALE as u128)
+                .put(EDGE_CAP_BY_LIQUIDITY_OFFSET, &[by_liquidity])
+                .build();
+            let out = quote_at_mark(&ctx, MAX_PROBABILITY, 0, 0).unwrap();
+            (u64::from_le_bytes(out[9..17].try_into().unwrap()), u64::from_le_bytes(out[17..25].try_into().unwrap()))
+        };
+        // Thin book: 3x edge keeps the market tradeable; deep book: 20x
+        assert_eq!(quote(50_000, 1), (20 + 90, 3_000_000));
+        assert_eq!(quote(50_000_000, 1), (20 + 600, 20_000_000));
+        // Off by default: the 10x cap applies at any depth
+        assert_eq!(quote(50_000, 0), (20 + 300, 10_000_000));
+        assert_eq!(quote(50_000_000, 0), (20 + 300, 10_000_000));
+        // The spread curve reads see the same cap
+        let thin = MockContextBuilder::new()
+            .spreads(20, 30, 1_000)
+            .liquidity(10, 50_000 * E6_SCALE as u128)
+            .put(EDGE_CAP_BY_LIQUIDITY_OFFSET, &[1])
+            .build();
+        let out = encode_spread_average(&thin, MAX_PROBABILITY, MAX_PROBABILITY, 2).unwrap();
+        assert_eq!(u64::from_le_bytes(out[0..8].try_into().unwrap()), 110);
+    }
 
-/// Tag 0x04: Resolve NCN slashing event — sets final probability to 0 (SLASHED) or 1_000_000 (SAFE)
-/// Accounts:
-///   [0] Matcher context account (writable)
-///   [1] NCN oracle account (signer — must be authorized oracle)
-/// Data:
-///   [0] tag (0x04)
-///   [1] outcome (u8: 0=SLASHED -> prob=0, 1=SAFE -> prob=1_000_000)
-pub fn process_resolve(
-    _program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    data: &[u8],
-) -> ProgramResult {
-    if accounts.len() < 2 {
-        return Err(ProgramError::NotEnoughAccountKeys);
+    #[test]
+    fn test_edge_cap_by_liquidity_requires_a_notional() {
+        let init = |liquidity: u128, flag: u8| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 134];
+            data[0] = 0x02;
+            data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+            data[34..50].copy_from_slice(&liquidity.to_le_bytes());
+            data[133] = flag;
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        assert_eq!(init(0, 1), Err(UptimeMatcherError::InvalidLiquidityConfig.into()));
+        assert_eq!(init(1_000_000, 2), Err(ProgramError::InvalidInstructionData));
+        // Off-chain the Clock sysvar read fails once validation passes; impact is off
+        // throughout, since fills draw down any configured notional
+        for (liquidity, flag) in [(1_000_000, 1), (0, 0)] {
+            let result = init(liquidity, flag);
+            assert_ne!(result, Err(UptimeMatcherError::InvalidLiquidityConfig.into()));
+            assert!(result.is_err());
+        }
     }
-    if data.len() < 2 {
-        return Err(ProgramError::InvalidInstructionData);
+
+    // -----------------------------------------------------------------------
+    // 59. Signal spread read from the oracle
+    // -----------------------------------------------------------------------
+    /// perf_feed with the given oracle signal severity byte
+    fn perf_feed_with_severity(severity: u8) -> Vec<u8> {
+        let mut feed = perf_feed(2, true);
+        let at = feed.len() - 8 - PERF_FEED_LAST_UPDATED_AFTER_HISTORY + PERF_FEED_SIGNAL_SEVERITY_AFTER_HISTORY;
+        feed[at] = severity;
+        feed
     }
 
-    let ctx_account = &accounts[0];
-    let oracle = &accounts[1];
+    #[test]
+    fn test_read_feed_signal_severity() {
+        assert_eq!(read_feed_signal_severity(&perf_feed(0, true)), Ok(SIGNAL_NONE));
+        assert_eq!(read_feed_signal_severity(&perf_feed_with_severity(3)), Ok(SIGNAL_CRITICAL));
+        assert_eq!(read_feed_signal_severity(&perf_feed_with_severity(4)), Err(ProgramError::InvalidAccountData));
+        assert_eq!(SIGNAL_SPREAD_BPS, [0, 25, 100, 300]);
+    }
 
-    // Oracle must be signer
-    if !oracle.is_signer {
-        msg!("NCN-UPTIME-MATCHER: Oracle must be signer for resolution");
-        return Err(ProgramError::MissingRequiredSignature);
+    #[test]
+    fn test_sync_reads_signal_spread_from_oracle() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).oracle(&oracle_key).build();
+
+        let sync = |feed: &mut Vec<u8>, oracle_owner: &Pubkey, ctx_data: &mut Vec<u8>| {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, feed, oracle_owner, false, 0);
+            let mut data = vec![0x03];
+            // Keeper claims no signal and a zero spread; only the oracle's severity counts
+            for field in [995_000u64, SIGNAL_NONE, 0, 1] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+            process_uptime_sync(&program_id, &[ctx, oracle], &data)
+        };
+
+        // The oracle must be a genuine feed now that its data is read
+        assert_eq!(
+            sync(&mut perf_feed_with_severity(2), &program_id, &mut ctx_data),
+            Err(UptimeMatcherError::InvalidOracleAccount.into())
+        );
+        assert_eq!(
+            sync(&mut perf_feed_with_severity(9), &NCN_ORACLE_PROGRAM_ID, &mut ctx_data),
+            Err(ProgramError::InvalidAccountData)
+        );
+        // Off-chain the Clock sysvar read fails once the oracle severity is read
+        let result = sync(&mut perf_feed_with_severity(2), &NCN_ORACLE_PROGRAM_ID, &mut ctx_data);
+        assert_ne!(result, Err(ProgramError::InvalidAccountData));
+        assert!(result.is_err());
     }
 
-    // Verify context + oracle
-    {
-        let ctx_data = ctx_account.try_borrow_data()?;
-        if !verify_magic(&ctx_data) {
-            return Err(ProgramError::UninitializedAccount);
-        }
+    // -----------------------------------------------------------------------
+    // 60. Missing sync accounts are named
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_missing_oracle_account_distinct_from_mismatch() {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut d0, mut d1, mut d2) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&keys[0], false, true, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&keys[1], false, false, &mut l1, &mut d1, &program_id, false, 0),
+            AccountInfo::new(&keys[2], false, true, &mut l2, &mut d2, &program_id, false, 0),
+        ];
+        let missing = Err(UptimeMatcherError::OracleAccountMissing.into());
 
-        if ctx_data[IS_RESOLVED_OFFSET] == 1 {
-            msg!("NCN-UPTIME-MATCHER: Already resolved");
-            return Err(UptimeMatcherError::MarketResolved.into());
-        }
+        assert_eq!(process_uptime_sync(&program_id, &[], &[0x03; 33]), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(process_uptime_sync(&program_id, &accounts[..1], &[0x03; 33]), missing);
+        assert_eq!(process_heartbeat(&program_id, &accounts[..1], &[0x09]), missing);
+        // Init-and-sync puts the context after the LP PDA
+        assert_eq!(process_init_and_sync(&program_id, &accounts[..2], &[0x0E]), missing);
+        // A batch checks every market's slots before applying any of them
+        let mut batch = vec![0x07, 2];
+        batch.resize(2 + 2 * SYNC_PAYLOAD_SIZE, 0);
+        assert_eq!(process_batch_uptime_sync(&program_id, &accounts, &batch), missing);
+        assert_eq!(process_batch_uptime_sync(&program_id, &accounts[..2], &batch), Err(ProgramError::NotEnoughAccountKeys));
+        // With both slots present a wrong account is a validation error, not a missing one
+        let result = process_uptime_sync(&program_id, &accounts[..2], &[0x03; 33]);
+        assert!(result.is_err() && result != missing);
+    }
 
-        let stored_oracle = read_ncn_oracle(&ctx_data)?;
-        if *oracle.key != stored_oracle {
-            msg!("NCN-UPTIME-MATCHER: Oracle mismatch");
-            return Err(UptimeMatcherError::OracleMismatch.into());
-        }
+    // -----------------------------------------------------------------------
+    // 61. Protocol fee on spread
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_protocol_fee_share_of_spread() {
+        // 1,000 notional at a 50 bps spread earns 5 (e6); a 20% fee takes 1 of it
+        assert_eq!(protocol_fee_e6(1_000 * E6_SCALE as u128, 50, 2_000), Ok(E6_SCALE));
+        assert_eq!(protocol_fee_e6(1_000 * E6_SCALE as u128, 50, 0), Ok(0));
+        assert_eq!(protocol_fee_e6(0, 50, 2_000), Ok(0));
+        // The whole spread at the max rate; dust rounds down in the LP's favor
+        assert_eq!(protocol_fee_e6(1_000 * E6_SCALE as u128, 50, MAX_PROTOCOL_FEE_BPS), Ok(5 * E6_SCALE));
+        assert_eq!(protocol_fee_e6(9_999, 1, 1), Ok(0));
+        assert_eq!(protocol_fee_e6(u128::MAX, 2, 1), Err(UptimeMatcherError::ArithmeticOverflow.into()));
+        assert_eq!(protocol_fee_e6(u128::MAX / 10_000, 1, 10_000), Err(UptimeMatcherError::ArithmeticOverflow.into()));
     }
 
-    let outcome = data[1];
-    if outcome > 1 {
-        msg!("NCN-UPTIME-MATCHER: Invalid outcome: {} (must be 0=SLASHED or 1=SAFE)", outcome);
-        return Err(UptimeMatcherError::InvalidOutcome.into());
+    #[test]
+    fn test_protocol_fee_accrual_saturates() {
+        let ctx = MockContextBuilder::new().put(ACCRUED_PROTOCOL_FEES_OFFSET, &(u64::MAX - 5).to_le_bytes()).build();
+        assert_eq!(accrue_protocol_fee(&ctx, 5), Ok(u64::MAX));
+        // Past the cap the balance stays pinned instead of the match failing
+        assert_eq!(accrue_protocol_fee(&ctx, 1_000), Ok(u64::MAX));
+        assert_eq!(accrue_protocol_fee(&MockContextBuilder::new().build(), 7), Ok(7));
     }
 
-    let final_probability = if outcome == 1 {
-        MAX_PROBABILITY // SAFE -> 100% uptime
-    } else {
-        0u64 // SLASHED -> 0%
-    };
+    #[test]
+    fn test_accrued_fees_read_and_claim() {
+        let ctx = MockContextBuilder::new()
+            .put(ACCRUED_PROTOCOL_FEES_OFFSET, &1_234_567u64.to_le_bytes())
+            .put(PROTOCOL_FEE_BPS_OFFSET, &2_000u16.to_le_bytes())
+            .build();
+        let out = encode_accrued_fees(&ctx).unwrap();
+        assert_eq!(u64::from_le_bytes(out[0..8].try_into().unwrap()), 1_234_567);
+        assert_eq!(u16::from_le_bytes(out[8..10].try_into().unwrap()), 2_000);
 
-    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
-    ctx_data[IS_RESOLVED_OFFSET] = 1;
-    ctx_data[RESOLUTION_OUTCOME_OFFSET] = outcome;
-    ctx_data[CURRENT_UPTIME_OFFSET..CURRENT_UPTIME_OFFSET + 8]
-        .copy_from_slice(&final_probability.to_le_bytes());
-    ctx_data[UPTIME_MARK_OFFSET..UPTIME_MARK_OFFSET + 8]
-        .copy_from_slice(&final_probability.to_le_bytes());
+        let mut claimed = ctx.clone();
+        assert_eq!(claim_protocol_fees(&mut claimed), Ok(1_234_567));
+        assert_eq!(encode_accrued_fees(&claimed).unwrap()[0..8], [0u8; 8]);
+        // The fee rate survives the claim
+        assert_eq!(claimed[PROTOCOL_FEE_BPS_OFFSET..PROTOCOL_FEE_BPS_OFFSET + 2], 2_000u16.to_le_bytes());
 
-    msg!(
-        "RESOLVE: outcome={} final_price={}",
-        if outcome == 1 { "SAFE" } else { "SLASHED" },
-        final_probability
-    );
+        // Only the protocol fee authority can claim, and it must sign
+        let authority = Pubkey::new_unique();
+        let claim = |signer: &Pubkey, is_signer: bool| {
+            let program_id = Pubkey::new_unique();
+            let ctx_key = Pubkey::new_unique();
+            let mut ctx_data = MockContextBuilder::new()
+                .put(PROTOCOL_FEE_AUTHORITY_OFFSET, authority.as_ref())
+                .put(ACCRUED_PROTOCOL_FEES_OFFSET, &500u64.to_le_bytes())
+                .build();
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut signer_data = [0u8; 0];
+            let result = {
+                let signer = AccountInfo::new(signer, is_signer, false, &mut l0, &mut signer_data, &program_id, false, 0);
+                let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+                process_claim_protocol_fees(&program_id, &[signer, ctx], &[0x1A])
+            };
+            (result, ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8].to_vec())
+        };
+        assert_eq!(claim(&authority, true), (Ok(()), vec![0u8; 8]));
+        assert_eq!(claim(&authority, false), (Err(ProgramError::MissingRequiredSignature), 500u64.to_le_bytes().to_vec()));
+        // The LP PDA that signs everything else is refused
+        let (result, after) = MockContextBuilder::new()
+            .put(PROTOCOL_FEE_AUTHORITY_OFFSET, authority.as_ref())
+            .put(ACCRUED_PROTOCOL_FEES_OFFSET, &500u64.to_le_bytes())
+            .run(process_claim_protocol_fees, &[0x1A]);
+        assert_eq!(result, Err(UptimeMatcherError::FeeAuthorityMismatch.into()));
+        assert_eq!(after[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8], 500u64.to_le_bytes());
+    }
 
-    Ok(())
-}
+    #[test]
+    fn test_init_rejects_protocol_fee_above_spread() {
+        let init = |fee_bps: u16, authority: &Pubkey| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 168];
+            data[0] = 0x02;
+            data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+            data[134..136].copy_from_slice(&fee_bps.to_le_bytes());
+            data[136..168].copy_from_slice(authority.as_ref());
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        let invalid = Err(UptimeMatcherError::InvalidProtocolFeeConfig.into());
+        let authority = Pubkey::new_unique();
+        assert_eq!(init(MAX_PROTOCOL_FEE_BPS + 1, &authority), invalid);
+        // A fee needs an authority to claim it
+        assert_eq!(init(1, &Pubkey::default()), invalid);
+        // Off-chain the Clock sysvar read fails once validation passes
+        for (fee_bps, authority) in [(MAX_PROTOCOL_FEE_BPS, authority), (0, Pubkey::default())] {
+            let result = init(fee_bps, &authority);
+            assert_ne!(result, invalid);
+            assert!(result.is_err());
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::state::*;
+    #[test]
+    fn test_init_rejects_expiry_multiplier_below_1x() {
+        let init = |window: u32, multiplier: u32| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 115];
+            data[0] = 0x02;
+            data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+            data[107..111].copy_from_slice(&window.to_le_bytes());
+            data[111..115].copy_from_slice(&multiplier.to_le_bytes());
+            super::process_init(&program_id, &[lp, ctx], &data)
+        };
+        let invalid = Err(UptimeMatcherError::InvalidExpiryMultiplier.into());
+        assert_eq!(init(3_600, 9_999), invalid);
+        // Off-chain the Clock sysvar read fails once validation passes
+        for (window, multiplier) in [(3_600, 10_000), (0, 0)] {
+            let result = init(window, multiplier);
+            assert_ne!(result, invalid);
+            assert!(result.is_err());
+        }
+    }
 
-    /// Replicates the edge spread calculation from process_match, purely arithmetic.
-    /// Returns (exec_price, total_spread, edge_factor).
-    fn compute_exec_price_edge(
-        uptime_e6: u64,
-        base_spread: u32,
-        edge_spread: u32,
-        max_spread: u32,
-        signal_adj: u64,
-    ) -> (u64, u64, u128) {
-        let p = uptime_e6 as u128;
-        let one_minus_p = MAX_PROBABILITY as u128 - p;
-
-        let edge_denominator = p
-            .checked_mul(one_minus_p)
-            .unwrap_or(0)
-            .checked_mul(4)
-            .unwrap_or(0)
-            / 1_000_000_000_000u128;
-
-        let edge_factor = if edge_denominator > 0 {
-            std::cmp::min(1_000_000u128 / edge_denominator, 10_000_000u128)
-        } else {
-            10_000_000u128
+    #[test]
+    fn test_init_version_1_sized_context() {
+        let init = |data: &[u8]| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            super::process_init(&program_id, &[lp, ctx], data)
         };
+        let mut data = vec![0u8; 136];
+        data[0] = 0x02;
+        data[18..26].copy_from_slice(&995_000u64.to_le_bytes());
+
+        // Without extension options a 320-byte account passes validation (off-chain the
+        // Clock sysvar read then fails)
+        let result = init(&data);
+        assert_ne!(result, Err(ProgramError::AccountDataTooSmall));
+        assert!(result.is_err());
+
+        // Extension options need the version-2 size
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        assert_eq!(init(&data), Err(ProgramError::AccountDataTooSmall));
+    }
 
-        let adjusted_edge = (edge_spread as u128)
-            .checked_mul(edge_factor)
-            .unwrap_or(0)
-            / 1_000_000u128;
+    #[test]
+    fn test_migrate_gates() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, payer_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let v1 = MockContextBuilder::for_lp(lp_key).version_1().build();
+        let migrate = |ctx_data: &mut Vec<u8>, signer: &Pubkey, payer_signs: bool, system: &Pubkey, data: &[u8]| {
+            let ctx_key = Pubkey::new_unique();
+            let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+            let (mut d0, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+            let lp = AccountInfo::new(signer, true, false, &mut l0, &mut d0, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, ctx_data, &program_id, false, 0);
+            let payer = AccountInfo::new(&payer_key, payer_signs, true, &mut l2, &mut d2, &system_program::ID, false, 0);
+            let sys = AccountInfo::new(system, false, false, &mut l3, &mut d3, &program_id, true, 0);
+            process_migrate(&program_id, &[lp, ctx, payer, sys], data)
+        };
+        let data = [0x1Cu8];
 
-        let total_spread = std::cmp::min(
-            (base_spread as u64)
-                .saturating_add(adjusted_edge as u64)
-                .saturating_add(signal_adj),
-            max_spread as u64,
+        assert_eq!(
+            migrate(&mut v1.clone(), &Pubkey::new_unique(), true, &system_program::ID, &data),
+            Err(ProgramError::InvalidAccountData)
         );
+        assert_eq!(migrate(&mut v1.clone(), &lp_key, false, &system_program::ID, &data), Err(ProgramError::MissingRequiredSignature));
+        assert_eq!(migrate(&mut v1.clone(), &lp_key, true, &Pubkey::new_unique(), &data), Err(ProgramError::IncorrectProgramId));
+
+        // A version-2 context has nothing to migrate
+        let mut v2 = MockContextBuilder::for_lp(lp_key).build();
+        assert_eq!(migrate(&mut v2, &lp_key, true, &system_program::ID, &data), Err(ProgramError::AccountAlreadyInitialized));
 
-        let spread_mult = 10_000u64.saturating_add(total_spread);
-        let exec_price = ((uptime_e6 as u128)
-            .checked_mul(spread_mult as u128)
-            .unwrap()
-            / 10_000u128) as u64;
+        // A context charging a protocol fee must name the authority that claims it
+        let mut charging = MockContextBuilder::for_lp(lp_key).version_1().put(PROTOCOL_FEE_BPS_OFFSET, &100u16.to_le_bytes()).build();
+        assert_eq!(
+            migrate(&mut charging, &lp_key, true, &system_program::ID, &data),
+            Err(UptimeMatcherError::InvalidProtocolFeeConfig.into())
+        );
 
-        (exec_price, total_spread, edge_factor)
+        // Past the gates only the Rent sysvar, unavailable off-chain, is left
+        let result = migrate(&mut v1.clone(), &lp_key, true, &system_program::ID, &data);
+        assert_ne!(result, Err(ProgramError::AccountAlreadyInitialized));
+        assert!(result.is_err());
     }
 
-    // -----------------------------------------------------------------------
-    // 1. 50% uptime (unlikely but tests base case)
-    // -----------------------------------------------------------------------
     #[test]
-    fn test_50_percent_uptime() {
-        let (price, spread, factor) = compute_exec_price_edge(500_000, 20, 30, 500, 0);
-        assert_eq!(factor, 1_000_000);
-        assert_eq!(spread, 50);
-        assert_eq!(price, 502_500);
+    fn test_version_1_context_cannot_claim_until_migrated() {
+        let authority_key = Pubkey::new_unique();
+        let ctx = MockContextBuilder::new().version_1().build();
+        assert!(!has_extension(&ctx));
+
+        let mut lamports = 0u64;
+        let mut data = [0u8; 0];
+        let authority = AccountInfo::new(&authority_key, true, false, &mut lamports, &mut data, &authority_key, false, 0);
+        assert_eq!(verify_fee_authority(&authority, &ctx), Err(UptimeMatcherError::FeeAuthorityMismatch.into()));
+
+        // Migrating writes version 2 and the extension fields
+        let mut migrated = ctx.clone();
+        migrated.resize(CTX_V2_SIZE, 0);
+        let extension = parse_extension(authority_key.as_ref(), &Pubkey::new_unique(), 0, 500, 100).unwrap();
+        assert_eq!(extension, ContextExtension { fee_authority: authority_key, ..ContextExtension::default() });
+        write_extension(&mut migrated, &extension);
+        assert!(has_extension(&migrated));
+        assert_eq!(verify_fee_authority(&authority, &migrated), Ok(()));
     }
 
     // -----------------------------------------------------------------------
-    // 2. 99.5% uptime (typical healthy NCN)
+    // 62. Match pre-check
     // -----------------------------------------------------------------------
     #[test]
-    fn test_995_percent_uptime() {
-        let (price, spread, factor) = compute_exec_price_edge(995_000, 20, 30, 500, 0);
-        // p=995000, 1-p=5000
-        // edge_denom = 995000*5000*4 / 1e12 = 19_900_000_000 / 1e12 = 0 (integer truncation!)
-        // edge_factor = 10_000_000 (max, since denominator is 0)
-        // adjusted_edge = 30 * 10_000_000 / 1_000_000 = 300
-        // total_spread = min(20 + 300, 500) = 320
-        assert_eq!(factor, 10_000_000);
-        assert_eq!(spread, 320);
-        // exec_price = 995000 * 10320 / 10000 = 1_026_840
-        assert_eq!(price, 1_026_840);
+    fn test_can_match_reports_first_failing_gate() {
+        let reason = |e: UptimeMatcherError| {
+            let mut out = [0u8; CAN_MATCH_RETURN_SIZE];
+            out[1..5].copy_from_slice(&e.code().to_le_bytes());
+            Ok(out)
+        };
+        // Checked in process_match order: resolved before pending, pending before unset uptime
+        let ctx = MockContextBuilder::new().resolved(OUTCOME_SAFE).pending_resolution(OUTCOME_SAFE, 0).build();
+        assert_eq!(encode_can_match(&ctx, &[0x1B]), reason(UptimeMatcherError::MarketResolved));
+        let ctx = MockContextBuilder::new().pending_resolution(OUTCOME_SAFE, 0).uptime(0).build();
+        assert_eq!(encode_can_match(&ctx, &[0x1B]), reason(UptimeMatcherError::ResolutionPending));
+        let ctx = MockContextBuilder::new().uptime(0).put(HAS_SYNCED_OFFSET, &[0]).put(ORACLE_INACTIVE_OFFSET, &[1]).build();
+        assert_eq!(encode_can_match(&ctx, &[0x1B]), reason(UptimeMatcherError::ProbabilityNotSet));
+        let ctx = MockContextBuilder::new()
+            .put(SIGNAL_ACTIONS_OFFSET, &[1])
+            .put(SIGNAL_SEVERITY_OFFSET, &SIGNAL_CRITICAL.to_le_bytes())
+            .put(ORACLE_INACTIVE_OFFSET, &[1])
+            .build();
+        assert_eq!(encode_can_match(&ctx, &[0x1B]), reason(UptimeMatcherError::CriticalSignalHalt));
+        let ctx = MockContextBuilder::new().put(ORACLE_INACTIVE_OFFSET, &[1]).liquidity(10, 0).build();
+        assert_eq!(encode_can_match(&ctx, &[0x1B]), reason(UptimeMatcherError::OracleInactive));
+        let ctx = MockContextBuilder::new().liquidity(10, 0).build();
+        assert_eq!(encode_can_match(&ctx, &[0x1B]), reason(UptimeMatcherError::InvalidLiquidityConfig));
+        // process_match fails the same way on the same context
+        let (result, _) = MockContextBuilder::new().liquidity(10, 0).run(process_match, &[0x00]);
+        assert_eq!(result, Err(UptimeMatcherError::InvalidLiquidityConfig.into()));
+    }
+
+    #[test]
+    fn test_can_match_passes_through_non_gate_errors() {
+        // Past the state gates the staleness check reads the Clock sysvar, which fails
+        // off-chain: an instruction error, not a reported reason
+        let result = encode_can_match(&MockContextBuilder::new().build(), &[0x1B]);
+        assert!(result.is_err());
+        assert!(matches!(result, Err(e) if e != ProgramError::Custom(UptimeMatcherError::OracleStale.code())));
     }
 
     // -----------------------------------------------------------------------
-    // 3. 10% uptime (NCN in trouble)
+    // 63. Per-severity signal actions
     // -----------------------------------------------------------------------
     #[test]
-    fn test_10_percent_uptime() {
-        let (price, spread, factor) = compute_exec_price_edge(100_000, 20, 30, 500, 0);
-        assert_eq!(factor, 10_000_000);
-        assert_eq!(spread, 320);
-        assert_eq!(price, 103_200);
+    fn test_signal_action_table_decoding() {
+        // CRITICAL in the low bits, NONE in the high bits
+        let table = 0b00_10_01_11;
+        assert_eq!(signal_action(table, SIGNAL_NONE), 0);
+        assert_eq!(signal_action(table, SIGNAL_LOW), SIGNAL_ACTION_NO_WIDEN);
+        assert_eq!(signal_action(table, SIGNAL_HIGH), SIGNAL_ACTION_HALT);
+        assert_eq!(signal_action(table, SIGNAL_CRITICAL), SIGNAL_ACTION_HALT | SIGNAL_ACTION_NO_WIDEN);
+        // Out-of-range severities read as CRITICAL
+        assert_eq!(signal_action(table, 7), signal_action(table, SIGNAL_CRITICAL));
+        // The legacy halt_on_critical flag halts on CRITICAL only
+        for severity in SIGNAL_NONE..SIGNAL_CRITICAL {
+            assert_eq!(signal_action(1, severity), 0);
+        }
+        assert_eq!(signal_action(1, SIGNAL_CRITICAL), SIGNAL_ACTION_HALT);
+    }
+
+    #[test]
+    fn test_signal_action_halts_configured_severities() {
+        // Halt on HIGH and CRITICAL, widen on LOW
+        let table = 0b00_00_01_01;
+        for severity in [SIGNAL_HIGH, SIGNAL_CRITICAL] {
+            assert_eq!(match_with_signal(severity, table), Err(UptimeMatcherError::CriticalSignalHalt.into()));
+        }
+        for severity in [SIGNAL_NONE, SIGNAL_LOW] {
+            let result = match_with_signal(severity, table);
+            assert_ne!(result, Err(UptimeMatcherError::CriticalSignalHalt.into()));
+            assert!(result.is_err());
+        }
+        // Halting on NONE stops quoting outright
+        assert_eq!(match_with_signal(SIGNAL_NONE, 0b01_00_00_00), Err(UptimeMatcherError::CriticalSignalHalt.into()));
+    }
+
+    #[test]
+    fn test_signal_action_no_widen_drops_signal_spread() {
+        let spread_at = |severity: u64, table: u8| {
+            let ctx = MockContextBuilder::new()
+                .put(SIGNAL_SEVERITY_OFFSET, &severity.to_le_bytes())
+                .put(SIGNAL_ADJUSTED_SPREAD_OFFSET, &SIGNAL_SPREAD_BPS[severity as usize].to_le_bytes())
+                .put(SIGNAL_ACTIONS_OFFSET, &[table])
+                .build();
+            let out = quote_at_mark(&ctx, 500_000, 0, 0).unwrap();
+            u64::from_le_bytes(out[9..17].try_into().unwrap())
+        };
+        let quiet = spread_at(SIGNAL_NONE, 0);
+        // Widen (the default) charges the severity's spread; NO_WIDEN quotes as if quiet
+        assert_eq!(spread_at(SIGNAL_HIGH, 0), quiet + SIGNAL_SPREAD_BPS[SIGNAL_HIGH as usize]);
+        assert_eq!(spread_at(SIGNAL_HIGH, 0b00_00_10_00), quiet);
+        // A NO_WIDEN bit on another level leaves this one widening
+        assert_eq!(spread_at(SIGNAL_LOW, 0b00_00_10_00), quiet + SIGNAL_SPREAD_BPS[SIGNAL_LOW as usize]);
+        // HALT | NO_WIDEN halts a match; the quote itself is unwidened
+        assert_eq!(spread_at(SIGNAL_CRITICAL, 0b11), quiet);
+        assert_eq!(match_with_signal(SIGNAL_CRITICAL, 0b11), Err(UptimeMatcherError::CriticalSignalHalt.into()));
     }
 
     // -----------------------------------------------------------------------
-    // 4. 90% uptime
+    // 64. Batch sync is all-or-nothing
     // -----------------------------------------------------------------------
     #[test]
-    fn test_90_percent_uptime() {
-        let (price, spread, factor) = compute_exec_price_edge(900_000, 20, 30, 500, 0);
-        assert_eq!(factor, 10_000_000);
-        assert_eq!(spread, 320);
-        assert_eq!(price, 928_800);
+    fn test_batch_sync_validates_every_entry_before_writing() {
+        let program_id = Pubkey::new_unique();
+        let lp_key = Pubkey::new_unique();
+        let ctx_keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let oracle_keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let ctx_for = |oracle: &Pubkey| {
+            MockContextBuilder::for_lp(lp_key).oracle(oracle).put(ORACLE_SEQUENCE_OFFSET, &5u64.to_le_bytes()).build()
+        };
+        let batch = |entries: &[(u64, u64)]| {
+            let mut data = vec![0x07, entries.len() as u8];
+            for &(uptime, sequence) in entries {
+                for field in [uptime, SIGNAL_NONE, 0, sequence] {
+                    data.extend_from_slice(&field.to_le_bytes());
+                }
+            }
+            data
+        };
+        let run = |ctx_keys: [&Pubkey; 2], data: &[u8]| {
+            let (mut c0, mut c1) = (ctx_for(&oracle_keys[0]), ctx_for(&oracle_keys[1]));
+            let (mut f0, mut f1) = (perf_feed(0, true), perf_feed(0, true));
+            let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+            let result = {
+                let accounts = [
+                    AccountInfo::new(ctx_keys[0], false, true, &mut l0, &mut c0, &program_id, false, 0),
+                    AccountInfo::new(&oracle_keys[0], false, false, &mut l1, &mut f0, &NCN_ORACLE_PROGRAM_ID, false, 0),
+                    AccountInfo::new(ctx_keys[1], false, true, &mut l2, &mut c1, &program_id, false, 0),
+                    AccountInfo::new(&oracle_keys[1], false, false, &mut l3, &mut f1, &NCN_ORACLE_PROGRAM_ID, false, 0),
+                ];
+                process_batch_uptime_sync(&program_id, &accounts, data)
+            };
+            (result, c0, c1)
+        };
+        let untouched = ctx_for(&oracle_keys[0]);
+
+        // A valid first market, then an out-of-range uptime or a replayed sequence: the
+        // batch fails on the bad entry and the first context is never written
+        for (bad, err) in [
+            ((MAX_PROBABILITY + 1, 6), UptimeMatcherError::InvalidProbability),
+            ((990_000, 5), UptimeMatcherError::OracleSequenceRegressed),
+        ] {
+            let (result, first, _) = run([&ctx_keys[0], &ctx_keys[1]], &batch(&[(995_000, 6), bad]));
+            assert_eq!(result, Err(err.into()));
+            assert_eq!(first, untouched);
+        }
+
+        // The same market twice is refused whatever its sequences
+        for entries in [[(995_000, 7), (990_000, 7)], [(995_000, 7), (990_000, 8)]] {
+            let (result, first, _) = run([&ctx_keys[0], &ctx_keys[0]], &batch(&entries));
+            assert_eq!(result, Err(UptimeMatcherError::DuplicateBatchMarket.into()));
+            assert_eq!(first, untouched);
+        }
+
+        // A fully valid batch gets past validation; off-chain the Clock read then fails
+        // before anything is written
+        let (result, first, _) = run([&ctx_keys[0], &ctx_keys[1]], &batch(&[(995_000, 7), (990_000, 8)]));
+        assert!(result.is_err());
+        assert_ne!(result, Err(UptimeMatcherError::OracleSequenceRegressed.into()));
+        assert_ne!(result, Err(UptimeMatcherError::DuplicateBatchMarket.into()));
+        assert_eq!(first, untouched);
     }
 
     // -----------------------------------------------------------------------
-    // 5. 1% uptime (NCN nearly dead)
+    // 65. Syncing from a deactivated oracle
     // -----------------------------------------------------------------------
     #[test]
-    fn test_1_percent_uptime() {
-        let (price, spread, factor) = compute_exec_price_edge(10_000, 20, 30, 500, 0);
-        assert_eq!(factor, 10_000_000);
-        assert_eq!(spread, 320);
-        assert_eq!(price, 10_320);
+    fn test_sync_rejects_inactive_oracle() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).oracle(&oracle_key).build();
+        let before = ctx_data.clone();
+
+        let mut payload = Vec::new();
+        for field in [995_000u64, SIGNAL_NONE, 0, 1] {
+            payload.extend_from_slice(&field.to_le_bytes());
+        }
+        let sync = |is_active: bool, data: &[u8], ctx_data: &mut Vec<u8>| {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut feed = perf_feed(2, is_active);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0);
+            if data[0] == 0x07 {
+                process_batch_uptime_sync(&program_id, &[ctx, oracle], data)
+            } else {
+                process_uptime_sync(&program_id, &[ctx, oracle], data)
+            }
+        };
+        let single = [&[0x03][..], &payload].concat();
+        let batch = [&[0x07, 1][..], &payload].concat();
+
+        let inactive = Err(UptimeMatcherError::OracleInactive.into());
+        assert_eq!(sync(false, &single, &mut ctx_data), inactive);
+        assert_eq!(sync(false, &batch, &mut ctx_data), inactive);
+        assert_eq!(ctx_data, before);
+
+        // An active feed gets past the guard; off-chain the Clock read fails instead
+        let result = sync(true, &single, &mut ctx_data);
+        assert_ne!(result, inactive);
+        assert!(result.is_err());
     }
 
     // -----------------------------------------------------------------------
-    // 6. 99% uptime
+    // 66. Heartbeats follow the oracle's own freshness
     // -----------------------------------------------------------------------
     #[test]
-    fn test_99_percent_uptime() {
-        let (price, spread, factor) = compute_exec_price_edge(990_000, 20, 30, 500, 0);
-        assert_eq!(factor, 10_000_000);
-        assert_eq!(spread, 320);
-        assert_eq!(price, 1_021_680);
+    fn test_heartbeat_requires_fresh_oracle() {
+        let feed = perf_feed(3, true);
+        assert_eq!(read_feed_last_updated(&feed[..feed.len() - 1]), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_feed_last_updated(&feed), Ok(FEED_UPDATED));
+
+        assert_eq!(MAX_HEARTBEAT_FEED_AGE_SECS, 80);
+        let stale = Err(UptimeMatcherError::OracleStale.into());
+        assert_eq!(check_heartbeat_feed_age(1_000, 1_000), Ok(()));
+        assert_eq!(check_heartbeat_feed_age(1_000, 1_000 + MAX_HEARTBEAT_FEED_AGE_SECS), Ok(()));
+        assert_eq!(check_heartbeat_feed_age(1_000, 1_001 + MAX_HEARTBEAT_FEED_AGE_SECS), stale);
+        // A dead keeper's feed never becomes fresh again, however often heartbeats are sent
+        assert_eq!(check_heartbeat_feed_age(0, i64::MAX), stale);
+        assert_eq!(check_heartbeat_feed_age(2_000, 1_000), Ok(()));
+    }
+
+    #[test]
+    fn test_heartbeat_rejects_inactive_oracle() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::new().oracle(&oracle_key).build();
+        let before = ctx_data.clone();
+
+        let heartbeat = |is_active: bool, ctx_data: &mut Vec<u8>| {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut feed = perf_feed(2, is_active);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0);
+            process_heartbeat(&program_id, &[ctx, oracle], &[0x09])
+        };
+
+        // A deactivated oracle cannot keep the context alive, however fresh it looks
+        let inactive = Err(UptimeMatcherError::OracleInactive.into());
+        assert_eq!(heartbeat(false, &mut ctx_data), inactive);
+        assert_eq!(ctx_data, before);
+
+        let result = heartbeat(true, &mut ctx_data);
+        assert_ne!(result, inactive);
+        assert!(result.is_err());
     }
 
     // -----------------------------------------------------------------------
-    // 7. Signal adjustment (Kalshify-style spread widening)
+    // 67. The oracle sequence cannot run ahead of the feed
     // -----------------------------------------------------------------------
     #[test]
-    fn test_signal_adjustment() {
-        let (price, spread, _) = compute_exec_price_edge(500_000, 20, 300, 500, 50);
-        assert_eq!(spread, 370);
-        assert_eq!(price, 518_500);
+    fn test_sequence_bounded_by_feed_last_updated() {
+        let ahead = Err(UptimeMatcherError::OracleSequenceAhead.into());
+        assert_eq!(check_sequence_within_feed(1_000, 1_000), Ok(()));
+        assert_eq!(check_sequence_within_feed(1_001, 1_000), ahead);
+        assert_eq!(check_sequence_within_feed(1, -5), ahead);
+
+        // A u64::MAX sequence would lock out every later sync; it never gets written
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).oracle(&oracle_key).build();
+        let before = ctx_data.clone();
+
+        let mut feed = perf_feed(2, true);
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+        let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0);
+        let mut data = vec![0x03];
+        for field in [995_000u64, SIGNAL_NONE, 0, u64::MAX] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        assert_eq!(process_uptime_sync(&program_id, &[ctx, oracle], &data), ahead);
+        assert_eq!(ctx_data, before);
     }
 
     // -----------------------------------------------------------------------
-    // 8. Signal CRITICAL widens spread significantly
+    // 68. Sync severity comes from the oracle, never the keeper
     // -----------------------------------------------------------------------
     #[test]
-    fn test_critical_signal_spread() {
-        let (price, spread, _) = compute_exec_price_edge(995_000, 20, 30, 500, 200);
-        // edge_factor = 10_000_000 (same reason as 99.5% test above)
-        // adjusted_edge = 30 * 10_000_000 / 1_000_000 = 300
-        // base(20) + edge(300) + signal(200) = 520, capped to max(500)
-        assert_eq!(spread, 500);
-        // exec_price = 995000 * 10500 / 10000 = 1_044_750
-        assert_eq!(price, 1_044_750);
+    fn test_sync_stores_oracle_severity() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::new()
+            .oracle(&oracle_key)
+            .put(MAX_SLASHING_BEFORE_RESOLVE_OFFSET, &[1])
+            .build();
+        let mut feed = perf_feed_with_severity(SIGNAL_CRITICAL as u8);
+
+        // The keeper claims no signal while the oracle has recorded a critical one
+        let mut payload = Vec::new();
+        for field in [995_000u64, SIGNAL_NONE, 0, 1] {
+            payload.extend_from_slice(&field.to_le_bytes());
+        }
+        {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0);
+            let sync = validate_uptime_sync(&program_id, &ctx, &oracle, &payload).unwrap();
+            write_uptime_sync(&ctx, &sync, &Clock { slot: 42, ..Clock::default() }).unwrap();
+        }
+
+        let read_u64 = |offset: usize| u64::from_le_bytes(ctx_data[offset..offset + 8].try_into().unwrap());
+        assert_eq!(read_u64(SIGNAL_SEVERITY_OFFSET), SIGNAL_CRITICAL);
+        assert_eq!(read_u64(SIGNAL_ADJUSTED_SPREAD_OFFSET), SIGNAL_SPREAD_BPS[SIGNAL_CRITICAL as usize]);
+        // The oracle's signal, not the keeper's, counts toward the slashing limit
+        assert_eq!((ctx_data[IS_RESOLVED_OFFSET], ctx_data[RESOLUTION_OUTCOME_OFFSET]), (1, OUTCOME_SLASHED));
     }
 
     // -----------------------------------------------------------------------
-    // 9. Max spread capping
+    // 69. A batch cannot sync one context twice
     // -----------------------------------------------------------------------
     #[test]
-    fn test_max_spread_capping() {
-        let (price, spread, _) = compute_exec_price_edge(500_000, 20, 1000, 500, 500);
-        assert_eq!(spread, 500);
-        assert_eq!(price, 525_000);
+    fn test_batch_sync_rejects_duplicate_context_after_slashing() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, oracle_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::new()
+            .oracle(&oracle_key)
+            .put(MAX_SLASHING_BEFORE_RESOLVE_OFFSET, &[1])
+            .build();
+        let before = ctx_data.clone();
+        let mut feed = perf_feed_with_severity(SIGNAL_CRITICAL as u8);
 
-        let (price2, spread2, _) = compute_exec_price_edge(10_000, 100, 200, 500, 300);
-        assert_eq!(spread2, 500);
-        assert_eq!(price2, 10_000 * 10_500 / 10_000);
+        // On its own the first entry crosses the slashing limit and resolves the market
+        // SLASHED; the second would then overwrite the resolved context
+        let mut data = vec![0x07, 2];
+        for (uptime, sequence) in [(500_000u64, 1u64), (999_000, 2)] {
+            for field in [uptime, SIGNAL_NONE, 0, sequence] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        let result = {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0);
+            let oracle = AccountInfo::new(&oracle_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0);
+            process_batch_uptime_sync(&program_id, &[ctx.clone(), oracle.clone(), ctx, oracle], &data)
+        };
+        assert_eq!(result, Err(UptimeMatcherError::DuplicateBatchMarket.into()));
+        assert_eq!(ctx_data, before);
+        assert_eq!(ctx_data[IS_RESOLVED_OFFSET], 0);
     }
 
     // -----------------------------------------------------------------------
-    // 10. Constants
+    // 70. Soft-stale band
     // -----------------------------------------------------------------------
     #[test]
-    fn test_max_probability_constant() {
-        assert_eq!(MAX_PROBABILITY, 1_000_000);
+    fn test_staleness_zones() {
+        let stale: ProgramError = UptimeMatcherError::OracleStale.into();
+
+        // Default, and always on a version-1 context: a single cliff at MAX_STALENESS_SLOTS
+        for ctx in [MockContextBuilder::new().build(), MockContextBuilder::new().version_1().build()] {
+            assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+            assert_eq!(check_staleness(&ctx, MAX_STALENESS_SLOTS), Ok(false));
+            assert_eq!(check_staleness(&ctx, MAX_STALENESS_SLOTS + 1), Err(stale.clone()));
+        }
+
+        // Fresh up to 500 slots, degraded through 1500, rejected beyond
+        let ctx = MockContextBuilder::new()
+            .put(SOFT_STALE_SLOTS_OFFSET, &500u32.to_le_bytes())
+            .put(HARD_STALE_SLOTS_OFFSET, &1_500u32.to_le_bytes())
+            .build();
+        assert_eq!(stale_limits(&ctx), Ok((500, 1_500)));
+        for (age, zone) in [(0, Ok(false)), (500, Ok(false)), (501, Ok(true)), (1_500, Ok(true)), (1_501, Err(stale.clone()))] {
+            assert_eq!(check_staleness(&ctx, age), zone, "age {}", age);
+        }
+
+        // A hard limit below the soft threshold leaves no band
+        let ctx = MockContextBuilder::new().put(HARD_STALE_SLOTS_OFFSET, &20u32.to_le_bytes()).build();
+        assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
     }
 
     #[test]
-    fn test_signal_severity_constants() {
-        assert_eq!(SIGNAL_NONE, 0);
-        assert_eq!(SIGNAL_LOW, 1);
-        assert_eq!(SIGNAL_HIGH, 2);
-        assert_eq!(SIGNAL_CRITICAL, 3);
+    fn test_soft_stale_quote_charges_penalty_spread() {
+        // No penalty configured: a degraded quote is floored at the 500 bps max
+        let ctx = MockContextBuilder::new().build();
+        assert_eq!(degraded_spread_floor(&ctx, 500), Ok(500));
+        let ctx = MockContextBuilder::new().put(PENALTY_SPREAD_OFFSET, &120u32.to_le_bytes()).build();
+        assert_eq!(degraded_spread_floor(&ctx, 500), Ok(120));
+
+        // Fresh: base 20 + edge 30 at 50%; degraded: floored at the 120 bps penalty
+        let fresh = SpreadParams { base_spread: 20, edge_spread: 30, max_spread: 500, ..Default::default() };
+        let degraded = SpreadParams { min_spread: 120, ..fresh };
+        let (fresh_price, fresh_spread, _) = uptime_exec_price(500_000, false, &fresh).unwrap();
+        let (degraded_price, degraded_spread, _) = uptime_exec_price(500_000, false, &degraded).unwrap();
+        assert_eq!((fresh_spread, degraded_spread), (50, 120));
+        assert!(degraded_price > fresh_price);
+        assert_eq!(RETURN_DEGRADED_OFFSET, RETURN_DATA_OFFSET + 24);
     }
 
-    // -----------------------------------------------------------------------
-    // 11. Resolution test — SLASHED snaps probability to 0
-    // -----------------------------------------------------------------------
     #[test]
-    fn test_resolution_slashed() {
-        // After SLASHED resolution, price should be 0
-        let final_prob: u64 = 0;
-        // No pricing possible (process_match would reject p=0)
-        assert_eq!(final_prob, 0);
+    fn test_extension_staleness_limits() {
+        let mut params = vec![0u8; 44];
+        params[32..36].copy_from_slice(&500u32.to_le_bytes());
+        params[36..40].copy_from_slice(&1_500u32.to_le_bytes());
+        params[40..44].copy_from_slice(&120u32.to_le_bytes());
+        let extension = parse_extension(&params, &Pubkey::new_unique(), 0, 500, 0).unwrap();
+        let mut ctx = MockContextBuilder::new().build();
+        write_extension(&mut ctx, &extension);
+        assert_eq!(stale_limits(&ctx), Ok((500, 1_500)));
+        assert_eq!(degraded_spread_floor(&ctx, 500), Ok(120));
+        // Absent, the single MAX_STALENESS_SLOTS cliff and the max spread apply
+        write_extension(&mut ctx, &parse_extension(&params[..32], &Pubkey::new_unique(), 0, 500, 0).unwrap());
+        assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+        assert_eq!(degraded_spread_floor(&ctx, 500), Ok(500));
+
+        let invalid = Err(UptimeMatcherError::InvalidStalenessConfig.into());
+        params[36..40].copy_from_slice(&499u32.to_le_bytes());
+        assert_eq!(parse_extension(&params, &Pubkey::new_unique(), 0, 500, 0), invalid);
+        // Against the default soft threshold too
+        params[32..36].copy_from_slice(&0u32.to_le_bytes());
+        params[36..40].copy_from_slice(&((MAX_STALENESS_SLOTS - 1) as u32).to_le_bytes());
+        assert_eq!(parse_extension(&params, &Pubkey::new_unique(), 0, 500, 0), invalid);
+        params[36..40].copy_from_slice(&(MAX_STALENESS_SLOTS as u32).to_le_bytes());
+        assert!(parse_extension(&params, &Pubkey::new_unique(), 0, 500, 0).is_ok());
+
+        // The penalty spread must sit between the min and max spread
+        let bad_spread = Err(UptimeMatcherError::InvalidPenaltySpread.into());
+        assert_eq!(parse_extension(&params, &Pubkey::new_unique(), 0, 100, 0), bad_spread);
+        assert_eq!(parse_extension(&params, &Pubkey::new_unique(), 130, 500, 0), bad_spread);
     }
 
     // -----------------------------------------------------------------------
-    // 12. Resolution test — SAFE snaps probability to 1_000_000
+    // 71. A liquidity notional caps fills without impact pricing
     // -----------------------------------------------------------------------
     #[test]
-    fn test_resolution_safe() {
-        let final_prob = MAX_PROBABILITY;
-        assert_eq!(final_prob, 1_000_000);
+    fn test_liquidity_caps_fills_with_impact_off() {
+        let liq = 1_000_000u128;
+        let ctx = MockContextBuilder::new().spreads(20, 30, 1_000).liquidity(0, liq).build();
+        assert_eq!(liquidity_capped(&ctx), Ok(true));
+        let impact_at = |out: [u8; QUOTE_AT_MARK_RETURN_SIZE]| u64::from_le_bytes(out[25..33].try_into().unwrap());
+        assert_eq!(impact_at(quote_at_mark(&ctx, 995_000, liq, 0).unwrap()), 0);
+        let short = Err(UptimeMatcherError::InsufficientLiquidity.into());
+        assert_eq!(quote_at_mark(&ctx, 995_000, liq + 1, 0), short);
+
+        // A drawn-down notional keeps capping instead of reading as unconfigured
+        let drained = MockContextBuilder::new()
+            .spreads(20, 30, 1_000)
+            .put(LIQUIDITY_CAPPED_OFFSET, &[1])
+            .build();
+        assert_eq!(liquidity_capped(&drained), Ok(true));
+        assert_eq!(quote_at_mark(&drained, 995_000, 1, 0), short);
+        assert!(quote_at_mark(&drained, 995_000, 0, 0).is_ok());
+
+        // No notional leaves fills unbounded; an impact-priced context from before the
+        // flag still draws its notional down
+        let open = MockContextBuilder::new().spreads(20, 30, 1_000).build();
+        assert_eq!(liquidity_capped(&open), Ok(false));
+        assert!(quote_at_mark(&open, 995_000, u64::MAX as u128, 0).is_ok());
+        let legacy = MockContextBuilder::new()
+            .liquidity(10, liq)
+            .put(LIQUIDITY_CAPPED_OFFSET, &[0])
+            .build();
+        assert_eq!(liquidity_capped(&legacy), Ok(true));
     }
 }
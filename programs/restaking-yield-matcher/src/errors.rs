@@ -1,12 +1,110 @@
 use solana_program::program_error::ProgramError;
 
-#[derive(Debug, Clone, Copy)]
+/// Yield matcher custom error codes occupy 0x30..=0x2FF, starting from the codes the
+/// matcher first shipped with (0x30..=0x34) so deployed clients keep decoding them.
+/// (Uptime matcher: 0x300..=0x3FF; ncn-oracle Anchor errors start at 6000.)
+pub const YIELD_MATCHER_ERROR_BASE: u32 = 0x30;
+pub const YIELD_MATCHER_ERROR_END: u32 = 0x2FF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum YieldMatcherError {
     OracleNotSynced = 0x30,
     OracleStale = 0x31,
     OracleAccountMismatch = 0x32,
     InvalidRegime = 0x33,
     ArithmeticOverflow = 0x34,
+    YieldMarkMismatch = 0x35,
+    InvalidImpactModel = 0x36,
+    InvalidSpreadConfig = 0x37,
+    InvalidLiquidityConfig = 0x38,
+    InsufficientLiquidity = 0x39,
+    OracleSequenceRegressed = 0x3A,
+    MatcherSettled = 0x3B,
+    InvalidBlendWeights = 0x3C,
+    SlippageExceeded = 0x3D,
+    InvalidMode = 0x3E,
+    YieldFeedMissing = 0x3F,
+    PerformanceFeedMissing = 0x40,
+    YieldMarkUnitMismatch = 0x41,
+    OracleInactive = 0x42,
+    InvalidStalenessConfig = 0x43,
+    OracleSequenceAhead = 0x44,
+    FeeAuthorityMismatch = 0x45,
+    InvalidProtocolFeeConfig = 0x46,
+    InvalidSpreadScaling = 0x47,
+    InvalidPenaltySpread = 0x48,
+}
+
+impl YieldMatcherError {
+    /// Every variant, in code order
+    pub const ALL: [YieldMatcherError; 25] = [
+        Self::OracleNotSynced,
+        Self::OracleStale,
+        Self::OracleAccountMismatch,
+        Self::InvalidRegime,
+        Self::ArithmeticOverflow,
+        Self::YieldMarkMismatch,
+        Self::InvalidImpactModel,
+        Self::InvalidSpreadConfig,
+        Self::InvalidLiquidityConfig,
+        Self::InsufficientLiquidity,
+        Self::OracleSequenceRegressed,
+        Self::MatcherSettled,
+        Self::InvalidBlendWeights,
+        Self::SlippageExceeded,
+        Self::InvalidMode,
+        Self::YieldFeedMissing,
+        Self::PerformanceFeedMissing,
+        Self::YieldMarkUnitMismatch,
+        Self::OracleInactive,
+        Self::InvalidStalenessConfig,
+        Self::OracleSequenceAhead,
+        Self::FeeAuthorityMismatch,
+        Self::InvalidProtocolFeeConfig,
+        Self::InvalidSpreadScaling,
+        Self::InvalidPenaltySpread,
+    ];
+
+    /// Custom error code as surfaced in `ProgramError::Custom`
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Map a custom error code back to its variant
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.iter().copied().find(|e| e.code() == code)
+    }
+
+    /// Human-readable description for clients
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::OracleNotSynced => "Yield matcher never synced -- oracle sync required",
+            Self::OracleStale => "Oracle data is stale",
+            Self::OracleAccountMismatch => "Oracle account does not match stored feed",
+            Self::InvalidRegime => "Invalid yield regime (must be 0-4)",
+            Self::ArithmeticOverflow => "Arithmetic overflow",
+            Self::YieldMarkMismatch => "Yield mark inconsistent with synced yield",
+            Self::InvalidImpactModel => "Invalid impact model (must be 0=Linear or 1=Sqrt)",
+            Self::InvalidSpreadConfig => "Invalid spread config (min spread exceeds max spread)",
+            Self::InvalidLiquidityConfig => "Invalid liquidity config (impact enabled with zero liquidity notional)",
+            Self::InsufficientLiquidity => "Fill exceeds remaining liquidity (partial fill not allowed)",
+            Self::OracleSequenceRegressed => "Oracle sequence not greater than the last applied sync (replayed or out of order)",
+            Self::MatcherSettled => "Matcher has been settled and no longer quotes",
+            Self::InvalidBlendWeights => "Invalid mark blend weights (must all be zero or sum to 10000 bps)",
+            Self::SlippageExceeded => "Execution price is above the taker's max acceptable price",
+            Self::InvalidMode => "Invalid mode (must be 0=AllNCN or 1=SingleNCN)",
+            Self::YieldFeedMissing => "NcnYieldFeed account not provided (see the log for the missing index)",
+            Self::PerformanceFeedMissing => "NcnPerformanceFeed account not provided (see the log for the missing index)",
+            Self::YieldMarkUnitMismatch => "Yield mark off from yield bps * 1e6 by a unit factor (mark scaled as bps, fraction or percent)",
+            Self::OracleInactive => "Referenced NcnYieldFeed is deactivated -- its data is no longer maintained",
+            Self::InvalidStalenessConfig => "Invalid staleness config (hard-stale limit below the soft-stale threshold)",
+            Self::OracleSequenceAhead => "Oracle sequence is ahead of the NcnYieldFeed's last_updated",
+            Self::FeeAuthorityMismatch => "Signer is not the context's protocol fee authority",
+            Self::InvalidProtocolFeeConfig => "Invalid protocol fee config (fee above 10000 bps, or a fee without a fee authority)",
+            Self::InvalidSpreadScaling => "Invalid spread scaling (must be 0=regime buckets or 1=continuous yield variance)",
+            Self::InvalidPenaltySpread => "Invalid penalty spread (must sit between the min and max spread)",
+        }
+    }
 }
 
 impl From<YieldMatcherError> for ProgramError {
@@ -14,3 +112,33 @@ impl From<YieldMatcherError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_round_trip() {
+        for e in YieldMatcherError::ALL {
+            assert_eq!(YieldMatcherError::from_code(e.code()), Some(e));
+            assert!(!e.description().is_empty());
+        }
+        assert_eq!(YieldMatcherError::from_code(0x2FF), None);
+    }
+
+    #[test]
+    fn test_original_error_codes_unchanged() {
+        assert_eq!(YieldMatcherError::OracleNotSynced.code(), 0x30);
+        assert_eq!(YieldMatcherError::OracleStale.code(), 0x31);
+        assert_eq!(YieldMatcherError::OracleAccountMismatch.code(), 0x32);
+        assert_eq!(YieldMatcherError::InvalidRegime.code(), 0x33);
+        assert_eq!(YieldMatcherError::ArithmeticOverflow.code(), 0x34);
+    }
+
+    #[test]
+    fn test_error_codes_within_range() {
+        for e in YieldMatcherError::ALL {
+            assert!((YIELD_MATCHER_ERROR_BASE..=YIELD_MATCHER_ERROR_END).contains(&e.code()));
+        }
+    }
+}
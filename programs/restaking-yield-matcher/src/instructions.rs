@@ -4,12 +4,12 @@ use shank::ShankInstruction;
 pub enum YieldMatcherInstruction {
     /// Execute match — compute yield-regime-adjusted execution price
     #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
-    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2)")]
     Match,
 
     /// Initialize restaking yield matcher context
     #[account(0, name = "lp_pda", desc = "LP PDA to store")]
-    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, writable)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2, writable)")]
     Init,
 
     /// Sync oracle — keeper updates yield data from NCN oracle
@@ -17,4 +17,77 @@ pub enum YieldMatcherInstruction {
     #[account(1, name = "ncn_yield_feed", desc = "NcnYieldFeed account")]
     #[account(2, name = "ncn_performance_feed", desc = "NcnPerformanceFeed account")]
     OracleSync,
+
+    /// Keeper liveness — refresh last-update slot without changing yield data
+    #[account(0, writable, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, name = "ncn_yield_feed", desc = "NcnYieldFeed account")]
+    #[account(2, name = "ncn_performance_feed", desc = "NcnPerformanceFeed account")]
+    Heartbeat,
+
+    /// Compare stored mark with the live NcnYieldFeed APY (writes deviation bps + alert flag to return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, name = "ncn_yield_feed", desc = "NcnYieldFeed account")]
+    CheckMarkDeviation,
+
+    /// Rotate the stored oracle feeds to migrated accounts (owner + discriminator checked)
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account")]
+    #[account(2, name = "new_ncn_yield_feed", desc = "New NcnYieldFeed account")]
+    #[account(3, name = "new_ncn_performance_feed", desc = "New NcnPerformanceFeed account")]
+    SetOracleAccounts,
+
+    /// Settle — terminate the market; process_match rejects afterwards
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account")]
+    Settle,
+
+    /// Read the yield regime, its name index and spread multiplier (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetRegime,
+
+    /// Read all pricing parameters as one packed struct (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetPricingParams,
+
+    /// Initialize context and apply the first oracle sync in one instruction
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA (must be signer)")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (320 bytes, 448 at version 2, writable)")]
+    #[account(2, name = "ncn_yield_feed", desc = "NcnYieldFeed account (must equal the init yield feed)")]
+    #[account(3, name = "ncn_performance_feed", desc = "NcnPerformanceFeed account (must equal the init performance feed)")]
+    InitAndSync,
+
+    /// Read slots remaining before the oracle data goes stale (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetStaleness,
+
+    /// Compare the stored oracle feed pubkeys against expected ones (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    CheckOracleConfig,
+
+    /// Price a hypothetical yield mark through the full pricing pipeline (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    QuoteAtMark,
+
+    /// Copy pricing params and yield mark into a new immutable PDA seeded by (matcher, slot)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    #[account(1, writable, name = "snapshot", desc = "Snapshot PDA [\"snapshot\", matcher_context, slot]")]
+    #[account(2, writable, signer, name = "payer", desc = "Pays rent for the snapshot")]
+    #[account(3, name = "system_program", desc = "System program")]
+    SnapshotMatcher,
+
+    /// Read the unclaimed protocol fees and fee rate (writes return data)
+    #[account(0, name = "matcher_context", desc = "Matcher context account")]
+    GetAccruedFees,
+
+    /// Zero the accrued protocol fees, returning the claimed amount (protocol fee authority must sign)
+    #[account(0, signer, name = "protocol_fee_authority", desc = "Protocol fee authority stored at init")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account")]
+    ClaimProtocolFees,
+
+    /// Grow a version-1 context to version 2 and write the extension fields (LP PDA must sign)
+    #[account(0, signer, name = "lp_pda", desc = "LP PDA")]
+    #[account(1, writable, name = "matcher_context", desc = "Matcher context account (version 1)")]
+    #[account(2, writable, signer, name = "payer", desc = "Pays rent for the larger account")]
+    #[account(3, name = "system_program", desc = "System program")]
+    Migrate,
 }
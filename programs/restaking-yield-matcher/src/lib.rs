@@ -3,12 +3,17 @@ use solana_program::{
     program_error::ProgramError, pubkey::Pubkey,
 };
 
-mod errors;
+pub mod errors;
 mod instructions;
+mod pricing;
 mod state;
 mod yield_pricing;
 
-use yield_pricing::{process_init, process_match, process_oracle_sync};
+use yield_pricing::{process_init, process_match, process_oracle_sync, process_heartbeat, process_check_mark_deviation,
+    process_set_oracle_accounts, process_settle, process_get_regime,
+    process_get_pricing_params, process_init_and_sync, process_get_staleness, process_check_oracle_config,
+    process_quote_at_mark, process_snapshot_matcher, process_get_accrued_fees, process_claim_protocol_fees,
+    process_migrate};
 
 entrypoint!(process_instruction);
 
@@ -34,6 +39,62 @@ pub fn process_instruction(
             msg!("YIELD-MATCHER: Oracle sync instruction");
             process_oracle_sync(program_id, accounts, instruction_data)
         }
+        0x04 => {
+            msg!("YIELD-MATCHER: Heartbeat instruction");
+            process_heartbeat(program_id, accounts, instruction_data)
+        }
+        0x05 => {
+            msg!("YIELD-MATCHER: Check mark deviation instruction");
+            process_check_mark_deviation(program_id, accounts, instruction_data)
+        }
+        0x06 => {
+            msg!("YIELD-MATCHER: Set oracle accounts instruction");
+            process_set_oracle_accounts(program_id, accounts, instruction_data)
+        }
+        0x07 => {
+            msg!("YIELD-MATCHER: Settle instruction");
+            process_settle(program_id, accounts, instruction_data)
+        }
+        0x08 => {
+            msg!("YIELD-MATCHER: Get regime instruction");
+            process_get_regime(program_id, accounts, instruction_data)
+        }
+        0x09 => {
+            msg!("YIELD-MATCHER: Get pricing params instruction");
+            process_get_pricing_params(program_id, accounts, instruction_data)
+        }
+        0x0A => {
+            msg!("YIELD-MATCHER: Init and sync instruction");
+            process_init_and_sync(program_id, accounts, instruction_data)
+        }
+        0x0B => {
+            msg!("YIELD-MATCHER: Get staleness instruction");
+            process_get_staleness(program_id, accounts, instruction_data)
+        }
+        0x0C => {
+            msg!("YIELD-MATCHER: Check oracle config instruction");
+            process_check_oracle_config(program_id, accounts, instruction_data)
+        }
+        0x0D => {
+            msg!("YIELD-MATCHER: Quote at mark instruction");
+            process_quote_at_mark(program_id, accounts, instruction_data)
+        }
+        0x0E => {
+            msg!("YIELD-MATCHER: Snapshot matcher instruction");
+            process_snapshot_matcher(program_id, accounts, instruction_data)
+        }
+        0x0F => {
+            msg!("YIELD-MATCHER: Get accrued fees instruction");
+            process_get_accrued_fees(program_id, accounts, instruction_data)
+        }
+        0x10 => {
+            msg!("YIELD-MATCHER: Claim protocol fees instruction");
+            process_claim_protocol_fees(program_id, accounts, instruction_data)
+        }
+        0x11 => {
+            msg!("YIELD-MATCHER: Migrate instruction");
+            process_migrate(program_id, accounts, instruction_data)
+        }
         _ => {
             msg!("YIELD-MATCHER: Unknown instruction tag {}", instruction_data[0]);
             Err(ProgramError::InvalidInstructionData)
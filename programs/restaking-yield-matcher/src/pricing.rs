@@ -0,0 +1,137 @@
+//! Pricing core for yield matches: the single source of truth used by
+//! process_match and the unit tests.
+
+use solana_program::program_error::ProgramError;
+
+use crate::errors::YieldMatcherError;
+use crate::state::{YieldRegime, BPS_SCALE, VARIANCE_SPREAD_REF_BPS};
+use crate::yield_pricing::{bps_to_e6, clamp_spread};
+
+/// Effective mark: w_spot * spot + w_7d * avg7d + w_30d * avg30d, with weights in bps
+/// summing to 10000. All-zero weights (the default) price purely off the spot mark.
+/// The spot mark may be negative (net yield underwater); the averages are not.
+pub(crate) fn blended_yield_mark(
+    spot_mark: i64,
+    yield_7d_bps: u64,
+    yield_30d_bps: u64,
+    weights: [u32; 3],
+) -> Result<i64, ProgramError> {
+    if weights == [0; 3] {
+        return Ok(spot_mark);
+    }
+    let marks = [
+        spot_mark as i128,
+        bps_to_e6(yield_7d_bps).ok_or(YieldMatcherError::ArithmeticOverflow)? as i128,
+        bps_to_e6(yield_30d_bps).ok_or(YieldMatcherError::ArithmeticOverflow)? as i128,
+    ];
+    // |i64| * u32 summed three times cannot overflow i128
+    let weighted: i128 = marks.iter().zip(weights).map(|(&m, w)| m * w as i128).sum();
+    i64::try_from(weighted / BPS_SCALE as i128).map_err(|_| YieldMatcherError::ArithmeticOverflow.into())
+}
+
+/// Regime spread multiplier (percent) at `now`, smoothing the last regime flip: moves
+/// linearly from the previous regime's multiplier at `transition_ts` to the current
+/// one `window_secs` later. No window, no recorded flip, or an elapsed window price at
+/// the current regime. Rounds toward the wider spread in both directions.
+pub(crate) fn transition_spread_multiplier(
+    prev: YieldRegime,
+    current: YieldRegime,
+    transition_ts: i64,
+    window_secs: u32,
+    now: i64,
+) -> u64 {
+    let (from, to) = (prev.spread_multiplier(), current.spread_multiplier());
+    let elapsed = now.saturating_sub(transition_ts).max(0) as u64;
+    if window_secs == 0 || transition_ts == 0 || elapsed >= window_secs as u64 {
+        return to;
+    }
+    // Multipliers differ by at most 200 and elapsed < window <= u32::MAX: no overflow
+    let window = window_secs as u64;
+    if to >= from {
+        from + ((to - from) * elapsed).div_ceil(window)
+    } else {
+        from - (from - to) * elapsed / window
+    }
+}
+
+/// Continuous-scaling spread multiplier (percent): linear in the synced yield variance,
+/// 1.0x at VARIANCE_SPREAD_REF_BPS, clamped to the VeryLow..Extreme bucket range so it
+/// never prices outside what the buckets could.
+pub(crate) fn variance_spread_multiplier(variance_bps: u64) -> u64 {
+    (variance_bps.saturating_mul(100) / VARIANCE_SPREAD_REF_BPS)
+        .clamp(YieldRegime::VeryLow.spread_multiplier(), YieldRegime::Extreme.spread_multiplier())
+}
+
+/// Execution price for a yield mark. Returns (exec_price, total_spread).
+///
+/// The volatility spread is scaled by the regime multiplier (percent, see
+/// YieldRegime::spread_multiplier), then total = base + adjusted vol + impact, clamped
+/// to [min, max]. A negative mark quotes a negative price (see
+/// compute_signed_bid_ask_lp_favorable).
+pub(crate) fn yield_exec_price(
+    yield_mark: i64,
+    regime_multiplier: u64,
+    base_spread: u32,
+    yield_vol_spread: u32,
+    min_spread: u32,
+    max_spread: u32,
+    impact: u64,
+) -> Result<(i64, u64), ProgramError> {
+    let adjusted_yield_vol = (yield_vol_spread as u64)
+        .checked_mul(regime_multiplier)
+        .ok_or(YieldMatcherError::ArithmeticOverflow)?
+        / 100;
+
+    let total_spread = clamp_spread(
+        (base_spread as u64).saturating_add(adjusted_yield_vol).saturating_add(impact),
+        min_spread,
+        max_spread,
+    );
+
+    let (_, exec_price) = compute_signed_bid_ask_lp_favorable(yield_mark, total_spread)?;
+    Ok((exec_price, total_spread))
+}
+
+/// LP-favorable two-sided quote around a signed mark. A negative mark is the mirror
+/// image of its magnitude: ask(-m) = -bid(m) and bid(-m) = -ask(m), so the ask is
+/// still the higher side and both roundings still favor the LP. Returns (bid, ask).
+pub(crate) fn compute_signed_bid_ask_lp_favorable(mark: i64, spread_bps: u64) -> Result<(i64, i64), ProgramError> {
+    let (bid, ask) = compute_bid_ask_lp_favorable(mark.unsigned_abs(), spread_bps)?;
+    let signed = |v: u64, negate: bool| -> Result<i64, ProgramError> {
+        let v = i128::from(v);
+        i64::try_from(if negate { -v } else { v }).map_err(|_| YieldMatcherError::ArithmeticOverflow.into())
+    };
+    if mark >= 0 {
+        Ok((signed(bid, false)?, signed(ask, false)?))
+    } else {
+        Ok((signed(ask, true)?, signed(bid, true)?))
+    }
+}
+
+/// Two-sided quote around a mark with rounding that always favors the LP: the ask
+/// (mark * (1 + spread)) is rounded up and the bid (mark * (1 - spread)) rounded down,
+/// so bid <= fair <= ask. Returns (bid, ask). Unsigned core of
+/// compute_signed_bid_ask_lp_favorable.
+pub(crate) fn compute_bid_ask_lp_favorable(mark: u64, spread_bps: u64) -> Result<(u64, u64), ProgramError> {
+    let scale = BPS_SCALE as u128;
+    let ask_num = (mark as u128)
+        .checked_mul(scale + spread_bps as u128)
+        .ok_or(YieldMatcherError::ArithmeticOverflow)?;
+    let ask = u64::try_from(ask_num.div_ceil(scale)).map_err(|_| YieldMatcherError::ArithmeticOverflow)?;
+    // A spread of 100% or more bids zero
+    let bid = (mark as u128 * scale.saturating_sub(spread_bps as u128) / scale) as u64;
+    Ok((bid, ask))
+}
+
+/// Protocol share of a fill's spread revenue, in e6 notional: the spread earns
+/// fill * total_spread / 10_000, of which protocol_fee_bps / 10_000 accrues to the
+/// protocol (rounded down, in the LP's favor). The rest of the spread stays with the LP.
+pub(crate) fn protocol_fee_e6(fill_size: u128, total_spread_bps: u64, protocol_fee_bps: u16) -> Result<u64, ProgramError> {
+    let scale = BPS_SCALE as u128;
+    let fee = fill_size
+        .checked_mul(total_spread_bps as u128)
+        .and_then(|v| v.checked_mul(protocol_fee_bps as u128))
+        .ok_or(YieldMatcherError::ArithmeticOverflow)?
+        / (scale * scale);
+    u64::try_from(fee).map_err(|_| YieldMatcherError::ArithmeticOverflow.into())
+}
@@ -1,27 +1,140 @@
+use solana_program::pubkey::Pubkey;
+
 // Re-export shared constants and functions from matcher-common
-pub use matcher_common::{CTX_SIZE, RETURN_DATA_OFFSET, RETURN_DATA_SIZE, MAGIC_OFFSET, LP_PDA_OFFSET, verify_magic as verify_magic_generic, read_lp_pda};
+pub use matcher_common::{CTX_SIZE, RETURN_DATA_OFFSET, RETURN_DATA_SIZE, MAGIC_OFFSET, LP_PDA_OFFSET, read_lp_pda};
+// Re-export the versioned header (VERSION_OFFSET u32, MODE_OFFSET u8: 0=AllNCN, 1=SingleNCN)
+// and fixed-point scales shared with the uptime matcher from matcher-utils
+pub use matcher_utils::{BPS_SCALE, CTX_V2_SIZE, CTX_VERSION_1, CTX_VERSION_2, E6_SCALE, MODE_OFFSET, VERSION_OFFSET};
 
 /// Magic bytes: "RSTKMATC" as u64 LE
 pub const YIELD_MATCHER_MAGIC: u64 = 0x5253_544B_4d41_5443;
 
 // Restaking-yield-matcher-specific field offsets
-pub const VERSION_OFFSET: usize = 72;                   // u32
-pub const MODE_OFFSET: usize = 76;                      // u8: 0=AllNCN, 1=SingleNCN
+pub const PRICE_DECIMALS_OFFSET: usize = 77;            // u8: mark = display value * 10^decimals (see YIELD_PRICE_DECIMALS)
+pub const QUOTE_KIND_OFFSET: usize = 78;                // u8: what the mark quotes (see QUOTE_KIND_YIELD)
 pub const BASE_SPREAD_OFFSET: usize = 112;              // u32
 pub const YIELD_VOL_SPREAD_OFFSET: usize = 116;         // u32: extra spread for yield volatility
 pub const MAX_SPREAD_OFFSET: usize = 120;               // u32
 pub const IMPACT_K_OFFSET: usize = 124;                 // u32
-pub const CURRENT_YIELD_OFFSET: usize = 128;            // u64: current restaking yield in bps
-pub const YIELD_MARK_PRICE_OFFSET: usize = 136;         // u64: mark price = yield * 1e6
+pub const CURRENT_YIELD_OFFSET: usize = 128;            // i64: current net restaking yield in bps (may be negative)
+pub const YIELD_MARK_PRICE_OFFSET: usize = 136;         // i64: mark price = yield * 1e6
 pub const LAST_UPDATE_SLOT_OFFSET: usize = 144;         // u64
 pub const YIELD_REGIME_OFFSET: usize = 152;             // u8: 0=VeryLow..4=Extreme
+pub const IMPACT_MODEL_OFFSET: usize = 153;             // u8: 0=Linear, 1=Sqrt
+pub const PREV_REGIME_OFFSET: usize = 154;              // u8: regime in force before the last flip
+pub const HAS_SYNCED_OFFSET: usize = 155;               // u8: 1 once an oracle sync has been applied
+pub const REGIME_TRANSITION_SECS_OFFSET: usize = 156;   // u32: regime smoothing window (0 = flips apply at once)
 pub const YIELD_7D_AVG_OFFSET: usize = 160;             // u64
 pub const YIELD_30D_AVG_OFFSET: usize = 168;            // u64
 pub const LIQUIDITY_OFFSET: usize = 176;                // u128 (16 bytes)
 pub const MAX_FILL_OFFSET: usize = 192;                 // u128 (16 bytes)
 pub const NCN_YIELD_FEED_OFFSET: usize = 208;           // Pubkey (32): NcnYieldFeed account
 pub const NCN_PERFORMANCE_FEED_OFFSET: usize = 240;     // Pubkey (32): NcnPerformanceFeed account
-// 272..320 = reserved
+pub const MIN_SPREAD_OFFSET: usize = 272;               // u32: spread floor (bps)
+pub const ORACLE_SEQUENCE_OFFSET: usize = 276;          // u64: sequence of the last applied sync
+pub const SETTLED_OFFSET: usize = 284;                  // u8: 1 = settled (end of life, no more quotes)
+pub const SPREAD_SCALING_OFFSET: usize = 285;           // u8: 0=regime buckets, 1=continuous yield variance
+pub const YIELD_VARIANCE_OFFSET: usize = 286;           // u16: yield_variance_bps of the last sync (saturating, 0 = not reported)
+pub const BLEND_WEIGHTS_OFFSET: usize = 288;            // 3 x u32 bps: spot, 7d, 30d (all zero = 100% spot)
+pub const REGIME_TRANSITION_TS_OFFSET: usize = 300;     // i64: unix time of the last smoothed regime flip (0 = none)
+pub const ACCRUED_PROTOCOL_FEES_OFFSET: usize = 308;    // u64: protocol fees accrued by matches, unclaimed (e6 notional)
+pub const PROTOCOL_FEE_BPS_OFFSET: usize = 316;         // u16: protocol share of the spread (bps of the spread, 0 = none)
+pub const LIQUIDITY_CAPPED_OFFSET: usize = 318;         // u8: 1 = fills draw down the liquidity notional (set at init when one is configured)
+// 319..320 = unused (the staleness limits live in the version-2 extension)
+
+// Context layout versions. Version 1 is the shared CTX_SIZE layout; version 2 appends
+// the extension below and needs a CTX_V2_SIZE account. Init writes version 2 when the
+// account is large enough, and tag 0x11 migrates a version-1 context in place. A
+// version-1 context keeps working and reads every extension field as zero.
+
+// Version-2 extension
+pub const PROTOCOL_FEE_AUTHORITY_OFFSET: usize = 320;   // Pubkey (32): signer of protocol fee claims
+pub const SOFT_STALE_SLOTS_OFFSET: usize = 352;         // u32: oracle age past which quotes are degraded (0 = MAX_STALENESS_SLOTS)
+pub const HARD_STALE_SLOTS_OFFSET: usize = 356;         // u32: oracle age past which matches are rejected (0 = no soft-stale band)
+pub const PENALTY_SPREAD_OFFSET: usize = 360;           // u32: spread floor of a degraded quote (bps, 0 = max spread)
+
+/// Reserved tail of a version-2 context: zeroed at init and must stay zero until a
+/// field is allocated there
+pub const RESERVED_OFFSET: usize = 364;
+const _: () = assert!(PENALTY_SPREAD_OFFSET + 4 <= RESERVED_OFFSET, "allocated fields overlap the reserved tail");
+
+/// Debug check that the reserved tail is still zero, so a handler writing past the
+/// allocated layout is caught in tests (and in handlers with `check-reserved`)
+#[cfg(any(test, feature = "check-reserved"))]
+pub fn assert_reserved_zero(ctx_data: &[u8]) {
+    debug_assert!(
+        ctx_data.len() < CTX_V2_SIZE || ctx_data[RESERVED_OFFSET..CTX_V2_SIZE].iter().all(|&b| b == 0),
+        "reserved context bytes {}..{} must stay zero",
+        RESERVED_OFFSET,
+        CTX_V2_SIZE
+    );
+}
+
+/// ncn-oracle program that owns NcnYieldFeed accounts
+pub const NCN_ORACLE_PROGRAM_ID: Pubkey = solana_program::pubkey!("NCNRsk1111111111111111111111111111111111111");
+
+/// Anchor discriminator of NcnYieldFeed: sha256("account:NcnYieldFeed")[..8]
+pub const NCN_YIELD_FEED_DISCRIMINATOR: [u8; 8] = [90, 189, 127, 237, 89, 70, 144, 106];
+
+/// Anchor discriminator of NcnPerformanceFeed: sha256("account:NcnPerformanceFeed")[..8]
+pub const NCN_PERFORMANCE_FEED_DISCRIMINATOR: [u8; 8] = [6, 52, 172, 160, 196, 213, 224, 156];
+
+/// NcnYieldFeed (Borsh) layout: [0..8] discriminator, [8..40] authority,
+/// [40..72] ncn_address, [72..80] current_apy_bps (u64)
+pub const YIELD_FEED_CURRENT_APY_OFFSET: usize = 72;
+
+/// After current_apy_bps: 7d avg, 30d avg, variance (u64 each), regime (u8), then the
+/// history Vec (u32 length + 24-byte samples), base staking, MEV and premium APY (u64
+/// each), then is_active (bool) and last_updated (i64 unix seconds)
+pub const YIELD_FEED_HISTORY_LEN_OFFSET: usize = 105;
+pub const YIELD_FEED_SAMPLE_SIZE: usize = 24;
+/// Largest history the oracle allows after resize_yield_history (30 days hourly)
+pub const YIELD_FEED_MAX_HISTORY: usize = 720;
+pub const YIELD_FEED_IS_ACTIVE_AFTER_HISTORY: usize = 24;
+pub const YIELD_FEED_LAST_UPDATED_AFTER_HISTORY: usize = 25;
+
+/// Oracle sync payload (tag 0x03 bytes [1..42]): current_yield (i64), yield_mark (i64),
+/// regime (u8), yield_7d (u64), yield_30d (u64), oracle_sequence (u64). Tag 0x03 may
+/// append yield_variance_bps (u64) after it.
+pub const ORACLE_SYNC_PAYLOAD_SIZE: usize = 41;
+
+/// Match return data: filled size (u128 LE) follows the 8-byte exec price
+pub const RETURN_FILLED_SIZE_OFFSET: usize = RETURN_DATA_OFFSET + 8;
+/// Match return data: u8 1 = priced off soft-stale oracle data at the penalty spread
+pub const RETURN_DEGRADED_OFFSET: usize = RETURN_FILLED_SIZE_OFFSET + 16;
+
+/// Matcher modes (header mode byte)
+pub const MODE_ALL_NCN: u8 = 0;
+pub const MODE_SINGLE_NCN: u8 = 1;
+
+/// Fill-size impact models
+pub const IMPACT_MODEL_LINEAR: u8 = 0;
+pub const IMPACT_MODEL_SQRT: u8 = 1;
+
+/// How yield_vol_spread is scaled: by the synced regime's bucket multiplier (default),
+/// or continuously by the synced yield variance
+pub const SPREAD_SCALING_REGIME: u8 = 0;
+pub const SPREAD_SCALING_VARIANCE: u8 = 1;
+
+/// Continuous scaling prices this yield variance (bps) at 1.0x: the midpoint of the
+/// oracle's default Normal band (150, 400]
+pub const VARIANCE_SPREAD_REF_BPS: u64 = 275;
+
+/// Yield mark scale: mark price = yield in bps * 1e6
+pub const YIELD_MARK_SCALE: u64 = E6_SCALE;
+
+/// Factors by which a keeper's mark misses bps * 1e6 when it encodes the yield in the
+/// wrong unit: percent * 1e6 (100x small), fraction * 1e6 (10,000x) or raw bps (1e6x).
+/// A mark off by one of these, either way, is reported as a unit mismatch.
+pub const YIELD_MARK_UNIT_RATIOS: [i64; 3] = [100, 10_000, 1_000_000];
+
+/// Display metadata written at init so SDKs can format any matcher's mark without
+/// per-program knowledge: value = mark / 10^price_decimals, read as the quote kind.
+/// Quote kinds are shared with ncn-uptime-matcher (its QUOTE_KIND_PROBABILITY = 0 =
+/// probability, 1 = annual yield rate); a yield mark is bps * 1e6, i.e. the rate as a
+/// fraction * 1e10.
+pub const QUOTE_KIND_YIELD: u8 = 1;
+pub const YIELD_PRICE_DECIMALS: u8 = 10;
 
 /// Yield regime enum — reuses vol-matcher's VolatilityRegime concept
 /// applied to restaking yield variance
@@ -57,9 +170,55 @@ impl YieldRegime {
             Self::Extreme => 250,  // 2.5x — slashing events, NCN instability
         }
     }
-}
 
-/// Local convenience wrapper that checks magic against YIELD_MATCHER_MAGIC
-pub fn verify_magic(ctx_data: &[u8]) -> bool {
-    verify_magic_generic(ctx_data, YIELD_MATCHER_MAGIC)
+    /// Index into REGIME_NAMES (equal to the enum discriminant)
+    pub fn name_index(&self) -> u8 {
+        *self as u8
+    }
 }
+
+/// Regime names by name index: 0=VeryLow, 1=Low, 2=Normal, 3=High, 4=Extreme
+pub const REGIME_NAMES: [&str; 5] = ["VeryLow", "Low", "Normal", "High", "Extreme"];
+
+/// GetRegime return data: [0] raw stored regime byte, [1] effective regime name index
+/// (out-of-range raw values price as Normal), [2..10] spread_multiplier (u64 LE, percent)
+pub const REGIME_INFO_RETURN_SIZE: usize = 10;
+
+/// GetPricingParams return data (all integers LE):
+///   [0] layout version, [1] mode, [2..6] base_spread, [6..10] yield_vol_spread,
+///   [10..14] max_spread, [14..18] min_spread, [18..22] impact_k (u32 bps each),
+///   [22] impact_model, [23] regime, [24..32] yield_mark_e6 (i64), [32..40] current_yield_bps (i64),
+///   [40..48] last_update_slot (u64), [48] is_settled, [49] price_decimals, [50] quote_kind
+pub const PRICING_PARAMS_LAYOUT_VERSION: u8 = 2;
+pub const PRICING_PARAMS_RETURN_SIZE: usize = 51;
+
+/// Oracle data older than this many slots is stale: by default matches are rejected,
+/// or degraded if the context configures a soft-stale band (see HARD_STALE_SLOTS_OFFSET)
+pub const MAX_STALENESS_SLOTS: u64 = 100;
+
+/// GetStaleness return data: [0..8] slots_until_stale (soft threshold), [8..16]
+/// last_update_slot, [16..24] soft-stale threshold, [24..32] hard-stale limit (u64 LE each)
+pub const STALENESS_RETURN_SIZE: usize = 32;
+
+/// CheckOracleConfig return data: [0] yield feed matches, [1] performance feed matches
+/// (u8 0/1 each), [2..34] stored NcnYieldFeed pubkey, [34..66] stored NcnPerformanceFeed pubkey
+pub const ORACLE_CONFIG_RETURN_SIZE: usize = 66;
+
+/// QuoteAtMark return data (all integers LE): [0] layout version, [1..9] exec_price_e6
+/// (i64), [9..17] total_spread_bps (u64), [17..25] priced (blended) mark_e6 (i64),
+/// [25..33] impact_bps (u64)
+pub const QUOTE_AT_MARK_LAYOUT_VERSION: u8 = 1;
+pub const QUOTE_AT_MARK_RETURN_SIZE: usize = 33;
+
+/// Snapshot account (written once at creation, never again; all integers LE): [0..8]
+/// YIELD_SNAPSHOT_MAGIC, [8..40] matcher context pubkey, [40..48] slot (u64),
+/// [48..56] unix_timestamp (i64), [56..107] pricing params (PRICING_PARAMS_RETURN_SIZE layout)
+pub const YIELD_SNAPSHOT_MAGIC: u64 = 0x5253_544b_534e_4150;
+pub const SNAPSHOT_SEED: &[u8] = b"snapshot";
+pub const SNAPSHOT_SIZE: usize = 56 + PRICING_PARAMS_RETURN_SIZE;
+
+/// Protocol fee is a share of the spread, so at most all of it
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 10_000;
+
+/// GetAccruedFees return data: [0..8] accrued_fees_e6 (u64 LE), [8..10] protocol_fee_bps (u16 LE)
+pub const ACCRUED_FEES_RETURN_SIZE: usize = 10;
@@ -1,17 +1,26 @@
 use solana_program::{
     account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, msg,
-    program_error::ProgramError, pubkey::Pubkey, sysvar::Sysvar,
+    program::{invoke, invoke_signed, set_return_data}, program_error::ProgramError, pubkey::Pubkey,
+    rent::Rent, sysvar::Sysvar,
 };
+use solana_system_interface::{instruction as system_instruction, program as system_program};
 
-use matcher_common::{verify_lp_pda as verify_lp_pda_common, verify_init_preconditions, write_header, write_exec_price, compute_exec_price};
+use matcher_common::{verify_lp_pda as verify_lp_pda_common, verify_init_preconditions, write_header, write_exec_price};
+use matcher_utils::{
+    has_extension, isqrt, log_match, read_ctx_u32, read_u128, verify_context_owner, write_u128, HeaderView,
+};
 
 use crate::errors::YieldMatcherError;
+use crate::pricing::{
+    blended_yield_mark, protocol_fee_e6, transition_spread_multiplier, variance_spread_multiplier, yield_exec_price,
+};
 use crate::state::*;
 
 /// Tag 0x02: Initialize restaking yield matcher context
 /// Accounts:
 ///   [0] LP PDA (signer)
-///   [1] Matcher context account (writable, 320 bytes)
+///   [1] Matcher context account (writable, CTX_V2_SIZE = 448 bytes for version 2, or
+///       CTX_SIZE = 320 bytes for a version-1 context without the extension)
 /// Data layout:
 ///   [0]    tag (0x02)
 ///   [1]    mode (u8: 0=AllNCN, 1=SingleNCN)
@@ -19,12 +28,30 @@ use crate::state::*;
 ///   [6..10] yield_vol_spread_bps (u32 LE)
 ///   [10..14] max_spread_bps (u32 LE)
 ///   [14..18] impact_k_bps (u32 LE)
-///   [18..34] liquidity_notional_e6 (u128 LE)
+///   [18..34] liquidity_notional_e6 (u128 LE, 0 = fills uncapped; otherwise drawn down by fills)
 ///   [34..50] max_fill_abs (u128 LE)
 ///   [50..82] ncn_yield_feed pubkey (32 bytes)
 ///   [82..114] ncn_performance_feed pubkey (32 bytes)
+///   [114]  impact_model (u8, optional: 0=Linear (default), 1=Sqrt)
+///   [115..119] min_spread_bps (u32 LE, optional, default 0)
+///   [119..131] mark blend weights spot/7d/30d (3 x u32 LE bps, optional: all zero or
+///              summing to 10000; default 100% spot)
+///   [131..135] regime_transition_secs (u32 LE, optional, default 0 = no smoothing)
+///   [135..137] protocol_fee_bps (u16 LE, optional, default 0: share of each match's spread
+///              revenue accrued as protocol fees, at most 10_000)
+///   [137]  spread_scaling (u8, optional: 0=regime buckets (default), 1=continuous yield variance)
+///   [138..]    version-2 extension fields (all optional; a version-1 context rejects any
+///              that are set):
+///   [138..142] soft_stale_slots (u32 LE, optional, default 0 = MAX_STALENESS_SLOTS: oracle
+///              age past which matches are charged the penalty spread and flagged degraded)
+///   [142..146] hard_stale_slots (u32 LE, optional, default 0 = no soft-stale band: oracle
+///              age past which matches are rejected; at least the soft threshold)
+///   [146..150] penalty_spread_bps (u32 LE, optional, default 0 = max spread: spread floor
+///              of a degraded quote, between min and max spread)
+///   [150..182] protocol_fee_authority pubkey (32 bytes, optional: signer of tag 0x10 claims;
+///              required when protocol_fee_bps > 0)
 pub fn process_init(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
@@ -38,13 +65,73 @@ pub fn process_init(
     let lp_pda = &accounts[0];
     let ctx_account = &accounts[1];
 
-    // Verify writable, sized, and not already initialized
+    // Verify owned, writable, sized, and not already initialized
+    verify_context_owner(ctx_account, program_id, "YIELD-MATCHER")?;
     verify_init_preconditions(ctx_account, YIELD_MATCHER_MAGIC, "YIELD-MATCHER")?;
+    let extended = ctx_account.data_len() >= CTX_V2_SIZE;
+
+    let mode = data[1];
+    if mode != MODE_ALL_NCN && mode != MODE_SINGLE_NCN {
+        msg!("YIELD-MATCHER: Invalid mode {}", mode);
+        return Err(YieldMatcherError::InvalidMode.into());
+    }
+
+    let impact_model = data.get(114).copied().unwrap_or(IMPACT_MODEL_LINEAR);
+    if impact_model > IMPACT_MODEL_SQRT {
+        msg!("YIELD-MATCHER: Invalid impact model {}", impact_model);
+        return Err(YieldMatcherError::InvalidImpactModel.into());
+    }
+
+    let impact_k = u32::from_le_bytes(
+        data[14..18].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let liquidity = u128::from_le_bytes(
+        data[18..34].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    check_liquidity_config(impact_k, liquidity)?;
+
+    let max_spread = u32::from_le_bytes(
+        data[10..14].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let min_spread = read_opt_u32(data, 115);
+    if min_spread > max_spread {
+        msg!("YIELD-MATCHER: Min spread {} exceeds max spread {}", min_spread, max_spread);
+        return Err(YieldMatcherError::InvalidSpreadConfig.into());
+    }
+
+    let blend_weights = [read_opt_u32(data, 119), read_opt_u32(data, 123), read_opt_u32(data, 127)];
+    validate_blend_weights(blend_weights)?;
+
+    let regime_transition_secs = read_opt_u32(data, 131);
+
+    let protocol_fee_bps = data
+        .get(135..137)
+        .and_then(|b| b.try_into().ok())
+        .map(u16::from_le_bytes)
+        .unwrap_or(0);
+    if protocol_fee_bps > MAX_PROTOCOL_FEE_BPS {
+        msg!("YIELD-MATCHER: Protocol fee {} bps exceeds max {}", protocol_fee_bps, MAX_PROTOCOL_FEE_BPS);
+        return Err(YieldMatcherError::InvalidProtocolFeeConfig.into());
+    }
+
+    let spread_scaling = data.get(137).copied().unwrap_or(SPREAD_SCALING_REGIME);
+    if spread_scaling > SPREAD_SCALING_VARIANCE {
+        msg!("YIELD-MATCHER: Invalid spread scaling {}", spread_scaling);
+        return Err(YieldMatcherError::InvalidSpreadScaling.into());
+    }
+
+    let extension = parse_extension(data.get(138..).unwrap_or(&[]), min_spread, max_spread, protocol_fee_bps)?;
+    if !extended && extension != ContextExtension::default() {
+        msg!("YIELD-MATCHER: Version-2 options need a {}-byte context, got {}", CTX_V2_SIZE, ctx_account.data_len());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
 
     let mut ctx_data = ctx_account.try_borrow_mut_data()?;
 
     // Write standard header (return data, magic, version, mode, padding, LP PDA)
-    write_header(&mut ctx_data, YIELD_MATCHER_MAGIC, data[1], lp_pda.key);
+    write_header(&mut ctx_data, YIELD_MATCHER_MAGIC, mode, lp_pda.key);
+    ctx_data[PRICE_DECIMALS_OFFSET] = YIELD_PRICE_DECIMALS;
+    ctx_data[QUOTE_KIND_OFFSET] = QUOTE_KIND_YIELD;
 
     // Spread params
     ctx_data[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4].copy_from_slice(&data[2..6]);
@@ -57,20 +144,36 @@ pub fn process_init(
     ctx_data[YIELD_MARK_PRICE_OFFSET..YIELD_MARK_PRICE_OFFSET + 8].copy_from_slice(&0u64.to_le_bytes());
     ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8].copy_from_slice(&0u64.to_le_bytes());
     ctx_data[YIELD_REGIME_OFFSET] = 2; // Normal
-    ctx_data[YIELD_REGIME_OFFSET + 1..YIELD_REGIME_OFFSET + 8].fill(0); // padding
+    ctx_data[IMPACT_MODEL_OFFSET] = impact_model;
+    ctx_data[PREV_REGIME_OFFSET] = 2; // Normal
+    ctx_data[HAS_SYNCED_OFFSET] = 0;
+    ctx_data[REGIME_TRANSITION_SECS_OFFSET..REGIME_TRANSITION_SECS_OFFSET + 4]
+        .copy_from_slice(&regime_transition_secs.to_le_bytes());
     ctx_data[YIELD_7D_AVG_OFFSET..YIELD_7D_AVG_OFFSET + 8].copy_from_slice(&0u64.to_le_bytes());
     ctx_data[YIELD_30D_AVG_OFFSET..YIELD_30D_AVG_OFFSET + 8].copy_from_slice(&0u64.to_le_bytes());
 
     // Liquidity + max fill
-    ctx_data[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&data[18..34]);
-    ctx_data[MAX_FILL_OFFSET..MAX_FILL_OFFSET + 16].copy_from_slice(&data[34..50]);
+    write_u128(&mut ctx_data, LIQUIDITY_OFFSET, read_u128(data, 18)?)?;
+    write_u128(&mut ctx_data, MAX_FILL_OFFSET, read_u128(data, 34)?)?;
 
     // Oracle accounts
     ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32].copy_from_slice(&data[50..82]);
     ctx_data[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32].copy_from_slice(&data[82..114]);
 
-    // Zero reserved
+    // Zero reserved, then write extension params
     ctx_data[272..CTX_SIZE].fill(0);
+    ctx_data[LIQUIDITY_CAPPED_OFFSET] = (read_u128(&ctx_data, LIQUIDITY_OFFSET)? > 0) as u8;
+    ctx_data[MIN_SPREAD_OFFSET..MIN_SPREAD_OFFSET + 4].copy_from_slice(&min_spread.to_le_bytes());
+    for (i, w) in blend_weights.iter().enumerate() {
+        let offset = BLEND_WEIGHTS_OFFSET + i * 4;
+        ctx_data[offset..offset + 4].copy_from_slice(&w.to_le_bytes());
+    }
+    ctx_data[PROTOCOL_FEE_BPS_OFFSET..PROTOCOL_FEE_BPS_OFFSET + 2]
+        .copy_from_slice(&protocol_fee_bps.to_le_bytes());
+    ctx_data[SPREAD_SCALING_OFFSET] = spread_scaling;
+    if extended {
+        write_extension(&mut ctx_data, &extension);
+    }
 
     let base_spread_val = u32::from_le_bytes(
         data[2..6].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
@@ -78,16 +181,16 @@ pub fn process_init(
     let yield_vol_val = u32::from_le_bytes(
         data[6..10].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
     );
-    let max_spread_val = u32::from_le_bytes(
-        data[10..14].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
     msg!(
-        "INIT: lp_pda={} mode={} base_spread={} yield_vol_spread={} max_spread={}",
+        "INIT: lp_pda={} mode={} base_spread={} yield_vol_spread={} max_spread={} min_spread={} blend={:?} regime_transition_secs={}",
         lp_pda.key,
         data[1],
         base_spread_val,
         yield_vol_val,
-        max_spread_val,
+        max_spread,
+        min_spread,
+        blend_weights,
+        regime_transition_secs,
     );
 
     Ok(())
@@ -97,10 +200,22 @@ pub fn process_init(
 /// Accounts:
 ///   [0] LP PDA (signer)
 ///   [1] Matcher context account (writable)
+/// Data:
+///   [0]     tag (0x00)
+///   [1..17] fill_size_abs (u128 LE, optional — 0/absent disables impact)
+///   [17]    partial_fill_allowed (u8, optional: 1 = fill what liquidity remains)
+///   [18..26] max_acceptable_price (i64 LE, optional — 0/absent disables the bound)
+/// When a liquidity notional is configured it is consumed by fills: a fill larger
+/// than what remains is rejected unless partial fills are allowed. The filled size is
+/// written to return data after the price. A negative net yield quotes a negative
+/// price, written as the i64's two's-complement bits in the u64 price slot. The quote
+/// is an ask, so the taker's slippage bound is a maximum price. Oracle data in the
+/// soft-stale band (see stale_limits) still quotes, at the penalty spread, with return data
+/// byte RETURN_DEGRADED_OFFSET set; past the hard limit the match is rejected.
 pub fn process_match(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _data: &[u8],
+    data: &[u8],
 ) -> ProgramResult {
     if accounts.len() < 2 {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -109,11 +224,23 @@ pub fn process_match(
     let lp_pda = &accounts[0];
     let ctx_account = &accounts[1];
 
+    validate_header(ctx_account, program_id)?;
+
+    // The exec price is written back to the context; fail here rather than at the borrow
+    if !ctx_account.is_writable {
+        msg!("YIELD-MATCHER: Matcher context must be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
     // Verify LP PDA signature, magic, and PDA match
     verify_lp_pda_common(lp_pda, ctx_account, YIELD_MATCHER_MAGIC, "YIELD-MATCHER")?;
 
     // Read pricing parameters
     let ctx_data = ctx_account.try_borrow_data()?;
+    if ctx_data[SETTLED_OFFSET] == 1 {
+        msg!("YIELD-MATCHER: Matcher is settled -- no more quotes");
+        return Err(YieldMatcherError::MatcherSettled.into());
+    }
     let base_spread = u32::from_le_bytes(
         ctx_data[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
@@ -126,56 +253,131 @@ pub fn process_match(
         ctx_data[MAX_SPREAD_OFFSET..MAX_SPREAD_OFFSET + 4]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
-    let yield_mark = u64::from_le_bytes(
+    let impact_k = u32::from_le_bytes(
+        ctx_data[IMPACT_K_OFFSET..IMPACT_K_OFFSET + 4]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let liquidity = read_u128(&ctx_data, LIQUIDITY_OFFSET)?;
+    let impact_model = ctx_data[IMPACT_MODEL_OFFSET];
+    let min_spread = u32::from_le_bytes(
+        ctx_data[MIN_SPREAD_OFFSET..MIN_SPREAD_OFFSET + 4]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let yield_mark = i64::from_le_bytes(
         ctx_data[YIELD_MARK_PRICE_OFFSET..YIELD_MARK_PRICE_OFFSET + 8]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
     let regime = YieldRegime::from_u8(ctx_data[YIELD_REGIME_OFFSET]);
+    let prev_regime = YieldRegime::from_u8(ctx_data[PREV_REGIME_OFFSET]);
+    let regime_transition_secs = u32::from_le_bytes(
+        ctx_data[REGIME_TRANSITION_SECS_OFFSET..REGIME_TRANSITION_SECS_OFFSET + 4]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let regime_transition_ts = i64::from_le_bytes(
+        ctx_data[REGIME_TRANSITION_TS_OFFSET..REGIME_TRANSITION_TS_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let yield_7d = u64::from_le_bytes(
+        ctx_data[YIELD_7D_AVG_OFFSET..YIELD_7D_AVG_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let yield_30d = u64::from_le_bytes(
+        ctx_data[YIELD_30D_AVG_OFFSET..YIELD_30D_AVG_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let blend_weights = read_blend_weights(&ctx_data)?;
 
-    // Reject if yield mark price not set
-    if yield_mark == 0 {
-        msg!("YIELD-MATCHER: Yield mark price not set -- oracle sync required");
+    // Reject until an oracle sync has landed; a synced mark of zero is a real yield
+    if ctx_data[HAS_SYNCED_OFFSET] == 0 {
+        msg!("YIELD-MATCHER: Never synced -- oracle sync required");
         return Err(YieldMatcherError::OracleNotSynced.into());
     }
 
-    // Check oracle staleness (reject if > 100 slots old)
+    // Impact-priced markets never quote flat; a fully consumed notional stops here too
+    check_liquidity_config(impact_k, liquidity)?;
+
+    // Check oracle staleness: reject past the hard limit, degrade in the soft-stale band
     let last_update = u64::from_le_bytes(
         ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
     let clock = Clock::get()?;
-    if clock.slot.saturating_sub(last_update) > 100 {
-        msg!("YIELD-MATCHER: Oracle stale -- last update slot {}, current {}", last_update, clock.slot);
-        return Err(YieldMatcherError::OracleStale.into());
+    let degraded = match check_staleness(&ctx_data, clock.slot.saturating_sub(last_update)) {
+        Ok(degraded) => degraded,
+        Err(e) => {
+            msg!("YIELD-MATCHER: Oracle stale -- last update slot {}, current {}", last_update, clock.slot);
+            return Err(e);
+        }
+    };
+    if degraded {
+        msg!("YIELD-MATCHER: Oracle soft-stale -- last update slot {}, quoting at penalty spread", last_update);
     }
 
-    // Dynamic spread based on yield regime
-    let regime_multiplier = regime.spread_multiplier();
-    let adjusted_yield_vol = (yield_vol_spread as u64)
-        .checked_mul(regime_multiplier)
-        .ok_or(YieldMatcherError::ArithmeticOverflow)?
-        / 100;
+    let requested_fill = read_fill_size(data)?;
+    let partial_fill_allowed = data.get(17).copied().unwrap_or(0) == 1;
+    // A configured notional caps fills whether or not impact prices them
+    let capped = liquidity_capped(&ctx_data)?;
+    let fill_size = if capped {
+        resolve_fill(requested_fill, liquidity, partial_fill_allowed)?
+    } else {
+        requested_fill
+    };
+    let impact = compute_impact_bps(impact_model, impact_k, fill_size, liquidity);
+
+    // Price off the configured spot/7d/30d blend (spot only by default)
+    let yield_mark = blended_yield_mark(yield_mark, yield_7d, yield_30d, blend_weights)?;
 
-    let total_spread = std::cmp::min(
-        (base_spread as u64).saturating_add(adjusted_yield_vol),
-        max_spread as u64,
+    // Regime-scaled spread (blended across a recent flip) and execution price via the
+    // shared pricing core
+    let regime_multiplier = transition_spread_multiplier(
+        prev_regime,
+        regime,
+        regime_transition_ts,
+        regime_transition_secs,
+        clock.unix_timestamp,
     );
+    let spread_multiplier = effective_spread_multiplier(&ctx_data, regime_multiplier)?;
+    // A degraded quote is floored at the penalty spread (the max unless configured)
+    let spread_floor = if degraded { degraded_spread_floor(&ctx_data, max_spread)? } else { min_spread };
+    let (exec_price, total_spread) =
+        yield_exec_price(yield_mark, spread_multiplier, base_spread, yield_vol_spread, spread_floor, max_spread, impact)?;
+    check_slippage(exec_price, read_max_acceptable_price(data))?;
 
-    // Compute execution price using shared utility
-    let exec_price = compute_exec_price(yield_mark, total_spread)?;
+    let fee = protocol_fee_e6(fill_size, total_spread, read_protocol_fee_bps(&ctx_data)?)?;
+    let accrued_fees = accrue_protocol_fee(&ctx_data, fee)?;
 
     drop(ctx_data);
 
     // Write execution price to return buffer using shared utility
     let mut ctx_data = ctx_account.try_borrow_mut_data()?;
-    write_exec_price(&mut ctx_data, exec_price);
+    write_exec_price(&mut ctx_data, exec_price as u64);
+    ctx_data[RETURN_FILLED_SIZE_OFFSET..RETURN_FILLED_SIZE_OFFSET + 16]
+        .copy_from_slice(&fill_size.to_le_bytes());
+    ctx_data[RETURN_DEGRADED_OFFSET] = degraded as u8;
+    if capped && fill_size > 0 {
+        write_u128(&mut ctx_data, LIQUIDITY_OFFSET, liquidity - fill_size)?;
+    }
+    ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8]
+        .copy_from_slice(&accrued_fees.to_le_bytes());
+    #[cfg(feature = "check-reserved")]
+    assert_reserved_zero(&ctx_data);
 
-    msg!(
-        "MATCH: price={} spread={} regime={:?} yield_mark={}",
+    log_match(
         exec_price,
         total_spread,
-        regime,
-        yield_mark
+        yield_mark,
+        &format!(
+            "regime={} regime_mult={} spread_mult={} yield_bps={}{} impact_bps={} fill={} fee_e6={} degraded={}",
+            regime as u8,
+            regime_multiplier,
+            spread_multiplier,
+            if yield_mark < 0 { "-" } else { "" },
+            e6_to_bps(yield_mark.unsigned_abs()),
+            impact,
+            fill_size,
+            fee,
+            degraded as u8
+        ),
     );
 
     Ok(())
@@ -184,234 +386,3231 @@ pub fn process_match(
 /// Tag 0x03: Sync oracle — keeper reads NCN oracle and updates matcher context
 /// Accounts:
 ///   [0] Matcher context account (writable)
-///   [1] NcnYieldFeed account (read)
+///   [1] NcnYieldFeed account (read — must be active)
 ///   [2] NcnPerformanceFeed account (read)
 /// Data layout:
 ///   [0]    tag (0x03)
-///   [1..9] current_yield_bps (u64 LE) — from keeper reading NCN oracle
-///   [9..17] yield_mark_price_e6 (u64 LE) — yield * 1e6
+///   [1..9] current_yield_bps (i64 LE) — net yield from the NCN oracle, may be negative
+///   [9..17] yield_mark_price_e6 (i64 LE) — yield * 1e6
 ///   [17]   regime (u8)
 ///   [18..26] yield_7d_avg_bps (u64 LE)
 ///   [26..34] yield_30d_avg_bps (u64 LE)
+///   [34..42] oracle_sequence (u64 LE, the yield feed's last_updated read at; must exceed
+///            the last applied sequence)
+///   [42..50] yield_variance_bps (u64 LE, optional — absent reports none; priced only by
+///            continuous spread scaling)
 pub fn process_oracle_sync(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
-    if accounts.len() < 3 {
-        return Err(ProgramError::NotEnoughAccountKeys);
-    }
-    if data.len() < 34 {
+    require_sync_accounts(accounts, 0)?;
+    if data.len() < 1 + ORACLE_SYNC_PAYLOAD_SIZE {
         return Err(ProgramError::InvalidInstructionData);
     }
 
     let ctx_account = &accounts[0];
-    let ncn_yield_feed = &accounts[1];
-    let ncn_performance_feed = &accounts[2];
-
-    if !ctx_account.is_writable {
-        return Err(ProgramError::InvalidAccountData);
-    }
+    verify_sync_accounts(program_id, ctx_account, &accounts[1], &accounts[2])?;
+    check_yield_feed_active(&accounts[1])?;
 
-    // Verify context is initialized
-    {
-        let ctx_data = ctx_account.try_borrow_data()?;
-        if !verify_magic(&ctx_data) {
-            return Err(ProgramError::UninitializedAccount);
-        }
+    let payload = parse_oracle_sync_payload(&data[1..])?;
+    check_sequence_within_feed(payload.sequence, read_yield_feed_last_updated(&accounts[1].try_borrow_data()?)?)?;
+    apply_oracle_sync(ctx_account, &payload)
+}
 
-        // Verify passed accounts match stored oracle accounts
-        let stored_yield_feed = Pubkey::new_from_array(
-            ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32]
-                .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
-        );
-        let stored_perf_feed = Pubkey::new_from_array(
-            ctx_data[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32]
-                .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
-        );
-        if *ncn_yield_feed.key != stored_yield_feed {
-            msg!("YIELD-MATCHER: NcnYieldFeed mismatch");
-            return Err(YieldMatcherError::OracleAccountMismatch.into());
-        }
-        if *ncn_performance_feed.key != stored_perf_feed {
-            msg!("YIELD-MATCHER: NcnPerformanceFeed mismatch");
-            return Err(YieldMatcherError::OracleAccountMismatch.into());
-        }
-    }
+/// Decoded tag 0x03 payload (data bytes [1..42], plus the optional variance)
+#[derive(Clone, Copy)]
+struct OracleSyncPayload {
+    current_yield: i64,
+    yield_mark: i64,
+    regime: u8,
+    yield_7d: u64,
+    yield_30d: u64,
+    sequence: u64,
+    yield_variance_bps: u64,
+}
 
-    let current_yield = u64::from_le_bytes(
-        data[1..9].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    let yield_mark = u64::from_le_bytes(
-        data[9..17].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    let regime = data[17];
-    let yield_7d = u64::from_le_bytes(
-        data[18..26].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-    let yield_30d = u64::from_le_bytes(
-        data[26..34].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
+/// Decode a sync payload and check the regime and the mark/yield consistency
+fn parse_oracle_sync_payload(payload: &[u8]) -> Result<OracleSyncPayload, ProgramError> {
+    let u64_at = |o: usize| -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(
+            payload
+                .get(o..o + 8)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(ProgramError::InvalidInstructionData)?,
+        ))
+    };
+    let parsed = OracleSyncPayload {
+        current_yield: u64_at(0)? as i64,
+        yield_mark: u64_at(8)? as i64,
+        regime: *payload.get(16).ok_or(ProgramError::InvalidInstructionData)?,
+        yield_7d: u64_at(17)?,
+        yield_30d: u64_at(25)?,
+        sequence: u64_at(33)?,
+        yield_variance_bps: u64_at(ORACLE_SYNC_PAYLOAD_SIZE).unwrap_or(0),
+    };
 
     // Validate regime
-    if regime > 4 {
+    if parsed.regime > 4 {
         return Err(YieldMatcherError::InvalidRegime.into());
     }
 
+    // Mark must be derived from the synced yield, not trusted independently
+    validate_yield_mark(parsed.current_yield, parsed.yield_mark)?;
+
+    Ok(parsed)
+}
+
+/// Write a validated sync payload to a context already checked by verify_sync_accounts
+fn apply_oracle_sync(ctx_account: &AccountInfo, payload: &OracleSyncPayload) -> ProgramResult {
+    let OracleSyncPayload { current_yield, yield_mark, regime, yield_7d, yield_30d, sequence, yield_variance_bps } =
+        *payload;
+
     let clock = Clock::get()?;
 
     let mut ctx_data = ctx_account.try_borrow_mut_data()?;
-    let old_yield = u64::from_le_bytes(
+    let last_sequence = u64::from_le_bytes(
+        ctx_data[ORACLE_SEQUENCE_OFFSET..ORACLE_SEQUENCE_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    check_oracle_sequence(last_sequence, sequence)?;
+
+    let old_yield = i64::from_le_bytes(
         ctx_data[CURRENT_YIELD_OFFSET..CURRENT_YIELD_OFFSET + 8]
             .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
     );
 
+    // A flip starts a smoothing window from the regime being left (a flip mid-window
+    // restarts it from the previous target). The first sync has nothing to smooth from.
+    let old_regime = ctx_data[YIELD_REGIME_OFFSET];
+    let regime_transition_secs = u32::from_le_bytes(
+        ctx_data[REGIME_TRANSITION_SECS_OFFSET..REGIME_TRANSITION_SECS_OFFSET + 4]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if regime != old_regime && regime_transition_secs > 0 && last_sequence > 0 {
+        ctx_data[PREV_REGIME_OFFSET] = old_regime;
+        ctx_data[REGIME_TRANSITION_TS_OFFSET..REGIME_TRANSITION_TS_OFFSET + 8]
+            .copy_from_slice(&clock.unix_timestamp.to_le_bytes());
+    }
+
     ctx_data[CURRENT_YIELD_OFFSET..CURRENT_YIELD_OFFSET + 8].copy_from_slice(&current_yield.to_le_bytes());
     ctx_data[YIELD_MARK_PRICE_OFFSET..YIELD_MARK_PRICE_OFFSET + 8].copy_from_slice(&yield_mark.to_le_bytes());
     ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8].copy_from_slice(&clock.slot.to_le_bytes());
     ctx_data[YIELD_REGIME_OFFSET] = regime;
     ctx_data[YIELD_7D_AVG_OFFSET..YIELD_7D_AVG_OFFSET + 8].copy_from_slice(&yield_7d.to_le_bytes());
     ctx_data[YIELD_30D_AVG_OFFSET..YIELD_30D_AVG_OFFSET + 8].copy_from_slice(&yield_30d.to_le_bytes());
+    ctx_data[ORACLE_SEQUENCE_OFFSET..ORACLE_SEQUENCE_OFFSET + 8].copy_from_slice(&sequence.to_le_bytes());
+    ctx_data[YIELD_VARIANCE_OFFSET..YIELD_VARIANCE_OFFSET + 2]
+        .copy_from_slice(&u16::try_from(yield_variance_bps).unwrap_or(u16::MAX).to_le_bytes());
+    ctx_data[HAS_SYNCED_OFFSET] = 1;
 
     msg!(
-        "ORACLE_SYNC: old_yield={} new_yield={} mark={} regime={}",
+        "ORACLE_SYNC: old_yield={} new_yield={} mark={} regime={} seq={}",
         old_yield,
         current_yield,
         yield_mark,
-        regime
+        regime,
+        sequence
     );
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::state::*;
-    use matcher_common::compute_exec_price;
+/// Tag 0x0A: Initialize and apply the first oracle sync in one instruction, so a new
+/// matcher can quote without a second transaction
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable, 320 bytes, or 448 for version 2)
+///   [2] NcnYieldFeed account (read — must equal the init yield feed pubkey and be active)
+///   [3] NcnPerformanceFeed account (read — must equal the init performance feed pubkey)
+/// Data:
+///   [0]     tag (0x0A)
+///   [1..42] sync payload, laid out as tag 0x03 bytes [1..42] (sequence must be > 0; no
+///           yield variance is reported, so continuous scaling prices at the regime until
+///           the next sync)
+///   [42..]  init fields, laid out as tag 0x02 bytes [1..] (optional trailing fields allowed)
+/// The sync payload is validated before anything is written.
+pub fn process_init_and_sync(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    require_sync_accounts(accounts, 1)?;
+    if data.len() < 1 + ORACLE_SYNC_PAYLOAD_SIZE + 113 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
 
-    // Helper: replicate the pricing math from process_match for unit-testing
-    fn calc_exec_price(
-        base_spread: u32,
-        yield_vol_spread: u32,
-        max_spread: u32,
-        regime: YieldRegime,
-        yield_mark: u64,
-    ) -> u64 {
-        let regime_multiplier = regime.spread_multiplier();
-        let adjusted_yield_vol = (yield_vol_spread as u64) * regime_multiplier / 100;
-        let total_spread = std::cmp::min(
-            (base_spread as u64).saturating_add(adjusted_yield_vol),
-            max_spread as u64,
-        );
-        compute_exec_price(yield_mark, total_spread).unwrap()
+    let payload = parse_oracle_sync_payload(&data[1..1 + ORACLE_SYNC_PAYLOAD_SIZE])?;
+    check_oracle_sequence(0, payload.sequence)?;
+
+    let mut init_data = Vec::with_capacity(data.len() - ORACLE_SYNC_PAYLOAD_SIZE);
+    init_data.push(0x02);
+    init_data.extend_from_slice(&data[1 + ORACLE_SYNC_PAYLOAD_SIZE..]);
+    process_init(program_id, &accounts[..2], &init_data)?;
+
+    verify_sync_accounts(program_id, &accounts[1], &accounts[2], &accounts[3])?;
+    check_yield_feed_active(&accounts[2])?;
+    check_sequence_within_feed(payload.sequence, read_yield_feed_last_updated(&accounts[2].try_borrow_data()?)?)?;
+    apply_oracle_sync(&accounts[1], &payload)
+}
+
+/// Replay protection: a sync must carry a sequence strictly greater than the last
+/// applied one, so a re-submitted or late transaction cannot regress the mark
+pub fn check_oracle_sequence(last_sequence: u64, sequence: u64) -> ProgramResult {
+    if sequence <= last_sequence {
+        msg!("YIELD-MATCHER: Oracle sequence {} not after last applied {}", sequence, last_sequence);
+        return Err(YieldMatcherError::OracleSequenceRegressed.into());
     }
+    Ok(())
+}
 
-    // -----------------------------------------------------------------------
-    // 1. Normal regime — typical restaking yield of ~8% (800 bps, mark = 800_000_000)
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_normal_regime_pricing() {
-        let price = calc_exec_price(20, 30, 200, YieldRegime::Normal, 800_000_000);
-        // adjusted_yield_vol = 30 * 100 / 100 = 30
-        // total_spread = min(20 + 30, 200) = 50
-        // exec_price   = 800_000_000 * 10050 / 10000 = 804_000_000
-        assert_eq!(price, 804_000_000);
+/// The sequence is the yield feed's last_updated the payload was read at, so it can never
+/// run ahead of the feed: a caller cannot jump it to u64::MAX and lock out every later sync
+pub fn check_sequence_within_feed(sequence: u64, feed_updated: i64) -> ProgramResult {
+    if sequence > feed_updated.max(0) as u64 {
+        msg!("YIELD-MATCHER: Oracle sequence {} ahead of feed last_updated {}", sequence, feed_updated);
+        return Err(YieldMatcherError::OracleSequenceAhead.into());
     }
+    Ok(())
+}
 
-    // -----------------------------------------------------------------------
-    // 2. Extreme regime — slashing event, yield instability
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_extreme_regime_pricing() {
-        let price = calc_exec_price(20, 30, 200, YieldRegime::Extreme, 800_000_000);
-        // adjusted_yield_vol = 30 * 250 / 100 = 75
-        // total_spread = min(20 + 75, 200) = 95
-        // exec_price   = 800_000_000 * 10095 / 10000 = 807_600_000
-        assert_eq!(price, 807_600_000);
+/// Version-2 extension fields, laid out in init data from byte 138 and in migrate data
+/// from byte 1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextExtension {
+    pub soft_stale_slots: u32,
+    pub hard_stale_slots: u32,
+    pub penalty_spread: u32,
+    pub fee_authority: Pubkey,
+}
+
+/// Parse and validate extension fields (all optional, absent = 0): [0..4] soft_stale_slots,
+/// [4..8] hard_stale_slots, [8..12] penalty_spread_bps, [12..44] protocol_fee_authority.
+/// A protocol fee needs an authority to claim it.
+pub fn parse_extension(
+    params: &[u8],
+    min_spread: u32,
+    max_spread: u32,
+    protocol_fee_bps: u16,
+) -> Result<ContextExtension, ProgramError> {
+    let extension = ContextExtension {
+        soft_stale_slots: read_opt_u32(params, 0),
+        hard_stale_slots: read_opt_u32(params, 4),
+        penalty_spread: read_opt_u32(params, 8),
+        fee_authority: params
+            .get(12..44)
+            .and_then(|b| b.try_into().ok())
+            .map(Pubkey::new_from_array)
+            .unwrap_or_default(),
+    };
+    let (soft, hard) = (extension.soft_stale_slots, extension.hard_stale_slots);
+    if hard != 0 && (hard as u64) < effective_soft_stale_slots(soft) {
+        msg!("YIELD-MATCHER: Hard-stale limit {} below soft-stale threshold {}", hard, soft);
+        return Err(YieldMatcherError::InvalidStalenessConfig.into());
     }
+    let penalty = extension.penalty_spread;
+    if penalty != 0 && (penalty < min_spread || penalty > max_spread) {
+        msg!("YIELD-MATCHER: Penalty spread {} outside min {} / max {}", penalty, min_spread, max_spread);
+        return Err(YieldMatcherError::InvalidPenaltySpread.into());
+    }
+    if protocol_fee_bps > 0 && extension.fee_authority == Pubkey::default() {
+        msg!("YIELD-MATCHER: Protocol fee {} bps set without a protocol fee authority", protocol_fee_bps);
+        return Err(YieldMatcherError::InvalidProtocolFeeConfig.into());
+    }
+    Ok(extension)
+}
 
-    // -----------------------------------------------------------------------
-    // 3. VeryLow regime — stable staking yield
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_very_low_regime_pricing() {
-        let price = calc_exec_price(20, 30, 200, YieldRegime::VeryLow, 800_000_000);
-        // adjusted_yield_vol = 30 * 50 / 100 = 15
-        // total_spread = min(20 + 15, 200) = 35
-        // exec_price   = 800_000_000 * 10035 / 10000 = 802_800_000
-        assert_eq!(price, 802_800_000);
+/// Mark the context version 2 and write the extension, zeroing its reserved tail
+pub fn write_extension(ctx_data: &mut [u8], extension: &ContextExtension) {
+    ctx_data[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(&CTX_VERSION_2.to_le_bytes());
+    ctx_data[CTX_SIZE..CTX_V2_SIZE].fill(0);
+    ctx_data[PROTOCOL_FEE_AUTHORITY_OFFSET..PROTOCOL_FEE_AUTHORITY_OFFSET + 32]
+        .copy_from_slice(extension.fee_authority.as_ref());
+    ctx_data[SOFT_STALE_SLOTS_OFFSET..SOFT_STALE_SLOTS_OFFSET + 4]
+        .copy_from_slice(&extension.soft_stale_slots.to_le_bytes());
+    ctx_data[HARD_STALE_SLOTS_OFFSET..HARD_STALE_SLOTS_OFFSET + 4]
+        .copy_from_slice(&extension.hard_stale_slots.to_le_bytes());
+    ctx_data[PENALTY_SPREAD_OFFSET..PENALTY_SPREAD_OFFSET + 4]
+        .copy_from_slice(&extension.penalty_spread.to_le_bytes());
+}
+
+/// Tag 0x11: Migrate a version-1 context to version 2 in place: grow the account to
+/// CTX_V2_SIZE, with the payer topping up rent, and write the extension. Unmigrated
+/// contexts keep working without the extension's features.
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable)
+///   [2] Payer (signer, writable)
+///   [3] System program
+/// Data:
+///   [0]    tag (0x11)
+///   [1..]  extension fields, laid out as tag 0x02 bytes [138..] (all optional)
+pub fn process_migrate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
     }
+    let lp_pda = &accounts[0];
+    let ctx_account = &accounts[1];
+    let payer = &accounts[2];
+    let system = &accounts[3];
 
-    // -----------------------------------------------------------------------
-    // 4. Spread capping (total_spread exceeds max_spread)
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_spread_capping() {
-        let price = calc_exec_price(100, 200, 150, YieldRegime::Extreme, 800_000_000);
-        // adjusted_yield_vol = 200 * 250 / 100 = 500
-        // total_spread = min(100 + 500, 150) = 150
-        // exec_price   = 800_000_000 * 10150 / 10000 = 812_000_000
-        assert_eq!(price, 812_000_000);
+    validate_header(ctx_account, program_id)?;
+    verify_lp_pda_common(lp_pda, ctx_account, YIELD_MATCHER_MAGIC, "YIELD-MATCHER")?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !payer.is_signer {
+        msg!("YIELD-MATCHER: Migration payer must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *system.key != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
     }
 
-    // -----------------------------------------------------------------------
-    // 5. YieldRegime::from_u8
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_regime_from_u8() {
-        assert_eq!(YieldRegime::from_u8(0), YieldRegime::VeryLow);
-        assert_eq!(YieldRegime::from_u8(1), YieldRegime::Low);
-        assert_eq!(YieldRegime::from_u8(2), YieldRegime::Normal);
-        assert_eq!(YieldRegime::from_u8(3), YieldRegime::High);
-        assert_eq!(YieldRegime::from_u8(4), YieldRegime::Extreme);
-        // Out-of-range defaults to Normal
-        assert_eq!(YieldRegime::from_u8(5), YieldRegime::Normal);
+    let extension = {
+        let ctx_data = ctx_account.try_borrow_data()?;
+        if has_extension(&ctx_data) {
+            msg!("YIELD-MATCHER: Context is already version {}", CTX_VERSION_2);
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        parse_extension(
+            data.get(1..).unwrap_or(&[]),
+            read_ctx_u32(&ctx_data, MIN_SPREAD_OFFSET)?,
+            read_ctx_u32(&ctx_data, MAX_SPREAD_OFFSET)?,
+            read_protocol_fee_bps(&ctx_data)?,
+        )?
+    };
+
+    let shortfall = Rent::get()?.minimum_balance(CTX_V2_SIZE).saturating_sub(ctx_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, ctx_account.key, shortfall),
+            &[payer.clone(), ctx_account.clone(), system.clone()],
+        )?;
     }
+    ctx_account.resize(CTX_V2_SIZE)?;
+    write_extension(&mut ctx_account.try_borrow_mut_data()?, &extension);
 
-    // -----------------------------------------------------------------------
-    // 6. YieldRegime::spread_multiplier
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_regime_spread_multiplier() {
-        assert_eq!(YieldRegime::VeryLow.spread_multiplier(), 50);
-        assert_eq!(YieldRegime::Low.spread_multiplier(), 75);
-        assert_eq!(YieldRegime::Normal.spread_multiplier(), 100);
-        assert_eq!(YieldRegime::High.spread_multiplier(), 150);
-        assert_eq!(YieldRegime::Extreme.spread_multiplier(), 250);
+    msg!("MIGRATE: context={} version={}->{} size={}", ctx_account.key, CTX_VERSION_1, CTX_VERSION_2, CTX_V2_SIZE);
+    Ok(())
+}
+
+/// Validate a context against this matcher's magic (see matcher_utils::validate_header).
+/// Every handler taking an initialized context starts here.
+pub fn validate_header(ctx_account: &AccountInfo, program_id: &Pubkey) -> Result<HeaderView, ProgramError> {
+    matcher_utils::validate_header(ctx_account, program_id, YIELD_MATCHER_MAGIC, "YIELD-MATCHER")
+}
+
+/// Check that a sync instruction carries a context at `ctx_index` followed by its
+/// NcnYieldFeed and NcnPerformanceFeed, logging which slot is missing. Each feed has its
+/// own error, distinct from OracleAccountMismatch; a missing context is NotEnoughAccountKeys.
+fn require_sync_accounts(accounts: &[AccountInfo], ctx_index: usize) -> ProgramResult {
+    let slots: [(&str, ProgramError); 3] = [
+        ("matcher context", ProgramError::NotEnoughAccountKeys),
+        ("NcnYieldFeed", YieldMatcherError::YieldFeedMissing.into()),
+        ("NcnPerformanceFeed", YieldMatcherError::PerformanceFeedMissing.into()),
+    ];
+    for (offset, (name, err)) in slots.into_iter().enumerate() {
+        if accounts.len() <= ctx_index + offset {
+            msg!("YIELD-MATCHER: Missing {} at index {}", name, ctx_index + offset);
+            return Err(err);
+        }
     }
+    Ok(())
+}
 
-    // -----------------------------------------------------------------------
-    // 7. Low yield scenario (e.g., 2% APY = 200 bps, mark = 200_000_000)
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_low_yield_pricing() {
-        let price = calc_exec_price(15, 25, 300, YieldRegime::Low, 200_000_000);
-        // adjusted_yield_vol = 25 * 75 / 100 = 18 (truncated)
-        // total_spread = min(15 + 18, 300) = 33
-        // exec_price   = 200_000_000 * 10033 / 10000 = 200_660_000
-        assert_eq!(price, 200_660_000);
+/// Check that a sync target is a writable yield context bound to both oracle feeds
+fn verify_sync_accounts(
+    program_id: &Pubkey,
+    ctx_account: &AccountInfo,
+    ncn_yield_feed: &AccountInfo,
+    ncn_performance_feed: &AccountInfo,
+) -> ProgramResult {
+    validate_header(ctx_account, program_id)?;
+
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
     }
 
-    // -----------------------------------------------------------------------
-    // 8. High yield scenario (e.g., 20% APY = 2000 bps, mark = 2_000_000_000)
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_high_yield_high_regime() {
-        let price = calc_exec_price(20, 40, 200, YieldRegime::High, 2_000_000_000);
-        // adjusted_yield_vol = 40 * 150 / 100 = 60
-        // total_spread = min(20 + 60, 200) = 80
-        // exec_price   = 2_000_000_000 * 10080 / 10000 = 2_016_000_000
-        assert_eq!(price, 2_016_000_000);
+    // Verify passed accounts match stored oracle accounts
+    let ctx_data = ctx_account.try_borrow_data()?;
+
+    let stored_yield_feed = Pubkey::new_from_array(
+        ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let stored_perf_feed = Pubkey::new_from_array(
+        ctx_data[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if *ncn_yield_feed.key != stored_yield_feed {
+        msg!("YIELD-MATCHER: NcnYieldFeed mismatch");
+        return Err(YieldMatcherError::OracleAccountMismatch.into());
+    }
+    if *ncn_performance_feed.key != stored_perf_feed {
+        msg!("YIELD-MATCHER: NcnPerformanceFeed mismatch");
+        return Err(YieldMatcherError::OracleAccountMismatch.into());
     }
 
-    // -----------------------------------------------------------------------
-    // 9. Zero spread scenario
-    // -----------------------------------------------------------------------
-    #[test]
-    fn test_zero_spread() {
-        let price = calc_exec_price(0, 0, 200, YieldRegime::Normal, 500_000_000);
-        // total_spread = 0
-        // exec_price = 500_000_000
-        assert_eq!(price, 500_000_000);
+    Ok(())
+}
+
+/// Refuse to sync or heartbeat from an NcnYieldFeed the oracle has deactivated: its data
+/// is no longer maintained
+fn check_yield_feed_active(ncn_yield_feed: &AccountInfo) -> ProgramResult {
+    verify_feed_type(ncn_yield_feed, &NCN_YIELD_FEED_DISCRIMINATOR, "NcnYieldFeed")?;
+    if !read_yield_feed_is_active(&ncn_yield_feed.try_borrow_data()?)? {
+        msg!("YIELD-MATCHER: NcnYieldFeed {} is deactivated -- refused", ncn_yield_feed.key);
+        return Err(YieldMatcherError::OracleInactive.into());
+    }
+    Ok(())
+}
+
+/// Tag 0x04: Heartbeat — keeper proves liveness when yield is unchanged.
+/// Refreshes the last-update slot (so the staleness check passes) without touching prices,
+/// only while the NcnYieldFeed itself is active and fresh: its last_updated must fall
+/// within the context's hard staleness limit (the keeper keeps it so with
+/// heartbeat_ncn_yield).
+/// Accounts:
+///   [0] Matcher context account (writable)
+///   [1] NcnYieldFeed account (read — must be the stored feed, owned by ncn-oracle, and active)
+///   [2] NcnPerformanceFeed account (read)
+/// Data layout:
+///   [0]    tag (0x04)
+pub fn process_heartbeat(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    require_sync_accounts(accounts, 0)?;
+
+    let ctx_account = &accounts[0];
+    verify_sync_accounts(program_id, ctx_account, &accounts[1], &accounts[2])?;
+    check_yield_feed_active(&accounts[1])?;
+
+    let clock = Clock::get()?;
+    let feed_updated = read_yield_feed_last_updated(&accounts[1].try_borrow_data()?)?;
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    check_heartbeat_feed_age(feed_updated, clock.unix_timestamp, stale_limits(&ctx_data)?.1)?;
+    ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+        .copy_from_slice(&clock.slot.to_le_bytes());
+
+    msg!("HEARTBEAT: slot={} feed_updated={}", clock.slot, feed_updated);
+
+    Ok(())
+}
+
+/// Refuse a heartbeat backed by a feed last updated longer ago than `max_staleness_slots`
+/// at ~400 ms per slot
+pub fn check_heartbeat_feed_age(feed_updated: i64, now: i64, max_staleness_slots: u64) -> ProgramResult {
+    let max_age = i64::try_from(max_staleness_slots.saturating_mul(2) / 5).unwrap_or(i64::MAX);
+    let age = now.saturating_sub(feed_updated);
+    if age > max_age {
+        msg!("YIELD-MATCHER: NcnYieldFeed last updated {}s ago -- heartbeat refused", age);
+        return Err(YieldMatcherError::OracleStale.into());
+    }
+    Ok(())
+}
+
+/// Tag 0x05: Compare the stored mark against the live NcnYieldFeed (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+///   [1] NcnYieldFeed account (read — must be the stored feed)
+/// Data layout:
+///   [0]    tag (0x05)
+///   [1..5] alert threshold (u32 LE, bps)
+/// Return data: [0..8] deviation_bps (u64 LE), [8] exceeds threshold (u8 0/1)
+pub fn process_check_mark_deviation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 5 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let threshold = u32::from_le_bytes(
+        data[1..5].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let ncn_yield_feed = &accounts[1];
+    let stored_yield_feed = Pubkey::new_from_array(
+        ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if *ncn_yield_feed.key != stored_yield_feed || *ncn_yield_feed.owner != NCN_ORACLE_PROGRAM_ID {
+        msg!("YIELD-MATCHER: NcnYieldFeed mismatch");
+        return Err(YieldMatcherError::OracleAccountMismatch.into());
+    }
+    let live_apy_bps = read_feed_current_apy_bps(&ncn_yield_feed.try_borrow_data()?)?;
+    let live_mark = bps_to_e6(live_apy_bps).ok_or(YieldMatcherError::ArithmeticOverflow)?;
+
+    // The feed's current_apy_bps is floored at zero, so compare a negative stored mark as zero
+    let stored_mark = i64::from_le_bytes(
+        ctx_data[YIELD_MARK_PRICE_OFFSET..YIELD_MARK_PRICE_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    )
+    .max(0) as u64;
+    let deviation = mark_deviation_bps(stored_mark, live_mark);
+    let exceeds = deviation > threshold as u64;
+    if exceeds {
+        msg!(
+            "YIELD-MATCHER: Mark deviation {} bps exceeds {} (stored={} live={})",
+            deviation, threshold, stored_mark, live_mark
+        );
+    }
+
+    let mut out = [0u8; 9];
+    out[..8].copy_from_slice(&deviation.to_le_bytes());
+    out[8] = exceeds as u8;
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Tag 0x08: Read the current yield regime and its spread multiplier (no state change),
+/// so clients need not hardcode the multiplier table
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data layout:
+///   [0]    tag (0x08)
+/// Return data: see REGIME_INFO_RETURN_SIZE layout in state.rs
+pub fn process_get_regime(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let out = encode_regime_info(ctx_data[YIELD_REGIME_OFFSET]);
+    msg!("REGIME: {} multiplier={}", REGIME_NAMES[out[1] as usize], YieldRegime::from_u8(out[0]).spread_multiplier());
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Encode a stored regime byte as (raw, effective name index, spread multiplier)
+pub fn encode_regime_info(raw_regime: u8) -> [u8; REGIME_INFO_RETURN_SIZE] {
+    let regime = YieldRegime::from_u8(raw_regime);
+    let mut out = [0u8; REGIME_INFO_RETURN_SIZE];
+    out[0] = raw_regime;
+    out[1] = regime.name_index();
+    out[2..10].copy_from_slice(&regime.spread_multiplier().to_le_bytes());
+    out
+}
+
+/// Tag 0x09: Read all pricing parameters in one packed struct (no state change),
+/// so clients need not reimplement the context offset table
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x09)
+/// Return data: see PRICING_PARAMS_RETURN_SIZE layout in state.rs
+pub fn process_get_pricing_params(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    set_return_data(&encode_pricing_params(&ctx_data)?);
+
+    Ok(())
+}
+
+/// Pack the pricing fields of a context into the GetPricingParams layout
+pub fn encode_pricing_params(ctx_data: &[u8]) -> Result<[u8; PRICING_PARAMS_RETURN_SIZE], ProgramError> {
+    // (context offset, return offset, length)
+    const FIELDS: [(usize, usize, usize); 14] = [
+        (MODE_OFFSET, 1, 1),
+        (BASE_SPREAD_OFFSET, 2, 4),
+        (YIELD_VOL_SPREAD_OFFSET, 6, 4),
+        (MAX_SPREAD_OFFSET, 10, 4),
+        (MIN_SPREAD_OFFSET, 14, 4),
+        (IMPACT_K_OFFSET, 18, 4),
+        (IMPACT_MODEL_OFFSET, 22, 1),
+        (YIELD_REGIME_OFFSET, 23, 1),
+        (YIELD_MARK_PRICE_OFFSET, 24, 8),
+        (CURRENT_YIELD_OFFSET, 32, 8),
+        (LAST_UPDATE_SLOT_OFFSET, 40, 8),
+        (SETTLED_OFFSET, 48, 1),
+        (PRICE_DECIMALS_OFFSET, 49, 1),
+        (QUOTE_KIND_OFFSET, 50, 1),
+    ];
+    let mut out = [0u8; PRICING_PARAMS_RETURN_SIZE];
+    out[0] = PRICING_PARAMS_LAYOUT_VERSION;
+    for (src, dst, len) in FIELDS {
+        out[dst..dst + len].copy_from_slice(ctx_data.get(src..src + len).ok_or(ProgramError::InvalidAccountData)?);
+    }
+    // Contexts initialized before the display metadata have zeros there
+    if out[49] == 0 {
+        out[49] = YIELD_PRICE_DECIMALS;
+        out[50] = QUOTE_KIND_YIELD;
+    }
+    Ok(out)
+}
+
+/// Tag 0x0B: Read how many slots remain before the matcher goes stale and stops quoting
+/// normally (no state change), so monitoring can alert before matches degrade or fail
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]    tag (0x0B)
+/// Return data: see STALENESS_RETURN_SIZE layout in state.rs
+pub fn process_get_staleness(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let last_update = u64::from_le_bytes(
+        ctx_data[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let current_slot = Clock::get()?.slot;
+    let (soft_stale, hard_stale) = stale_limits(&ctx_data)?;
+    let remaining = slots_until_stale(last_update, current_slot, soft_stale);
+
+    let mut out = [0u8; STALENESS_RETURN_SIZE];
+    out[0..8].copy_from_slice(&remaining.to_le_bytes());
+    out[8..16].copy_from_slice(&last_update.to_le_bytes());
+    out[16..24].copy_from_slice(&soft_stale.to_le_bytes());
+    out[24..32].copy_from_slice(&hard_stale.to_le_bytes());
+    msg!("STALENESS: slots_until_stale={} last_update={} current={}", remaining, last_update, current_slot);
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Tag 0x0C: Compare the stored oracle feed pubkeys against expected ones (no state
+/// change), so a monitoring job can sweep a fleet of matchers against its oracle registry
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]      tag (0x0C)
+///   [1..33]  expected NcnYieldFeed pubkey
+///   [33..65] expected NcnPerformanceFeed pubkey
+/// Return data: see ORACLE_CONFIG_RETURN_SIZE layout in state.rs
+pub fn process_check_oracle_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if data.len() < 65 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let read_key = |at: usize| -> Result<Pubkey, ProgramError> {
+        Ok(Pubkey::new_from_array(data[at..at + 32].try_into().map_err(|_| ProgramError::InvalidInstructionData)?))
+    };
+    let (expected_yield_feed, expected_perf_feed) = (read_key(1)?, read_key(33)?);
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let out = encode_oracle_config(&ctx_data, &expected_yield_feed, &expected_perf_feed)?;
+    msg!("ORACLE_CONFIG: yield_feed_ok={} perf_feed_ok={}", out[0], out[1]);
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Pack the oracle-config check for the expected feeds into the CheckOracleConfig layout
+pub fn encode_oracle_config(
+    ctx_data: &[u8],
+    expected_yield_feed: &Pubkey,
+    expected_perf_feed: &Pubkey,
+) -> Result<[u8; ORACLE_CONFIG_RETURN_SIZE], ProgramError> {
+    let stored_yield_feed = ctx_data
+        .get(NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let stored_perf_feed = ctx_data
+        .get(NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let mut out = [0u8; ORACLE_CONFIG_RETURN_SIZE];
+    out[0] = (stored_yield_feed == expected_yield_feed.as_ref()) as u8;
+    out[1] = (stored_perf_feed == expected_perf_feed.as_ref()) as u8;
+    out[2..34].copy_from_slice(stored_yield_feed);
+    out[34..66].copy_from_slice(stored_perf_feed);
+    Ok(out)
+}
+
+/// Tag 0x0D: Quote a hypothetical spot yield mark through the full match pricing pipeline
+/// (no state change), so LP strategies can be backtested independently of the live oracle
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0]      tag (0x0D)
+///   [1..9]   yield_mark_e6 (i64 LE) — stands in for the synced spot mark
+///   [9..25]  fill_size_abs (u128 LE, optional — 0/absent disables impact)
+/// Return data: see QUOTE_AT_MARK_RETURN_SIZE layout in state.rs
+/// Regime, spreads, impact, liquidity and the 7d/30d blend come from the context as a
+/// match would see them; the settled and staleness checks are skipped, and the regime
+/// prices at its settled multiplier (no transition smoothing).
+pub fn process_quote_at_mark(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let yield_mark = i64::from_le_bytes(
+        data.get(1..9)
+            .ok_or(ProgramError::InvalidInstructionData)?
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+    let fill_size = data
+        .get(9..25)
+        .and_then(|b| b.try_into().ok())
+        .map(u128::from_le_bytes)
+        .unwrap_or(0);
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    set_return_data(&quote_at_mark(&ctx_data, yield_mark, fill_size)?);
+
+    Ok(())
+}
+
+/// Price a spot `yield_mark` against the context's pricing config, as process_match would
+/// after a sync to that mark
+pub fn quote_at_mark(
+    ctx_data: &[u8],
+    yield_mark: i64,
+    fill_size: u128,
+) -> Result<[u8; QUOTE_AT_MARK_RETURN_SIZE], ProgramError> {
+    let read_u32 = |at: usize| -> Result<u32, ProgramError> {
+        Ok(u32::from_le_bytes(
+            ctx_data.get(at..at + 4).and_then(|b| b.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?,
+        ))
+    };
+    let read_u64 = |at: usize| -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(
+            ctx_data.get(at..at + 8).and_then(|b| b.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?,
+        ))
+    };
+
+    let impact_k = read_u32(IMPACT_K_OFFSET)?;
+    let liquidity = read_u128(ctx_data, LIQUIDITY_OFFSET)?;
+    // A fill the live match would reject is rejected here too
+    check_liquidity_config(impact_k, liquidity)?;
+    let fill_size = if liquidity_capped(ctx_data)? { resolve_fill(fill_size, liquidity, false)? } else { fill_size };
+    let impact = compute_impact_bps(ctx_data[IMPACT_MODEL_OFFSET], impact_k, fill_size, liquidity);
+
+    let yield_mark = blended_yield_mark(
+        yield_mark,
+        read_u64(YIELD_7D_AVG_OFFSET)?,
+        read_u64(YIELD_30D_AVG_OFFSET)?,
+        read_blend_weights(ctx_data)?,
+    )?;
+    let (exec_price, total_spread) = yield_exec_price(
+        yield_mark,
+        effective_spread_multiplier(ctx_data, YieldRegime::from_u8(ctx_data[YIELD_REGIME_OFFSET]).spread_multiplier())?,
+        read_u32(BASE_SPREAD_OFFSET)?,
+        read_u32(YIELD_VOL_SPREAD_OFFSET)?,
+        read_u32(MIN_SPREAD_OFFSET)?,
+        read_u32(MAX_SPREAD_OFFSET)?,
+        impact,
+    )?;
+
+    let mut out = [0u8; QUOTE_AT_MARK_RETURN_SIZE];
+    out[0] = QUOTE_AT_MARK_LAYOUT_VERSION;
+    out[1..9].copy_from_slice(&exec_price.to_le_bytes());
+    out[9..17].copy_from_slice(&total_spread.to_le_bytes());
+    out[17..25].copy_from_slice(&yield_mark.to_le_bytes());
+    out[25..33].copy_from_slice(&impact.to_le_bytes());
+    Ok(out)
+}
+
+/// Slots left before a context last updated at `last_update` goes stale under a
+/// `max_staleness` slot limit (0 once the limit is reached)
+pub fn slots_until_stale(last_update: u64, current_slot: u64, max_staleness: u64) -> u64 {
+    max_staleness.saturating_sub(current_slot.saturating_sub(last_update))
+}
+
+/// Stored soft-stale threshold in slots, with 0 meaning MAX_STALENESS_SLOTS
+fn effective_soft_stale_slots(stored: u32) -> u64 {
+    if stored == 0 { MAX_STALENESS_SLOTS } else { stored as u64 }
+}
+
+/// (soft, hard) staleness limits in slots: oracle data older than `soft` quotes
+/// degraded at the penalty spread, older than `hard` is rejected. Without a hard
+/// limit the two coincide and there is no soft-stale band.
+pub fn stale_limits(ctx_data: &[u8]) -> Result<(u64, u64), ProgramError> {
+    if !has_extension(ctx_data) {
+        return Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS));
+    }
+    let soft = effective_soft_stale_slots(read_ctx_u32(ctx_data, SOFT_STALE_SLOTS_OFFSET)?);
+    let hard = (read_ctx_u32(ctx_data, HARD_STALE_SLOTS_OFFSET)? as u64).max(soft);
+    Ok((soft, hard))
+}
+
+/// Spread floor of a degraded quote: the configured penalty spread, or the max
+/// spread when none is set
+pub fn degraded_spread_floor(ctx_data: &[u8], max_spread: u32) -> Result<u32, ProgramError> {
+    if !has_extension(ctx_data) {
+        return Ok(max_spread);
+    }
+    let penalty = read_ctx_u32(ctx_data, PENALTY_SPREAD_OFFSET)?;
+    Ok(if penalty == 0 { max_spread } else { penalty })
+}
+
+/// Classify oracle data `age` slots old: Ok(false) fresh, Ok(true) soft-stale (quote
+/// degraded), OracleStale past the hard limit
+pub fn check_staleness(ctx_data: &[u8], age: u64) -> Result<bool, ProgramError> {
+    let (soft, hard) = stale_limits(ctx_data)?;
+    if age > hard {
+        return Err(YieldMatcherError::OracleStale.into());
+    }
+    Ok(age > soft)
+}
+
+/// Tag 0x06: Rotate the referenced oracle feeds to migrated accounts
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable)
+///   [2] New NcnYieldFeed account (owner + discriminator checked)
+///   [3] New NcnPerformanceFeed account (owner + discriminator checked)
+/// Data layout:
+///   [0]    tag (0x06)
+pub fn process_set_oracle_accounts(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let lp_pda = &accounts[0];
+    let ctx_account = &accounts[1];
+    let new_yield_feed = &accounts[2];
+    let new_perf_feed = &accounts[3];
+
+    validate_header(ctx_account, program_id)?;
+    verify_lp_pda_common(lp_pda, ctx_account, YIELD_MATCHER_MAGIC, "YIELD-MATCHER")?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if ctx_account.try_borrow_data()?[SETTLED_OFFSET] == 1 {
+        msg!("YIELD-MATCHER: Matcher is settled -- oracle feeds are frozen");
+        return Err(YieldMatcherError::MatcherSettled.into());
+    }
+
+    verify_feed_type(new_yield_feed, &NCN_YIELD_FEED_DISCRIMINATOR, "NcnYieldFeed")?;
+    verify_feed_type(new_perf_feed, &NCN_PERFORMANCE_FEED_DISCRIMINATOR, "NcnPerformanceFeed")?;
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    let old_yield_feed = Pubkey::new_from_array(
+        ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    let old_perf_feed = Pubkey::new_from_array(
+        ctx_data[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32].copy_from_slice(new_yield_feed.key.as_ref());
+    ctx_data[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32]
+        .copy_from_slice(new_perf_feed.key.as_ref());
+
+    msg!(
+        "ORACLE_ROTATED: yield_feed={}->{} perf_feed={}->{}",
+        old_yield_feed, new_yield_feed.key, old_perf_feed, new_perf_feed.key
+    );
+
+    Ok(())
+}
+
+/// Tag 0x07: Settle — terminate a wound-down market. Once settled, process_match
+/// rejects every quote; the last synced yield stays readable for reference.
+/// Accounts:
+///   [0] LP PDA (signer)
+///   [1] Matcher context account (writable)
+/// Data layout:
+///   [0]    tag (0x07)
+pub fn process_settle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let lp_pda = &accounts[0];
+    let ctx_account = &accounts[1];
+
+    validate_header(ctx_account, program_id)?;
+    verify_lp_pda_common(lp_pda, ctx_account, YIELD_MATCHER_MAGIC, "YIELD-MATCHER")?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    if ctx_data[SETTLED_OFFSET] == 1 {
+        msg!("YIELD-MATCHER: Already settled");
+        return Err(YieldMatcherError::MatcherSettled.into());
+    }
+    ctx_data[SETTLED_OFFSET] = 1;
+
+    let last_yield = i64::from_le_bytes(
+        ctx_data[CURRENT_YIELD_OFFSET..CURRENT_YIELD_OFFSET + 8]
+            .try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    msg!("SETTLED: last_yield_bps={}", last_yield);
+
+    Ok(())
+}
+
+/// Tag 0x0E: Snapshot the pricing params and yield mark into a new immutable account, so
+/// auditors can prove the matcher's configuration at a given slot
+/// Accounts:
+///   [0] Matcher context account (read)
+///   [1] Snapshot account (writable, PDA of [SNAPSHOT_SEED, matcher context, slot (u64 LE)])
+///   [2] Payer (signer, writable)
+///   [3] System program
+/// Data:
+///   [0]    tag (0x0E)
+/// Layout: see SNAPSHOT_SIZE in state.rs. The snapshot is owned by this program and no
+/// instruction writes to it after creation; one snapshot per matcher per slot.
+pub fn process_snapshot_matcher(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let ctx_account = &accounts[0];
+    let snapshot = &accounts[1];
+    let payer = &accounts[2];
+    let system = &accounts[3];
+
+    validate_header(ctx_account, program_id)?;
+    if !payer.is_signer {
+        msg!("YIELD-MATCHER: Snapshot payer must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !snapshot.is_writable {
+        msg!("YIELD-MATCHER: Snapshot account must be writable");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if *system.key != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let clock = Clock::get()?;
+    let slot_bytes = clock.slot.to_le_bytes();
+    let (expected, bump) =
+        Pubkey::find_program_address(&[SNAPSHOT_SEED, ctx_account.key.as_ref(), &slot_bytes], program_id);
+    if *snapshot.key != expected {
+        msg!("YIELD-MATCHER: Snapshot account is not the PDA for slot {}", clock.slot);
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let out = encode_snapshot(&ctx_account.try_borrow_data()?, ctx_account.key, clock.slot, clock.unix_timestamp)?;
+
+    // create_account fails if the PDA already exists, so a snapshot is never overwritten
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            snapshot.key,
+            Rent::get()?.minimum_balance(SNAPSHOT_SIZE),
+            SNAPSHOT_SIZE as u64,
+            program_id,
+        ),
+        &[payer.clone(), snapshot.clone(), system.clone()],
+        &[&[SNAPSHOT_SEED, ctx_account.key.as_ref(), &slot_bytes, &[bump]]],
+    )?;
+    snapshot.try_borrow_mut_data()?.copy_from_slice(&out);
+
+    msg!("SNAPSHOT: matcher={} slot={}", ctx_account.key, clock.slot);
+    Ok(())
+}
+
+/// Serialize a snapshot of the context's pricing params and mark (see SNAPSHOT_SIZE)
+pub fn encode_snapshot(
+    ctx_data: &[u8],
+    matcher: &Pubkey,
+    slot: u64,
+    unix_timestamp: i64,
+) -> Result<[u8; SNAPSHOT_SIZE], ProgramError> {
+    let mut out = [0u8; SNAPSHOT_SIZE];
+    out[0..8].copy_from_slice(&YIELD_SNAPSHOT_MAGIC.to_le_bytes());
+    out[8..40].copy_from_slice(matcher.as_ref());
+    out[40..48].copy_from_slice(&slot.to_le_bytes());
+    out[48..56].copy_from_slice(&unix_timestamp.to_le_bytes());
+    out[56..].copy_from_slice(&encode_pricing_params(ctx_data)?);
+    Ok(out)
+}
+
+/// Tag 0x0F: Read the unclaimed protocol fees and the fee rate (no state change)
+/// Accounts:
+///   [0] Matcher context account (read)
+/// Data:
+///   [0] tag (0x0F)
+/// Return data: see ACCRUED_FEES_RETURN_SIZE layout in state.rs
+pub fn process_get_accrued_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    validate_header(&accounts[0], program_id)?;
+    let ctx_data = accounts[0].try_borrow_data()?;
+
+    let out = encode_accrued_fees(&ctx_data)?;
+    msg!(
+        "ACCRUED_FEES: fees_e6={} protocol_fee_bps={}",
+        read_accrued_fees(&ctx_data)?,
+        read_protocol_fee_bps(&ctx_data)?
+    );
+    set_return_data(&out);
+
+    Ok(())
+}
+
+/// Encode the GetAccruedFees return data (see ACCRUED_FEES_RETURN_SIZE)
+pub fn encode_accrued_fees(ctx_data: &[u8]) -> Result<[u8; ACCRUED_FEES_RETURN_SIZE], ProgramError> {
+    let mut out = [0u8; ACCRUED_FEES_RETURN_SIZE];
+    out[0..8].copy_from_slice(&read_accrued_fees(ctx_data)?.to_le_bytes());
+    out[8..10].copy_from_slice(&read_protocol_fee_bps(ctx_data)?.to_le_bytes());
+    Ok(out)
+}
+
+/// Tag 0x10: Claim the accrued protocol fees. The matcher holds no funds, so claiming
+/// zeroes the counter and reports the amount for the caller to settle. Allowed after
+/// settlement, since fees accrued before it are still owed. Only the protocol fee
+/// authority stored at init can claim; the LP cannot.
+/// Accounts:
+///   [0] Protocol fee authority (signer)
+///   [1] Matcher context account (writable)
+/// Data:
+///   [0] tag (0x10)
+/// Return data: [0..8] claimed fees_e6 (u64 LE)
+pub fn process_claim_protocol_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let fee_authority = &accounts[0];
+    let ctx_account = &accounts[1];
+
+    validate_header(ctx_account, program_id)?;
+    if !ctx_account.is_writable {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut ctx_data = ctx_account.try_borrow_mut_data()?;
+    verify_fee_authority(fee_authority, &ctx_data)?;
+    let claimed = claim_protocol_fees(&mut ctx_data)?;
+
+    msg!("FEES_CLAIMED: fees_e6={}", claimed);
+    set_return_data(&claimed.to_le_bytes());
+
+    Ok(())
+}
+
+/// Check that `authority` signed and is the protocol fee authority stored at init or
+/// migration. A version-1 context has no authority, so nobody can claim until it migrates.
+pub fn verify_fee_authority(authority: &AccountInfo, ctx_data: &[u8]) -> ProgramResult {
+    if !authority.is_signer {
+        msg!("YIELD-MATCHER: Protocol fee authority must sign");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !has_extension(ctx_data) {
+        msg!("YIELD-MATCHER: Version-1 context has no protocol fee authority -- migrate with tag 0x11");
+        return Err(YieldMatcherError::FeeAuthorityMismatch.into());
+    }
+    let stored = Pubkey::new_from_array(
+        ctx_data[PROTOCOL_FEE_AUTHORITY_OFFSET..PROTOCOL_FEE_AUTHORITY_OFFSET + 32]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    );
+    if *authority.key != stored {
+        msg!("YIELD-MATCHER: {} is not the protocol fee authority {}", authority.key, stored);
+        return Err(YieldMatcherError::FeeAuthorityMismatch.into());
+    }
+    Ok(())
+}
+
+/// Zero the accrued protocol fees, returning what was accrued
+pub fn claim_protocol_fees(ctx_data: &mut [u8]) -> Result<u64, ProgramError> {
+    let claimed = read_accrued_fees(ctx_data)?;
+    ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8].fill(0);
+    Ok(claimed)
+}
+
+/// Check that a feed account is owned by ncn-oracle and carries the expected discriminator
+pub fn verify_feed_type(feed: &AccountInfo, discriminator: &[u8; 8], name: &str) -> ProgramResult {
+    if *feed.owner != NCN_ORACLE_PROGRAM_ID
+        || feed.try_borrow_data()?.get(..8) != Some(&discriminator[..])
+    {
+        msg!("YIELD-MATCHER: Account is not an {}", name);
+        return Err(YieldMatcherError::OracleAccountMismatch.into());
+    }
+    Ok(())
+}
+
+/// Read current_apy_bps from raw NcnYieldFeed account data (discriminator checked)
+pub fn read_feed_current_apy_bps(feed_data: &[u8]) -> Result<u64, ProgramError> {
+    if feed_data.get(..8) != Some(&NCN_YIELD_FEED_DISCRIMINATOR[..]) {
+        return Err(YieldMatcherError::OracleAccountMismatch.into());
+    }
+    let apy = feed_data
+        .get(YIELD_FEED_CURRENT_APY_OFFSET..YIELD_FEED_CURRENT_APY_OFFSET + 8)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    Ok(u64::from_le_bytes(apy.try_into().map_err(|_| ProgramError::InvalidAccountData)?))
+}
+
+/// Offset of the first field after the yield history in raw NcnYieldFeed account data
+/// (discriminator checked; see YIELD_FEED_* layout)
+fn yield_feed_after_history_at(feed_data: &[u8]) -> Result<usize, ProgramError> {
+    if feed_data.get(..8) != Some(&NCN_YIELD_FEED_DISCRIMINATOR[..]) {
+        return Err(YieldMatcherError::OracleAccountMismatch.into());
+    }
+    let history_len = feed_data
+        .get(YIELD_FEED_HISTORY_LEN_OFFSET..YIELD_FEED_HISTORY_LEN_OFFSET + 4)
+        .and_then(|b| b.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)? as usize;
+    if history_len > YIELD_FEED_MAX_HISTORY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(YIELD_FEED_HISTORY_LEN_OFFSET + 4 + history_len * YIELD_FEED_SAMPLE_SIZE)
+}
+
+/// Read last_updated (unix seconds) from raw NcnYieldFeed account data (discriminator checked)
+pub fn read_yield_feed_last_updated(feed_data: &[u8]) -> Result<i64, ProgramError> {
+    let at = yield_feed_after_history_at(feed_data)? + YIELD_FEED_LAST_UPDATED_AFTER_HISTORY;
+    let bytes = feed_data.get(at..at + 8).ok_or(ProgramError::InvalidAccountData)?;
+    Ok(i64::from_le_bytes(bytes.try_into().map_err(|_| ProgramError::InvalidAccountData)?))
+}
+
+/// Read is_active from raw NcnYieldFeed account data (discriminator checked)
+pub fn read_yield_feed_is_active(feed_data: &[u8]) -> Result<bool, ProgramError> {
+    let at = yield_feed_after_history_at(feed_data)? + YIELD_FEED_IS_ACTIVE_AFTER_HISTORY;
+    match feed_data.get(at) {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Relative deviation of the stored mark from the live mark, in bps of the live mark.
+/// A zero live mark yields 0 if the stored mark is also 0, otherwise u64::MAX.
+pub fn mark_deviation_bps(stored_mark: u64, live_mark: u64) -> u64 {
+    if live_mark == 0 {
+        return if stored_mark == 0 { 0 } else { u64::MAX };
+    }
+    let deviation = (stored_mark.abs_diff(live_mark) as u128) * BPS_SCALE as u128 / live_mark as u128;
+    u64::try_from(deviation).unwrap_or(u64::MAX)
+}
+
+/// Apply the spread floor, then the max cap (the cap wins if misconfigured below the floor)
+pub fn clamp_spread(spread: u64, min_spread: u32, max_spread: u32) -> u64 {
+    std::cmp::min(std::cmp::max(spread, min_spread as u64), max_spread as u64)
+}
+
+/// Read an optional trailing u32 extension field from instruction data (0 when absent)
+fn read_opt_u32(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4)
+        .and_then(|b| b.try_into().ok())
+        .map(u32::from_le_bytes)
+        .unwrap_or(0)
+}
+
+/// Blend weights must either be all zero (spot only) or sum to exactly 10000 bps
+pub fn validate_blend_weights(weights: [u32; 3]) -> ProgramResult {
+    let total: u64 = weights.iter().map(|&w| w as u64).sum();
+    if total != 0 && total != BPS_SCALE {
+        msg!("YIELD-MATCHER: Blend weights {:?} sum to {} bps (expected 10000)", weights, total);
+        return Err(YieldMatcherError::InvalidBlendWeights.into());
+    }
+    Ok(())
+}
+
+/// Read the stored spot/7d/30d mark blend weights (bps)
+fn read_blend_weights(ctx_data: &[u8]) -> Result<[u32; 3], ProgramError> {
+    let mut weights = [0u32; 3];
+    for (i, w) in weights.iter_mut().enumerate() {
+        let offset = BLEND_WEIGHTS_OFFSET + i * 4;
+        *w = u32::from_le_bytes(
+            ctx_data[offset..offset + 4].try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+        );
+    }
+    Ok(weights)
+}
+
+/// Unclaimed protocol fees (e6 notional)
+fn read_accrued_fees(ctx_data: &[u8]) -> Result<u64, ProgramError> {
+    Ok(u64::from_le_bytes(
+        ctx_data[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
+
+/// Multiplier (percent) applied to yield_vol_spread: `regime_multiplier` under regime
+/// scaling, or the synced yield variance's under continuous scaling. A context whose
+/// last sync reported no variance prices at the regime either way.
+pub fn effective_spread_multiplier(ctx_data: &[u8], regime_multiplier: u64) -> Result<u64, ProgramError> {
+    let variance_bps = u16::from_le_bytes(
+        ctx_data
+            .get(YIELD_VARIANCE_OFFSET..YIELD_VARIANCE_OFFSET + 2)
+            .and_then(|b| b.try_into().ok())
+            .ok_or(ProgramError::InvalidAccountData)?,
+    );
+    if ctx_data[SPREAD_SCALING_OFFSET] != SPREAD_SCALING_VARIANCE || variance_bps == 0 {
+        return Ok(regime_multiplier);
+    }
+    Ok(variance_spread_multiplier(variance_bps as u64))
+}
+
+/// Accrued protocol fees after adding `fee`. Saturates at u64::MAX rather than failing,
+/// so an unclaimed balance can never halt matching; the fee authority claims to reset it.
+fn accrue_protocol_fee(ctx_data: &[u8], fee: u64) -> Result<u64, ProgramError> {
+    Ok(read_accrued_fees(ctx_data)?.saturating_add(fee))
+}
+
+fn read_protocol_fee_bps(ctx_data: &[u8]) -> Result<u16, ProgramError> {
+    Ok(u16::from_le_bytes(
+        ctx_data[PROTOCOL_FEE_BPS_OFFSET..PROTOCOL_FEE_BPS_OFFSET + 2]
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?,
+    ))
+}
+
+/// Read the optional fill size from match instruction data (0 when absent)
+fn read_fill_size(data: &[u8]) -> Result<u128, ProgramError> {
+    if data.len() < 17 {
+        return Ok(0);
+    }
+    Ok(u128::from_le_bytes(
+        data[1..17].try_into().map_err(|_| ProgramError::InvalidInstructionData)?,
+    ))
+}
+
+/// Read the optional taker price bound from match instruction data (0 when absent)
+fn read_max_acceptable_price(data: &[u8]) -> i64 {
+    data.get(18..26)
+        .and_then(|b| b.try_into().ok())
+        .map(i64::from_le_bytes)
+        .unwrap_or(0)
+}
+
+/// Reject a fill priced above the taker's bound (0 = no bound; a negative bound is a
+/// real limit on a negative-yield quote)
+pub fn check_slippage(exec_price: i64, max_acceptable_price: i64) -> ProgramResult {
+    if max_acceptable_price != 0 && exec_price > max_acceptable_price {
+        msg!("YIELD-MATCHER: Exec price {} exceeds taker max {}", exec_price, max_acceptable_price);
+        return Err(YieldMatcherError::SlippageExceeded.into());
+    }
+    Ok(())
+}
+
+/// Impact pricing divides by the liquidity notional: with impact enabled a zero notional
+/// would quote flat, so it is an error wherever a quote is priced (init, match, quote)
+pub fn check_liquidity_config(impact_k: u32, liquidity: u128) -> ProgramResult {
+    if impact_k > 0 && liquidity == 0 {
+        msg!("YIELD-MATCHER: Impact enabled (k={}) with zero liquidity notional", impact_k);
+        return Err(YieldMatcherError::InvalidLiquidityConfig.into());
+    }
+    Ok(())
+}
+
+/// Whether fills draw down the liquidity notional: a context initialized with one, or an
+/// impact-priced context from before LIQUIDITY_CAPPED_OFFSET was set at init
+pub fn liquidity_capped(ctx_data: &[u8]) -> Result<bool, ProgramError> {
+    Ok(ctx_data.get(LIQUIDITY_CAPPED_OFFSET).copied() == Some(1) || read_ctx_u32(ctx_data, IMPACT_K_OFFSET)? > 0)
+}
+
+/// Size actually filled against `available` liquidity: the full request when it fits,
+/// otherwise what remains if partial fills are allowed, else an error
+pub fn resolve_fill(requested: u128, available: u128, partial_fill_allowed: bool) -> Result<u128, ProgramError> {
+    if requested <= available {
+        Ok(requested)
+    } else if partial_fill_allowed {
+        Ok(available)
+    } else {
+        msg!("YIELD-MATCHER: Fill {} exceeds remaining liquidity {}", requested, available);
+        Err(YieldMatcherError::InsufficientLiquidity.into())
+    }
+}
+
+/// Fill-size impact in bps. `impact_k` is the impact charged when the fill equals
+/// the configured liquidity notional; both models agree at that point.
+///   Linear: impact = k * fill / liquidity
+///   Sqrt:   impact = k * sqrt(fill / liquidity)   (Almgren-style square-root impact)
+/// Returns 0 if impact is disabled (k == 0), the fill is empty, or no liquidity is configured.
+pub fn compute_impact_bps(model: u8, impact_k: u32, fill_size: u128, liquidity: u128) -> u64 {
+    if impact_k == 0 || fill_size == 0 {
+        return 0;
+    }
+    let impact = if model == IMPACT_MODEL_SQRT {
+        // sqrt(fill / liquidity) in e6 = isqrt(fill * 1e12 / liquidity)
+        let e12 = E6_SCALE as u128 * E6_SCALE as u128;
+        let ratio_e12 = match fill_size.checked_mul(e12) {
+            Some(v) => v.checked_div(liquidity),
+            None => fill_size.checked_div(liquidity).map(|r| r.saturating_mul(e12)),
+        };
+        ratio_e12.map(|r| (impact_k as u128).saturating_mul(isqrt(r)) / E6_SCALE as u128)
+    } else {
+        match (impact_k as u128).checked_mul(fill_size) {
+            Some(v) => v.checked_div(liquidity),
+            None => fill_size.checked_div(liquidity).map(|r| (impact_k as u128).saturating_mul(r)),
+        }
+    };
+    // Zero liquidity (rejected by check_liquidity_config when impact is enabled) prices no impact rather than panicking
+    impact.map_or(0, |v| u64::try_from(v).unwrap_or(u64::MAX))
+}
+
+/// Convert a yield in bps to the e6 mark scale (bps * YIELD_MARK_SCALE). None on overflow.
+pub fn bps_to_e6(bps: u64) -> Option<u64> {
+    bps.checked_mul(YIELD_MARK_SCALE)
+}
+
+/// Convert an e6-scaled mark back to whole bps (truncating any sub-bps remainder)
+pub fn e6_to_bps(e6: u64) -> u64 {
+    e6 / YIELD_MARK_SCALE
+}
+
+/// Signed counterpart of bps_to_e6 for net yields that can go negative. None on overflow.
+pub fn signed_bps_to_e6(bps: i64) -> Option<i64> {
+    bps.checked_mul(YIELD_MARK_SCALE as i64)
+}
+
+/// Unit factor (YIELD_MARK_UNIT_RATIOS) by which `yield_mark` misses `expected`, signed
+/// negative when the mark is too small
+fn yield_mark_unit_ratio(expected: i64, yield_mark: i64) -> Option<i64> {
+    let (expected, yield_mark) = (expected as i128, yield_mark as i128);
+    if expected == 0 {
+        return None;
+    }
+    YIELD_MARK_UNIT_RATIOS.iter().find_map(|&ratio| {
+        let ratio_wide = ratio as i128;
+        if yield_mark * ratio_wide == expected {
+            Some(-ratio)
+        } else if yield_mark == expected * ratio_wide {
+            Some(ratio)
+        } else {
+            None
+        }
+    })
+}
+
+/// Check that a keeper-supplied yield mark equals `current_yield_bps * YIELD_MARK_SCALE`.
+/// Both may be negative. A mark off by a unit factor (the keeper mixing bps and e6) fails
+/// with YieldMarkUnitMismatch rather than the generic mismatch. Returns the derived mark.
+pub fn validate_yield_mark(current_yield_bps: i64, yield_mark: i64) -> Result<i64, ProgramError> {
+    let expected = signed_bps_to_e6(current_yield_bps).ok_or(YieldMatcherError::ArithmeticOverflow)?;
+    if let Some(ratio) = yield_mark_unit_ratio(expected, yield_mark) {
+        msg!(
+            "YIELD-MATCHER: Yield mark {} is {}x {} the expected {} -- bps/e6 unit mismatch",
+            yield_mark,
+            ratio.unsigned_abs(),
+            if ratio < 0 { "below" } else { "above" },
+            expected
+        );
+        return Err(YieldMatcherError::YieldMarkUnitMismatch.into());
+    }
+    if yield_mark != expected {
+        msg!("YIELD-MATCHER: Yield mark {} inconsistent with yield {} bps (expected {})", yield_mark, current_yield_bps, expected);
+        return Err(YieldMatcherError::YieldMarkMismatch.into());
+    }
+    Ok(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::YieldMatcherError;
+    use crate::pricing::{
+        blended_yield_mark, compute_bid_ask_lp_favorable, protocol_fee_e6, transition_spread_multiplier,
+        variance_spread_multiplier, yield_exec_price,
+    };
+    use crate::state::*;
+    use super::{bps_to_e6, check_oracle_sequence, check_slippage, clamp_spread, compute_impact_bps, e6_to_bps, isqrt, mark_deviation_bps, process_heartbeat,
+        read_feed_current_apy_bps, read_yield_feed_is_active, process_match, process_oracle_sync, process_set_oracle_accounts, process_settle, process_init_and_sync, slots_until_stale, resolve_fill,
+        validate_blend_weights, validate_yield_mark, encode_regime_info, encode_pricing_params, encode_oracle_config, validate_header,
+        process_quote_at_mark, quote_at_mark, check_liquidity_config, liquidity_capped, read_u128, write_u128, encode_snapshot, process_snapshot_matcher,
+        encode_accrued_fees, claim_protocol_fees, accrue_protocol_fee, process_claim_protocol_fees, verify_fee_authority, parse_oracle_sync_payload,
+        effective_spread_multiplier, stale_limits, check_staleness, degraded_spread_floor, read_yield_feed_last_updated, check_heartbeat_feed_age,
+        check_sequence_within_feed, has_extension, parse_extension, write_extension, process_migrate, ContextExtension};
+    use matcher_utils::{format_match_log, testing::{run_lp_signed, MockContext}};
+    use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+    use solana_system_interface::program as system_program;
+
+    // Helper: quote through the same pricing core as process_match (no floor, no impact)
+    fn calc_exec_price(
+        base_spread: u32,
+        yield_vol_spread: u32,
+        max_spread: u32,
+        regime: YieldRegime,
+        yield_mark: i64,
+    ) -> i64 {
+        yield_exec_price(yield_mark, regime.spread_multiplier(), base_spread, yield_vol_spread, 0, max_spread, 0).unwrap().0
+    }
+
+    /// Shared context builder (header, put, version_1, build, run); the yield defaults
+    /// and setters come from YieldContext
+    type MockContextBuilder = MockContext;
+
+    /// Builds a valid LP-owned yield context (spreads 20/30/200, Normal regime, synced at
+    /// 8% APY, unsettled) so handler tests only spell out the fields they exercise
+    trait YieldContext: Sized {
+        fn new() -> Self;
+        fn for_lp(lp_key: Pubkey) -> Self;
+        fn spreads(self, base: u32, yield_vol: u32, max: u32) -> Self;
+        /// Synced spot yield; the mark is the bps yield scaled by 1e6
+        fn yield_bps(self, yield_bps: i64) -> Self;
+        fn liquidity(self, impact_k: u32, liquidity: u128) -> Self;
+    }
+
+    impl YieldContext for MockContext {
+        fn new() -> Self {
+            Self::for_lp(Pubkey::new_unique())
+        }
+
+        fn for_lp(lp_key: Pubkey) -> Self {
+            let mut ctx = Self::with_header(YIELD_MATCHER_MAGIC, MODE_ALL_NCN, lp_key);
+            write_extension(&mut ctx.data, &ContextExtension::default());
+            ctx.spreads(20, 30, 200)
+                .put(YIELD_REGIME_OFFSET, &[YieldRegime::Normal as u8])
+                .yield_bps(800)
+        }
+
+        fn spreads(self, base: u32, yield_vol: u32, max: u32) -> Self {
+            self.put(BASE_SPREAD_OFFSET, &base.to_le_bytes())
+                .put(YIELD_VOL_SPREAD_OFFSET, &yield_vol.to_le_bytes())
+                .put(MAX_SPREAD_OFFSET, &max.to_le_bytes())
+        }
+
+        fn yield_bps(self, yield_bps: i64) -> Self {
+            self.put(CURRENT_YIELD_OFFSET, &yield_bps.to_le_bytes())
+                .put(YIELD_MARK_PRICE_OFFSET, &(yield_bps * 1_000_000).to_le_bytes())
+                .put(HAS_SYNCED_OFFSET, &[1])
+        }
+
+        fn liquidity(self, impact_k: u32, liquidity: u128) -> Self {
+            self.put(IMPACT_K_OFFSET, &impact_k.to_le_bytes())
+                .put(LIQUIDITY_OFFSET, &liquidity.to_le_bytes())
+                .put(LIQUIDITY_CAPPED_OFFSET, &[(liquidity > 0) as u8])
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 1. Normal regime — typical restaking yield of ~8% (800 bps, mark = 800_000_000)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_normal_regime_pricing() {
+        let price = calc_exec_price(20, 30, 200, YieldRegime::Normal, 800_000_000);
+        // adjusted_yield_vol = 30 * 100 / 100 = 30
+        // total_spread = min(20 + 30, 200) = 50
+        // exec_price   = 800_000_000 * 10050 / 10000 = 804_000_000
+        assert_eq!(price, 804_000_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 2. Extreme regime — slashing event, yield instability
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_extreme_regime_pricing() {
+        let price = calc_exec_price(20, 30, 200, YieldRegime::Extreme, 800_000_000);
+        // adjusted_yield_vol = 30 * 250 / 100 = 75
+        // total_spread = min(20 + 75, 200) = 95
+        // exec_price   = 800_000_000 * 10095 / 10000 = 807_600_000
+        assert_eq!(price, 807_600_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 3. VeryLow regime — stable staking yield
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_very_low_regime_pricing() {
+        let price = calc_exec_price(20, 30, 200, YieldRegime::VeryLow, 800_000_000);
+        // adjusted_yield_vol = 30 * 50 / 100 = 15
+        // total_spread = min(20 + 15, 200) = 35
+        // exec_price   = 800_000_000 * 10035 / 10000 = 802_800_000
+        assert_eq!(price, 802_800_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 4. Spread capping (total_spread exceeds max_spread)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_spread_capping() {
+        let price = calc_exec_price(100, 200, 150, YieldRegime::Extreme, 800_000_000);
+        // adjusted_yield_vol = 200 * 250 / 100 = 500
+        // total_spread = min(100 + 500, 150) = 150
+        // exec_price   = 800_000_000 * 10150 / 10000 = 812_000_000
+        assert_eq!(price, 812_000_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 5. YieldRegime::from_u8
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_regime_from_u8() {
+        assert_eq!(YieldRegime::from_u8(0), YieldRegime::VeryLow);
+        assert_eq!(YieldRegime::from_u8(1), YieldRegime::Low);
+        assert_eq!(YieldRegime::from_u8(2), YieldRegime::Normal);
+        assert_eq!(YieldRegime::from_u8(3), YieldRegime::High);
+        assert_eq!(YieldRegime::from_u8(4), YieldRegime::Extreme);
+        // Out-of-range defaults to Normal
+        assert_eq!(YieldRegime::from_u8(5), YieldRegime::Normal);
+    }
+
+    // -----------------------------------------------------------------------
+    // 6. YieldRegime::spread_multiplier
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_regime_spread_multiplier() {
+        assert_eq!(YieldRegime::VeryLow.spread_multiplier(), 50);
+        assert_eq!(YieldRegime::Low.spread_multiplier(), 75);
+        assert_eq!(YieldRegime::Normal.spread_multiplier(), 100);
+        assert_eq!(YieldRegime::High.spread_multiplier(), 150);
+        assert_eq!(YieldRegime::Extreme.spread_multiplier(), 250);
+    }
+
+    // -----------------------------------------------------------------------
+    // 7. Low yield scenario (e.g., 2% APY = 200 bps, mark = 200_000_000)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_low_yield_pricing() {
+        let price = calc_exec_price(15, 25, 300, YieldRegime::Low, 200_000_000);
+        // adjusted_yield_vol = 25 * 75 / 100 = 18 (truncated)
+        // total_spread = min(15 + 18, 300) = 33
+        // exec_price   = 200_000_000 * 10033 / 10000 = 200_660_000
+        assert_eq!(price, 200_660_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 8. High yield scenario (e.g., 20% APY = 2000 bps, mark = 2_000_000_000)
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_high_yield_high_regime() {
+        let price = calc_exec_price(20, 40, 200, YieldRegime::High, 2_000_000_000);
+        // adjusted_yield_vol = 40 * 150 / 100 = 60
+        // total_spread = min(20 + 60, 200) = 80
+        // exec_price   = 2_000_000_000 * 10080 / 10000 = 2_016_000_000
+        assert_eq!(price, 2_016_000_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 9. Zero spread scenario
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_zero_spread() {
+        let price = calc_exec_price(0, 0, 200, YieldRegime::Normal, 500_000_000);
+        // total_spread = 0
+        // exec_price = 500_000_000
+        assert_eq!(price, 500_000_000);
+    }
+
+    // -----------------------------------------------------------------------
+    // 10. Yield mark must match current yield * 1e6
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_consistent_yield_mark_accepted() {
+        assert_eq!(validate_yield_mark(800, 800_000_000), Ok(800_000_000));
+        assert_eq!(validate_yield_mark(0, 0), Ok(0));
+    }
+
+    #[test]
+    fn test_inconsistent_yield_mark_rejected() {
+        let err: ProgramError = YieldMatcherError::YieldMarkMismatch.into();
+        assert_eq!(validate_yield_mark(800, 1_200_000_000), Err(err.clone()));
+        assert_eq!(validate_yield_mark(800, 80_000_000), Err(err));
+    }
+
+    #[test]
+    fn test_yield_mark_overflow_rejected() {
+        let err: ProgramError = YieldMatcherError::ArithmeticOverflow.into();
+        assert_eq!(validate_yield_mark(i64::MAX, 0), Err(err));
+    }
+
+    // -----------------------------------------------------------------------
+    // 11. Impact models — linear vs square-root at several fill sizes
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_linear_impact() {
+        let liq = 10_000_000_000u128; // 10k notional (e6)
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 40, 0, liq), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 40, liq / 10, liq), 4);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 40, liq / 2, liq), 20);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 40, liq, liq), 40);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 40, liq * 9, liq), 360);
+    }
+
+    #[test]
+    fn test_sqrt_impact() {
+        let liq = 10_000_000_000u128;
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 40, 0, liq), 0);
+        // sqrt(0.1) = 0.316227 -> 12 bps (truncated)
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 40, liq / 10, liq), 12);
+        // sqrt(0.5) = 0.707106 -> 28 bps
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 40, liq / 2, liq), 28);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 40, liq, liq), 40);
+        // sqrt(9) = 3 -> 120 bps
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 40, liq * 9, liq), 120);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(8), 2);
+        assert_eq!(isqrt(9), 3);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        // Either side of a square rounds down, up to the largest square in u128
+        for r in [1u128 << 32, u64::MAX as u128] {
+            assert_eq!(isqrt(r * r - 1), r - 1);
+            assert_eq!(isqrt(r * r + 1), r);
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 12. Cross-program confusion — an uptime matcher context is rejected
+    // -----------------------------------------------------------------------
+    const UPTIME_MATCHER_MAGIC: u64 = 0x4e43_4e55_4d41_5443; // "NCNUMATC"
+
+    fn uptime_ctx() -> Vec<u8> {
+        MockContext::with_header(UPTIME_MATCHER_MAGIC, 0, Pubkey::new_unique()).build()
+    }
+
+    #[test]
+    fn test_match_rejects_uptime_context() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lp_lamports, mut ctx_lamports) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut ctx_data = uptime_ctx();
+        let accounts = [
+            AccountInfo::new(&lp_key, true, false, &mut lp_lamports, &mut lp_data, &program_id, false, 0),
+            AccountInfo::new(&ctx_key, false, true, &mut ctx_lamports, &mut ctx_data, &program_id, false, 0),
+        ];
+        assert_eq!(
+            process_match(&program_id, &accounts, &[0x00]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn test_oracle_sync_rejects_uptime_context() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, yield_key, perf_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let mut ctx_data = uptime_ctx();
+        let (mut d1, mut d2) = ([0u8; 0], [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0),
+            AccountInfo::new(&yield_key, false, false, &mut l1, &mut d1, &program_id, false, 0),
+            AccountInfo::new(&perf_key, false, false, &mut l2, &mut d2, &program_id, false, 0),
+        ];
+        assert_eq!(
+            process_oracle_sync(&program_id, &accounts, &[0x03; 42]),
+            Err(ProgramError::UninitializedAccount)
+        );
+        assert_eq!(
+            process_heartbeat(&program_id, &accounts, &[0x04]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn test_set_oracle_accounts_rejects_uptime_context() {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+        let mut ctx_data = uptime_ctx();
+        let (mut d0, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&keys[0], true, false, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&keys[1], false, true, &mut l1, &mut ctx_data, &program_id, false, 0),
+            AccountInfo::new(&keys[2], false, false, &mut l2, &mut d2, &program_id, false, 0),
+            AccountInfo::new(&keys[3], false, false, &mut l3, &mut d3, &program_id, false, 0),
+        ];
+        assert_eq!(
+            process_set_oracle_accounts(&program_id, &accounts, &[0x06]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 13. Minimum spread floor
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_min_spread_floor() {
+        // VeryLow regime: base 0 + 10 * 0.5 = 5 bps, floor 20 applies
+        let adjusted = 10u64 * YieldRegime::VeryLow.spread_multiplier() / 100;
+        let spread = clamp_spread(adjusted, 20, 200);
+        assert_eq!(spread, 20);
+        assert_eq!(
+            yield_exec_price(500_000_000, YieldRegime::VeryLow.spread_multiplier(), 0, 10, 20, 200, 0),
+            Ok((501_000_000, 20))
+        );
+    }
+
+    #[test]
+    fn test_min_spread_floor_inactive_above_floor_and_under_cap() {
+        assert_eq!(clamp_spread(95, 20, 200), 95);
+        assert_eq!(clamp_spread(600, 20, 150), 150);
+        assert_eq!(clamp_spread(0, 0, 200), 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // 14. bps <-> e6 conversions
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_bps_e6_conversions() {
+        assert_eq!(bps_to_e6(0), Some(0));
+        assert_eq!(bps_to_e6(800), Some(800_000_000));
+        assert_eq!(e6_to_bps(800_000_000), 800);
+        // Sub-bps remainder truncates
+        assert_eq!(e6_to_bps(800_999_999), 800);
+        // Round trip for every representable bps value near the top of the range
+        let max_bps = u64::MAX / YIELD_MARK_SCALE;
+        assert_eq!(e6_to_bps(bps_to_e6(max_bps).unwrap()), max_bps);
+        assert_eq!(bps_to_e6(max_bps + 1), None);
+    }
+
+    // -----------------------------------------------------------------------
+    // 15. Zero liquidity never divides by zero
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_impact_zero_liquidity_is_zero() {
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, 100, 1_000_000, 0), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, 100, 1_000_000, 0), 0);
+        // Overflowing fill sizes take the fallback path and still guard the division
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_LINEAR, u32::MAX, u128::MAX, 0), 0);
+        assert_eq!(compute_impact_bps(IMPACT_MODEL_SQRT, u32::MAX, u128::MAX, 0), 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // 16. Heartbeat is bound to the context's oracle feeds
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_heartbeat_rejects_wrong_feeds() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, yield_key, perf_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let mut ctx_data = MockContextBuilder::new().put(NCN_YIELD_FEED_OFFSET, yield_key.as_ref()).build();
+        let (mut d1, mut d2) = ([0u8; 0], [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0),
+            AccountInfo::new(&yield_key, false, false, &mut l1, &mut d1, &program_id, false, 0),
+            AccountInfo::new(&perf_key, false, false, &mut l2, &mut d2, &program_id, false, 0),
+        ];
+        // Yield feed matches, performance feed does not
+        assert_eq!(
+            process_heartbeat(&program_id, &accounts, &[0x04]),
+            Err(YieldMatcherError::OracleAccountMismatch.into())
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 17. Mark deviation against the live oracle
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_mark_deviation_bps() {
+        // Stored 800 bps vs live 808 bps -> ~0.99% stale
+        assert_eq!(mark_deviation_bps(800_000_000, 808_000_000), 99);
+        assert_eq!(mark_deviation_bps(808_000_000, 800_000_000), 100);
+        assert_eq!(mark_deviation_bps(0, 0), 0);
+        assert_eq!(mark_deviation_bps(1, 0), u64::MAX);
+    }
+
+    #[test]
+    fn test_read_feed_current_apy_bps() {
+        let mut feed = vec![0u8; YIELD_FEED_CURRENT_APY_OFFSET + 8];
+        feed[..8].copy_from_slice(&NCN_YIELD_FEED_DISCRIMINATOR);
+        feed[YIELD_FEED_CURRENT_APY_OFFSET..].copy_from_slice(&850u64.to_le_bytes());
+        assert_eq!(read_feed_current_apy_bps(&feed), Ok(850));
+
+        assert_eq!(read_feed_current_apy_bps(&feed[..feed.len() - 1]), Err(ProgramError::InvalidAccountData));
+        feed[0] ^= 0xff;
+        assert_eq!(
+            read_feed_current_apy_bps(&feed),
+            Err(YieldMatcherError::OracleAccountMismatch.into())
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 18. Partial fills against remaining liquidity
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_resolve_fill_within_liquidity() {
+        assert_eq!(resolve_fill(0, 0, false), Ok(0));
+        assert_eq!(resolve_fill(400, 1_000, false), Ok(400));
+        assert_eq!(resolve_fill(1_000, 1_000, false), Ok(1_000));
+    }
+
+    #[test]
+    fn test_resolve_fill_oversized() {
+        assert_eq!(
+            resolve_fill(1_500, 1_000, false),
+            Err(crate::errors::YieldMatcherError::InsufficientLiquidity.into())
+        );
+        // Partial fill takes what remains; nothing left means a zero fill
+        assert_eq!(resolve_fill(1_500, 1_000, true), Ok(1_000));
+        assert_eq!(resolve_fill(1_500, 0, true), Ok(0));
+    }
+
+    // -----------------------------------------------------------------------
+    // 19. Reserved context tail stays zero
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_reserved_tail_after_allocated_fields() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[..RESERVED_OFFSET].fill(0xFF);
+        assert_reserved_zero(&ctx);
+    }
+
+    #[test]
+    #[should_panic(expected = "reserved context bytes")]
+    fn test_reserved_tail_write_detected() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[CTX_V2_SIZE - 1] = 1;
+        assert_reserved_zero(&ctx);
+    }
+
+    // -----------------------------------------------------------------------
+    // 20. Oracle sync replay protection
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_oracle_sequence_must_increase() {
+        assert_eq!(check_oracle_sequence(0, 1), Ok(()));
+        assert_eq!(check_oracle_sequence(41, 42), Ok(()));
+        // Replayed (equal) and late (older) syncs are rejected
+        assert_eq!(check_oracle_sequence(42, 42), Err(YieldMatcherError::OracleSequenceRegressed.into()));
+        assert_eq!(check_oracle_sequence(42, 7), Err(YieldMatcherError::OracleSequenceRegressed.into()));
+        // A fresh context (sequence 0) rejects a zero sequence
+        assert_eq!(check_oracle_sequence(0, 0), Err(YieldMatcherError::OracleSequenceRegressed.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 21. Oracle feed rotation
+    // -----------------------------------------------------------------------
+    /// Rotate an LP-signed context to feeds with the given owner and discriminators;
+    /// returns the result and the stored (yield, performance) feed keys afterwards
+    fn rotate_feeds(owner: &Pubkey, yield_disc: [u8; 8], perf_disc: [u8; 8]) -> (ProgramResult, [Pubkey; 2], [Pubkey; 2]) {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let old = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let new = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key)
+            .put(NCN_YIELD_FEED_OFFSET, old[0].as_ref())
+            .put(NCN_PERFORMANCE_FEED_OFFSET, old[1].as_ref())
+            .build();
+        let (mut yield_data, mut perf_data) = (vec![0u8; 80], vec![0u8; 80]);
+        yield_data[..8].copy_from_slice(&yield_disc);
+        perf_data[..8].copy_from_slice(&perf_disc);
+        let result = {
+            let accounts = [
+                AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0),
+                AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0),
+                AccountInfo::new(&new[0], false, false, &mut l2, &mut yield_data, owner, false, 0),
+                AccountInfo::new(&new[1], false, false, &mut l3, &mut perf_data, owner, false, 0),
+            ];
+            process_set_oracle_accounts(&program_id, &accounts, &[0x06])
+        };
+        let stored = [
+            Pubkey::new_from_array(ctx_data[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32].try_into().unwrap()),
+            Pubkey::new_from_array(ctx_data[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32].try_into().unwrap()),
+        ];
+        let expected = if result.is_ok() { new } else { old };
+        (result, stored, expected)
+    }
+
+    #[test]
+    fn test_set_oracle_accounts_rewrites_both_feeds() {
+        let (result, stored, expected) =
+            rotate_feeds(&NCN_ORACLE_PROGRAM_ID, NCN_YIELD_FEED_DISCRIMINATOR, NCN_PERFORMANCE_FEED_DISCRIMINATOR);
+        assert_eq!(result, Ok(()));
+        assert_eq!(stored, expected);
+    }
+
+    #[test]
+    fn test_set_oracle_accounts_checks_feed_types() {
+        let cases = [
+            // Wrong owner
+            (Pubkey::default(), NCN_YIELD_FEED_DISCRIMINATOR, NCN_PERFORMANCE_FEED_DISCRIMINATOR),
+            // Feeds passed in swapped order
+            (NCN_ORACLE_PROGRAM_ID, NCN_PERFORMANCE_FEED_DISCRIMINATOR, NCN_YIELD_FEED_DISCRIMINATOR),
+        ];
+        for (owner, yield_disc, perf_disc) in cases {
+            let (result, stored, expected) = rotate_feeds(&owner, yield_disc, perf_disc);
+            assert_eq!(result, Err(YieldMatcherError::OracleAccountMismatch.into()));
+            assert_eq!(stored, expected);
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 22. Settlement ends quoting
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_settle_blocks_match_and_is_one_shot() {
+        let lp_key = Pubkey::new_unique();
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+
+        assert_eq!(run_lp_signed(process_settle, &lp_key, &mut ctx_data, &[0x07]), Ok(()));
+        assert_eq!(ctx_data[SETTLED_OFFSET], 1);
+        assert_eq!(
+            run_lp_signed(process_match, &lp_key, &mut ctx_data, &[0x00]),
+            Err(YieldMatcherError::MatcherSettled.into())
+        );
+        assert_eq!(
+            run_lp_signed(process_settle, &lp_key, &mut ctx_data, &[0x07]),
+            Err(YieldMatcherError::MatcherSettled.into())
+        );
+    }
+
+    #[test]
+    fn test_settle_requires_stored_lp_and_own_context() {
+        let lp_key = Pubkey::new_unique();
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+        assert!(run_lp_signed(process_settle, &Pubkey::new_unique(), &mut ctx_data, &[0x07]).is_err());
+        assert_eq!(ctx_data[SETTLED_OFFSET], 0);
+
+        let mut foreign = uptime_ctx();
+        assert_eq!(
+            run_lp_signed(process_settle, &lp_key, &mut foreign, &[0x07]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 23. Spot / 7d / 30d mark blend
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_blended_yield_mark_weight_combinations() {
+        // spot 8% (mark 800e6), 7d avg 7%, 30d avg 6%
+        let spot = 800 * YIELD_MARK_SCALE as i64;
+        let cases: [([u32; 3], i64); 5] = [
+            ([0, 0, 0], 800),              // default: spot only
+            ([10_000, 0, 0], 800),         // explicit spot only
+            ([0, 10_000, 0], 700),         // 7d only
+            ([5_000, 2_500, 2_500], 725),  // 0.5*800 + 0.25*700 + 0.25*600
+            ([2_000, 3_000, 5_000], 670),  // 0.2*800 + 0.3*700 + 0.5*600
+        ];
+        for (weights, expected_bps) in cases {
+            assert_eq!(validate_blend_weights(weights), Ok(()));
+            assert_eq!(blended_yield_mark(spot, 700, 600, weights).unwrap(), expected_bps * YIELD_MARK_SCALE as i64);
+        }
+        // A single spike in spot moves a smoothed mark by only its weight
+        let spiked = blended_yield_mark(2_000 * YIELD_MARK_SCALE as i64, 700, 600, [2_000, 3_000, 5_000]).unwrap();
+        assert_eq!(spiked, 910 * YIELD_MARK_SCALE as i64);
+    }
+
+    #[test]
+    fn test_blend_weights_must_sum_to_one() {
+        for weights in [[5_000, 5_000, 1], [3_000, 3_000, 3_000], [u32::MAX, u32::MAX, u32::MAX]] {
+            assert_eq!(validate_blend_weights(weights), Err(YieldMatcherError::InvalidBlendWeights.into()));
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 24. LP-favorable rounding on both sides
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_bid_ask_rounding_favors_lp() {
+        // Marks and spreads chosen so mark * (10000 +/- spread) is rarely a multiple of 10000
+        for mark in [0u64, 1, 3, 7, 9_999, 10_001, 333_333, 995_001, 999_999] {
+            for spread in [0u64, 1, 3, 7, 33, 9_999, 10_000, 12_345] {
+                let (bid, ask) = compute_bid_ask_lp_favorable(mark, spread).unwrap();
+                let ask_fair = mark as u128 * (BPS_SCALE + spread) as u128;
+                let bid_fair = mark as u128 * BPS_SCALE.saturating_sub(spread) as u128;
+                // bid <= fair <= ask, each within one unit of the exact value
+                assert!(ask as u128 * BPS_SCALE as u128 >= ask_fair);
+                assert!(ask == 0 || (ask as u128 - 1) * (BPS_SCALE as u128) < ask_fair);
+                assert!(bid as u128 * BPS_SCALE as u128 <= bid_fair);
+                assert!((bid as u128 + 1) * (BPS_SCALE as u128) > bid_fair);
+                assert!(bid <= mark && mark <= ask);
+            }
+        }
+        // 1 unit at 1 bp: fair ask is 1.0001 -> 2, fair bid 0.9999 -> 0
+        assert_eq!(compute_bid_ask_lp_favorable(1, 1).unwrap(), (0, 2));
+        // Exact multiples are not rounded
+        assert_eq!(compute_bid_ask_lp_favorable(500_000, 20).unwrap(), (499_000, 501_000));
+    }
+
+    #[test]
+    fn test_bid_ask_ask_overflow_is_rejected() {
+        assert!(compute_bid_ask_lp_favorable(u64::MAX, 1).is_err());
+        assert_eq!(compute_bid_ask_lp_favorable(u64::MAX, 0).unwrap(), (u64::MAX, u64::MAX));
+    }
+
+    // -----------------------------------------------------------------------
+    // 25. Regime read-out
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_regime_info() {
+        let expected = [(0u8, "VeryLow", 50u64), (1, "Low", 75), (2, "Normal", 100), (3, "High", 150), (4, "Extreme", 250)];
+        for (raw, name, multiplier) in expected {
+            let out = encode_regime_info(raw);
+            assert_eq!((out[0], out[1]), (raw, raw));
+            assert_eq!(REGIME_NAMES[out[1] as usize], name);
+            assert_eq!(u64::from_le_bytes(out[2..10].try_into().unwrap()), multiplier);
+        }
+        // An out-of-range stored byte is reported raw but prices as Normal
+        let out = encode_regime_info(9);
+        assert_eq!((out[0], out[1]), (9, YieldRegime::Normal.name_index()));
+        assert_eq!(u64::from_le_bytes(out[2..10].try_into().unwrap()), 100);
+    }
+
+    // -----------------------------------------------------------------------
+    // 26. Bulk pricing-params read-out
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_pricing_params_layout() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[MODE_OFFSET] = 1;
+        for (offset, value) in [(BASE_SPREAD_OFFSET, 10u32), (YIELD_VOL_SPREAD_OFFSET, 40), (MAX_SPREAD_OFFSET, 300), (MIN_SPREAD_OFFSET, 15), (IMPACT_K_OFFSET, 3)] {
+            ctx[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        ctx[IMPACT_MODEL_OFFSET] = IMPACT_MODEL_SQRT;
+        ctx[YIELD_REGIME_OFFSET] = 3;
+        ctx[YIELD_MARK_PRICE_OFFSET..YIELD_MARK_PRICE_OFFSET + 8].copy_from_slice(&800_000_000u64.to_le_bytes());
+        ctx[CURRENT_YIELD_OFFSET..CURRENT_YIELD_OFFSET + 8].copy_from_slice(&800u64.to_le_bytes());
+        ctx[LAST_UPDATE_SLOT_OFFSET..LAST_UPDATE_SLOT_OFFSET + 8].copy_from_slice(&42u64.to_le_bytes());
+        ctx[SETTLED_OFFSET] = 1;
+
+        let out = encode_pricing_params(&ctx).unwrap();
+        let u32_at = |o: usize| u32::from_le_bytes(out[o..o + 4].try_into().unwrap());
+        let u64_at = |o: usize| u64::from_le_bytes(out[o..o + 8].try_into().unwrap());
+        assert_eq!((out[0], out[1]), (PRICING_PARAMS_LAYOUT_VERSION, 1));
+        assert_eq!((u32_at(2), u32_at(6), u32_at(10), u32_at(14), u32_at(18)), (10, 40, 300, 15, 3));
+        assert_eq!((out[22], out[23]), (IMPACT_MODEL_SQRT, 3));
+        assert_eq!((u64_at(24), u64_at(32), u64_at(40)), (800_000_000, 800, 42));
+        assert_eq!(out[48], 1);
+        // A context from before the display metadata reports the program's scale
+        assert_eq!((out[49], out[50]), (YIELD_PRICE_DECIMALS, QUOTE_KIND_YIELD));
+        // 800 bps = 8% = 0.08: the mark over 10^decimals
+        assert_eq!(800_000_000f64 / 10f64.powi(YIELD_PRICE_DECIMALS as i32), 0.08);
+        ctx[PRICE_DECIMALS_OFFSET] = 6;
+        ctx[QUOTE_KIND_OFFSET] = 0;
+        let out = encode_pricing_params(&ctx).unwrap();
+        assert_eq!((out[49], out[50]), (6, 0));
+    }
+
+    // -----------------------------------------------------------------------
+    // 27. Match requires a writable context
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_match_rejects_read_only_context() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+        let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+        let ctx = AccountInfo::new(&ctx_key, false, false, &mut l1, &mut ctx_data, &program_id, false, 0);
+        assert_eq!(process_match(&program_id, &[lp, ctx], &[0x00]), Err(ProgramError::InvalidAccountData));
+    }
+
+    // -----------------------------------------------------------------------
+    // 28. Combined init + first sync
+    // -----------------------------------------------------------------------
+    fn init_and_sync(yield_bps: u64, yield_mark: u64, regime: u8, sequence: u64, init_len: usize) -> ProgramResult {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+        let (mut d0, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+        let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+        let accounts = [
+            AccountInfo::new(&keys[0], true, false, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&keys[1], false, true, &mut l1, &mut ctx_data, &program_id, false, 0),
+            AccountInfo::new(&keys[2], false, false, &mut l2, &mut d2, &program_id, false, 0),
+            AccountInfo::new(&keys[3], false, false, &mut l3, &mut d3, &program_id, false, 0),
+        ];
+        let mut data = vec![0x0A];
+        data.extend_from_slice(&yield_bps.to_le_bytes());
+        data.extend_from_slice(&yield_mark.to_le_bytes());
+        data.push(regime);
+        data.extend_from_slice(&yield_bps.to_le_bytes());
+        data.extend_from_slice(&yield_bps.to_le_bytes());
+        data.extend_from_slice(&sequence.to_le_bytes());
+        assert_eq!(data.len(), 1 + ORACLE_SYNC_PAYLOAD_SIZE);
+        data.resize(1 + ORACLE_SYNC_PAYLOAD_SIZE + init_len, 0);
+        process_init_and_sync(&program_id, &accounts, &data)
+    }
+
+    #[test]
+    fn test_init_and_sync_validates_sync_payload_first() {
+        let mark = bps_to_e6(800).unwrap();
+        assert_eq!(init_and_sync(800, mark, 2, 1, 112), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(init_and_sync(800, mark, 5, 1, 113), Err(YieldMatcherError::InvalidRegime.into()));
+        assert_eq!(init_and_sync(800, mark + 1, 2, 1, 113), Err(YieldMatcherError::YieldMarkMismatch.into()));
+        // A fresh context has applied no sequence yet, so 0 can never be the first sync
+        assert_eq!(init_and_sync(800, mark, 2, 0, 113), Err(YieldMatcherError::OracleSequenceRegressed.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 29. Slots until stale
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_slots_until_stale() {
+        assert_eq!(MAX_STALENESS_SLOTS, 100);
+        let slots_until_stale = |last_update, current_slot| slots_until_stale(last_update, current_slot, MAX_STALENESS_SLOTS);
+        assert_eq!(slots_until_stale(1_000, 1_000), MAX_STALENESS_SLOTS);
+        assert_eq!(slots_until_stale(1_000, 1_040), MAX_STALENESS_SLOTS - 40);
+        // Still quotable at exactly the limit, stale one slot later
+        assert_eq!(slots_until_stale(1_000, 1_000 + MAX_STALENESS_SLOTS), 0);
+        assert_eq!(slots_until_stale(1_000, 1_001 + MAX_STALENESS_SLOTS), 0);
+        assert_eq!(slots_until_stale(0, u64::MAX), 0);
+        // An update slot ahead of the clock counts as fresh
+        assert_eq!(slots_until_stale(2_000, 1_000), MAX_STALENESS_SLOTS);
+    }
+
+    // -----------------------------------------------------------------------
+    // 30. Negative net yield
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_negative_yield_prices_signed() {
+        // Net yield of -2.5% after slashing losses
+        let mark = super::signed_bps_to_e6(-250).unwrap();
+        assert_eq!(mark, -250_000_000);
+        assert_eq!(validate_yield_mark(-250, mark), Ok(mark));
+        assert_eq!(validate_yield_mark(-250, -mark), Err(YieldMatcherError::YieldMarkMismatch.into()));
+
+        let mut payload = Vec::with_capacity(ORACLE_SYNC_PAYLOAD_SIZE);
+        payload.extend_from_slice(&(-250i64).to_le_bytes());
+        payload.extend_from_slice(&mark.to_le_bytes());
+        payload.push(0);
+        payload.extend_from_slice(&100u64.to_le_bytes());
+        payload.extend_from_slice(&300u64.to_le_bytes());
+        payload.extend_from_slice(&7u64.to_le_bytes());
+        let parsed = super::parse_oracle_sync_payload(&payload).unwrap();
+        assert_eq!((parsed.current_yield, parsed.yield_mark), (-250, mark));
+
+        // Mirror image of the positive quote: ask(-m) = -bid(m), bid(-m) = -ask(m)
+        let (bid, ask) = crate::pricing::compute_signed_bid_ask_lp_favorable(mark, 50).unwrap();
+        assert_eq!((bid, ask), (-251_250_000, -248_750_000));
+        assert!(bid < mark && mark < ask);
+        // base 20 + vol 30 * 0.5 (VeryLow) = 35 bps above a negative mark
+        assert_eq!(calc_exec_price(20, 30, 200, YieldRegime::VeryLow, mark), -249_125_000);
+
+        // Blending a negative spot with positive averages: 0.5*-250 + 0.25*100 + 0.25*300
+        let blended = blended_yield_mark(mark, 100, 300, [5_000, 2_500, 2_500]).unwrap();
+        assert_eq!(blended, -25 * YIELD_MARK_SCALE as i64);
+    }
+
+    // -----------------------------------------------------------------------
+    // 31. Context must be owned by the matcher program
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_foreign_owned_context_rejected() {
+        let (program_id, foreign_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+        let (mut d0, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+        // Valid magic and LP binding, but written by some other program
+        let mut ctx_data = MockContextBuilder::for_lp(keys[0]).build();
+        let lp = AccountInfo::new(&keys[0], true, false, &mut l0, &mut d0, &program_id, false, 0);
+        let ctx = AccountInfo::new(&keys[1], false, true, &mut l1, &mut ctx_data, &foreign_program, false, 0);
+        let yield_feed = AccountInfo::new(&keys[2], false, false, &mut l2, &mut d2, &program_id, false, 0);
+        let perf_feed = AccountInfo::new(&keys[3], false, false, &mut l3, &mut d3, &program_id, false, 0);
+
+        let err = Err(ProgramError::IllegalOwner);
+        assert_eq!(process_match(&program_id, &[lp.clone(), ctx.clone()], &[0x00]), err);
+        assert_eq!(super::process_init(&program_id, &[lp.clone(), ctx.clone()], &[0x02; 114]), err);
+        assert_eq!(super::process_settle(&program_id, &[lp, ctx.clone()], &[0x07]), err);
+        let feeds = [ctx, yield_feed, perf_feed];
+        assert_eq!(super::process_oracle_sync(&program_id, &feeds, &[0x03; 42]), err);
+        assert_eq!(process_heartbeat(&program_id, &feeds, &[0x04]), err);
+    }
+
+    // -----------------------------------------------------------------------
+    // 32. Taker slippage bound
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_check_slippage() {
+        assert_eq!(check_slippage(80_000, 0), Ok(()));
+        assert_eq!(check_slippage(80_000, 80_000), Ok(()));
+        assert_eq!(check_slippage(80_001, 80_000), Err(YieldMatcherError::SlippageExceeded.into()));
+        // Negative quotes are bounded by negative limits
+        assert_eq!(check_slippage(-20_000, -10_000), Ok(()));
+        assert_eq!(check_slippage(-5_000, -10_000), Err(YieldMatcherError::SlippageExceeded.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 33. Oracle config check against a registry
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_oracle_config() {
+        let (yield_feed, perf_feed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[NCN_YIELD_FEED_OFFSET..NCN_YIELD_FEED_OFFSET + 32].copy_from_slice(yield_feed.as_ref());
+        ctx[NCN_PERFORMANCE_FEED_OFFSET..NCN_PERFORMANCE_FEED_OFFSET + 32].copy_from_slice(perf_feed.as_ref());
+
+        let out = encode_oracle_config(&ctx, &yield_feed, &perf_feed).unwrap();
+        assert_eq!(out[..2], [1, 1]);
+        assert_eq!(&out[2..34], yield_feed.as_ref());
+        assert_eq!(&out[34..66], perf_feed.as_ref());
+
+        // Each feed is reported separately
+        let out = encode_oracle_config(&ctx, &yield_feed, &Pubkey::new_unique()).unwrap();
+        assert_eq!(out[..2], [1, 0]);
+        let out = encode_oracle_config(&ctx, &perf_feed, &yield_feed).unwrap();
+        assert_eq!(out[..2], [0, 0]);
+    }
+
+    // -----------------------------------------------------------------------
+    // 34. One-call header validation
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_validate_header() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, lp_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let check = |owner: &Pubkey, data: &mut Vec<u8>| {
+            let mut lamports = 0u64;
+            let ctx = AccountInfo::new(&ctx_key, false, false, &mut lamports, data, owner, false, 0);
+            validate_header(&ctx, &program_id)
+        };
+
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).put(MODE_OFFSET, &[1]).build();
+        let header = check(&program_id, &mut ctx_data).unwrap();
+        assert_eq!((header.mode, header.lp_pda), (1, lp_key));
+
+        // Owner is checked before size, size before magic
+        assert_eq!(check(&Pubkey::new_unique(), &mut vec![0u8; 8]), Err(ProgramError::IllegalOwner));
+        assert_eq!(check(&program_id, &mut ctx_data[..CTX_SIZE - 1].to_vec()), Err(ProgramError::AccountDataTooSmall));
+        assert_eq!(check(&program_id, &mut vec![0u8; CTX_V2_SIZE]), Err(ProgramError::UninitializedAccount));
+
+        // A version-1 context keeps the shared size; version 2 needs the extension bytes
+        let mut v1 = MockContextBuilder::for_lp(lp_key).version_1().build();
+        assert_eq!(check(&program_id, &mut v1).map(|h| h.version), Ok(1));
+        v1[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(&CTX_VERSION_2.to_le_bytes());
+        assert_eq!(check(&program_id, &mut v1), Err(ProgramError::AccountDataTooSmall));
+    }
+
+    // -----------------------------------------------------------------------
+    // 35. Canonical match log line
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_format_match_log() {
+        assert_eq!(
+            format_match_log(995_500, 50, 995_000, "regime=2 fill=0"),
+            "MATCH v=1 price=995500 spread_bps=50 mark_e6=995000 regime=2 fill=0"
+        );
+        assert_eq!(format_match_log(-20_000, 0, -20_000, ""), "MATCH v=1 price=-20000 spread_bps=0 mark_e6=-20000");
+    }
+
+    // -----------------------------------------------------------------------
+    // 36. Mode byte is range-checked at init
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_init_rejects_out_of_range_mode() {
+        let init = |mode: u8| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0u8; 114];
+            data[0] = 0x02;
+            data[1] = mode;
+            let result = super::process_init(&program_id, &[lp, ctx], &data);
+            (result, ctx_data[MODE_OFFSET])
+        };
+        assert_eq!(init(99).0, Err(YieldMatcherError::InvalidMode.into()));
+        assert_eq!(init(MODE_SINGLE_NCN + 1).0, Err(YieldMatcherError::InvalidMode.into()));
+        for mode in [MODE_ALL_NCN, MODE_SINGLE_NCN] {
+            assert_eq!(init(mode), (Ok(()), mode));
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 37. Quote at a hypothetical mark
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_quote_at_mark_runs_match_pipeline() {
+        let decode = |out: [u8; QUOTE_AT_MARK_RETURN_SIZE]| {
+            let at = |o: usize| <[u8; 8]>::try_from(&out[o..o + 8]).unwrap();
+            assert_eq!(out[0], QUOTE_AT_MARK_LAYOUT_VERSION);
+            (i64::from_le_bytes(at(1)), u64::from_le_bytes(at(9)), i64::from_le_bytes(at(17)), u64::from_le_bytes(at(25)))
+        };
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        ctx[BASE_SPREAD_OFFSET..BASE_SPREAD_OFFSET + 4].copy_from_slice(&20u32.to_le_bytes());
+        ctx[YIELD_VOL_SPREAD_OFFSET..YIELD_VOL_SPREAD_OFFSET + 4].copy_from_slice(&30u32.to_le_bytes());
+        ctx[MAX_SPREAD_OFFSET..MAX_SPREAD_OFFSET + 4].copy_from_slice(&200u32.to_le_bytes());
+        ctx[YIELD_REGIME_OFFSET] = YieldRegime::Normal as u8;
+        // The live mark plays no part
+        ctx[YIELD_MARK_PRICE_OFFSET..YIELD_MARK_PRICE_OFFSET + 8].copy_from_slice(&1i64.to_le_bytes());
+
+        for mark in [800_000_000, -20_000_000] {
+            let (price, spread, priced_mark, impact) = decode(quote_at_mark(&ctx, mark, 0).unwrap());
+            assert_eq!(price, calc_exec_price(20, 30, 200, YieldRegime::Normal, mark));
+            assert_eq!((spread, priced_mark, impact), (50, mark, 0));
+        }
+        // A zero mark is a real yield, not a missing sync
+        assert_eq!(decode(quote_at_mark(&ctx, 0, 0).unwrap()), (0, 50, 0, 0));
+
+        // The stored regime scales the spread
+        ctx[YIELD_REGIME_OFFSET] = YieldRegime::Extreme as u8;
+        assert_eq!(
+            decode(quote_at_mark(&ctx, 800_000_000, 0).unwrap()).0,
+            calc_exec_price(20, 30, 200, YieldRegime::Extreme, 800_000_000)
+        );
+        ctx[YIELD_REGIME_OFFSET] = YieldRegime::Normal as u8;
+
+        // The hypothetical mark replaces spot in the configured blend
+        ctx[YIELD_30D_AVG_OFFSET..YIELD_30D_AVG_OFFSET + 8].copy_from_slice(&600u64.to_le_bytes());
+        for (i, w) in [5_000u32, 0, 5_000].iter().enumerate() {
+            let offset = BLEND_WEIGHTS_OFFSET + i * 4;
+            ctx[offset..offset + 4].copy_from_slice(&w.to_le_bytes());
+        }
+        assert_eq!(decode(quote_at_mark(&ctx, 800_000_000, 0).unwrap()).2, 700_000_000);
+        ctx[BLEND_WEIGHTS_OFFSET..BLEND_WEIGHTS_OFFSET + 12].fill(0);
+
+        // Impact uses the remaining liquidity, and an oversized fill fails like a match
+        let liq = 1_000_000_000u128;
+        ctx[IMPACT_K_OFFSET..IMPACT_K_OFFSET + 4].copy_from_slice(&100u32.to_le_bytes());
+        ctx[LIQUIDITY_OFFSET..LIQUIDITY_OFFSET + 16].copy_from_slice(&liq.to_le_bytes());
+        let (_, _, _, impact) = decode(quote_at_mark(&ctx, 800_000_000, liq / 100).unwrap());
+        assert_eq!(impact, compute_impact_bps(IMPACT_MODEL_LINEAR, 100, liq / 100, liq));
+        assert!(impact > 0);
+        assert_eq!(quote_at_mark(&ctx, 800_000_000, liq + 1), Err(YieldMatcherError::InsufficientLiquidity.into()));
+    }
+
+    #[test]
+    fn test_quote_at_mark_is_read_only() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, lp_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0u64;
+        let mut ctx_data = MockContextBuilder::for_lp(lp_key).build();
+        let before = ctx_data.clone();
+        {
+            let ctx = AccountInfo::new(&ctx_key, false, false, &mut lamports, &mut ctx_data, &program_id, false, 0);
+            let mut data = vec![0x0D];
+            data.extend_from_slice(&800_000_000i64.to_le_bytes());
+            assert_eq!(process_quote_at_mark(&program_id, std::slice::from_ref(&ctx), &data), Ok(()));
+            assert_eq!(process_quote_at_mark(&program_id, &[ctx], &[0x0D, 0]), Err(ProgramError::InvalidInstructionData));
+        }
+        assert_eq!(ctx_data, before);
+    }
+
+    // -----------------------------------------------------------------------
+    // 38. Impact with zero liquidity fails instead of quoting flat
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_zero_liquidity_with_impact_rejected() {
+        let err: ProgramError = YieldMatcherError::InvalidLiquidityConfig.into();
+        assert_eq!(check_liquidity_config(100, 0), Err(err.clone()));
+        assert_eq!(check_liquidity_config(0, 0), Ok(()));
+        assert_eq!(check_liquidity_config(100, 1), Ok(()));
+
+        // A synced matcher whose notional slipped past init as zero
+        let builder = MockContextBuilder::new().liquidity(100, 0);
+        assert_eq!(quote_at_mark(&builder.data, 800_000_000, 0), Err(err.clone()));
+        // Rejected even for a zero-size fill, which would otherwise price no impact
+        assert_eq!(builder.run(process_match, &[0x00]).0, Err(err));
+    }
+
+    // -----------------------------------------------------------------------
+    // 39. Handler-level match gates on builder contexts
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_match_gates_on_built_context() {
+        // A valid context clears every pre-clock gate; off-chain the Clock read fails instead
+        let (valid, _) = MockContextBuilder::new().run(process_match, &[0x00]);
+        assert!(valid.is_err());
+
+        let cases = [
+            (MockContextBuilder::new().put(SETTLED_OFFSET, &[1]), YieldMatcherError::MatcherSettled),
+            (MockContextBuilder::new().put(HAS_SYNCED_OFFSET, &[0]), YieldMatcherError::OracleNotSynced),
+            (MockContextBuilder::new().liquidity(100, 0), YieldMatcherError::InvalidLiquidityConfig),
+        ];
+        for (builder, err) in cases {
+            let err: ProgramError = err.into();
+            assert_ne!(valid, Err(err.clone()));
+            let before = builder.data.clone();
+            let (result, after) = builder.run(process_match, &[0x00]);
+            assert_eq!(result, Err(err));
+            // Rejected matches leave the return buffer untouched
+            assert_eq!(after, before);
+        }
+    }
+
+    #[test]
+    fn test_quote_at_mark_on_built_context() {
+        let ctx = MockContextBuilder::new().liquidity(100, 1_000_000_000).build();
+        let out = quote_at_mark(&ctx, 800_000_000, 0).unwrap();
+        assert_eq!(
+            i64::from_le_bytes(out[1..9].try_into().unwrap()),
+            calc_exec_price(20, 30, 200, YieldRegime::Normal, 800_000_000)
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 40. Bounds-checked u128 context fields
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_u128_accessors_are_bounds_checked() {
+        let mut ctx = vec![0u8; CTX_V2_SIZE];
+        write_u128(&mut ctx, LIQUIDITY_OFFSET, u128::MAX - 1).unwrap();
+        write_u128(&mut ctx, MAX_FILL_OFFSET, 7).unwrap();
+        assert_eq!(read_u128(&ctx, LIQUIDITY_OFFSET), Ok(u128::MAX - 1));
+        assert_eq!(read_u128(&ctx, MAX_FILL_OFFSET), Ok(7));
+
+        // A field running past the end of a short account errors instead of panicking
+        let mut short = vec![0u8; MAX_FILL_OFFSET + 15];
+        assert_eq!(read_u128(&short, MAX_FILL_OFFSET), Err(ProgramError::InvalidAccountData));
+        assert_eq!(write_u128(&mut short, MAX_FILL_OFFSET, 1), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_u128(&short, usize::MAX - 8), Err(ProgramError::InvalidAccountData));
+        assert!(short.iter().all(|&b| b == 0));
+    }
+
+    // -----------------------------------------------------------------------
+    // 41. yield_exec_price safe input range
+    // -----------------------------------------------------------------------
+    /// Largest yield mark whose LP-favorable ask, ceil(mark * (10_000 + spread) / 10_000),
+    /// fits an i64. At spreads up to 10_000 bps this is above 4.6e18, so real yield marks
+    /// (~2e9) are far inside.
+    fn max_safe_yield_mark(spread_bps: u64) -> i64 {
+        (i64::MAX as u128 * BPS_SCALE as u128 / (BPS_SCALE + spread_bps) as u128) as i64
+    }
+
+    #[test]
+    fn test_yield_exec_price_safe_range() {
+        // A flat spread: base only, clamped to itself
+        let quote = |mark: i64, spread: u32| yield_exec_price(mark, 100, spread, 0, 0, spread, 0);
+        let overflow = Err(YieldMatcherError::ArithmeticOverflow.into());
+
+        assert_eq!(max_safe_yield_mark(0), i64::MAX);
+        assert_eq!(quote(i64::MAX, 0), Ok((i64::MAX, 0)));
+        for spread in [1, 500, 10_000] {
+            let max = max_safe_yield_mark(spread as u64);
+            assert!(max >= 4_611_686_018_427_387_903);
+            assert!(quote(max, spread).is_ok());
+            assert_eq!(quote(max + 1, spread), overflow);
+        }
+        // Overflow is an error, never a panic, at either extreme of the signed range
+        assert_eq!(quote(i64::MAX, 1), overflow);
+        assert_eq!(quote(i64::MAX, u32::MAX), overflow);
+        assert!(quote(i64::MIN, 1).is_err());
+        // The unsigned quote core has the same contract
+        assert_eq!(
+            compute_bid_ask_lp_favorable(u64::MAX, 1),
+            Err(YieldMatcherError::ArithmeticOverflow.into())
+        );
+        assert!(compute_bid_ask_lp_favorable(u64::MAX, u64::MAX).is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // 42. Immutable pricing snapshots
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_encode_snapshot_layout() {
+        let matcher = Pubkey::new_unique();
+        let ctx = MockContextBuilder::new().spreads(25, 30, 400).build();
+        let out = encode_snapshot(&ctx, &matcher, 1_234, -5).unwrap();
+        assert_eq!(u64::from_le_bytes(out[0..8].try_into().unwrap()), YIELD_SNAPSHOT_MAGIC);
+        assert_eq!(&out[8..40], matcher.as_ref());
+        assert_eq!(u64::from_le_bytes(out[40..48].try_into().unwrap()), 1_234);
+        assert_eq!(i64::from_le_bytes(out[48..56].try_into().unwrap()), -5);
+        assert_eq!(out[56..], encode_pricing_params(&ctx).unwrap());
+        // The snapshot can never pass as a matcher context
+        assert_ne!(YIELD_SNAPSHOT_MAGIC, YIELD_MATCHER_MAGIC);
+        assert_eq!(encode_snapshot(&ctx[..100], &matcher, 0, 0), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_snapshot_validates_accounts_before_clock() {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), solana_system_interface::program::ID];
+        let snapshot = |ctx_data: &mut Vec<u8>, payer_signs: bool, system_key: &Pubkey| {
+            let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+            let (mut d1, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+            let accounts = [
+                AccountInfo::new(&keys[0], false, false, &mut l0, ctx_data, &program_id, false, 0),
+                AccountInfo::new(&keys[1], false, true, &mut l1, &mut d1, &keys[3], false, 0),
+                AccountInfo::new(&keys[2], payer_signs, true, &mut l2, &mut d2, &keys[3], false, 0),
+                AccountInfo::new(system_key, false, false, &mut l3, &mut d3, &keys[3], false, 0),
+            ];
+            process_snapshot_matcher(&program_id, &accounts, &[0x0E])
+        };
+        let mut ctx = MockContextBuilder::new().build();
+        assert_eq!(snapshot(&mut ctx, false, &keys[3]), Err(ProgramError::MissingRequiredSignature));
+        assert_eq!(snapshot(&mut ctx, true, &Pubkey::new_unique()), Err(ProgramError::IncorrectProgramId));
+        let mut foreign = uptime_ctx();
+        assert_eq!(snapshot(&mut foreign, true, &keys[3]), Err(ProgramError::UninitializedAccount));
+    }
+
+    // -----------------------------------------------------------------------
+    // 43. Regime transition smoothing
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_transition_multiplier_interpolates_over_window() {
+        use YieldRegime::*;
+        let (ts, window) = (1_000_000i64, 600u32);
+        let mult = |prev, current, now| transition_spread_multiplier(prev, current, ts, window, now);
+        // Normal (1.0x) -> Extreme (2.5x): starts at the old multiplier, ends at the new one
+        assert_eq!(mult(Normal, Extreme, ts), 100);
+        assert_eq!(mult(Normal, Extreme, ts + 150), 138); // 137.5 rounds wider
+        assert_eq!(mult(Normal, Extreme, ts + 300), 175);
+        assert_eq!(mult(Normal, Extreme, ts + 599), 250);
+        assert_eq!(mult(Normal, Extreme, ts + 600), 250);
+        assert_eq!(mult(Normal, Extreme, ts + 10_000), 250);
+        // Extreme -> Normal narrows gradually, also rounding toward the wider spread
+        assert_eq!(mult(Extreme, Normal, ts + 200), 200);
+        assert_eq!(mult(Extreme, Normal, ts + 450), 138); // 137.5 rounds wider
+        assert_eq!(mult(Extreme, Normal, ts + 600), 100);
+        // A clock behind the flip holds the old multiplier
+        assert_eq!(mult(Normal, Extreme, ts - 5), 100);
+        // No window or no recorded flip prices at the current regime
+        assert_eq!(transition_spread_multiplier(Normal, Extreme, ts, 0, ts), 250);
+        assert_eq!(transition_spread_multiplier(Normal, Extreme, 0, window, ts), 250);
+    }
+
+    #[test]
+    fn test_mid_transition_spread_between_regimes() {
+        let quote = |multiplier: u64| yield_exec_price(800_000_000, multiplier, 20, 30, 0, 500, 0).unwrap();
+        let mid = transition_spread_multiplier(YieldRegime::Normal, YieldRegime::Extreme, 1_000, 600, 1_300);
+        // vol 30 * 1.75 = 52 (floor), total 72: between Normal's 50 and Extreme's 95
+        assert_eq!(quote(mid).1, 72);
+        assert_eq!(quote(YieldRegime::Normal.spread_multiplier()).1, 50);
+        assert_eq!(quote(YieldRegime::Extreme.spread_multiplier()).1, 95);
+        assert!(quote(mid).0 > quote(100).0 && quote(mid).0 < quote(250).0);
+    }
+
+    #[test]
+    fn test_init_stores_regime_transition_window() {
+        let init = |data: &[u8]| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            assert_eq!(super::process_init(&program_id, &[lp, ctx], data), Ok(()));
+            ctx_data
+        };
+        let mut data = vec![0u8; 135];
+        data[0] = 0x02;
+        data[131..135].copy_from_slice(&900u32.to_le_bytes());
+        let ctx = init(&data);
+        assert_eq!(u32::from_le_bytes(ctx[REGIME_TRANSITION_SECS_OFFSET..REGIME_TRANSITION_SECS_OFFSET + 4].try_into().unwrap()), 900);
+        assert_eq!(ctx[PREV_REGIME_OFFSET], YieldRegime::Normal as u8);
+        assert_eq!(&ctx[REGIME_TRANSITION_TS_OFFSET..REGIME_TRANSITION_TS_OFFSET + 8], &[0u8; 8]);
+        // Display metadata is written alongside the header
+        assert_eq!((ctx[PRICE_DECIMALS_OFFSET], ctx[QUOTE_KIND_OFFSET]), (YIELD_PRICE_DECIMALS, QUOTE_KIND_YIELD));
+        // Absent, smoothing is off
+        let ctx = init(&data[..131]);
+        assert_eq!(&ctx[REGIME_TRANSITION_SECS_OFFSET..REGIME_TRANSITION_SECS_OFFSET + 4], &[0u8; 4]);
+    }
+
+    // -----------------------------------------------------------------------
+    // 44. Missing sync accounts are named
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_missing_sync_accounts_have_distinct_errors() {
+        let program_id = Pubkey::new_unique();
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut d0, mut d1, mut d2) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&keys[0], false, true, &mut l0, &mut d0, &program_id, false, 0),
+            AccountInfo::new(&keys[1], false, false, &mut l1, &mut d1, &program_id, false, 0),
+            AccountInfo::new(&keys[2], false, false, &mut l2, &mut d2, &program_id, false, 0),
+        ];
+        let sync = [0x03; 1 + ORACLE_SYNC_PAYLOAD_SIZE];
+        let yield_missing = Err(YieldMatcherError::YieldFeedMissing.into());
+        let perf_missing = Err(YieldMatcherError::PerformanceFeedMissing.into());
+
+        assert_eq!(process_oracle_sync(&program_id, &[], &sync), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(process_oracle_sync(&program_id, &accounts[..1], &sync), yield_missing);
+        assert_eq!(process_oracle_sync(&program_id, &accounts[..2], &sync), perf_missing);
+        assert_eq!(process_heartbeat(&program_id, &accounts[..1], &[0x04]), yield_missing);
+        // Init-and-sync puts the context after the LP PDA, so the feeds sit one slot later
+        assert_eq!(process_init_and_sync(&program_id, &accounts[..2], &[0x0A]), yield_missing);
+        assert_eq!(process_init_and_sync(&program_id, &accounts, &[0x0A]), perf_missing);
+        // With every slot present the accounts themselves are checked
+        let result = process_oracle_sync(&program_id, &accounts, &sync);
+        assert!(result.is_err() && result != yield_missing && result != perf_missing);
+    }
+
+    // -----------------------------------------------------------------------
+    // 45. Protocol fee on spread
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_protocol_fee_share_of_spread() {
+        // 1,000 notional at a 50 bps spread earns 5 (e6); a 20% fee takes 1 of it
+        assert_eq!(protocol_fee_e6(1_000 * E6_SCALE as u128, 50, 2_000), Ok(E6_SCALE));
+        assert_eq!(protocol_fee_e6(1_000 * E6_SCALE as u128, 50, 0), Ok(0));
+        assert_eq!(protocol_fee_e6(1_000 * E6_SCALE as u128, 50, MAX_PROTOCOL_FEE_BPS), Ok(5 * E6_SCALE));
+        // Dust rounds down in the LP's favor
+        assert_eq!(protocol_fee_e6(9_999, 1, 1), Ok(0));
+        assert_eq!(protocol_fee_e6(u128::MAX, 2, 1), Err(YieldMatcherError::ArithmeticOverflow.into()));
+    }
+
+    #[test]
+    fn test_protocol_fee_accrual_saturates() {
+        let ctx = MockContextBuilder::new().put(ACCRUED_PROTOCOL_FEES_OFFSET, &(u64::MAX - 5).to_le_bytes()).build();
+        assert_eq!(accrue_protocol_fee(&ctx, 5), Ok(u64::MAX));
+        // Past the cap the balance stays pinned instead of the match failing
+        assert_eq!(accrue_protocol_fee(&ctx, 1_000), Ok(u64::MAX));
+        assert_eq!(accrue_protocol_fee(&MockContextBuilder::new().build(), 7), Ok(7));
+    }
+
+    #[test]
+    fn test_accrued_fees_read_and_claim() {
+        let authority = Pubkey::new_unique();
+        let mut ctx = MockContextBuilder::new()
+            .put(ACCRUED_PROTOCOL_FEES_OFFSET, &1_234_567u64.to_le_bytes())
+            .put(PROTOCOL_FEE_BPS_OFFSET, &2_000u16.to_le_bytes())
+            .put(PROTOCOL_FEE_AUTHORITY_OFFSET, authority.as_ref())
+            .put(SETTLED_OFFSET, &[1])
+            .build();
+        // Fees accrued before settlement are still claimable
+        assert_eq!(run_lp_signed(process_claim_protocol_fees, &authority, &mut ctx, &[0x10]), Ok(()));
+        let out = encode_accrued_fees(&ctx).unwrap();
+        assert_eq!(u64::from_le_bytes(out[0..8].try_into().unwrap()), 0);
+        assert_eq!(u16::from_le_bytes(out[8..10].try_into().unwrap()), 2_000);
+
+        let mut ctx = MockContextBuilder::new().put(ACCRUED_PROTOCOL_FEES_OFFSET, &42u64.to_le_bytes()).build();
+        assert_eq!(claim_protocol_fees(&mut ctx), Ok(42));
+        assert_eq!(claim_protocol_fees(&mut ctx), Ok(0));
+
+        // Only the protocol fee authority can claim: not the LP PDA...
+        let (result, ctx) = MockContextBuilder::new()
+            .put(PROTOCOL_FEE_AUTHORITY_OFFSET, authority.as_ref())
+            .put(ACCRUED_PROTOCOL_FEES_OFFSET, &500u64.to_le_bytes())
+            .run(process_claim_protocol_fees, &[0x10]);
+        assert_eq!(result, Err(YieldMatcherError::FeeAuthorityMismatch.into()));
+        assert_eq!(ctx[ACCRUED_PROTOCOL_FEES_OFFSET..ACCRUED_PROTOCOL_FEES_OFFSET + 8], 500u64.to_le_bytes());
+
+        // ...and the authority must sign
+        let (mut lamports, mut no_data) = (0u64, [0u8; 0]);
+        let unsigned = AccountInfo::new(&authority, false, false, &mut lamports, &mut no_data, &authority, false, 0);
+        assert_eq!(verify_fee_authority(&unsigned, &ctx), Err(ProgramError::MissingRequiredSignature));
+    }
+
+    #[test]
+    fn test_init_stores_protocol_fee() {
+        let init = |data: &[u8]| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            super::process_init(&program_id, &[lp, ctx], data).map(|()| ctx_data)
+        };
+        let authority = Pubkey::new_unique();
+        let mut data = vec![0u8; 182];
+        data[0] = 0x02;
+        data[135..137].copy_from_slice(&2_500u16.to_le_bytes());
+        data[150..182].copy_from_slice(authority.as_ref());
+        let ctx = init(&data).unwrap();
+        assert_eq!(encode_accrued_fees(&ctx).unwrap(), [0, 0, 0, 0, 0, 0, 0, 0, 0xC4, 0x09]);
+        assert_eq!(&ctx[PROTOCOL_FEE_AUTHORITY_OFFSET..PROTOCOL_FEE_AUTHORITY_OFFSET + 32], authority.as_ref());
+        // Absent, no fee is taken
+        let ctx = init(&data[..135]).unwrap();
+        assert_eq!(&ctx[PROTOCOL_FEE_BPS_OFFSET..PROTOCOL_FEE_BPS_OFFSET + 2], &[0u8; 2]);
+        // A fee needs an authority to claim it
+        assert_eq!(init(&data[..150]), Err(YieldMatcherError::InvalidProtocolFeeConfig.into()));
+        data[135..137].copy_from_slice(&(MAX_PROTOCOL_FEE_BPS + 1).to_le_bytes());
+        assert_eq!(init(&data), Err(YieldMatcherError::InvalidProtocolFeeConfig.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 46. Yield mark unit mismatch
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_yield_mark_unit_mismatch_detected() {
+        let unit: ProgramError = YieldMatcherError::YieldMarkUnitMismatch.into();
+        // 8% scaled correctly, then as raw bps, as a fraction * 1e6 and as percent * 1e6
+        assert_eq!(validate_yield_mark(800, 800_000_000), Ok(800_000_000));
+        for mark in [800, 80_000, 8_000_000] {
+            assert_eq!(validate_yield_mark(800, mark), Err(unit.clone()), "mark {}", mark);
+        }
+        // Yield sent already in e6 alongside a correct mark; negative yields too
+        assert_eq!(validate_yield_mark(800_000_000, 800_000_000), Err(unit.clone()));
+        assert_eq!(validate_yield_mark(-150, -150), Err(unit));
+
+        // Other mismatches, including a dropped digit, keep the generic error
+        let generic: ProgramError = YieldMatcherError::YieldMarkMismatch.into();
+        assert_eq!(validate_yield_mark(800, 80_000_001), Err(generic.clone()));
+        assert_eq!(validate_yield_mark(800, 80_000_000), Err(generic.clone()));
+        assert_eq!(validate_yield_mark(0, 800), Err(generic));
+    }
+
+    #[test]
+    fn test_sync_rejects_mis_scaled_mark() {
+        let mut payload = vec![0u8; ORACLE_SYNC_PAYLOAD_SIZE];
+        payload[0..8].copy_from_slice(&800i64.to_le_bytes());
+        payload[8..16].copy_from_slice(&800i64.to_le_bytes());
+        payload[16] = 2;
+        assert!(matches!(
+            parse_oracle_sync_payload(&payload),
+            Err(e) if e == YieldMatcherError::YieldMarkUnitMismatch.into()
+        ));
+        payload[8..16].copy_from_slice(&800_000_000i64.to_le_bytes());
+        assert!(parse_oracle_sync_payload(&payload).is_ok());
+    }
+
+    // -----------------------------------------------------------------------
+    // 47. Explicit sync flag
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_synced_low_yield_passes_sync_gate() {
+        let not_synced: ProgramError = YieldMatcherError::OracleNotSynced.into();
+        // A synced zero or near-zero yield clears the gate; off-chain the Clock read fails instead
+        for yield_bps in [0, 1, -1] {
+            let (result, _) = MockContextBuilder::new().yield_bps(yield_bps).run(process_match, &[0x00]);
+            assert_ne!(result, Err(not_synced.clone()), "yield {}", yield_bps);
+            assert!(result.is_err());
+        }
+        // Never synced is rejected whatever the mark bytes hold
+        for yield_bps in [0, 800] {
+            let builder = MockContextBuilder::new().yield_bps(yield_bps).put(HAS_SYNCED_OFFSET, &[0]);
+            assert_eq!(builder.run(process_match, &[0x00]).0, Err(not_synced.clone()));
+        }
+    }
+
+    #[test]
+    fn test_init_clears_sync_flag() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut l0, mut l1) = (0u64, 0u64);
+        let mut lp_data = [0u8; 0];
+        let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+        ctx_data[HAS_SYNCED_OFFSET] = 1;
+        let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+        let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+        let mut data = vec![0u8; 131];
+        data[0] = 0x02;
+        super::process_init(&program_id, &[lp, ctx], &data).unwrap();
+        assert_eq!(ctx_data[HAS_SYNCED_OFFSET], 0);
+    }
+
+    // -----------------------------------------------------------------------
+    // 48. Continuous yield variance spread scaling
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_variance_spread_multiplier() {
+        assert_eq!(variance_spread_multiplier(VARIANCE_SPREAD_REF_BPS), 100);
+        assert_eq!(variance_spread_multiplier(160), 58);
+        assert_eq!(variance_spread_multiplier(390), 141);
+        // Clamped to the bucket range
+        assert_eq!(variance_spread_multiplier(1), YieldRegime::VeryLow.spread_multiplier());
+        assert_eq!(variance_spread_multiplier(u64::MAX), YieldRegime::Extreme.spread_multiplier());
+    }
+
+    #[test]
+    fn test_variance_scaling_vs_regime_buckets() {
+        // Regime the oracle's default thresholds assign to a variance
+        let classify = |v: u16| [50, 150, 400, 800].iter().take_while(|&&upper| v > upper).count() as u8;
+        let spread_at = |scaling: u8, variance: u16| {
+            let ctx = MockContextBuilder::new()
+                .put(YIELD_REGIME_OFFSET, &[classify(variance)])
+                .put(SPREAD_SCALING_OFFSET, &[scaling])
+                .put(YIELD_VARIANCE_OFFSET, &variance.to_le_bytes())
+                .build();
+            u64::from_le_bytes(quote_at_mark(&ctx, 800_000_000, 0).unwrap()[9..17].try_into().unwrap())
+        };
+
+        // Two Normal feeds: one bucket spread, but continuous scaling tells them apart
+        assert_eq!(spread_at(SPREAD_SCALING_REGIME, 160), 50);
+        assert_eq!(spread_at(SPREAD_SCALING_REGIME, 390), 50);
+        assert_eq!(spread_at(SPREAD_SCALING_VARIANCE, 160), 20 + 30 * 58 / 100);
+        assert_eq!(spread_at(SPREAD_SCALING_VARIANCE, 390), 20 + 30 * 141 / 100);
+        // The reference variance prices as Normal in both modes
+        assert_eq!(spread_at(SPREAD_SCALING_VARIANCE, VARIANCE_SPREAD_REF_BPS as u16), 50);
+
+        // Across the range both are monotone within the VeryLow..Extreme spreads; the
+        // buckets take five values, continuous many more
+        let (mut bucketed, mut continuous) = (Vec::new(), Vec::new());
+        for variance in (10..=1_200).step_by(10) {
+            bucketed.push(spread_at(SPREAD_SCALING_REGIME, variance));
+            continuous.push(spread_at(SPREAD_SCALING_VARIANCE, variance));
+        }
+        for spreads in [&bucketed, &continuous] {
+            assert!(spreads.windows(2).all(|w| w[0] <= w[1]));
+            assert!(spreads.iter().all(|&s| (35..=95).contains(&s)));
+        }
+        let distinct = |v: &Vec<u64>| v.windows(2).filter(|w| w[0] != w[1]).count() + 1;
+        assert_eq!(distinct(&bucketed), 5);
+        assert!(distinct(&continuous) > 20);
+    }
+
+    #[test]
+    fn test_variance_scaling_without_reported_variance_prices_at_regime() {
+        let ctx = MockContextBuilder::new()
+            .put(YIELD_REGIME_OFFSET, &[YieldRegime::Extreme as u8])
+            .put(SPREAD_SCALING_OFFSET, &[SPREAD_SCALING_VARIANCE])
+            .build();
+        assert_eq!(effective_spread_multiplier(&ctx, 250), Ok(250));
+        let ctx = MockContextBuilder::new()
+            .put(SPREAD_SCALING_OFFSET, &[SPREAD_SCALING_VARIANCE])
+            .put(YIELD_VARIANCE_OFFSET, &390u16.to_le_bytes())
+            .build();
+        assert_eq!(effective_spread_multiplier(&ctx, 250), Ok(141));
+    }
+
+    #[test]
+    fn test_sync_payload_optional_variance() {
+        let mut payload = vec![0u8; ORACLE_SYNC_PAYLOAD_SIZE];
+        payload[0..8].copy_from_slice(&800i64.to_le_bytes());
+        payload[8..16].copy_from_slice(&800_000_000i64.to_le_bytes());
+        payload[16] = 2;
+        assert_eq!(parse_oracle_sync_payload(&payload).unwrap().yield_variance_bps, 0);
+        payload.extend_from_slice(&390u64.to_le_bytes());
+        assert_eq!(parse_oracle_sync_payload(&payload).unwrap().yield_variance_bps, 390);
+    }
+
+    #[test]
+    fn test_init_stores_spread_scaling() {
+        let init = |data: &[u8]| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            super::process_init(&program_id, &[lp, ctx], data).map(|()| ctx_data)
+        };
+        let mut data = vec![0u8; 138];
+        data[0] = 0x02;
+        data[137] = SPREAD_SCALING_VARIANCE;
+        assert_eq!(init(&data).unwrap()[SPREAD_SCALING_OFFSET], SPREAD_SCALING_VARIANCE);
+        // Absent, the regime buckets scale the spread
+        assert_eq!(init(&data[..137]).unwrap()[SPREAD_SCALING_OFFSET], SPREAD_SCALING_REGIME);
+        data[137] = 2;
+        assert_eq!(init(&data), Err(YieldMatcherError::InvalidSpreadScaling.into()));
+    }
+
+    // -----------------------------------------------------------------------
+    // 49. Syncing from a deactivated yield feed
+    // -----------------------------------------------------------------------
+    const FEED_UPDATED: i64 = 1_700_000_000;
+
+    /// Serialized NcnYieldFeed with `history_len` samples and the given is_active
+    fn yield_feed(history_len: u32, is_active: bool) -> Vec<u8> {
+        let mut feed = vec![0u8; YIELD_FEED_HISTORY_LEN_OFFSET];
+        feed[..8].copy_from_slice(&NCN_YIELD_FEED_DISCRIMINATOR);
+        feed.extend_from_slice(&history_len.to_le_bytes());
+        feed.extend(std::iter::repeat_n(0u8, history_len as usize * YIELD_FEED_SAMPLE_SIZE));
+        feed.extend_from_slice(&[0u8; YIELD_FEED_IS_ACTIVE_AFTER_HISTORY]);
+        feed.push(is_active as u8);
+        feed.extend_from_slice(&FEED_UPDATED.to_le_bytes());
+        feed
+    }
+
+    #[test]
+    fn test_read_yield_feed_is_active() {
+        assert_eq!(read_yield_feed_is_active(&yield_feed(0, true)), Ok(true));
+        assert_eq!(read_yield_feed_is_active(&yield_feed(5, false)), Ok(false));
+        let feed = yield_feed(2, true);
+        assert_eq!(read_yield_feed_is_active(&feed[..feed.len() - 9]), Err(ProgramError::InvalidAccountData));
+        let mut bad = feed.clone();
+        bad[feed.len() - 9] = 2;
+        assert_eq!(read_yield_feed_is_active(&bad), Err(ProgramError::InvalidAccountData));
+        let mut long = feed.clone();
+        long[YIELD_FEED_HISTORY_LEN_OFFSET..YIELD_FEED_HISTORY_LEN_OFFSET + 4]
+            .copy_from_slice(&(YIELD_FEED_MAX_HISTORY as u32 + 1).to_le_bytes());
+        assert_eq!(read_yield_feed_is_active(&long), Err(ProgramError::InvalidAccountData));
+        let mut foreign = feed;
+        foreign[0] ^= 0xff;
+        assert_eq!(read_yield_feed_is_active(&foreign), Err(YieldMatcherError::OracleAccountMismatch.into()));
+    }
+
+    #[test]
+    fn test_sync_rejects_inactive_yield_feed() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, yield_key, perf_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::new()
+            .put(NCN_YIELD_FEED_OFFSET, yield_key.as_ref())
+            .put(NCN_PERFORMANCE_FEED_OFFSET, perf_key.as_ref())
+            .build();
+        let before = ctx_data.clone();
+
+        let mut data = vec![0x03];
+        data.extend_from_slice(&800i64.to_le_bytes());
+        data.extend_from_slice(&800_000_000i64.to_le_bytes());
+        data.push(YieldRegime::Normal as u8);
+        for field in [800u64, 800, 1] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        let sync = |is_active: bool, ctx_data: &mut Vec<u8>| {
+            let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+            let (mut feed, mut perf) = (yield_feed(2, is_active), [0u8; 0]);
+            let accounts = [
+                AccountInfo::new(&ctx_key, false, true, &mut l0, ctx_data, &program_id, false, 0),
+                AccountInfo::new(&yield_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0),
+                AccountInfo::new(&perf_key, false, false, &mut l2, &mut perf, &NCN_ORACLE_PROGRAM_ID, false, 0),
+            ];
+            process_oracle_sync(&program_id, &accounts, &data)
+        };
+
+        let inactive = Err(YieldMatcherError::OracleInactive.into());
+        assert_eq!(sync(false, &mut ctx_data), inactive);
+        assert_eq!(ctx_data, before);
+
+        // An active feed gets past the guard; off-chain the Clock read fails instead
+        let result = sync(true, &mut ctx_data);
+        assert_ne!(result, inactive);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_rejects_inactive_yield_feed() {
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, yield_key, perf_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::new()
+            .put(NCN_YIELD_FEED_OFFSET, yield_key.as_ref())
+            .put(NCN_PERFORMANCE_FEED_OFFSET, perf_key.as_ref())
+            .build();
+        let before = ctx_data.clone();
+
+        let heartbeat = |is_active: bool, ctx_data: &mut Vec<u8>| {
+            let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+            let (mut feed, mut perf) = (yield_feed(2, is_active), [0u8; 0]);
+            let accounts = [
+                AccountInfo::new(&ctx_key, false, true, &mut l0, ctx_data, &program_id, false, 0),
+                AccountInfo::new(&yield_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0),
+                AccountInfo::new(&perf_key, false, false, &mut l2, &mut perf, &NCN_ORACLE_PROGRAM_ID, false, 0),
+            ];
+            process_heartbeat(&program_id, &accounts, &[0x04])
+        };
+
+        // A deactivated feed cannot keep the context alive, however fresh it looks
+        let inactive = Err(YieldMatcherError::OracleInactive.into());
+        assert_eq!(heartbeat(false, &mut ctx_data), inactive);
+        assert_eq!(ctx_data, before);
+
+        let result = heartbeat(true, &mut ctx_data);
+        assert_ne!(result, inactive);
+        assert!(result.is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // 50. Soft-stale band
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_staleness_zones() {
+        let stale: ProgramError = YieldMatcherError::OracleStale.into();
+
+        // Default: a single cliff at MAX_STALENESS_SLOTS
+        let ctx = MockContextBuilder::new().build();
+        assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+        assert_eq!(check_staleness(&ctx, MAX_STALENESS_SLOTS), Ok(false));
+        assert_eq!(check_staleness(&ctx, MAX_STALENESS_SLOTS + 1), Err(stale.clone()));
+
+        // Fresh up to 500 slots, degraded through 1500, rejected beyond (past a u8)
+        let ctx = MockContextBuilder::new()
+            .put(SOFT_STALE_SLOTS_OFFSET, &500u32.to_le_bytes())
+            .put(HARD_STALE_SLOTS_OFFSET, &1_500u32.to_le_bytes())
+            .build();
+        assert_eq!(stale_limits(&ctx), Ok((500, 1_500)));
+        for (age, zone) in [(0, Ok(false)), (500, Ok(false)), (501, Ok(true)), (1_500, Ok(true)), (1_501, Err(stale.clone()))] {
+            assert_eq!(check_staleness(&ctx, age), zone, "age {}", age);
+        }
+
+        // A hard limit below the soft threshold leaves no band
+        let ctx = MockContextBuilder::new().put(HARD_STALE_SLOTS_OFFSET, &20u32.to_le_bytes()).build();
+        assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+    }
+
+    #[test]
+    fn test_soft_stale_quote_charges_penalty_spread() {
+        // No penalty configured: a degraded quote is floored at the 200 bps max
+        let ctx = MockContextBuilder::new().build();
+        assert_eq!(degraded_spread_floor(&ctx, 200), Ok(200));
+        let ctx = MockContextBuilder::new().put(PENALTY_SPREAD_OFFSET, &120u32.to_le_bytes()).build();
+        assert_eq!(degraded_spread_floor(&ctx, 200), Ok(120));
+
+        let mark = 800_000_000;
+        // Fresh: base 20 + vol 30 at Normal; degraded: floored at the 120 bps penalty
+        let (fresh_price, fresh_spread) = yield_exec_price(mark, 100, 20, 30, 0, 200, 0).unwrap();
+        let (degraded_price, degraded_spread) = yield_exec_price(mark, 100, 20, 30, 120, 200, 0).unwrap();
+        assert_eq!((fresh_spread, degraded_spread), (50, 120));
+        assert!(degraded_price > fresh_price);
+        assert_eq!(RETURN_DEGRADED_OFFSET, RETURN_DATA_OFFSET + 24);
+    }
+
+    #[test]
+    fn test_init_stores_staleness_limits() {
+        let init = |data: &[u8]| {
+            let program_id = Pubkey::new_unique();
+            let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_V2_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            super::process_init(&program_id, &[lp, ctx], data).map(|()| ctx_data)
+        };
+        let mut data = vec![0u8; 150];
+        data[0] = 0x02;
+        data[10..14].copy_from_slice(&200u32.to_le_bytes());
+        data[138..142].copy_from_slice(&500u32.to_le_bytes());
+        data[142..146].copy_from_slice(&1_500u32.to_le_bytes());
+        data[146..150].copy_from_slice(&120u32.to_le_bytes());
+        let ctx = init(&data).unwrap();
+        assert_eq!(stale_limits(&ctx), Ok((500, 1_500)));
+        assert_eq!(degraded_spread_floor(&ctx, 200), Ok(120));
+        assert_eq!(ctx[VERSION_OFFSET..VERSION_OFFSET + 4], CTX_VERSION_2.to_le_bytes());
+        // Absent, the single MAX_STALENESS_SLOTS cliff and the max spread apply
+        let ctx = init(&data[..138]).unwrap();
+        assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+        assert_eq!(degraded_spread_floor(&ctx, 200), Ok(200));
+
+        let invalid = Err(YieldMatcherError::InvalidStalenessConfig.into());
+        data[142..146].copy_from_slice(&499u32.to_le_bytes());
+        assert_eq!(init(&data), invalid);
+        // Against the default soft threshold too
+        data[138..142].copy_from_slice(&0u32.to_le_bytes());
+        data[142..146].copy_from_slice(&99u32.to_le_bytes());
+        assert_eq!(init(&data), invalid);
+        data[142..146].copy_from_slice(&100u32.to_le_bytes());
+        assert_eq!(stale_limits(&init(&data).unwrap()), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+
+        // The penalty spread must sit between the min and max spread
+        let bad_spread = Err(YieldMatcherError::InvalidPenaltySpread.into());
+        data[146..150].copy_from_slice(&201u32.to_le_bytes());
+        assert_eq!(init(&data), bad_spread);
+        data[115..119].copy_from_slice(&130u32.to_le_bytes());
+        data[146..150].copy_from_slice(&120u32.to_le_bytes());
+        assert_eq!(init(&data), bad_spread);
+    }
+
+    #[test]
+    fn test_init_version_1_sized_context() {
+        let program_id = Pubkey::new_unique();
+        let (lp_key, ctx_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let init = |data: &[u8]| {
+            let (mut l0, mut l1) = (0u64, 0u64);
+            let mut lp_data = [0u8; 0];
+            let mut ctx_data = vec![0u8; CTX_SIZE];
+            let lp = AccountInfo::new(&lp_key, true, false, &mut l0, &mut lp_data, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, &mut ctx_data, &program_id, false, 0);
+            super::process_init(&program_id, &[lp, ctx], data).map(|()| ctx_data)
+        };
+        let mut data = vec![0u8; 138];
+        data[0] = 0x02;
+
+        // Without extension options a 320-byte account initializes as version 1
+        let ctx = init(&data).unwrap();
+        assert_eq!(ctx.len(), CTX_SIZE);
+        assert_eq!(ctx[VERSION_OFFSET..VERSION_OFFSET + 4], CTX_VERSION_1.to_le_bytes());
+        assert!(!has_extension(&ctx));
+        assert_eq!(stale_limits(&ctx), Ok((MAX_STALENESS_SLOTS, MAX_STALENESS_SLOTS)));
+
+        // Extension options need the version-2 size
+        data.extend_from_slice(&10u32.to_le_bytes());
+        data.extend_from_slice(&100u32.to_le_bytes());
+        assert_eq!(init(&data), Err(ProgramError::AccountDataTooSmall));
+    }
+
+    #[test]
+    fn test_migrate_gates() {
+        let program_id = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let builder = MockContextBuilder::new();
+        let lp_key = builder.lp_key;
+        let v1 = builder.version_1().build();
+        let migrate = |ctx_data: &mut Vec<u8>, signer: &Pubkey, payer_signs: bool, system: &Pubkey, data: &[u8]| {
+            let ctx_key = Pubkey::new_unique();
+            let (mut l0, mut l1, mut l2, mut l3) = (0u64, 0u64, 0u64, 0u64);
+            let (mut d0, mut d2, mut d3) = ([0u8; 0], [0u8; 0], [0u8; 0]);
+            let lp = AccountInfo::new(signer, true, false, &mut l0, &mut d0, &program_id, false, 0);
+            let ctx = AccountInfo::new(&ctx_key, false, true, &mut l1, ctx_data, &program_id, false, 0);
+            let payer = AccountInfo::new(&payer_key, payer_signs, true, &mut l2, &mut d2, &system_program::ID, false, 0);
+            let sys = AccountInfo::new(system, false, false, &mut l3, &mut d3, &program_id, true, 0);
+            process_migrate(&program_id, &[lp, ctx, payer, sys], data)
+        };
+        let data = [0x11u8];
+
+        assert_eq!(
+            migrate(&mut v1.clone(), &Pubkey::new_unique(), true, &system_program::ID, &data),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(migrate(&mut v1.clone(), &lp_key, false, &system_program::ID, &data), Err(ProgramError::MissingRequiredSignature));
+        assert_eq!(migrate(&mut v1.clone(), &lp_key, true, &Pubkey::new_unique(), &data), Err(ProgramError::IncorrectProgramId));
+
+        // A version-2 context has nothing to migrate
+        let v2 = MockContextBuilder::new().put(LP_PDA_OFFSET, lp_key.as_ref()).build();
+        assert_eq!(migrate(&mut v2.clone(), &lp_key, true, &system_program::ID, &data), Err(ProgramError::AccountAlreadyInitialized));
+
+        // Extension parameters are validated like init's
+        let mut bad = vec![0x11u8];
+        bad.extend_from_slice(&100u32.to_le_bytes());
+        bad.extend_from_slice(&10u32.to_le_bytes());
+        assert_eq!(
+            migrate(&mut v1.clone(), &lp_key, true, &system_program::ID, &bad),
+            Err(YieldMatcherError::InvalidStalenessConfig.into())
+        );
+
+        // Past the gates only the Rent sysvar, unavailable off-chain, is left
+        let result = migrate(&mut v1.clone(), &lp_key, true, &system_program::ID, &data);
+        assert_ne!(result, Err(ProgramError::AccountAlreadyInitialized));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_version_1_context_defaults() {
+        let authority_key = Pubkey::new_unique();
+        let ctx = MockContextBuilder::new().version_1().build();
+        assert!(!has_extension(&ctx));
+        assert_eq!(degraded_spread_floor(&ctx, 200), Ok(200));
+        assert_eq!(check_staleness(&ctx, MAX_STALENESS_SLOTS), Ok(false));
+
+        // Nobody can claim protocol fees until the context migrates
+        let mut lamports = 0u64;
+        let mut data = [0u8; 0];
+        let authority = AccountInfo::new(&authority_key, true, false, &mut lamports, &mut data, &authority_key, false, 0);
+        assert_eq!(verify_fee_authority(&authority, &ctx), Err(YieldMatcherError::FeeAuthorityMismatch.into()));
+
+        // Migrating writes version 2 and the extension fields
+        let mut migrated = ctx.clone();
+        migrated.resize(CTX_V2_SIZE, 0);
+        let mut params = vec![0u8; 12];
+        params.extend_from_slice(authority_key.as_ref());
+        let extension = parse_extension(&params, 20, 200, 0).unwrap();
+        write_extension(&mut migrated, &extension);
+        assert!(has_extension(&migrated));
+        assert_eq!(verify_fee_authority(&authority, &migrated), Ok(()));
+        assert_eq!(extension, ContextExtension { fee_authority: authority_key, ..ContextExtension::default() });
+    }
+
+    // -----------------------------------------------------------------------
+    // 51. Heartbeats follow the yield feed's own freshness
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_heartbeat_requires_fresh_yield_feed() {
+        let mut feed = yield_feed(2, true);
+        assert_eq!(read_yield_feed_last_updated(&feed[..feed.len() - 1]), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_yield_feed_last_updated(&feed), Ok(FEED_UPDATED));
+        feed[0] ^= 1;
+        assert_eq!(read_yield_feed_last_updated(&feed), Err(YieldMatcherError::OracleAccountMismatch.into()));
+
+        // The window follows the context's hard staleness limit: 100 slots ~ 40s
+        let stale = Err(YieldMatcherError::OracleStale.into());
+        assert_eq!(check_heartbeat_feed_age(1_000, 1_040, MAX_STALENESS_SLOTS), Ok(()));
+        assert_eq!(check_heartbeat_feed_age(1_000, 1_041, MAX_STALENESS_SLOTS), stale);
+        assert_eq!(check_heartbeat_feed_age(1_000, 1_100, 250), Ok(()));
+        // A dead keeper's feed never becomes fresh again, however often heartbeats are sent
+        assert_eq!(check_heartbeat_feed_age(0, i64::MAX, MAX_STALENESS_SLOTS), stale);
+    }
+
+    // -----------------------------------------------------------------------
+    // 52. The oracle sequence cannot run ahead of the yield feed
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_sequence_bounded_by_feed_last_updated() {
+        let ahead = Err(YieldMatcherError::OracleSequenceAhead.into());
+        assert_eq!(check_sequence_within_feed(1_000, 1_000), Ok(()));
+        assert_eq!(check_sequence_within_feed(1_001, 1_000), ahead);
+        assert_eq!(check_sequence_within_feed(1, -5), ahead);
+
+        // A u64::MAX sequence would lock out every later sync; it never gets written
+        let program_id = Pubkey::new_unique();
+        let (ctx_key, yield_key, perf_key) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ctx_data = MockContextBuilder::new()
+            .put(NCN_YIELD_FEED_OFFSET, yield_key.as_ref())
+            .put(NCN_PERFORMANCE_FEED_OFFSET, perf_key.as_ref())
+            .build();
+        let before = ctx_data.clone();
+
+        let mut data = vec![0x03];
+        data.extend_from_slice(&800i64.to_le_bytes());
+        data.extend_from_slice(&800_000_000i64.to_le_bytes());
+        data.push(YieldRegime::Normal as u8);
+        for field in [800u64, 800, u64::MAX] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        let (mut l0, mut l1, mut l2) = (0u64, 0u64, 0u64);
+        let (mut feed, mut perf) = (yield_feed(2, true), [0u8; 0]);
+        let accounts = [
+            AccountInfo::new(&ctx_key, false, true, &mut l0, &mut ctx_data, &program_id, false, 0),
+            AccountInfo::new(&yield_key, false, false, &mut l1, &mut feed, &NCN_ORACLE_PROGRAM_ID, false, 0),
+            AccountInfo::new(&perf_key, false, false, &mut l2, &mut perf, &NCN_ORACLE_PROGRAM_ID, false, 0),
+        ];
+        assert_eq!(process_oracle_sync(&program_id, &accounts, &data), ahead);
+        assert_eq!(ctx_data, before);
+    }
+
+    // -----------------------------------------------------------------------
+    // 53. A liquidity notional caps fills without impact pricing
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_liquidity_caps_fills_with_impact_off() {
+        let liq = 1_000_000u128;
+        let ctx = MockContextBuilder::new().spreads(20, 30, 200).liquidity(0, liq).build();
+        assert_eq!(liquidity_capped(&ctx), Ok(true));
+        let impact_at = |out: [u8; QUOTE_AT_MARK_RETURN_SIZE]| u64::from_le_bytes(out[25..33].try_into().unwrap());
+        assert_eq!(impact_at(quote_at_mark(&ctx, 800_000_000, liq).unwrap()), 0);
+        let short = Err(YieldMatcherError::InsufficientLiquidity.into());
+        assert_eq!(quote_at_mark(&ctx, 800_000_000, liq + 1), short);
+
+        // A drawn-down notional keeps capping instead of reading as unconfigured
+        let drained = MockContextBuilder::new()
+            .spreads(20, 30, 200)
+            .put(LIQUIDITY_CAPPED_OFFSET, &[1])
+            .build();
+        assert_eq!(liquidity_capped(&drained), Ok(true));
+        assert_eq!(quote_at_mark(&drained, 800_000_000, 1), short);
+        assert!(quote_at_mark(&drained, 800_000_000, 0).is_ok());
+
+        // No notional leaves fills unbounded; an impact-priced context from before the
+        // flag still draws its notional down
+        let open = MockContextBuilder::new().spreads(20, 30, 200).build();
+        assert_eq!(liquidity_capped(&open), Ok(false));
+        assert!(quote_at_mark(&open, 800_000_000, u64::MAX as u128).is_ok());
+        let legacy = MockContextBuilder::new()
+            .liquidity(10, liq)
+            .put(LIQUIDITY_CAPPED_OFFSET, &[0])
+            .build();
+        assert_eq!(liquidity_capped(&legacy), Ok(true));
     }
 }
@@ -260,6 +260,9 @@ export async function fetchAggregatedRestakingFeed(
   offset += 1;
   const lastUpdated = view.getBigInt64(offset, true);
   offset += 8;
+  offset += 1; // bump
+  const oldestMemberUpdate = view.getBigInt64(offset, true);
+  offset += 8;
 
   return {
     authority,
@@ -269,5 +272,6 @@ export async function fetchAggregatedRestakingFeed(
     ncnFeeds,
     isActive,
     lastUpdated,
+    oldestMemberUpdate,
   };
 }
@@ -61,6 +61,8 @@ export interface AggregatedRestakingFeedData {
   ncnFeeds: PublicKey[];
   isActive: boolean;
   lastUpdated: bigint;
+  /** Oldest member feed update at the last recompute; the aggregate is only this fresh */
+  oldestMemberUpdate: bigint;
 }
 
 // ============================================================================
@@ -94,6 +96,8 @@ export interface YieldMatcherContext {
   maxFillAbs: bigint;
   ncnYieldFeed: PublicKey;
   ncnPerformanceFeed: PublicKey;
+  /** Mark blend weights [spot, 7d, 30d] in bps; all zero = spot only */
+  blendWeightsBps: [number, number, number];
 }
 
 export enum YieldMatcherMode {
@@ -21,7 +21,7 @@ const UPTIME_MATCHER_PROGRAM_ID = new PublicKey(
 );
 
 // ============================================================================
-// Context Deserialization (320-byte raw context account)
+// Context Deserialization (320-byte raw context account; version 2 appends an extension)
 // ============================================================================
 
 export function deserializeUptimeMatcherContext(
@@ -141,13 +141,17 @@ export function buildUptimeSyncIx(
   ncnOracle: PublicKey,
   newUptimeE6: BN,
   signalSeverity: BN,
-  signalAdjustedSpread: BN
+  signalAdjustedSpread: BN,
+  oracleSequence: BN
 ): TransactionInstruction {
-  const data = Buffer.alloc(25);
+  // oracleSequence is the feed's last_updated the payload was read at: it must exceed the
+  // last applied sync (replay protection) and may not run ahead of the feed
+  const data = Buffer.alloc(33);
   data.writeUInt8(0x03, 0);
   newUptimeE6.toBuffer("le", 8).copy(data, 1);
   signalSeverity.toBuffer("le", 8).copy(data, 9);
   signalAdjustedSpread.toBuffer("le", 8).copy(data, 17);
+  oracleSequence.toBuffer("le", 8).copy(data, 25);
 
   return new TransactionInstruction({
     programId: UPTIME_MATCHER_PROGRAM_ID,
@@ -163,6 +167,29 @@ export function buildUptimeSyncIx(
   });
 }
 
+/**
+ * Build InitAndSync instruction (tag 0x0E) from an Init (0x02) and an UptimeSync (0x03)
+ * instruction for the same context, so a new market can quote after one transaction.
+ * The sync's oracleSequence must be > 0.
+ */
+export function buildUptimeMatcherInitAndSyncIx(
+  initIx: TransactionInstruction,
+  syncIx: TransactionInstruction
+): TransactionInstruction {
+  // [0] tag, [1..33] sync payload, [33..] init fields (tag 0x02 bytes [1..])
+  const data = Buffer.concat([
+    Buffer.from([0x0e]),
+    syncIx.data.subarray(1, 33),
+    initIx.data.subarray(1),
+  ]);
+
+  return new TransactionInstruction({
+    programId: UPTIME_MATCHER_PROGRAM_ID,
+    keys: [...initIx.keys, syncIx.keys[1]],
+    data,
+  });
+}
+
 /** Build Resolve instruction (tag 0x04) */
 export function buildResolveIx(
   matcherContext: PublicKey,
@@ -21,7 +21,7 @@ const YIELD_MATCHER_PROGRAM_ID = new PublicKey(
 );
 
 // ============================================================================
-// Context Deserialization (320-byte raw context account)
+// Context Deserialization (320-byte raw context account; version 2 appends an extension)
 // ============================================================================
 
 export function deserializeYieldMatcherContext(
@@ -42,8 +42,8 @@ export function deserializeYieldMatcherContext(
     yieldVolSpreadBps: view.getUint32(116, true),
     maxSpreadBps: view.getUint32(120, true),
     impactKBps: view.getUint32(124, true),
-    currentYieldBps: view.getBigUint64(128, true),
-    yieldMarkPriceE6: view.getBigUint64(136, true),
+    currentYieldBps: view.getBigInt64(128, true),
+    yieldMarkPriceE6: view.getBigInt64(136, true),
     lastUpdateSlot: view.getBigUint64(144, true),
     yieldRegime: data[152] as YieldRegime,
     yield7dAvgBps: view.getBigUint64(160, true),
@@ -52,6 +52,11 @@ export function deserializeYieldMatcherContext(
     maxFillAbs: view.getBigUint64(192, true),
     ncnYieldFeed: new PublicKey(data.subarray(208, 240)),
     ncnPerformanceFeed: new PublicKey(data.subarray(240, 272)),
+    blendWeightsBps: [
+      view.getUint32(288, true),
+      view.getUint32(292, true),
+      view.getUint32(296, true),
+    ],
   };
 }
 
@@ -141,15 +146,20 @@ export function buildYieldMatcherOracleSyncIx(
   yieldMarkPriceE6: BN,
   regime: number,
   yield7dAvgBps: BN,
-  yield30dAvgBps: BN
+  yield30dAvgBps: BN,
+  oracleSequence: BN
 ): TransactionInstruction {
-  const data = Buffer.alloc(34);
+  // oracleSequence is the yield feed's last_updated the payload was read at: it must exceed
+  // the last applied sync (replay protection) and may not run ahead of the feed.
+  // Yield and mark are signed (i64): a net yield can go negative.
+  const data = Buffer.alloc(42);
   data.writeUInt8(0x03, 0);
-  currentYieldBps.toBuffer("le", 8).copy(data, 1);
-  yieldMarkPriceE6.toBuffer("le", 8).copy(data, 9);
+  currentYieldBps.toTwos(64).toArrayLike(Buffer, "le", 8).copy(data, 1);
+  yieldMarkPriceE6.toTwos(64).toArrayLike(Buffer, "le", 8).copy(data, 9);
   data.writeUInt8(regime, 17);
   yield7dAvgBps.toBuffer("le", 8).copy(data, 18);
   yield30dAvgBps.toBuffer("le", 8).copy(data, 26);
+  oracleSequence.toBuffer("le", 8).copy(data, 34);
 
   return new TransactionInstruction({
     programId: YIELD_MATCHER_PROGRAM_ID,
@@ -174,10 +184,45 @@ export function buildYieldMatcherOracleSyncIx(
   });
 }
 
+/**
+ * Build InitAndSync instruction (tag 0x0A) from an Init (0x02) and an OracleSync (0x03)
+ * instruction for the same context, so a new matcher can quote after one transaction.
+ * The sync's oracleSequence must be > 0.
+ */
+export function buildYieldMatcherInitAndSyncIx(
+  initIx: TransactionInstruction,
+  syncIx: TransactionInstruction
+): TransactionInstruction {
+  // [0] tag, [1..42] sync payload, [42..] init fields (tag 0x02 bytes [1..])
+  const data = Buffer.concat([
+    Buffer.from([0x0a]),
+    syncIx.data.subarray(1, 42),
+    initIx.data.subarray(1),
+  ]);
+
+  return new TransactionInstruction({
+    programId: YIELD_MATCHER_PROGRAM_ID,
+    keys: [...initIx.keys, syncIx.keys[1], syncIx.keys[2]],
+    data,
+  });
+}
+
 // ============================================================================
 // Pricing Simulation (client-side)
 // ============================================================================
 
+/** Effective mark: spot/7d/30d blend (spot only when all weights are zero) */
+export function blendedYieldMarkE6(ctx: YieldMatcherContext): bigint {
+  const [wSpot, w7d, w30d] = ctx.blendWeightsBps.map(BigInt);
+  if (wSpot + w7d + w30d === 0n) return ctx.yieldMarkPriceE6;
+  return (
+    (wSpot * ctx.yieldMarkPriceE6 +
+      w7d * ctx.yield7dAvgBps * 1_000_000n +
+      w30d * ctx.yield30dAvgBps * 1_000_000n) /
+    10000n
+  );
+}
+
 export function simulateYieldExecPrice(
   ctx: YieldMatcherContext
 ): bigint {
@@ -193,5 +238,9 @@ export function simulateYieldExecPrice(
       ? BigInt(ctx.baseSpreadBps) + adjustedYieldVol
       : BigInt(ctx.maxSpreadBps);
 
-  return (ctx.yieldMarkPriceE6 * (10000n + totalSpread)) / 10000n;
+  // Ask rounds up (LP-favorable), matching the on-chain pricing core. A negative
+  // mark mirrors the bid of its magnitude: ask(-m) = -bid(m).
+  const mark = blendedYieldMarkE6(ctx);
+  if (mark < 0n) return -((-mark * (10000n - totalSpread)) / 10000n);
+  return (mark * (10000n + totalSpread) + 9999n) / 10000n;
 }